@@ -966,12 +966,14 @@ class App {
             }
 
             if (payload.scan_id) {
-                const query = {
-                    scan_type: payload.scan_id,
-                    params: answers,
-                    date_from: this.elements.dateFrom.value || null,
-                    date_to: this.elements.dateTo.value || null,
-                };
+                // An existing scan comes back as a ready query, maybe with its own date window
+                const query = payload.query
+                    ? { ...payload.query }
+                    : { scan_type: payload.scan_id, params: answers };
+                if (!query.lookback_days && !query.date_from && !query.date_to) {
+                    query.date_from = this.elements.dateFrom.value || null;
+                    query.date_to = this.elements.dateTo.value || null;
+                }
                 this.runScanWithQuery(query);
             }
         } catch (err) {
@@ -0,0 +1,156 @@
+//! Optional API-key authentication for `/api/*` routes
+//!
+//! Enabled by setting `RETRO_API_KEY` (comma-separated for multiple keys).
+//! Clients send `Authorization: Bearer <key>` or `X-Api-Key: <key>`.
+
+use crate::error::ApiError;
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct ApiKeys {
+    keys: Vec<Vec<u8>>,
+}
+
+impl ApiKeys {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let keys: Vec<Vec<u8>> = raw
+            .split(',')
+            .map(|k| k.trim())
+            .filter(|k| !k.is_empty())
+            .map(|k| k.as_bytes().to_vec())
+            .collect();
+
+        if keys.is_empty() {
+            None
+        } else {
+            Some(Self { keys })
+        }
+    }
+
+    /// Keys from `RETRO_API_KEY`; `None` means auth is disabled
+    pub fn from_env() -> Option<Self> {
+        std::env::var("RETRO_API_KEY").ok().and_then(|v| Self::parse(&v))
+    }
+
+    pub fn is_valid(&self, candidate: &str) -> bool {
+        // Check every key so timing doesn't reveal which one matched
+        self.keys
+            .iter()
+            .fold(false, |ok, key| constant_time_eq(key, candidate.as_bytes()) | ok)
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn presented_key(req: &Request) -> Option<&str> {
+    let headers = req.headers();
+    if let Some(bearer) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        return Some(bearer.trim());
+    }
+    headers
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim())
+}
+
+/// Require a key on every route of `router`; with no keys it's returned as is
+pub fn protect<S: Clone + Send + Sync + 'static>(router: Router<S>, keys: Option<ApiKeys>) -> Router<S> {
+    match keys {
+        Some(keys) => router.layer(middleware::from_fn_with_state(Arc::new(keys), require_api_key)),
+        None => router,
+    }
+}
+
+/// Middleware rejecting requests without a valid key with 401
+pub async fn require_api_key(
+    State(keys): State<Arc<ApiKeys>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !presented_key(&req).is_some_and(|k| keys.is_valid(k)) {
+        return (
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            ApiError::Unauthorized("Missing or invalid API key".into()),
+        )
+            .into_response();
+    }
+
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::get};
+    use tower::ServiceExt;
+
+    fn app(keys: Option<ApiKeys>) -> Router {
+        let api = Router::new()
+            .route("/scan-types", get(|| async { "[]" }))
+            .route("/openapi.json", get(|| async { "{}" }));
+        Router::new()
+            .route("/api/health", get(|| async { "OK" }))
+            .nest("/api", protect(api, keys))
+            .route("/index.html", get(|| async { "<html>" }))
+    }
+
+    async fn status(app: &Router, path: &str, headers: &[(&str, &str)]) -> StatusCode {
+        let mut req = Request::builder().uri(path);
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        app.clone().oneshot(req.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_api_nest_requires_key_when_set() {
+        let open = app(None);
+        assert_eq!(status(&open, "/api/scan-types", &[]).await, StatusCode::OK);
+
+        let locked = app(ApiKeys::parse("secret"));
+        assert_eq!(status(&locked, "/api/scan-types", &[]).await, StatusCode::UNAUTHORIZED);
+        let wrong = [("authorization", "Bearer nope")];
+        assert_eq!(status(&locked, "/api/scan-types", &wrong).await, StatusCode::UNAUTHORIZED);
+        let not_bearer = [("authorization", "secret")];
+        assert_eq!(status(&locked, "/api/scan-types", &not_bearer).await, StatusCode::UNAUTHORIZED);
+
+        let bearer = [("authorization", "Bearer secret")];
+        assert_eq!(status(&locked, "/api/scan-types", &bearer).await, StatusCode::OK);
+        assert_eq!(status(&locked, "/api/scan-types", &[("x-api-key", "secret")]).await, StatusCode::OK);
+        // The API description is behind the key too
+        assert_eq!(status(&locked, "/api/openapi.json", &[]).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(&locked, "/api/openapi.json", &bearer).await, StatusCode::OK);
+
+        // Outside the nest
+        assert_eq!(status(&locked, "/api/health", &[]).await, StatusCode::OK);
+        assert_eq!(status(&locked, "/index.html", &[]).await, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_parse_and_validate_keys() {
+        assert!(ApiKeys::parse(" , ").is_none());
+
+        let keys = ApiKeys::parse("alpha, beta").unwrap();
+        assert!(keys.is_valid("alpha"));
+        assert!(keys.is_valid("beta"));
+        assert!(!keys.is_valid("alph"));
+        assert!(!keys.is_valid("gamma"));
+        assert!(!keys.is_valid(""));
+    }
+}
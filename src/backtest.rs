@@ -0,0 +1,615 @@
+//! Backtesting - simulate trades from scan signals with simple exit rules
+//!
+//! Signals are evaluated at a bar's close and filled at the next bar's open,
+//! so no decision uses prices from the bar it trades on.
+
+use crate::data::{parse_date, TickerData};
+use crate::indicators::chandelier_stop_from;
+use crate::registry::ScanRegistry;
+use crate::scanner::{run_scan, run_scan_with, ScanMatch, ScanQuery};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+/// When to close a position. Rules combine; the first to trigger wins.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExitRules {
+    /// Exit at the open this many bars after entry
+    pub hold_bars: Option<usize>,
+    /// Exit when the low reaches entry × (1 - pct/100); gaps fill at the open
+    pub stop_loss_pct: Option<f64>,
+    /// Exit when the high reaches entry × (1 + pct/100); gaps fill at the open
+    pub take_profit_pct: Option<f64>,
+    /// Exit at the next open after the close falls below a chandelier stop
+    /// anchored at the entry bar
+    pub trailing_stop: Option<TrailingStop>,
+    /// Exit at the next open after this scan fires on a held ticker
+    pub exit_scan: Option<String>,
+    #[serde(default)]
+    pub exit_params: HashMap<String, Value>,
+}
+
+/// Chandelier trailing stop: highest high since entry minus ATR × multiplier
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct TrailingStop {
+    pub period: usize,
+    pub multiplier: f64,
+}
+
+impl Default for TrailingStop {
+    fn default() -> Self {
+        Self {
+            period: 22,
+            multiplier: 3.0,
+        }
+    }
+}
+
+impl ExitRules {
+    pub fn is_empty(&self) -> bool {
+        self.hold_bars.is_none()
+            && self.stop_loss_pct.is_none()
+            && self.take_profit_pct.is_none()
+            && self.trailing_stop.is_none()
+            && self.exit_scan.is_none()
+    }
+}
+
+/// Equal-weight sizing: each entry gets equity / max_positions (capped by cash)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Sizing {
+    pub initial_capital: f64,
+    pub max_positions: usize,
+}
+
+impl Default for Sizing {
+    fn default() -> Self {
+        Self {
+            initial_capital: 100_000.0,
+            max_positions: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BacktestRequest {
+    pub entry: ScanQuery,
+    #[serde(default)]
+    pub exit: ExitRules,
+    #[serde(default)]
+    pub sizing: Sizing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    HoldPeriod,
+    StopLoss,
+    TakeProfit,
+    TrailingStop,
+    ExitSignal,
+    EndOfData,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Trade {
+    pub ticker: String,
+    pub entry_date: String,
+    pub entry_price: f64,
+    pub exit_date: String,
+    pub exit_price: f64,
+    pub shares: f64,
+    pub pnl: f64,
+    pub return_pct: f64,
+    pub bars_held: usize,
+    pub exit_reason: ExitReason,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EquityPoint {
+    pub date: String,
+    pub equity: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BacktestStats {
+    pub total_trades: usize,
+    pub winning_trades: usize,
+    pub win_rate: f64,
+    pub total_return_pct: f64,
+    /// None when the curve spans less than a day
+    pub cagr_pct: Option<f64>,
+    pub max_drawdown_pct: f64,
+    /// Gross profit / gross loss; None when there were no losing trades
+    pub profit_factor: Option<f64>,
+    pub final_equity: f64,
+    /// Entry signals not taken (ticker already held, all slots full, or no next bar)
+    pub signals_skipped: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestResult {
+    pub trades: Vec<Trade>,
+    pub equity_curve: Vec<EquityPoint>,
+    pub stats: BacktestStats,
+}
+
+/// Run the entry (and optional exit) scans, then simulate. Scan ids and params
+/// are expected to be validated by the caller; `benchmark` serves the entry
+/// query's `correlation_filter`.
+pub fn run_backtest(
+    registry: &ScanRegistry,
+    data: &HashMap<String, Arc<TickerData>>,
+    req: &BacktestRequest,
+    benchmark: Option<Arc<TickerData>>,
+) -> BacktestResult {
+    let entries = signal_bars(data, &run_scan_with(registry, data, &req.entry, benchmark).matches);
+
+    let exits = match &req.exit.exit_scan {
+        Some(scan_type) => {
+            let query = ScanQuery {
+                scan_type: scan_type.clone(),
+                params: req.exit.exit_params.clone(),
+                timeout_ms: req.entry.timeout_ms,
+                ..Default::default()
+            };
+            signal_bars(data, &run_scan(registry, data, &query).matches)
+        }
+        None => HashMap::new(),
+    };
+    let exits = exits
+        .into_iter()
+        .map(|(ticker, bars)| (ticker, bars.into_iter().collect()))
+        .collect();
+
+    simulate(data, &entries, &exits, &req.exit, &req.sizing)
+}
+
+/// Bar indices of scan matches, per ticker, ascending
+fn signal_bars(data: &HashMap<String, Arc<TickerData>>, matches: &[ScanMatch]) -> HashMap<String, Vec<usize>> {
+    let mut bars: HashMap<String, Vec<usize>> = HashMap::new();
+    for m in matches {
+        let (Some(ticker_data), Some(day)) = (data.get(&m.ticker), parse_date(&m.date)) else {
+            continue;
+        };
+        let i = ticker_data.days.partition_point(|&d| d < day);
+        if ticker_data.days.get(i) == Some(&day) {
+            bars.entry(m.ticker.clone()).or_default().push(i);
+        }
+    }
+    for list in bars.values_mut() {
+        list.sort_unstable();
+        list.dedup();
+    }
+    bars
+}
+
+struct Position<'a> {
+    ticker: &'a str,
+    data: &'a TickerData,
+    entry_bar: usize,
+    entry_price: f64,
+    shares: f64,
+    last_close: f64,
+    /// Chandelier stop per bar of `data`, when a trailing stop is configured
+    trail: Option<Vec<f64>>,
+}
+
+impl Position<'_> {
+    fn bar_on(&self, day: NaiveDate) -> Option<usize> {
+        let i = self.data.days.partition_point(|&d| d < day);
+        (self.data.days.get(i) == Some(&day)).then_some(i)
+    }
+
+    fn close(&self, bar: usize, price: f64, reason: ExitReason) -> Trade {
+        let pnl = (price - self.entry_price) * self.shares;
+        Trade {
+            ticker: self.ticker.to_string(),
+            entry_date: self.data.date[self.entry_bar].clone(),
+            entry_price: self.entry_price,
+            exit_date: self.data.date[bar].clone(),
+            exit_price: price,
+            shares: self.shares,
+            pnl,
+            return_pct: (price / self.entry_price - 1.0) * 100.0,
+            bars_held: bar - self.entry_bar,
+            exit_reason: reason,
+        }
+    }
+}
+
+/// Event-driven simulation over the union of the signalled tickers' dates.
+///
+/// Each day runs: exits at the open (holding period, trailing stop broken at
+/// the prior close, prior-bar exit signal),
+/// entries at the open, intraday stop-loss/take-profit (stop first if both
+/// are hit), end-of-data exits at the close, then mark-to-market. A new signal
+/// for a ticker already held is skipped, as are signals once all slots are full.
+pub fn simulate(
+    data: &HashMap<String, Arc<TickerData>>,
+    entries: &HashMap<String, Vec<usize>>,
+    exits: &HashMap<String, HashSet<usize>>,
+    rules: &ExitRules,
+    sizing: &Sizing,
+) -> BacktestResult {
+    let mut signals_skipped = 0;
+
+    // Signal at bar b fills at the open of bar b + 1
+    let mut fills: BTreeMap<NaiveDate, Vec<(&str, &TickerData, usize)>> = BTreeMap::new();
+    for (ticker, bars) in entries {
+        let Some(ticker_data) = data.get(ticker) else {
+            continue;
+        };
+        for &bar in bars {
+            if bar + 1 < ticker_data.len() {
+                fills
+                    .entry(ticker_data.days[bar + 1])
+                    .or_default()
+                    .push((ticker, ticker_data, bar + 1));
+            } else {
+                signals_skipped += 1;
+            }
+        }
+    }
+
+    let Some(&first_day) = fills.keys().next() else {
+        return summarize(Vec::new(), Vec::new(), sizing.initial_capital, signals_skipped);
+    };
+
+    let timeline: BTreeSet<NaiveDate> = fills
+        .values()
+        .flatten()
+        .flat_map(|(_, d, _)| d.days.iter().copied().filter(|&day| day >= first_day))
+        .collect();
+
+    let max_positions = sizing.max_positions.max(1);
+    let mut cash = sizing.initial_capital;
+    let mut positions: Vec<Position> = Vec::new();
+    let mut trades = Vec::new();
+    let mut curve = Vec::new();
+
+    for day in timeline {
+        // Exits at the open
+        positions.retain(|p| {
+            let Some(i) = p.bar_on(day) else {
+                return true;
+            };
+            let hold_expired = rules.hold_bars.is_some_and(|h| i >= p.entry_bar + h);
+            let trail_hit = i > p.entry_bar
+                && p.trail.as_ref().is_some_and(|t| p.data.close[i - 1] < t[i - 1]);
+            let signalled = i > p.entry_bar
+                && exits.get(p.ticker).is_some_and(|bars| bars.contains(&(i - 1)));
+            let reason = if hold_expired {
+                ExitReason::HoldPeriod
+            } else if trail_hit {
+                ExitReason::TrailingStop
+            } else if signalled {
+                ExitReason::ExitSignal
+            } else {
+                return true;
+            };
+            let trade = p.close(i, p.data.open[i], reason);
+            cash += trade.exit_price * trade.shares;
+            trades.push(trade);
+            false
+        });
+
+        // Entries at the open, in ticker order for determinism
+        if let Some(todays) = fills.get_mut(&day) {
+            todays.sort_by(|a, b| a.0.cmp(b.0));
+            for &(ticker, ticker_data, bar) in todays.iter() {
+                let price = ticker_data.open[bar];
+                if positions.len() >= max_positions
+                    || positions.iter().any(|p| p.ticker == ticker)
+                    || price.is_nan() || price <= 0.0
+                {
+                    signals_skipped += 1;
+                    continue;
+                }
+                let equity = cash + positions.iter().map(|p| p.shares * p.last_close).sum::<f64>();
+                let allocation = (equity / max_positions as f64).min(cash);
+                if allocation.is_nan() || allocation <= 0.0 {
+                    signals_skipped += 1;
+                    continue;
+                }
+                cash -= allocation;
+                positions.push(Position {
+                    ticker,
+                    data: ticker_data,
+                    entry_bar: bar,
+                    entry_price: price,
+                    shares: allocation / price,
+                    last_close: price,
+                    trail: rules.trailing_stop.map(|t| {
+                        let d = ticker_data;
+                        chandelier_stop_from(&d.high, &d.low, &d.close, bar, t.period, t.multiplier)
+                    }),
+                });
+            }
+        }
+
+        // Intraday stops and targets, then end-of-data at the close
+        positions.retain_mut(|p| {
+            let Some(i) = p.bar_on(day) else {
+                return true;
+            };
+            let d = p.data;
+            let stop = rules.stop_loss_pct.map(|pct| p.entry_price * (1.0 - pct / 100.0));
+            let target = rules.take_profit_pct.map(|pct| p.entry_price * (1.0 + pct / 100.0));
+
+            let exit = if let Some(stop) = stop.filter(|&s| d.low[i] <= s) {
+                Some((stop.min(d.open[i]), ExitReason::StopLoss))
+            } else if let Some(target) = target.filter(|&t| d.high[i] >= t) {
+                Some((target.max(d.open[i]), ExitReason::TakeProfit))
+            } else if i + 1 == d.len() {
+                Some((d.close[i], ExitReason::EndOfData))
+            } else {
+                None
+            };
+
+            match exit {
+                Some((price, reason)) => {
+                    let trade = p.close(i, price, reason);
+                    cash += trade.exit_price * trade.shares;
+                    trades.push(trade);
+                    false
+                }
+                None => {
+                    p.last_close = d.close[i];
+                    true
+                }
+            }
+        });
+
+        let equity = cash + positions.iter().map(|p| p.shares * p.last_close).sum::<f64>();
+        curve.push(EquityPoint {
+            date: day.format("%Y-%m-%d").to_string(),
+            equity,
+        });
+    }
+
+    summarize(trades, curve, sizing.initial_capital, signals_skipped)
+}
+
+fn summarize(
+    trades: Vec<Trade>,
+    equity_curve: Vec<EquityPoint>,
+    initial_capital: f64,
+    signals_skipped: usize,
+) -> BacktestResult {
+    let final_equity = equity_curve.last().map_or(initial_capital, |p| p.equity);
+    let winning_trades = trades.iter().filter(|t| t.pnl > 0.0).count();
+    let gross_profit: f64 = trades.iter().filter(|t| t.pnl > 0.0).map(|t| t.pnl).sum();
+    let gross_loss: f64 = trades.iter().filter(|t| t.pnl < 0.0).map(|t| -t.pnl).sum();
+
+    let cagr_pct = match (equity_curve.first(), equity_curve.last()) {
+        (Some(first), Some(last)) => {
+            let days = parse_date(&last.date)
+                .zip(parse_date(&first.date))
+                .map_or(0, |(l, f)| (l - f).num_days());
+            (days > 0 && initial_capital > 0.0).then(|| {
+                let years = days as f64 / 365.25;
+                ((final_equity / initial_capital).powf(1.0 / years) - 1.0) * 100.0
+            })
+        }
+        _ => None,
+    };
+
+    let equity: Vec<f64> = equity_curve.iter().map(|p| p.equity).collect();
+
+    BacktestResult {
+        stats: BacktestStats {
+            total_trades: trades.len(),
+            winning_trades,
+            win_rate: if trades.is_empty() {
+                0.0
+            } else {
+                winning_trades as f64 / trades.len() as f64
+            },
+            total_return_pct: (final_equity / initial_capital - 1.0) * 100.0,
+            cagr_pct,
+            max_drawdown_pct: max_drawdown_pct(&equity),
+            profit_factor: (gross_loss > 0.0).then(|| gross_profit / gross_loss),
+            final_equity,
+            signals_skipped,
+        },
+        trades,
+        equity_curve,
+    }
+}
+
+/// Largest peak-to-trough decline, as a percent of the peak
+fn max_drawdown_pct(equity: &[f64]) -> f64 {
+    let mut peak = f64::NEG_INFINITY;
+    let mut worst = 0.0f64;
+    for &e in equity {
+        peak = peak.max(e);
+        if peak > 0.0 {
+            worst = worst.max((peak - e) / peak * 100.0);
+        }
+    }
+    worst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bars on consecutive January days with the given (open, high, low, close)
+    fn bars(ohlc: &[(f64, f64, f64, f64)]) -> Arc<TickerData> {
+        let n = ohlc.len();
+        Arc::new(TickerData::from_columns(
+            (0..n).map(|i| format!("2024-01-{:02}", i + 1)).collect(),
+            ohlc.iter().map(|b| b.0).collect(),
+            ohlc.iter().map(|b| b.1).collect(),
+            ohlc.iter().map(|b| b.2).collect(),
+            ohlc.iter().map(|b| b.3).collect(),
+            vec![1000.0; n],
+        ))
+    }
+
+    fn flat(n: usize, price: f64) -> Vec<(f64, f64, f64, f64)> {
+        vec![(price, price, price, price); n]
+    }
+
+    fn one_slot(capital: f64) -> Sizing {
+        Sizing {
+            initial_capital: capital,
+            max_positions: 1,
+        }
+    }
+
+    #[test]
+    fn test_hold_period_fills_next_open() {
+        let mut ohlc = flat(6, 10.0);
+        ohlc[2] = (11.0, 12.0, 10.5, 11.5); // entry bar: fills at 11
+        ohlc[4] = (13.2, 13.5, 13.0, 13.0); // exit bar: hold 2 -> open of bar 4
+        let data = HashMap::from([("AAA".to_string(), bars(&ohlc))]);
+        let entries = HashMap::from([("AAA".to_string(), vec![1])]);
+        let rules = ExitRules {
+            hold_bars: Some(2),
+            ..Default::default()
+        };
+
+        let result = simulate(&data, &entries, &HashMap::new(), &rules, &one_slot(1100.0));
+
+        assert_eq!(result.trades.len(), 1);
+        let t = &result.trades[0];
+        assert_eq!((t.entry_date.as_str(), t.exit_date.as_str()), ("2024-01-03", "2024-01-05"));
+        assert_eq!((t.entry_price, t.exit_price), (11.0, 13.2));
+        assert_eq!(t.bars_held, 2);
+        assert_eq!(t.exit_reason, ExitReason::HoldPeriod);
+        assert!((t.pnl - 220.0).abs() < 1e-9);
+
+        // Curve starts on the fill day and marks the open position at the close
+        let equity: Vec<f64> = result.equity_curve.iter().map(|p| p.equity).collect();
+        assert_eq!(equity.len(), 4);
+        assert!((equity[0] - 1150.0).abs() < 1e-9);
+        assert!((equity[1] - 1000.0).abs() < 1e-9);
+        assert!((result.stats.final_equity - 1320.0).abs() < 1e-9);
+        assert!((result.stats.max_drawdown_pct - 150.0 / 1150.0 * 100.0).abs() < 1e-9);
+        assert_eq!(result.stats.profit_factor, None);
+    }
+
+    #[test]
+    fn test_stop_loss_gap_fills_at_open() {
+        let mut ohlc = flat(5, 100.0);
+        ohlc[3] = (80.0, 85.0, 78.0, 84.0); // gaps through a 10% stop
+        let data = HashMap::from([("AAA".to_string(), bars(&ohlc))]);
+        let entries = HashMap::from([("AAA".to_string(), vec![0])]);
+        let rules = ExitRules {
+            stop_loss_pct: Some(10.0),
+            take_profit_pct: Some(50.0),
+            ..Default::default()
+        };
+
+        let result = simulate(&data, &entries, &HashMap::new(), &rules, &one_slot(1000.0));
+
+        let t = &result.trades[0];
+        assert_eq!(t.exit_reason, ExitReason::StopLoss);
+        assert_eq!(t.exit_price, 80.0);
+        assert_eq!(t.exit_date, "2024-01-04");
+        assert_eq!(result.stats.win_rate, 0.0);
+    }
+
+    #[test]
+    fn test_overlapping_signals_and_slots() {
+        let data = HashMap::from([
+            ("AAA".to_string(), bars(&flat(6, 10.0))),
+            ("BBB".to_string(), bars(&flat(6, 20.0))),
+        ]);
+        // AAA re-signals while held; BBB signals the same day as AAA with one slot
+        let entries = HashMap::from([
+            ("AAA".to_string(), vec![0, 1]),
+            ("BBB".to_string(), vec![0]),
+        ]);
+        let rules = ExitRules {
+            hold_bars: Some(3),
+            ..Default::default()
+        };
+
+        let result = simulate(&data, &entries, &HashMap::new(), &rules, &one_slot(1000.0));
+
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.trades[0].ticker, "AAA");
+        assert_eq!(result.stats.signals_skipped, 2);
+    }
+
+    #[test]
+    fn test_exit_signal_and_end_of_data() {
+        let mut ohlc = flat(5, 10.0);
+        ohlc[4] = (10.0, 10.0, 10.0, 12.0);
+        let data = HashMap::from([
+            ("AAA".to_string(), bars(&flat(5, 10.0))),
+            ("BBB".to_string(), bars(&ohlc)),
+        ]);
+        // Signals on the last bar can't fill
+        let entries = HashMap::from([
+            ("AAA".to_string(), vec![0, 4]),
+            ("BBB".to_string(), vec![0]),
+        ]);
+        let exits = HashMap::from([("AAA".to_string(), HashSet::from([0, 2]))]);
+        let rules = ExitRules {
+            exit_scan: Some("rsi_overbought".into()),
+            ..Default::default()
+        };
+        let sizing = Sizing {
+            initial_capital: 1000.0,
+            max_positions: 2,
+        };
+
+        let result = simulate(&data, &entries, &exits, &rules, &sizing);
+
+        let aaa = result.trades.iter().find(|t| t.ticker == "AAA").unwrap();
+        // Exit signal on the signal bar itself is ignored; bar 2's fires at bar 3's open
+        assert_eq!(aaa.exit_reason, ExitReason::ExitSignal);
+        assert_eq!(aaa.exit_date, "2024-01-04");
+
+        let bbb = result.trades.iter().find(|t| t.ticker == "BBB").unwrap();
+        assert_eq!(bbb.exit_reason, ExitReason::EndOfData);
+        assert_eq!(bbb.exit_price, 12.0);
+        assert_eq!(result.stats.signals_skipped, 1);
+        assert!((result.stats.final_equity - 1100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_trailing_stop_ratchets_from_entry() {
+        // Entry fills at bar 1; rallies to 20 then fades. 1-bar ATR is the bar's true range
+        let ohlc = [
+            (10.0, 10.5, 9.5, 10.0),
+            (10.0, 12.5, 9.5, 12.0),
+            (12.0, 16.5, 11.5, 16.0),
+            (16.0, 20.5, 15.5, 20.0),
+            (20.0, 20.5, 17.5, 18.0), // close 18 holds above 20.5 - 3.0
+            (18.0, 18.5, 16.5, 17.0), // stop ratchets to 20.5 - 2.0; close 17 breaks it
+            (16.0, 16.5, 15.5, 16.0), // exit at this open
+            (16.0, 16.5, 15.5, 16.0),
+        ];
+        let data = HashMap::from([("AAA".to_string(), bars(&ohlc))]);
+        let entries = HashMap::from([("AAA".to_string(), vec![0])]);
+        let rules = ExitRules {
+            trailing_stop: Some(TrailingStop {
+                period: 1,
+                multiplier: 1.0,
+            }),
+            ..Default::default()
+        };
+
+        let result = simulate(&data, &entries, &HashMap::new(), &rules, &one_slot(1000.0));
+
+        let t = &result.trades[0];
+        assert_eq!(t.exit_reason, ExitReason::TrailingStop);
+        assert_eq!(t.exit_date, "2024-01-07");
+        assert_eq!((t.entry_price, t.exit_price), (10.0, 16.0));
+    }
+
+    #[test]
+    fn test_max_drawdown() {
+        assert_eq!(max_drawdown_pct(&[]), 0.0);
+        assert_eq!(max_drawdown_pct(&[100.0, 120.0, 90.0, 130.0, 117.0]), 25.0);
+    }
+}
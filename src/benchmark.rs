@@ -0,0 +1,212 @@
+//! Benchmark-relative filters - beta and correlation of a ticker to an index
+//!
+//! Returns are compared on shared dates only: a ticker bar with no benchmark
+//! bar on the same day (or whose previous bar has none) has no return pair,
+//! and any window containing it is left undefined.
+
+use crate::data::{align_days, TickerData};
+use crate::indicators::{rolling_beta, rolling_correlation};
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// `correlation_filter` block of a scan query. Each bound is optional; a
+/// match is kept only when every given bound holds on its bar.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CorrelationFilter {
+    #[serde(default = "default_benchmark")]
+    pub benchmark: String,
+    #[serde(default = "default_period")]
+    pub period: usize,
+    pub min_beta: Option<f64>,
+    pub max_beta: Option<f64>,
+    pub min_correlation: Option<f64>,
+    pub max_correlation: Option<f64>,
+}
+
+fn default_benchmark() -> String {
+    "SPY".into()
+}
+
+fn default_period() -> usize {
+    60
+}
+
+impl CorrelationFilter {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.period < 2 {
+            return Err("correlation_filter.period must be at least 2".into());
+        }
+        let bounds = [self.min_beta, self.max_beta, self.min_correlation, self.max_correlation];
+        if bounds.iter().all(Option::is_none) {
+            return Err("correlation_filter needs at least one beta or correlation bound".into());
+        }
+        Ok(())
+    }
+
+    /// Per bar of `data`: whether its rolling beta/correlation to `bench` is in bounds
+    pub fn mask(&self, data: &TickerData, bench: &TickerData) -> Vec<bool> {
+        let (asset, market) = aligned_returns(data, bench);
+        let in_range = |v: f64, lo: Option<f64>, hi: Option<f64>| {
+            !v.is_nan() && lo.is_none_or(|lo| v >= lo) && hi.is_none_or(|hi| v <= hi)
+        };
+
+        let beta = (self.min_beta.is_some() || self.max_beta.is_some())
+            .then(|| rolling_beta(&asset, &market, self.period));
+        let corr = (self.min_correlation.is_some() || self.max_correlation.is_some())
+            .then(|| rolling_correlation(&asset, &market, self.period));
+
+        (0..data.len())
+            .map(|i| {
+                beta.as_ref().is_none_or(|b| in_range(b[i], self.min_beta, self.max_beta))
+                    && corr
+                        .as_ref()
+                        .is_none_or(|c| in_range(c[i], self.min_correlation, self.max_correlation))
+            })
+            .collect()
+    }
+}
+
+/// One-bar returns of `data` and of `bench` over the same interval, per bar of `data`
+pub fn aligned_returns(data: &TickerData, bench: &TickerData) -> (Vec<f64>, Vec<f64>) {
+    let bench_idx = align_days(&data.days, &bench.days);
+    let n = data.len();
+    let mut asset = vec![f64::NAN; n];
+    let mut market = vec![f64::NAN; n];
+
+    for i in 1..n {
+        let (Some(j0), Some(j1)) = (bench_idx[i - 1], bench_idx[i]) else {
+            continue;
+        };
+        if data.close[i - 1] != 0.0 && bench.close[j0] != 0.0 {
+            asset[i] = data.close[i] / data.close[i - 1] - 1.0;
+            market[i] = bench.close[j1] / bench.close[j0] - 1.0;
+        }
+    }
+
+    (asset, market)
+}
+
+/// Pairwise return correlations over a window of days every series shares
+#[derive(Debug)]
+pub struct CorrelationMatrix {
+    /// Day before the first return in the window, and the last day
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// `matrix[i][j]` correlates `series[i]` with `series[j]`; NaN when either is flat
+    pub matrix: Vec<Vec<f64>>,
+}
+
+/// Correlation of one-bar returns over the last `lookback` returns on the
+/// days all of `series` have. None when they share fewer than `lookback + 1` days.
+pub fn correlation_matrix(series: &[&TickerData], lookback: usize) -> Option<CorrelationMatrix> {
+    let mut common = series.first()?.days.clone();
+    for data in &series[1..] {
+        let present = align_days(&common, &data.days);
+        common = common.into_iter().zip(present).filter_map(|(day, j)| j.map(|_| day)).collect();
+    }
+    if lookback < 2 || common.len() < lookback + 1 {
+        return None;
+    }
+    let window = &common[common.len() - lookback - 1..];
+
+    let returns: Vec<Vec<f64>> = series
+        .iter()
+        .map(|data| {
+            let closes: Vec<f64> = align_days(window, &data.days).into_iter().flatten().map(|j| data.close[j]).collect();
+            closes
+                .windows(2)
+                .map(|w| if w[0] != 0.0 { w[1] / w[0] - 1.0 } else { f64::NAN })
+                .collect()
+        })
+        .collect();
+
+    let n = series.len();
+    let mut matrix = vec![vec![f64::NAN; n]; n];
+    for i in 0..n {
+        for j in i..n {
+            let corr = rolling_correlation(&returns[i], &returns[j], lookback)[lookback - 1];
+            matrix[i][j] = corr;
+            matrix[j][i] = corr;
+        }
+    }
+
+    Some(CorrelationMatrix { from: window[0], to: window[lookback], matrix })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(dates: &[&str], close: &[f64]) -> TickerData {
+        let n = close.len();
+        TickerData::from_columns(
+            dates.iter().map(|d| d.to_string()).collect(),
+            close.to_vec(),
+            close.to_vec(),
+            close.to_vec(),
+            close.to_vec(),
+            vec![100.0; n],
+        )
+    }
+
+    #[test]
+    fn test_beta_filter_on_aligned_dates() {
+        let bench = series(
+            &["2024-01-02", "2024-01-03", "2024-01-04", "2024-01-05", "2024-01-08", "2024-01-09"],
+            &[100.0, 101.0, 99.0, 102.0, 100.0, 103.0],
+        );
+        // Skips Jan 4; each return is 2× the benchmark's over the same span
+        let bench_ret = |a: f64, b: f64| b / a - 1.0;
+        let mut close = vec![50.0];
+        for (a, b) in [(100.0, 101.0), (101.0, 102.0), (102.0, 100.0), (100.0, 103.0)] {
+            let last = *close.last().unwrap();
+            close.push(last * (1.0 + 2.0 * bench_ret(a, b)));
+        }
+        let ticker = series(&["2024-01-02", "2024-01-03", "2024-01-05", "2024-01-08", "2024-01-09"], &close);
+
+        let (asset, market) = aligned_returns(&ticker, &bench);
+        assert!(asset[0].is_nan());
+        // Jan 3 -> Jan 5 spans the missing Jan 4 on the ticker side only
+        assert!((market[2] - (102.0 / 101.0 - 1.0)).abs() < 1e-12);
+
+        let filter = CorrelationFilter {
+            benchmark: "SPY".into(),
+            period: 3,
+            min_beta: Some(1.5),
+            max_beta: Some(2.5),
+            min_correlation: Some(0.99),
+            max_correlation: None,
+        };
+        assert_eq!(filter.mask(&ticker, &bench), [false, false, false, true, true]);
+
+        let low_beta = CorrelationFilter { max_beta: Some(1.0), min_beta: None, ..filter };
+        assert!(low_beta.mask(&ticker, &bench).iter().all(|&m| !m));
+    }
+
+    #[test]
+    fn test_correlation_matrix_on_shared_days() {
+        let days = ["2024-01-02", "2024-01-03", "2024-01-04", "2024-01-05", "2024-01-08", "2024-01-09"];
+        let a = series(&days, &[100.0, 101.0, 99.0, 102.0, 100.0, 103.0]);
+        // Mirrors a's moves, so perfectly anti-correlated on the same days
+        let b = series(&days, &[100.0, 99.0, 101.0, 98.0, 100.0, 97.0]);
+        // Missing Jan 4: the shared window skips it for every series
+        let c = series(
+            &["2024-01-02", "2024-01-03", "2024-01-05", "2024-01-08", "2024-01-09"],
+            &[10.0, 10.1, 10.2, 10.0, 10.3],
+        );
+
+        let result = correlation_matrix(&[&a, &b], 3).unwrap();
+        assert_eq!(result.from.to_string(), "2024-01-04");
+        assert_eq!(result.to.to_string(), "2024-01-09");
+        assert!((result.matrix[0][0] - 1.0).abs() < 1e-12);
+        assert!(result.matrix[0][1] < -0.99 && result.matrix[0][1] == result.matrix[1][0]);
+
+        let shared = correlation_matrix(&[&a, &c], 4).unwrap();
+        assert_eq!(shared.from.to_string(), "2024-01-02");
+        assert!(correlation_matrix(&[&a, &c], 5).is_none());
+
+        let flat = series(&days, &[5.0; 6]);
+        assert!(correlation_matrix(&[&a, &flat], 3).unwrap().matrix[0][1].is_nan());
+    }
+}
@@ -0,0 +1,125 @@
+//! Market breadth - per-day counts across the loaded universe
+//!
+//! Each ticker contributes to the days it has a bar for: whether the bar set
+//! a new `lookback`-bar high or low, and whether it closed above its 200-day
+//! SMA. Intraday tickers are aggregated to daily bars first.
+
+use crate::data::{TickerData, Timeframe};
+use crate::indicators::{higher_high, lower_low, sma};
+use crate::resample::{resample, Interval};
+use chrono::NaiveDate;
+use rayon::prelude::*;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Period of the SMA the `above_sma` count compares against
+pub const BREADTH_SMA_PERIOD: usize = 200;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub struct BreadthPoint {
+    pub date: String,
+    /// Tickers with a bar on this day
+    pub tickers: usize,
+    /// High above the prior `lookback` bars' highest high
+    pub new_highs: usize,
+    /// Low below the prior `lookback` bars' lowest low
+    pub new_lows: usize,
+    /// Close above the 200-day SMA
+    pub above_sma: usize,
+    /// Tickers with enough history for a 200-day SMA on this day
+    pub with_sma: usize,
+}
+
+/// Breadth series over `data`, one point per day any ticker has a bar for,
+/// limited to `from..=to`
+pub fn breadth(
+    data: &HashMap<String, Arc<TickerData>>,
+    lookback: usize,
+    (from, to): (Option<NaiveDate>, Option<NaiveDate>),
+) -> Vec<BreadthPoint> {
+    let in_range = |day: NaiveDate| from.is_none_or(|f| day >= f) && to.is_none_or(|t| day <= t);
+
+    let per_ticker: Vec<Vec<(NaiveDate, [bool; 4])>> = data
+        .par_iter()
+        .map(|(_, data)| {
+            let daily;
+            let data = match data.timeframe {
+                Timeframe::Intraday => {
+                    daily = resample(data, Interval::Daily);
+                    &daily
+                }
+                Timeframe::Daily => data.as_ref(),
+            };
+            let highs = higher_high(&data.high, lookback);
+            let lows = lower_low(&data.low, lookback);
+            let average = sma(&data.close, BREADTH_SMA_PERIOD);
+            (0..data.len())
+                .filter(|&i| in_range(data.days[i]))
+                .map(|i| {
+                    let has_sma = !average[i].is_nan();
+                    (data.days[i], [highs[i], lows[i], has_sma && data.close[i] > average[i], has_sma])
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut days: BTreeMap<NaiveDate, BreadthPoint> = BTreeMap::new();
+    for (day, [high, low, above, has_sma]) in per_ticker.into_iter().flatten() {
+        let point = days.entry(day).or_default();
+        point.tickers += 1;
+        point.new_highs += high as usize;
+        point.new_lows += low as usize;
+        point.above_sma += above as usize;
+        point.with_sma += has_sma as usize;
+    }
+
+    days.into_iter()
+        .map(|(day, point)| BreadthPoint { date: day.format("%Y-%m-%d").to_string(), ..point })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(start: &str, close: &[f64]) -> Arc<TickerData> {
+        let start = NaiveDate::parse_from_str(start, "%Y-%m-%d").unwrap();
+        let dates = (0..close.len())
+            .map(|i| (start + chrono::Duration::days(i as i64)).format("%Y-%m-%d").to_string())
+            .collect();
+        Arc::new(TickerData::from_columns(
+            dates,
+            close.to_vec(),
+            close.to_vec(),
+            close.to_vec(),
+            close.to_vec(),
+            vec![100.0; close.len()],
+        ))
+    }
+
+    #[test]
+    fn test_counts_highs_lows_and_sma_per_day() {
+        let rising: Vec<f64> = (0..205).map(|i| 100.0 + i as f64).collect();
+        let falling: Vec<f64> = (0..205).map(|i| 400.0 - i as f64).collect();
+        let data = HashMap::from([
+            ("UP".to_string(), series("2024-01-01", &rising)),
+            ("DOWN".to_string(), series("2024-01-01", &falling)),
+            // Starts a day late, so the first day has only two tickers
+            ("LATE".to_string(), series("2024-01-02", &[50.0, 50.0, 60.0])),
+        ]);
+
+        let points = breadth(&data, 2, (None, None));
+        assert_eq!(points.len(), 205);
+        assert_eq!(points[0], BreadthPoint { date: "2024-01-01".into(), tickers: 2, ..Default::default() });
+        // Day 3: UP and LATE make new 2-bar highs, DOWN a new low
+        assert_eq!((points[3].tickers, points[3].new_highs, points[3].new_lows), (3, 2, 1));
+        // The SMA is defined from the 200th bar; only the rising ticker is above it
+        assert_eq!(points[198].with_sma, 0);
+        assert_eq!((points[199].with_sma, points[199].above_sma), (2, 1));
+
+        let ranged = breadth(&data, 2, (NaiveDate::from_ymd_opt(2024, 1, 3), NaiveDate::from_ymd_opt(2024, 1, 4)));
+        assert_eq!(ranged.iter().map(|p| p.date.as_str()).collect::<Vec<_>>(), ["2024-01-03", "2024-01-04"]);
+    }
+}
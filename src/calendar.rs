@@ -0,0 +1,145 @@
+//! Calendar lookbacks - `"6m"` or `"52w"` windows resolved against each
+//! ticker's own dates
+//!
+//! A bar count covers the same number of bars on every ticker. A calendar
+//! span covers whatever bars a ticker has in that stretch of time, so a data
+//! gap (a halt, a late listing) shortens the window instead of pushing it
+//! further back in time.
+
+use chrono::{Duration, Months, NaiveDate};
+use serde_json::Value;
+use std::fmt;
+
+/// A lookback param: a number of bars or a calendar span
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookback {
+    Bars(usize),
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+}
+
+impl Lookback {
+    /// A bar count (a number or numeric string), or a span: a count followed
+    /// by `d`, `w`, `m`/`mo` or `y` (`"90d"`, `"52w"`, `"6m"`, `"1y"`)
+    pub fn parse(value: &Value) -> Result<Self, String> {
+        if let Some(bars) = value.as_u64() {
+            return Ok(Self::Bars(bars as usize));
+        }
+        let text = value.as_str().ok_or_else(|| format!("expected a bar count or a span like \"6m\", got {}", value))?;
+        let text = text.trim().to_lowercase();
+        let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+        let (count, unit) = text.split_at(split);
+        let count: u32 = count
+            .parse()
+            .map_err(|_| format!("expected a bar count or a span like \"6m\", got {:?}", text))?;
+        match unit.trim() {
+            "" => Ok(Self::Bars(count as usize)),
+            "d" => Ok(Self::Days(count)),
+            "w" => Ok(Self::Weeks(count)),
+            "m" | "mo" => Ok(Self::Months(count)),
+            "y" => Ok(Self::Years(count)),
+            other => Err(format!("unknown lookback unit {:?} (use d, w, m or y)", other)),
+        }
+    }
+
+    /// The bar count or span's number, for bounds checks
+    pub fn count(&self) -> usize {
+        match *self {
+            Self::Bars(n) => n,
+            Self::Days(n) | Self::Weeks(n) | Self::Months(n) | Self::Years(n) => n as usize,
+        }
+    }
+
+    /// The day the span ending on `day` reaches back to; None for bar counts
+    /// (and dates out of chrono's range)
+    fn anchor(&self, day: NaiveDate) -> Option<NaiveDate> {
+        match *self {
+            Self::Bars(_) => None,
+            Self::Days(n) => day.checked_sub_signed(Duration::days(n as i64)),
+            Self::Weeks(n) => day.checked_sub_signed(Duration::weeks(n as i64)),
+            Self::Months(n) => day.checked_sub_months(Months::new(n)),
+            Self::Years(n) => day.checked_sub_months(Months::new(n.saturating_mul(12))),
+        }
+    }
+
+    /// Per bar `i`, where the window ending on it (inclusive) starts. For a
+    /// span that's the first bar dated after the anchor, so "3m" on May 15
+    /// covers Feb 16 through May 15. None while the history is too short: too
+    /// few bars, or a first bar later than the anchor. `days` must be sorted.
+    pub fn window_starts(&self, days: &[NaiveDate]) -> Vec<Option<usize>> {
+        match *self {
+            Self::Bars(n) => (0..days.len()).map(|i| (n > 0 && i + 1 >= n).then(|| i + 1 - n)).collect(),
+            _ => days
+                .iter()
+                .enumerate()
+                .map(|(i, &day)| {
+                    let anchor = self.anchor(day)?;
+                    (days.first()? <= &anchor).then(|| days[..=i].partition_point(|&d| d <= anchor))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Lookback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bars(n) => write!(f, "{}", n),
+            Self::Days(n) => write!(f, "{}d", n),
+            Self::Weeks(n) => write!(f, "{}w", n),
+            Self::Months(n) => write!(f, "{}m", n),
+            Self::Years(n) => write!(f, "{}y", n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Lookback::parse(&Value::from(20)), Ok(Lookback::Bars(20)));
+        assert_eq!(Lookback::parse(&Value::from("20")), Ok(Lookback::Bars(20)));
+        assert_eq!(Lookback::parse(&Value::from("6M")), Ok(Lookback::Months(6)));
+        assert_eq!(Lookback::parse(&Value::from("6 mo")), Ok(Lookback::Months(6)));
+        assert_eq!(Lookback::parse(&Value::from("52w")), Ok(Lookback::Weeks(52)));
+        assert_eq!(Lookback::Years(1).to_string(), "1y");
+        assert!(Lookback::parse(&Value::from("6x")).is_err());
+        assert!(Lookback::parse(&Value::from("m")).is_err());
+        assert!(Lookback::parse(&Value::from(2.5)).is_err());
+    }
+
+    #[test]
+    fn test_window_starts_follow_dates() {
+        // Weekdays in January, then a gap until March
+        let mut days: Vec<NaiveDate> = (1..=31)
+            .map(|d| day("2024-01-01") + Duration::days(d - 1))
+            .filter(|d| chrono::Datelike::weekday(d).number_from_monday() <= 5)
+            .collect();
+        days.extend(["2024-03-01", "2024-03-04", "2024-03-05"].map(day));
+        let n = days.len();
+
+        let bars = Lookback::Bars(3).window_starts(&days);
+        assert_eq!(bars[..3], [None, None, Some(0)]);
+        assert_eq!(bars[n - 1], Some(n - 3));
+
+        // One week back from Jan 10 reaches Jan 3, so the window starts on Jan 4
+        let week = Lookback::Weeks(1).window_starts(&days);
+        assert_eq!(week[..5], [None; 5]);
+        assert_eq!(week[5], Some(1));
+        assert_eq!(week[7].map(|s| days[s]), Some(day("2024-01-04")));
+        // Across the gap the window shrinks to the bars actually in it
+        assert_eq!(week[n - 1].map(|s| days[s]), Some(day("2024-03-01")));
+        let month = Lookback::Months(1).window_starts(&days);
+        assert_eq!(month[n - 3].map(|s| days[s]), Some(day("2024-03-01")));
+        assert_eq!(month[n - 4], None);
+        assert!(Lookback::Years(1).window_starts(&days).iter().all(Option::is_none));
+    }
+}
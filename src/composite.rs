@@ -0,0 +1,291 @@
+//! Composite filters - declarative per-bar indicator conditions, ANDed
+//!
+//! Each filter compares an indicator (any `overlays` name, or a raw price
+//! column) against a number or a second indicator:
+//!
+//! ```json
+//! { "indicator": "rsi", "period": 14, "op": "<", "value": 40 }
+//! { "indicator": "close", "op": ">", "value": { "indicator": "sma", "period": 50 } }
+//! ```
+
+use crate::data::TickerData;
+use crate::overlays::{self, OVERLAY_NAMES};
+use crate::scratch::{self, above, below, crossed_above, crossed_above_level, crossed_below, crossed_below_level};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Price columns usable as an indicator as-is
+const PRICE_FIELDS: &[&str] = &["open", "high", "low", "close", "volume"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Filter {
+    #[serde(flatten)]
+    pub series: SeriesRef,
+    pub op: Op,
+    pub value: Operand,
+}
+
+/// An indicator and its period (the indicator's own default when omitted)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SeriesRef {
+    pub indicator: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Op {
+    #[serde(rename = ">")]
+    Above,
+    #[serde(rename = "<")]
+    Below,
+    #[serde(rename = "crosses_above")]
+    CrossesAbove,
+    #[serde(rename = "crosses_below")]
+    CrossesBelow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Operand {
+    Number(f64),
+    Series(SeriesRef),
+}
+
+impl fmt::Display for SeriesRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.period {
+            Some(period) => write!(f, "{}_{}", self.indicator, period),
+            None => f.write_str(&self.indicator),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            Op::Above => ">",
+            Op::Below => "<",
+            Op::CrossesAbove => "crosses_above",
+            Op::CrossesBelow => "crosses_below",
+        };
+        match &self.value {
+            Operand::Number(n) => write!(f, "{} {} {}", self.series, op, n),
+            Operand::Series(other) => write!(f, "{} {} {}", self.series, op, other),
+        }
+    }
+}
+
+impl SeriesRef {
+    /// A `series` scan param: `{indicator, period}`, that object as a JSON
+    /// string, or the shorthand `"ema(9)"` (or just `"close"`)
+    pub fn parse(value: &Value) -> Result<Self, String> {
+        let series: Self = match value {
+            Value::String(s) if s.trim_start().starts_with('{') => {
+                serde_json::from_str(s).map_err(|e| format!("invalid JSON: {}", e))?
+            }
+            Value::String(s) => {
+                let s = s.trim().to_lowercase();
+                let (indicator, period) = match s.strip_suffix(')').and_then(|s| s.split_once('(')) {
+                    Some((name, period)) => {
+                        let period = period.trim().parse().map_err(|_| format!("invalid period in {:?}", s))?;
+                        (name.trim().to_string(), Some(period))
+                    }
+                    None => (s, None),
+                };
+                Self { indicator, period }
+            }
+            _ => serde_json::from_value(value.clone()).map_err(|e| e.to_string())?,
+        };
+        series.validate()?;
+        Ok(series)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let name = self.indicator.as_str();
+        if !PRICE_FIELDS.contains(&name) && !OVERLAY_NAMES.contains(&name) {
+            return Err(format!(
+                "unknown indicator {:?} (expected one of {}, {})",
+                name,
+                PRICE_FIELDS.join(", "),
+                OVERLAY_NAMES.join(", ")
+            ));
+        }
+        if self.period == Some(0) {
+            return Err(format!("{}: period must be at least 1", name));
+        }
+        Ok(())
+    }
+
+    /// The series over all of `data`; multi-series indicators give their first
+    /// (`macd` line, `bollinger` middle band, chandelier `long`)
+    pub fn compute(&self, data: &TickerData) -> Vec<f64> {
+        if PRICE_FIELDS.contains(&self.indicator.as_str()) {
+            return scratch::copy(match self.indicator.as_str() {
+                "open" => &data.open,
+                "high" => &data.high,
+                "low" => &data.low,
+                "volume" => &data.volume,
+                _ => &data.close,
+            });
+        }
+        let params: HashMap<String, String> =
+            self.period.map(|p| ("period".to_string(), p.to_string())).into_iter().collect();
+        overlays::compute(data, &self.indicator, &params)
+            .ok()
+            .and_then(|series| series.into_iter().next())
+            .map_or_else(|| vec![f64::NAN; data.len()], |(_, values)| values)
+    }
+}
+
+impl SeriesRef {
+    /// Bars before the series settles: its period (or the overlay's default
+    /// one), longer for indicators built from several EMAs
+    pub fn warmup_bars(&self) -> usize {
+        let period = |default| self.period.unwrap_or(default);
+        match self.indicator.as_str() {
+            "open" | "high" | "low" | "close" | "volume" => 0,
+            "true_range" | "close_location" | "obv" | "vwap" | "streak" => 1,
+            "macd" | "macd_histogram" | "ppo" | "elder_impulse" => 26 + 9,
+            "trix" => 3 * period(15),
+            "ultimate_oscillator" => 28,
+            "pct_change" => period(1),
+            "rsi" | "cmo" | "atr" | "vortex" | "ease_of_movement" => period(14),
+            "elder_ray" | "force_index" => period(13),
+            "chandelier_exit" => period(22),
+            _ => period(20),
+        }
+    }
+}
+
+impl Filter {
+    /// Bars before the filter can first hold: the slower side's warm-up, plus
+    /// the prior bar a cross compares against
+    fn warmup_bars(&self) -> usize {
+        let right = match &self.value {
+            Operand::Series(other) => other.warmup_bars(),
+            Operand::Number(_) => 0,
+        };
+        let cross = matches!(self.op, Op::CrossesAbove | Op::CrossesBelow) as usize;
+        self.series.warmup_bars().max(right) + cross
+    }
+
+    /// Which bars pass, plus the series compared (for `explain`)
+    pub fn evaluate(&self, data: &TickerData) -> (Vec<bool>, Vec<(String, Vec<f64>)>) {
+        let left = self.series.compute(data);
+        match &self.value {
+            Operand::Number(level) => {
+                let mask = match self.op {
+                    Op::Above => above(&left, *level),
+                    Op::Below => below(&left, *level),
+                    Op::CrossesAbove => crossed_above_level(&left, *level),
+                    Op::CrossesBelow => crossed_below_level(&left, *level),
+                };
+                (mask, vec![(self.series.to_string(), left)])
+            }
+            Operand::Series(other) => {
+                let right = other.compute(data);
+                let compare = |test: fn(f64, f64) -> bool| {
+                    scratch::bool_with(|out| {
+                        out.clear();
+                        out.extend(left.iter().zip(&right).map(|(&a, &b)| test(a, b)));
+                    })
+                };
+                let mask = match self.op {
+                    Op::Above => compare(|a, b| a > b),
+                    Op::Below => compare(|a, b| a < b),
+                    Op::CrossesAbove => crossed_above(&left, &right),
+                    Op::CrossesBelow => crossed_below(&left, &right),
+                };
+                (mask, vec![(self.series.to_string(), left), (other.to_string(), right)])
+            }
+        }
+    }
+}
+
+/// The `filters` param of a `composite` scan; errors name the offending entry
+pub fn parse_filters(value: &Value) -> Result<Vec<Filter>, String> {
+    let entries = value.as_array().ok_or("filters: expected an array")?;
+    if entries.is_empty() {
+        return Err("filters: needs at least one filter".into());
+    }
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let filter: Filter =
+                serde_json::from_value(entry.clone()).map_err(|e| format!("filters[{}]: {}", i, e))?;
+            filter.series.validate().map_err(|e| format!("filters[{}]: {}", i, e))?;
+            match &filter.value {
+                Operand::Series(other) => other.validate().map_err(|e| format!("filters[{}].value: {}", i, e))?,
+                Operand::Number(n) if !n.is_finite() => return Err(format!("filters[{}]: value must be finite", i)),
+                Operand::Number(_) => {}
+            }
+            Ok(filter)
+        })
+        .collect()
+}
+
+/// Warm-up of a `composite` scan: its slowest filter's
+pub fn warmup_bars(params: &HashMap<String, Value>) -> usize {
+    params
+        .get("filters")
+        .and_then(|v| parse_filters(v).ok())
+        .map_or(0, |filters| filters.iter().map(Filter::warmup_bars).max().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::generate_sample_data;
+    use crate::indicators::{rsi, sma};
+
+    #[test]
+    fn test_parse_and_evaluate_filters() {
+        let filters = parse_filters(&serde_json::json!([
+            { "indicator": "rsi", "period": 14, "op": "<", "value": 40 },
+            { "indicator": "close", "op": "crosses_above", "value": { "indicator": "sma", "period": 50 } },
+        ]))
+        .unwrap();
+        assert_eq!(filters[0].to_string(), "rsi_14 < 40");
+        assert_eq!(filters[1].to_string(), "close crosses_above sma_50");
+
+        let data = generate_sample_data(300);
+        let (mask, series) = filters[0].evaluate(&data);
+        let expected: Vec<bool> = rsi(&data.close, 14).iter().map(|&v| v < 40.0).collect();
+        assert_eq!(mask, expected);
+        assert_eq!(series[0].0, "rsi_14");
+
+        let (mask, series) = filters[1].evaluate(&data);
+        assert_eq!(mask, crossed_above(&data.close, &sma(&data.close, 50)));
+        assert_eq!(series.len(), 2);
+
+        for bad in [
+            serde_json::json!([]),
+            serde_json::json!({ "indicator": "rsi" }),
+            serde_json::json!([{ "indicator": "nope", "op": ">", "value": 1 }]),
+            serde_json::json!([{ "indicator": "rsi", "op": ">=", "value": 1 }]),
+            serde_json::json!([{ "indicator": "sma", "period": 0, "op": ">", "value": 1 }]),
+            serde_json::json!([{ "indicator": "close", "op": ">", "value": { "indicator": "wma" } }]),
+        ] {
+            assert!(parse_filters(&bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_series() {
+        let ema_9 = SeriesRef::parse(&serde_json::json!("EMA(9)")).unwrap();
+        assert_eq!((ema_9.indicator.as_str(), ema_9.period), ("ema", Some(9)));
+        assert_eq!(SeriesRef::parse(&serde_json::json!("close")).unwrap().to_string(), "close");
+        let object = serde_json::json!({ "indicator": "sma", "period": 50 });
+        assert_eq!(SeriesRef::parse(&object).unwrap().to_string(), "sma_50");
+        assert_eq!(SeriesRef::parse(&object.to_string().into()).unwrap().to_string(), "sma_50");
+        for bad in [serde_json::json!("wma(9)"), serde_json::json!("ema(x)"), serde_json::json!("sma(0)"), serde_json::json!(9)] {
+            assert!(SeriesRef::parse(&bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -1,20 +1,45 @@
 //! Data loading and storage
 
+use crate::events::{EventStore, TickerEvents};
+use chrono::{NaiveDate, NaiveDateTime};
 use polars::prelude::*;
-use std::collections::HashMap;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
 
+/// Granularity of a ticker's source bars
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Timeframe {
+    /// One bar per day; `date` is `YYYY-MM-DD` as loaded
+    #[default]
+    Daily,
+    /// Several bars per day; `date` is an RFC 3339 UTC timestamp
+    /// (`YYYY-MM-DDTHH:MM:SSZ`), so timestamps sort and compare as strings
+    Intraday,
+}
+
 /// Raw ticker data in memory-efficient format
 #[derive(Debug, Clone)]
 pub struct TickerData {
     pub date: Vec<String>,
+    /// `date` parsed once at load, for range filters and bucketing. Intraday
+    /// bars share their day's entry.
+    pub days: Vec<NaiveDate>,
     pub open: Vec<f64>,
     pub high: Vec<f64>,
     pub low: Vec<f64>,
     pub close: Vec<f64>,
     pub volume: Vec<f64>,
+    /// This ticker's entries from the event store, attached at load
+    pub events: Arc<TickerEvents>,
+    pub timeframe: Timeframe,
+    /// Split- and dividend-adjusted close, when the source file has one;
+    /// `close` and the other prices stay as traded (see `adjusted`)
+    pub adj_close: Option<Vec<f64>>,
 }
 
 impl TickerData {
@@ -25,8 +50,222 @@ impl TickerData {
     pub fn is_empty(&self) -> bool {
         self.close.is_empty()
     }
+
+    /// Copy of the bars in `range`
+    pub fn slice(&self, range: std::ops::Range<usize>) -> TickerData {
+        TickerData {
+            date: self.date[range.clone()].to_vec(),
+            days: self.days[range.clone()].to_vec(),
+            open: self.open[range.clone()].to_vec(),
+            high: self.high[range.clone()].to_vec(),
+            low: self.low[range.clone()].to_vec(),
+            close: self.close[range.clone()].to_vec(),
+            volume: self.volume[range.clone()].to_vec(),
+            events: self.events.clone(),
+            timeframe: self.timeframe,
+            adj_close: self.adj_close.as_ref().map(|adj| adj[range].to_vec()),
+        }
+    }
+
+    /// Build from loaded columns, parsing dates and dropping rows whose date
+    /// can't be parsed (they could never match a range filter anyway). Rows
+    /// are sorted by date, since every scan and resample assumes that order.
+    /// Timestamped rows with several per day are intraday bars, and keep
+    /// their time normalized to RFC 3339 UTC.
+    pub fn from_columns(
+        date: Vec<String>,
+        open: Vec<f64>,
+        high: Vec<f64>,
+        low: Vec<f64>,
+        close: Vec<f64>,
+        volume: Vec<f64>,
+    ) -> Self {
+        Self::from_columns_adjusted(date, open, high, low, close, volume, None)
+    }
+
+    /// `from_columns` with an adjusted close column, kept aligned through
+    /// the same row drops and sorting
+    pub fn from_columns_adjusted(
+        date: Vec<String>,
+        open: Vec<f64>,
+        high: Vec<f64>,
+        low: Vec<f64>,
+        close: Vec<f64>,
+        volume: Vec<f64>,
+        adj_close: Option<Vec<f64>>,
+    ) -> Self {
+        let parsed: Vec<Option<NaiveDate>> = date.iter().map(|d| parse_date(d)).collect();
+
+        let mut data = if parsed.iter().all(Option::is_some) {
+            let days = parsed.into_iter().flatten().collect();
+            TickerData {
+                date,
+                days,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                events: Default::default(),
+                timeframe: Timeframe::Daily,
+                adj_close,
+            }
+        } else {
+            let keep: Vec<usize> = (0..parsed.len()).filter(|&i| parsed[i].is_some()).collect();
+            tracing::warn!("Dropping {} rows with unparseable dates", parsed.len() - keep.len());
+            TickerData {
+                date: keep.iter().map(|&i| date[i].clone()).collect(),
+                days: keep.iter().filter_map(|&i| parsed[i]).collect(),
+                open: keep.iter().map(|&i| open[i]).collect(),
+                high: keep.iter().map(|&i| high[i]).collect(),
+                low: keep.iter().map(|&i| low[i]).collect(),
+                close: keep.iter().map(|&i| close[i]).collect(),
+                volume: keep.iter().map(|&i| volume[i]).collect(),
+                events: Default::default(),
+                timeframe: Timeframe::Daily,
+                adj_close: adj_close.map(|adj| keep.iter().map(|&i| adj[i]).collect()),
+            }
+        };
+
+        // Daily files sometimes stamp each bar with a close time; only a
+        // repeated day makes the series intraday
+        let mut seen = HashSet::new();
+        let repeated_day = !data.days.iter().all(|d| seen.insert(*d));
+        if repeated_day {
+            if let Some(stamps) = data.date.iter().map(|d| parse_timestamp(d)).collect::<Option<Vec<_>>>() {
+                data.timeframe = Timeframe::Intraday;
+                data.days = stamps.iter().map(|t| t.date()).collect();
+                data.date = stamps.iter().map(|t| t.format(TIMESTAMP_FORMAT).to_string()).collect();
+            }
+        }
+
+        if data.is_sorted() {
+            return data;
+        }
+        tracing::warn!("Sorting {} rows that were out of date order", data.len());
+        let mut order: Vec<usize> = (0..data.len()).collect();
+        // Stable, so same-day (or same-time) rows keep their file order
+        match data.timeframe {
+            Timeframe::Daily => order.sort_by_key(|&i| data.days[i]),
+            Timeframe::Intraday => order.sort_by(|&a, &b| data.date[a].cmp(&data.date[b])),
+        }
+        data.select(&order)
+    }
+
+    /// Whether the bars ascend (ties allowed), as `from_columns` guarantees:
+    /// by day, or by full timestamp when intraday
+    pub fn is_sorted(&self) -> bool {
+        match self.timeframe {
+            Timeframe::Daily => self.days.is_sorted(),
+            Timeframe::Intraday => self.date.is_sorted(),
+        }
+    }
+
+    /// Build a new series from the bars at `indices`, in the given order
+    pub fn select(&self, indices: &[usize]) -> TickerData {
+        TickerData {
+            date: indices.iter().map(|&i| self.date[i].clone()).collect(),
+            days: indices.iter().map(|&i| self.days[i]).collect(),
+            open: indices.iter().map(|&i| self.open[i]).collect(),
+            high: indices.iter().map(|&i| self.high[i]).collect(),
+            low: indices.iter().map(|&i| self.low[i]).collect(),
+            close: indices.iter().map(|&i| self.close[i]).collect(),
+            volume: indices.iter().map(|&i| self.volume[i]).collect(),
+            events: self.events.clone(),
+            timeframe: self.timeframe,
+            adj_close: self.adj_close.as_ref().map(|adj| indices.iter().map(|&i| adj[i]).collect()),
+        }
+    }
+
+    /// The series in adjusted prices: the adjusted close, with open, high and
+    /// low scaled by the same per-bar factor (adjusted / raw close). Bars
+    /// without a usable adjusted close carry the prior bar's factor (leading
+    /// ones the first usable one). Volume is left as loaded. None when the
+    /// ticker has no adjustment data.
+    pub fn adjusted(&self) -> Option<TickerData> {
+        let adj_close = self.adj_close.as_ref()?;
+        let usable = |(&adj, &close): (&f64, &f64)| (adj.is_finite() && adj > 0.0 && close > 0.0).then(|| adj / close);
+        let mut factor = adj_close.iter().zip(&self.close).find_map(usable).unwrap_or(1.0);
+        let factors: Vec<f64> = adj_close
+            .iter()
+            .zip(&self.close)
+            .map(|pair| {
+                factor = usable(pair).unwrap_or(factor);
+                factor
+            })
+            .collect();
+        let scale = |prices: &[f64]| prices.iter().zip(&factors).map(|(p, f)| p * f).collect();
+        Some(TickerData {
+            date: self.date.clone(),
+            days: self.days.clone(),
+            open: scale(&self.open),
+            high: scale(&self.high),
+            low: scale(&self.low),
+            close: scale(&self.close),
+            volume: self.volume.clone(),
+            events: self.events.clone(),
+            timeframe: self.timeframe,
+            adj_close: None,
+        })
+    }
+
+    /// Repair missing and invalid prices from a loaded file: leading bars
+    /// without a valid close are dropped, and any NaN, zero or negative OHLC
+    /// value is replaced by the prior bar's. Zero-filled prices would show up
+    /// as -100% returns and wreck RSI, so carrying the last good value is the
+    /// least misleading choice. Missing volume becomes 0.
+    pub fn sanitize_prices(self) -> TickerData {
+        let valid = |v: f64| v.is_finite() && v > 0.0;
+
+        let Some(first) = self.close.iter().position(|&c| valid(c)) else {
+            if !self.is_empty() {
+                tracing::warn!("Dropping all {} rows: no valid close", self.len());
+            }
+            return self.slice(0..0);
+        };
+        let mut data = if first > 0 {
+            tracing::warn!("Dropping {} leading rows without a valid close", first);
+            self.slice(first..self.len())
+        } else {
+            self
+        };
+
+        let first_close = data.close[0];
+        let mut missing = 0;
+        let mut nonpositive = 0;
+        for series in [&mut data.open, &mut data.high, &mut data.low, &mut data.close] {
+            for i in 0..series.len() {
+                if valid(series[i]) {
+                    continue;
+                }
+                if series[i] <= 0.0 {
+                    nonpositive += 1;
+                } else {
+                    missing += 1;
+                }
+                // The first close is valid, so the first bar can borrow it
+                series[i] = if i > 0 { series[i - 1] } else { first_close };
+            }
+        }
+        for v in data.volume.iter_mut().filter(|v| !v.is_finite()) {
+            *v = 0.0;
+        }
+
+        if nonpositive > 0 {
+            tracing::warn!("Forward-filled {} zero or negative prices", nonpositive);
+        }
+        if missing > 0 {
+            tracing::debug!("Forward-filled {} missing prices", missing);
+        }
+        data
+    }
 }
 
+/// Default for `RETRO_MIN_BARS`: tickers with less history aren't loaded.
+/// Kept low so recent listings stay scannable; each scan declares how much
+/// history it needs on top of this.
+pub const DEFAULT_MIN_LOAD_BARS: usize = 30;
+
 /// Load a single parquet file
 pub fn load_parquet(path: &Path) -> anyhow::Result<TickerData> {
     let df = LazyFrame::scan_parquet(path, Default::default())?
@@ -45,17 +284,14 @@ pub fn load_parquet(path: &Path) -> anyhow::Result<TickerData> {
     let low = extract_f64_column(&df, "low")?;
     let close = extract_f64_column(&df, "close")?;
     let volume = extract_f64_column(&df, "volume")?;
+    let adj_close = ADJ_CLOSE_COLUMNS.iter().find_map(|name| extract_f64_column(&df, name).ok());
     
-    Ok(TickerData {
-        date,
-        open,
-        high,
-        low,
-        close,
-        volume,
-    })
+    Ok(TickerData::from_columns_adjusted(date, open, high, low, close, volume, adj_close).sanitize_prices())
 }
 
+/// Column names read as the adjusted close, in order of preference
+const ADJ_CLOSE_COLUMNS: &[&str] = &["adj_close", "adjusted_close", "Adj Close", "adj close", "Adj_Close", "AdjClose"];
+
 fn extract_f64_column(df: &DataFrame, name: &str) -> anyhow::Result<Vec<f64>> {
     let col = df.column(name)?;
     
@@ -63,7 +299,7 @@ fn extract_f64_column(df: &DataFrame, name: &str) -> anyhow::Result<Vec<f64>> {
     let f64_col = col.cast(&DataType::Float64)?;
     let chunked = f64_col.f64()?;
     
-    Ok(chunked.into_iter().map(|v| v.unwrap_or(0.0)).collect())
+    Ok(chunked.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect())
 }
 
 /// Load a single CSV file
@@ -97,33 +333,101 @@ pub fn load_csv(path: &Path) -> anyhow::Result<TickerData> {
     let low = extract_f64_column_flexible(&df, &["low", "Low", "LOW"])?;
     let close = extract_f64_column_flexible(&df, &["close", "Close", "CLOSE"])?;
     let volume = extract_f64_column_flexible(&df, &["volume", "Volume", "VOLUME"])?;
+    let adj_close = extract_f64_column_flexible(&df, ADJ_CLOSE_COLUMNS).ok();
     
-    Ok(TickerData {
-        date,
-        open,
-        high,
-        low,
-        close,
-        volume,
-    })
+    Ok(TickerData::from_columns_adjusted(date, open, high, low, close, volume, adj_close).sanitize_prices())
 }
 
 fn extract_f64_column_flexible(df: &DataFrame, names: &[&str]) -> anyhow::Result<Vec<f64>> {
     for name in names {
-        if let Ok(col) = df.column(*name) {
+        if let Ok(col) = df.column(name) {
             let f64_col = col.cast(&DataType::Float64)?;
             let chunked = f64_col.f64()?;
-            return Ok(chunked.into_iter().map(|v| v.unwrap_or(0.0)).collect());
+            return Ok(chunked.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect());
         }
     }
     
     anyhow::bail!("Could not find column with names: {:?}", names)
 }
 
+/// Sector/industry classification from `metadata.csv`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TickerMeta {
+    pub sector: Option<String>,
+    pub industry: Option<String>,
+}
+
+/// Load ticker metadata from a CSV with a `ticker` (or `symbol`) column and
+/// optional `sector` and `industry` columns. Headers are case-insensitive.
+pub fn load_metadata(path: &Path) -> anyhow::Result<HashMap<String, TickerMeta>> {
+    let df = CsvReadOptions::default()
+        .with_has_header(true)
+        .with_infer_schema_length(Some(0)) // every column as text
+        .try_into_reader_with_file_path(Some(path.to_path_buf()))?
+        .finish()?;
+    
+    let column = |names: &[&str]| -> Option<Vec<Option<String>>> {
+        let name = df
+            .get_column_names()
+            .into_iter()
+            .find(|c| names.contains(&c.to_lowercase().as_str()))?;
+        let values = df.column(name).ok()?.str().ok()?;
+        Some(
+            values
+                .into_iter()
+                .map(|v| v.map(str::trim).filter(|v| !v.is_empty()).map(String::from))
+                .collect(),
+        )
+    };
+    
+    let tickers = column(&["ticker", "symbol"])
+        .ok_or_else(|| anyhow::anyhow!("Could not find a ticker or symbol column"))?;
+    let sectors = column(&["sector"]).unwrap_or_else(|| vec![None; tickers.len()]);
+    let industries = column(&["industry"]).unwrap_or_else(|| vec![None; tickers.len()]);
+    
+    Ok(tickers
+        .into_iter()
+        .zip(sectors.into_iter().zip(industries))
+        .filter_map(|(ticker, (sector, industry))| {
+            Some((ticker?.to_uppercase(), TickerMeta { sector, industry }))
+        })
+        .collect())
+}
+
+/// Per-ticker coverage summary
+#[derive(Debug, Clone, Serialize)]
+pub struct TickerStats {
+    pub ticker: String,
+    pub first_date: String,
+    pub last_date: String,
+    pub bars: usize,
+    /// Calendar days from `last_date` to the newest date in the store
+    pub days_stale: i64,
+}
+
+/// Aggregate figures for the whole store, computed at load time
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DataStoreStats {
+    pub ticker_count: usize,
+    pub total_bars: usize,
+    pub min_date: Option<String>,
+    pub max_date: Option<String>,
+    pub memory_bytes: usize,
+    pub excluded_short_history: usize,
+    pub failed_loads: usize,
+}
+
 /// Data store - holds all loaded ticker data in memory
 pub struct DataStore {
     pub data: HashMap<String, Arc<TickerData>>,
     pub tickers: Vec<String>,
+    pub stats: DataStoreStats,
+    pub ticker_stats: Vec<TickerStats>,
+    /// Keyed by upper-case ticker; tickers may have no entry
+    pub metadata: HashMap<String, TickerMeta>,
+    pub events: EventStore,
+    excluded_short_history: usize,
+    failed_loads: usize,
 }
 
 impl DataStore {
@@ -131,12 +435,79 @@ impl DataStore {
         Self {
             data: HashMap::new(),
             tickers: Vec::new(),
+            stats: DataStoreStats::default(),
+            ticker_stats: Vec::new(),
+            metadata: HashMap::new(),
+            events: EventStore::default(),
+            excluded_short_history: 0,
+            failed_loads: 0,
+        }
+    }
+
+    /// Recompute cached stats; call after any change to `data`
+    pub fn refresh_stats(&mut self) {
+        let mut stats = DataStoreStats {
+            excluded_short_history: self.excluded_short_history,
+            failed_loads: self.failed_loads,
+            ..Default::default()
+        };
+        let mut ticker_stats = Vec::with_capacity(self.tickers.len());
+
+        for ticker in &self.tickers {
+            let Some(data) = self.data.get(ticker) else {
+                continue;
+            };
+            let (Some(first), Some(last)) = (data.date.first(), data.date.last()) else {
+                continue;
+            };
+
+            stats.total_bars += data.len();
+            let columns = 5 + data.adj_close.is_some() as usize;
+            stats.memory_bytes += data.len() * columns * std::mem::size_of::<f64>()
+                + data
+                    .date
+                    .iter()
+                    .map(|d| d.capacity() + std::mem::size_of::<String>())
+                    .sum::<usize>();
+
+            if stats.min_date.as_ref().is_none_or(|d| first < d) {
+                stats.min_date = Some(first.clone());
+            }
+            if stats.max_date.as_ref().is_none_or(|d| last > d) {
+                stats.max_date = Some(last.clone());
+            }
+
+            ticker_stats.push((
+                data.days.last().copied(),
+                TickerStats {
+                    ticker: ticker.clone(),
+                    first_date: first.clone(),
+                    last_date: last.clone(),
+                    bars: data.len(),
+                    days_stale: 0,
+                },
+            ));
         }
+
+        let newest = ticker_stats.iter().filter_map(|(last, _)| *last).max();
+        let ticker_stats: Vec<TickerStats> = ticker_stats
+            .into_iter()
+            .map(|(last, stats)| TickerStats {
+                days_stale: last.zip(newest).map_or(0, |(last, newest)| (newest - last).num_days()),
+                ..stats
+            })
+            .collect();
+
+        stats.ticker_count = ticker_stats.len();
+        self.stats = stats;
+        self.ticker_stats = ticker_stats;
     }
     
-    /// Load all data from a directory (parquet or CSV files)
-    pub fn load_directory(&mut self, dir: &Path) -> anyhow::Result<()> {
+    /// Load all data from a directory (parquet or CSV files), skipping
+    /// tickers with fewer than `min_bars` bars
+    pub fn load_directory(&mut self, dir: &Path, min_bars: usize) -> anyhow::Result<()> {
         tracing::info!("Loading data from {:?}", dir);
+        let start = std::time::Instant::now();
         
         if !dir.exists() {
             anyhow::bail!("Data directory does not exist: {:?}", dir);
@@ -177,16 +548,18 @@ impl DataStore {
             
             match result {
                 Ok(data) => {
-                    if data.len() >= 200 {
-                        // Only include tickers with enough history
+                    if data.len() >= min_bars {
                         self.data.insert(ticker.clone(), Arc::new(data));
                         self.tickers.push(ticker);
                         loaded += 1;
+                    } else {
+                        self.excluded_short_history += 1;
                     }
                 }
                 Err(e) => {
                     tracing::warn!("Failed to load {}: {}", ticker, e);
                     failed += 1;
+                    self.failed_loads += 1;
                 }
             }
             
@@ -196,17 +569,85 @@ impl DataStore {
         }
         
         self.tickers.sort();
+        self.refresh_stats();
+        
+        metrics::gauge!("tickers_loaded").set(self.stats.ticker_count as f64);
+        metrics::histogram!("data_load_duration_seconds").record(start.elapsed().as_secs_f64());
         
         tracing::info!(
             "Loaded {} tickers ({} failed) with {} total data points",
             loaded,
             failed,
-            self.data.values().map(|d| d.len()).sum::<usize>()
+            self.stats.total_bars
         );
         
         Ok(())
     }
     
+    /// Install `events` and attach each ticker's share to its data
+    pub fn set_events(&mut self, events: EventStore) {
+        for (ticker, data) in self.data.iter_mut() {
+            Arc::make_mut(data).events = Arc::new(events.for_ticker(ticker));
+        }
+        self.events = events;
+    }
+    
+    /// Append `bars` to an existing ticker's series, returning how many were
+    /// added. Bars whose date (timestamp, for intraday series) is already
+    /// present are skipped, and a repeated date within `bars` keeps its last
+    /// row; any other bar must come after the series' last one. Copies the
+    /// series if scans still hold it, so they keep the bars they started with.
+    pub fn append_bars(&mut self, ticker: &str, bars: TickerData) -> Result<usize, String> {
+        let ticker = ticker.to_uppercase();
+        let data = self.data.get_mut(&ticker).ok_or_else(|| format!("Ticker not found: {}", ticker))?;
+        let timeframe = data.timeframe;
+        if timeframe == Timeframe::Daily && bars.timeframe == Timeframe::Intraday {
+            return Err(format!("{} has daily bars; can't append intraday ones", ticker));
+        }
+
+        // Daily bars compare by day, so a re-sent bar matches whatever
+        // time suffix the file used
+        let key = |date: &str, day: NaiveDate| match timeframe {
+            Timeframe::Daily => Some(day.format("%Y-%m-%d").to_string()),
+            Timeframe::Intraday => parse_timestamp(date).map(|t| t.format(TIMESTAMP_FORMAT).to_string()),
+        };
+        let existing: HashSet<String> = (0..data.len()).filter_map(|i| key(&data.date[i], data.days[i])).collect();
+        let last = existing.iter().max().cloned();
+
+        let mut new: BTreeMap<String, usize> = BTreeMap::new();
+        for i in 0..bars.len() {
+            let Some(date) = key(&bars.date[i], bars.days[i]) else {
+                return Err(format!("{} needs a time of day: {} has intraday bars", bars.date[i], ticker));
+            };
+            if existing.contains(&date) {
+                continue;
+            }
+            if let Some(last) = last.as_ref().filter(|last| date <= **last) {
+                return Err(format!("{} is not after the last bar ({})", bars.date[i], last));
+            }
+            new.insert(date, i);
+        }
+
+        let data = Arc::make_mut(data);
+        for (date, &i) in &new {
+            data.date.push(date.clone());
+            data.days.push(bars.days[i]);
+            data.open.push(bars.open[i]);
+            data.high.push(bars.high[i]);
+            data.low.push(bars.low[i]);
+            data.close.push(bars.close[i]);
+            data.volume.push(bars.volume[i]);
+            // Adjustments apply backwards, so the newest bars are as traded
+            if let Some(adj) = data.adj_close.as_mut() {
+                adj.push(bars.close[i]);
+            }
+        }
+        if !new.is_empty() {
+            self.refresh_stats();
+        }
+        Ok(new.len())
+    }
+
     /// Get data for a single ticker
     pub fn get(&self, ticker: &str) -> Option<Arc<TickerData>> {
         self.data.get(&ticker.to_uppercase()).cloned()
@@ -216,6 +657,42 @@ impl DataStore {
     pub fn get_tickers(&self) -> &[String] {
         &self.tickers
     }
+    
+    /// Whether `ticker` is classified under `sector` (case-insensitive)
+    pub fn in_sector(&self, ticker: &str, sector: &str) -> bool {
+        self.metadata
+            .get(ticker)
+            .and_then(|m| m.sector.as_deref())
+            .is_some_and(|s| s.eq_ignore_ascii_case(sector))
+    }
+    
+    /// Newest bar date across every ticker, which staleness is measured from
+    pub fn newest_day(&self) -> Option<NaiveDate> {
+        self.data.values().filter_map(|d| d.days.last().copied()).max()
+    }
+
+    /// Tickers whose data ends more than `days` calendar days before the
+    /// newest date in the store, most out of date first
+    pub fn stale_tickers(&self, days: u32) -> Vec<TickerStats> {
+        let mut stale: Vec<TickerStats> =
+            self.ticker_stats.iter().filter(|t| t.days_stale > days as i64).cloned().collect();
+        stale.sort_by(|a, b| b.days_stale.cmp(&a.days_stale).then_with(|| a.ticker.cmp(&b.ticker)));
+        stale
+    }
+
+    /// Tickers to scan: all of them, or only those in `sector`. Tickers
+    /// without metadata are excluded when a sector is given.
+    pub fn universe(&self, sector: Option<&str>) -> HashMap<String, Arc<TickerData>> {
+        match sector {
+            None => self.data.clone(),
+            Some(sector) => self
+                .data
+                .iter()
+                .filter(|(ticker, _)| self.in_sector(ticker, sector))
+                .map(|(ticker, data)| (ticker.clone(), data.clone()))
+                .collect(),
+        }
+    }
 }
 
 impl Default for DataStore {
@@ -224,6 +701,62 @@ impl Default for DataStore {
     }
 }
 
+/// Parse a date string: `YYYY-MM-DD` (zero padding optional, `/` also
+/// accepted), ignoring any time suffix such as `T00:00:00` or ` 00:00:00`
+pub fn parse_date(date: &str) -> Option<NaiveDate> {
+    let day = date.trim().split(['T', ' ']).next()?;
+    NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(day, "%Y/%m/%d"))
+        .ok()
+}
+
+/// How intraday bar times are stored in `TickerData::date`
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Parse a date with a time of day: RFC 3339 (converted to UTC), or a naive
+/// `YYYY-MM-DD HH:MM[:SS[.f]]` (`T` also accepted), taken as UTC. `None` for a
+/// bare date.
+pub fn parse_timestamp(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(time.naive_utc());
+    }
+    let value = value.trim_end_matches('Z');
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+}
+
+/// A request bound with a time of day, formatted to compare against intraday
+/// `date`s; `None` when absent or a bare date
+pub fn timestamp_bound(value: Option<&str>) -> Option<String> {
+    value.and_then(parse_timestamp).map(|t| t.format(TIMESTAMP_FORMAT).to_string())
+}
+
+/// Parse an optional date from a request, naming the field in the error
+pub fn parse_date_param(name: &str, value: Option<&str>) -> Result<Option<NaiveDate>, String> {
+    match value {
+        None => Ok(None),
+        Some(raw) => parse_date(raw)
+            .map(Some)
+            .ok_or_else(|| format!("Invalid {}: '{}' (expected YYYY-MM-DD)", name, raw)),
+    }
+}
+
+/// For each of `days`, the index of the same day in `other`, if present.
+/// Both must be sorted ascending.
+pub fn align_days(days: &[NaiveDate], other: &[NaiveDate]) -> Vec<Option<usize>> {
+    let mut j = 0;
+    days.iter()
+        .map(|day| {
+            while j < other.len() && other[j] < *day {
+                j += 1;
+            }
+            (other.get(j) == Some(day)).then_some(j)
+        })
+        .collect()
+}
+
 /// Generate sample data for testing
 pub fn generate_sample_data(num_days: usize) -> TickerData {
     use std::f64::consts::PI;
@@ -236,7 +769,7 @@ pub fn generate_sample_data(num_days: usize) -> TickerData {
     let mut volume = Vec::with_capacity(num_days);
     
     let mut price = 100.0;
-    let start_date = chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+    let start_date = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
     
     for i in 0..num_days {
         let d = start_date + chrono::Duration::days(i as i64);
@@ -263,14 +796,7 @@ pub fn generate_sample_data(num_days: usize) -> TickerData {
         volume.push(day_volume);
     }
     
-    TickerData {
-        date,
-        open,
-        high,
-        low,
-        close,
-        volume,
-    }
+    TickerData::from_columns(date, open, high, low, close, volume)
 }
 
 // Simple pseudo-random number generator (no external dependency)
@@ -290,3 +816,247 @@ fn rand_float() -> f64 {
         (SEED >> 33) as f64 / (1u64 << 31) as f64
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date_formats() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 5);
+        assert_eq!(parse_date("2024-01-05"), day);
+        assert_eq!(parse_date("2024-1-5"), day);
+        assert_eq!(parse_date("2024-01-05T00:00:00Z"), day);
+        assert_eq!(parse_date("2024-01-05 16:00:00"), day);
+        assert_eq!(parse_date("2024/01/05"), day);
+        assert_eq!(parse_date("01/05/2024"), None);
+        assert!(parse_date_param("date_from", Some("soon")).is_err());
+
+        let data = TickerData::from_columns(
+            vec!["2024-1-5".into(), "".into(), "2024-01-08".into()],
+            vec![1.0; 3],
+            vec![1.0; 3],
+            vec![1.0; 3],
+            vec![1.0, 2.0, 3.0],
+            vec![1.0; 3],
+        );
+        assert_eq!(data.len(), 2);
+        assert_eq!(data.close, vec![1.0, 3.0]);
+        assert_eq!(data.days[0], day.unwrap());
+    }
+
+    #[test]
+    fn test_load_csv_fills_invalid_prices() {
+        let path = std::env::temp_dir().join(format!("retro-nulls-{}.csv", std::process::id()));
+        fs::write(
+            &path,
+            "date,open,high,low,close,volume\n\
+             2024-01-01,,,,,\n\
+             2024-01-02,10,11,9,10,100\n\
+             2024-01-03,,,,,\n\
+             2024-01-04,10.5,0,10,10.5,\n\
+             2024-01-05,11,11.5,10.5,-1,300\n\
+             2024-01-08,11,12,10.5,11.5,400\n",
+        )
+        .unwrap();
+        let data = load_csv(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(data.len(), 5);
+        assert_eq!(data.date[0], "2024-01-02");
+        assert_eq!(data.close, vec![10.0, 10.0, 10.5, 10.5, 11.5]);
+        assert_eq!(data.high, vec![11.0, 11.0, 11.0, 11.5, 12.0]);
+        assert_eq!(data.volume, vec![100.0, 0.0, 0.0, 300.0, 400.0]);
+
+        // No fake crash bars: every one-day return is small and RSI stays defined
+        let returns = crate::indicators::pct_change(&data.close, 1);
+        assert!(returns.iter().skip(1).all(|r| r.abs() < 10.0));
+        assert!(crate::indicators::rsi(&data.close, 2).iter().skip(2).all(|r| r.is_finite()));
+    }
+
+    #[test]
+    fn test_adjusted_prices() {
+        let path = std::env::temp_dir().join(format!("retro-adjusted-{}.csv", std::process::id()));
+        // A 2:1 split after the second bar; rows out of order to check alignment
+        fs::write(
+            &path,
+            "date,open,high,low,close,volume,Adj Close\n\
+             2024-01-03,50,52,49,51,300,51\n\
+             2024-01-01,98,102,96,100,100,50\n\
+             2024-01-02,100,104,98,102,200,\n\
+             2024-01-04,51,53,50,52,400,52\n",
+        )
+        .unwrap();
+        let data = load_csv(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let adj_close = data.adj_close.as_ref().unwrap();
+        assert_eq!((adj_close[0], adj_close[2], adj_close[3]), (50.0, 51.0, 52.0));
+        assert!(adj_close[1].is_nan());
+        let adjusted = data.adjusted().unwrap();
+        // The missing adjusted close on 01-02 carries the prior bar's halving
+        assert_eq!(adjusted.close, vec![50.0, 51.0, 51.0, 52.0]);
+        assert_eq!(adjusted.high, vec![51.0, 52.0, 52.0, 53.0]);
+        assert_eq!(adjusted.volume, data.volume);
+        assert!(adjusted.adj_close.is_none());
+        assert_eq!(data.slice(2..4).adjusted().unwrap().close, vec![51.0, 52.0]);
+
+        let raw = TickerData::from_columns(vec!["2024-01-01".into()], vec![1.0], vec![1.0], vec![1.0], vec![1.0], vec![1.0]);
+        assert!(raw.adjusted().is_none());
+    }
+
+    #[test]
+    fn test_intraday_timeframe() {
+        let bars = |dates: &[&str]| {
+            let n = dates.len();
+            TickerData::from_columns(
+                dates.iter().map(|d| d.to_string()).collect(),
+                (0..n).map(|i| i as f64 + 1.0).collect(),
+                vec![1.0; n],
+                vec![1.0; n],
+                vec![1.0; n],
+                vec![1.0; n],
+            )
+        };
+
+        // Stamped with a close time, but one bar a day: still daily, as given
+        let daily = bars(&["2024-01-02 16:00:00", "2024-01-03 16:00:00"]);
+        assert_eq!(daily.timeframe, Timeframe::Daily);
+        assert_eq!(daily.date[0], "2024-01-02 16:00:00");
+
+        let intraday = bars(&["2024-01-02T09:31:00-05:00", "2024-01-02 14:30", "2024-01-03T14:30:00Z", "2024-01-02T14:31:00.000Z"]);
+        assert_eq!(intraday.timeframe, Timeframe::Intraday);
+        assert_eq!(
+            intraday.date,
+            ["2024-01-02T14:30:00Z", "2024-01-02T14:31:00Z", "2024-01-02T14:31:00Z", "2024-01-03T14:30:00Z"]
+        );
+        // Same-time rows keep their file order
+        assert_eq!(intraday.open, vec![2.0, 1.0, 4.0, 3.0]);
+        assert_eq!(intraday.days[1], NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(timestamp_bound(Some("2024-01-02")), None);
+        assert_eq!(timestamp_bound(Some("2024-01-02 09:30")).as_deref(), Some("2024-01-02T09:30:00Z"));
+    }
+
+    #[test]
+    fn test_append_bars() {
+        let bars = |dates: &[&str], close: f64| {
+            let n = dates.len();
+            TickerData::from_columns(
+                dates.iter().map(|d| d.to_string()).collect(),
+                vec![close; n],
+                vec![close; n],
+                vec![close; n],
+                vec![close; n],
+                vec![1.0; n],
+            )
+        };
+        let mut store = DataStore::new();
+        store.data.insert("AAPL".into(), Arc::new(bars(&["2024-01-02", "2024-01-03 16:00:00"], 1.0)));
+        store.tickers.push("AAPL".into());
+        let held = store.get("AAPL").unwrap();
+
+        // The already-loaded day is skipped; the repeated new day keeps its last row
+        let added = store.append_bars("aapl", bars(&["2024-01-03", "2024-01-04", "2024-01-05", "2024-01-05"], 2.0));
+        assert_eq!(added, Ok(2));
+        let data = store.get("AAPL").unwrap();
+        assert_eq!(data.date, ["2024-01-02", "2024-01-03 16:00:00", "2024-01-04", "2024-01-05"]);
+        assert_eq!(data.close, [1.0, 1.0, 2.0, 2.0]);
+        assert_eq!(held.len(), 2);
+        assert_eq!(store.stats.max_date.as_deref(), Some("2024-01-05"));
+
+        assert!(store.append_bars("AAPL", bars(&["2024-01-01"], 3.0)).is_err());
+        assert!(store.append_bars("AAPL", bars(&["2024-01-08T14:30:00Z", "2024-01-08T14:31:00Z"], 3.0)).is_err());
+        assert!(store.append_bars("MSFT", bars(&["2024-01-08"], 3.0)).is_err());
+        assert_eq!(store.get("AAPL").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_load_directory_stats() {
+        let dir = std::env::temp_dir().join(format!("retro-stats-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let csv = |rows: &[&str]| {
+            let mut text = "date,open,high,low,close,volume\n".to_string();
+            for day in rows {
+                text += &format!("{},10,11,9,10,100\n", day);
+            }
+            text
+        };
+        fs::write(dir.join("AAA.csv"), csv(&["2024-01-02", "2024-01-03", "2024-01-04", "2024-01-05"])).unwrap();
+        fs::write(dir.join("bbb.us.csv"), csv(&["2024-01-01", "2024-01-02", "2024-01-03"])).unwrap();
+        fs::write(dir.join("SHORT.csv"), csv(&["2024-01-05"])).unwrap();
+        fs::write(dir.join("BAD.csv"), "ticker,price\nBAD,oops\n").unwrap();
+
+        let mut store = DataStore::new();
+        store.load_directory(&dir, 3).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(store.tickers, ["AAA", "BBB"]);
+        let stats = &store.stats;
+        assert_eq!((stats.ticker_count, stats.total_bars), (2, 7));
+        assert_eq!(stats.min_date.as_deref(), Some("2024-01-01"));
+        assert_eq!(stats.max_date.as_deref(), Some("2024-01-05"));
+        assert_eq!((stats.excluded_short_history, stats.failed_loads), (1, 1));
+        assert!(stats.memory_bytes > 7 * 5 * std::mem::size_of::<f64>());
+
+        let lag: Vec<(&str, usize, i64)> =
+            store.ticker_stats.iter().map(|t| (t.ticker.as_str(), t.bars, t.days_stale)).collect();
+        assert_eq!(lag, [("AAA", 4, 0), ("BBB", 3, 2)]);
+    }
+
+    #[test]
+    fn test_stale_tickers() {
+        let mut store = DataStore::new();
+        let full = generate_sample_data(300);
+        for (ticker, data) in [("NEW", full.clone()), ("OLD", full.slice(0..200)), ("LAG", full.slice(0..290))] {
+            store.data.insert(ticker.into(), Arc::new(data));
+            store.tickers.push(ticker.into());
+        }
+        store.refresh_stats();
+
+        let newest = store.newest_day().unwrap();
+        assert_eq!(Some(newest), full.days.last().copied());
+        let lag: HashMap<&str, i64> = store.ticker_stats.iter().map(|t| (t.ticker.as_str(), t.days_stale)).collect();
+        assert_eq!(lag["NEW"], 0);
+        assert_eq!(lag["OLD"], (newest - full.days[199]).num_days());
+
+        let stale = |days| store.stale_tickers(days).into_iter().map(|t| t.ticker).collect::<Vec<_>>();
+        assert_eq!(stale(30), ["OLD"]);
+        assert_eq!(stale(0), ["OLD", "LAG"]);
+    }
+
+    #[test]
+    fn test_align_days() {
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let ticker = [day(2), day(3), day(5), day(9)];
+        let bench = [day(1), day(2), day(5), day(8), day(9)];
+        assert_eq!(align_days(&ticker, &bench), [Some(1), None, Some(2), Some(4)]);
+        assert_eq!(align_days(&ticker, &[]), [None; 4]);
+    }
+
+    #[test]
+    fn test_metadata_sector_universe() {
+        let path = std::env::temp_dir().join(format!("retro-metadata-{}.csv", std::process::id()));
+        fs::write(
+            &path,
+            "Symbol,Sector,Industry\naapl,Technology,Consumer Electronics\nXOM,Energy,\n,Energy,Orphan\n",
+        )
+        .unwrap();
+        let metadata = load_metadata(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata["AAPL"].industry.as_deref(), Some("Consumer Electronics"));
+        assert_eq!(metadata["XOM"].industry, None);
+
+        let mut store = DataStore::new();
+        for ticker in ["AAPL", "XOM", "NOMETA"] {
+            store.data.insert(ticker.into(), Arc::new(generate_sample_data(5)));
+        }
+        store.metadata = metadata;
+
+        let tech = store.universe(Some("technology"));
+        assert_eq!(tech.keys().collect::<Vec<_>>(), ["AAPL"]);
+        assert_eq!(store.universe(None).len(), 3);
+        assert!(store.universe(Some("Utilities")).is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+//! API error type - every failure maps to a status code and `{"error": "..."}` body
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    NotFound(String),
+    /// The server isn't in a state to answer, e.g. a scan with no data loaded
+    #[error("{0}")]
+    Conflict(String),
+    /// Well-formed input that fails semantic checks (e.g. an invalid generated scan)
+    #[error("{0}")]
+    Unprocessable(String),
+    #[error("{0}")]
+    TooManyRequests(String),
+    #[error("{0}")]
+    Internal(String),
+    /// The LLM backend is unconfigured or its call failed
+    #[error("{0}")]
+    LlmUnavailable(String),
+    #[error("{0}")]
+    Timeout(String),
+}
+
+#[derive(Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::LlmUnavailable(_) | ApiError::Timeout(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!("{}", self);
+        }
+        (status, Json(ErrorResponse { error: self.to_string() })).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_error_response_shape() {
+        let response = ApiError::NotFound("Ticker not found: XYZ".into()).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json, serde_json::json!({ "error": "Ticker not found: XYZ" }));
+
+        assert_eq!(ApiError::Conflict("No market data is loaded".into()).status(), StatusCode::CONFLICT);
+        assert_eq!(
+            ApiError::LlmUnavailable("ANTHROPIC_API_KEY is not set".into()).status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Event calendars - dated per-ticker events (earnings, dividends, splits)
+//!
+//! Each `<type>.csv` in the events directory holds `ticker,date` rows; the
+//! file stem names the event type. Dates are kept sorted and deduplicated.
+
+use crate::data::parse_date;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Event type → sorted dates, for one ticker
+pub type TickerEvents = HashMap<String, Vec<NaiveDate>>;
+
+/// Event type → ticker → sorted dates
+#[derive(Debug, Clone, Default)]
+pub struct EventStore {
+    by_type: HashMap<String, HashMap<String, Vec<NaiveDate>>>,
+}
+
+impl EventStore {
+    /// Load every `*.csv` in `dir`. A file that fails to load is logged and skipped.
+    pub fn load_dir(dir: &Path) -> anyhow::Result<Self> {
+        let mut store = Self::default();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("csv") {
+                continue;
+            }
+            let Some(event_type) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            match load_event_file(&path) {
+                Ok(events) => {
+                    tracing::info!("Loaded {} events for {} tickers", event_type, events.len());
+                    store.insert(&event_type.to_lowercase(), events);
+                }
+                Err(e) => tracing::warn!("Could not load {:?}: {}", path, e),
+            }
+        }
+
+        Ok(store)
+    }
+
+    /// Replace all events of `event_type`
+    pub fn insert(&mut self, event_type: &str, events: HashMap<String, Vec<NaiveDate>>) {
+        self.by_type.insert(event_type.to_string(), events);
+    }
+
+    /// Event types loaded, sorted
+    pub fn types(&self) -> Vec<&str> {
+        let mut types: Vec<&str> = self.by_type.keys().map(String::as_str).collect();
+        types.sort_unstable();
+        types
+    }
+
+    /// Dates of `event_type` for `ticker`; None if the type was never loaded
+    pub fn get(&self, event_type: &str, ticker: &str) -> Option<&[NaiveDate]> {
+        let by_ticker = self.by_type.get(event_type)?;
+        Some(by_ticker.get(ticker).map_or(&[], Vec::as_slice))
+    }
+
+    /// Every event type with dates for `ticker`
+    pub fn for_ticker(&self, ticker: &str) -> TickerEvents {
+        self.by_type
+            .iter()
+            .filter_map(|(event_type, by_ticker)| Some((event_type.clone(), by_ticker.get(ticker)?.clone())))
+            .collect()
+    }
+}
+
+/// Load a `ticker` (or `symbol`) + `date` CSV. Rows with a missing ticker or
+/// unparseable date are skipped.
+pub fn load_event_file(path: &Path) -> anyhow::Result<HashMap<String, Vec<NaiveDate>>> {
+    let df = CsvReadOptions::default()
+        .with_has_header(true)
+        .with_infer_schema_length(Some(0)) // every column as text
+        .try_into_reader_with_file_path(Some(path.to_path_buf()))?
+        .finish()?;
+
+    let column = |names: &[&str]| -> anyhow::Result<Vec<Option<String>>> {
+        let name = df
+            .get_column_names()
+            .into_iter()
+            .find(|c| names.contains(&c.to_lowercase().as_str()))
+            .ok_or_else(|| anyhow::anyhow!("Could not find column with names: {:?}", names))?;
+        Ok(df.column(name)?.str()?.into_iter().map(|v| v.map(|v| v.trim().to_string())).collect())
+    };
+
+    let tickers = column(&["ticker", "symbol"])?;
+    let dates = column(&["date"])?;
+
+    let mut events: HashMap<String, Vec<NaiveDate>> = HashMap::new();
+    for (ticker, date) in tickers.into_iter().zip(dates) {
+        let (Some(ticker), Some(day)) = (ticker.filter(|t| !t.is_empty()), date.as_deref().and_then(parse_date)) else {
+            continue;
+        };
+        events.entry(ticker.to_uppercase()).or_default().push(day);
+    }
+    for dates in events.values_mut() {
+        dates.sort_unstable();
+        dates.dedup();
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_events_dir() {
+        let dir = std::env::temp_dir().join(format!("retro-events-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("earnings.csv"),
+            "Ticker,Date\naapl,2024-05-02\nAAPL,2024-02-01\nAAPL,2024-05-02\nMSFT,not a date\n,2024-01-01\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let store = EventStore::load_dir(&dir).unwrap();
+        assert_eq!(store.types(), ["earnings"]);
+
+        let day = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        assert_eq!(store.get("earnings", "AAPL").unwrap(), [day("2024-02-01"), day("2024-05-02")]);
+        assert_eq!(store.get("earnings", "MSFT").unwrap(), []);
+        assert!(store.get("dividends", "AAPL").is_none());
+        assert_eq!(store.for_ticker("AAPL").len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,9 @@
 //! Auto-generated scans (edit via generator only)
 
 use crate::data::TickerData;
+#[allow(unused_imports)]
 use crate::indicators::*;
+#[allow(unused_imports)]
 use crate::scan_types::{ScanParam, ScanType};
 use serde_json::Value;
 use std::collections::HashMap;
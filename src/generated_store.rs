@@ -1,20 +1,37 @@
 //! Persistence + codegen for LLM-generated scans
+//!
+//! The spec store and generated.rs are replaced atomically (temp file, fsync,
+//! rename), and each save keeps the previous pair as `.bak` files so the last
+//! generation can be rolled back. Callers serialize saves (see
+//! `AppState::generated_lock`); the files themselves aren't locked.
+//!
+//! Each spec carries its own `version` and the last few versions it replaced,
+//! so edits can be reverted long after the one-step rollback is used up.
 
 use crate::scan_types::{ScanParam, ScanType};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 pub const GENERATED_SCANS_PATH: &str = "./data/generated_scans.json";
 pub const GENERATED_RS_PATH: &str = "./src/generated.rs";
 
+/// Previous versions kept per generated scan
+pub const MAX_KEPT_VERSIONS: usize = 10;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeneratedParam {
     pub name: String,
     pub param_type: String,
     pub default: Value,
     pub description: String,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +42,53 @@ pub struct GeneratedScanSpec {
     pub params: Vec<GeneratedParam>,
     pub function_body: String,
     pub helpers: Option<String>,
+    #[serde(default)]
+    pub default_lookback_days: Option<u32>,
+    /// Bumped on every save; files from before versioning load as version 1
+    #[serde(default = "first_version")]
+    pub version: u32,
+    /// RFC 3339; unknown for specs saved before versioning
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    /// Versions this one replaced, oldest first, at most `MAX_KEPT_VERSIONS`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<GeneratedScanSpec>,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+impl GeneratedScanSpec {
+    /// This spec as it was at `version`, current or kept in `history`
+    pub fn at_version(&self, version: u32) -> Option<&GeneratedScanSpec> {
+        std::iter::once(self).chain(&self.history).find(|s| s.version == version)
+    }
+}
+
+/// Direct edits to a stored spec; omitted fields keep their current value
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GeneratedScanEdit {
+    pub description: Option<String>,
+    pub params: Option<Vec<GeneratedParam>>,
+    pub function_body: Option<String>,
+    pub helpers: Option<String>,
+}
+
+impl GeneratedScanEdit {
+    /// `spec` with these edits applied, ready for `validate_spec`
+    pub fn apply(self, spec: &GeneratedScanSpec) -> GeneratedScanSpec {
+        GeneratedScanSpec {
+            description: self.description.unwrap_or_else(|| spec.description.clone()),
+            params: self.params.unwrap_or_else(|| spec.params.clone()),
+            function_body: self.function_body.unwrap_or_else(|| spec.function_body.clone()),
+            helpers: self.helpers.or_else(|| spec.helpers.clone()),
+            history: Vec::new(),
+            ..spec.clone()
+        }
+    }
 }
 
 pub fn load_specs(path: &Path) -> anyhow::Result<Vec<GeneratedScanSpec>> {
@@ -37,34 +101,120 @@ pub fn load_specs(path: &Path) -> anyhow::Result<Vec<GeneratedScanSpec>> {
 }
 
 pub fn save_specs(path: &Path, specs: &[GeneratedScanSpec]) -> anyhow::Result<()> {
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
-    }
     let raw = serde_json::to_string_pretty(specs)?;
-    fs::write(path, raw)?;
+    write_atomic(path, raw.as_bytes())?;
     Ok(())
 }
 
+/// Replace `path` with `contents` so readers (and a crash) see either the old
+/// file or the new one, never a truncated one
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+    // Same directory, so the rename can't cross filesystems
+    let tmp = dir.join(format!(".{}.tmp-{}-{}", name, std::process::id(), SEQ.fetch_add(1, Ordering::Relaxed)));
+
+    let result = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        // Persist the rename itself; not every platform can open a directory
+        if let Ok(dir) = fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Where the copy of `path` from before the last save is kept
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Add or replace `spec` in the store at `json_path` and regenerate
+/// `rs_path`, backing up both first. Returns the saved specs.
+pub fn save_generated(json_path: &Path, rs_path: &Path, spec: GeneratedScanSpec) -> anyhow::Result<Vec<GeneratedScanSpec>> {
+    let previous = load_specs(json_path)?;
+    let mut specs = previous.clone();
+    upsert_spec(&mut specs, spec);
+    let rendered = render_generated_rs(&specs)?;
+
+    // Backups first: a crash after them still leaves a consistent current pair
+    if let Ok(previous_rs) = fs::read(rs_path) {
+        write_atomic(&backup_path(json_path), serde_json::to_string_pretty(&previous)?.as_bytes())?;
+        write_atomic(&backup_path(rs_path), &previous_rs)?;
+    }
+    save_specs(json_path, &specs)?;
+    write_atomic(rs_path, rendered.as_bytes())?;
+    Ok(specs)
+}
+
+/// Restore the spec store and generated.rs from before the last
+/// `save_generated`. The backups are used up, so a second rollback finds
+/// nothing; returns the restored specs, or `None` without backups.
+pub fn rollback_generated(json_path: &Path, rs_path: &Path) -> anyhow::Result<Option<Vec<GeneratedScanSpec>>> {
+    let (json_bak, rs_bak) = (backup_path(json_path), backup_path(rs_path));
+    if !json_bak.exists() || !rs_bak.exists() {
+        return Ok(None);
+    }
+    let specs = load_specs(&json_bak)?;
+    fs::rename(&json_bak, json_path)?;
+    fs::rename(&rs_bak, rs_path)?;
+    Ok(Some(specs))
+}
+
+/// Add `new_spec`, or make it the next version of the spec with its id. The
+/// replaced version moves into `history`; the oldest ones beyond
+/// `MAX_KEPT_VERSIONS` are dropped.
 pub fn upsert_spec(specs: &mut Vec<GeneratedScanSpec>, mut new_spec: GeneratedScanSpec) {
+    let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
     new_spec.id = normalize_scan_id(&new_spec.id);
+    new_spec.updated_at = Some(now.clone());
 
     if let Some(existing) = specs.iter_mut().find(|s| s.id == new_spec.id) {
+        let mut history = std::mem::take(&mut existing.history);
+        let latest = history.iter().map(|s| s.version).chain([existing.version]).max().unwrap_or(0);
+        new_spec.version = latest + 1;
+        new_spec.created_at = existing.created_at.clone();
+        history.push(existing.clone());
+        if history.len() > MAX_KEPT_VERSIONS {
+            history.drain(..history.len() - MAX_KEPT_VERSIONS);
+        }
+        new_spec.history = history;
         *existing = new_spec;
     } else {
+        new_spec.version = first_version();
+        new_spec.created_at = Some(now);
+        new_spec.history.clear();
         specs.push(new_spec);
     }
 }
 
 pub fn write_generated_rs(path: &Path, specs: &[GeneratedScanSpec]) -> anyhow::Result<()> {
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
-    }
+    write_atomic(path, render_generated_rs(specs)?.as_bytes())?;
+    Ok(())
+}
 
+fn render_generated_rs(specs: &[GeneratedScanSpec]) -> anyhow::Result<String> {
     let mut output = String::new();
     output.push_str("//! Auto-generated scans (edit via generator only)\n\n");
     output.push_str("use crate::data::TickerData;\n");
-    output.push_str("use crate::indicators::*;\n");
-    output.push_str("use crate::scan_types::{ScanParam, ScanType};\n");
+    // Only used once there are generated scans
+    output.push_str("#[allow(unused_imports)]\nuse crate::indicators::*;\n");
+    output.push_str("#[allow(unused_imports)]\nuse crate::scan_types::{ScanParam, ScanType};\n");
     output.push_str("use serde_json::Value;\n");
     output.push_str("use std::collections::HashMap;\n\n");
     output.push_str("pub type ScanFn = fn(&TickerData, &HashMap<String, Value>) -> Vec<bool>;\n\n");
@@ -89,9 +239,17 @@ pub fn write_generated_rs(path: &Path, specs: &[GeneratedScanSpec]) -> anyhow::R
                 "                    description: {:?}.into(),\n",
                 param.description
             ));
+            output.push_str(&format!("                    min: {:?},\n", param.min));
+            output.push_str(&format!("                    max: {:?},\n", param.max));
+            output.push_str("                    integer: false,\n");
             output.push_str("                },\n");
         }
         output.push_str("            ],\n");
+        output.push_str(&format!(
+            "            default_lookback_days: {:?},\n",
+            spec.default_lookback_days
+        ));
+        output.push_str(&format!("            version: Some({}),\n", spec.version));
         output.push_str("        },\n");
     }
     output.push_str("    ]\n");
@@ -128,8 +286,161 @@ pub fn write_generated_rs(path: &Path, specs: &[GeneratedScanSpec]) -> anyhow::R
         output.push_str("}\n\n");
     }
 
-    fs::write(path, output)?;
-    Ok(())
+    Ok(output)
+}
+
+/// Constructs generated code must not use
+const FORBIDDEN_PATTERNS: &[&str] = &[
+    "unsafe",
+    "std::process",
+    "std::fs",
+    "std::net",
+    "std::env",
+    "include!",
+    "include_str!",
+    "include_bytes!",
+    "extern ",
+    "#[no_mangle]",
+];
+
+const PARAM_TYPES: &[&str] = &["number", "text", "select"];
+
+/// Static checks on a spec before it is written to generated.rs.
+/// Returns a list of problems; empty means the spec passed.
+pub fn validate_spec(spec: &GeneratedScanSpec) -> Vec<String> {
+    let mut errors = Vec::new();
+    let id = normalize_scan_id(&spec.id);
+
+    if spec.id.trim().is_empty() {
+        errors.push("id is empty".to_string());
+    }
+    if spec.name.trim().is_empty() {
+        errors.push("name is empty".to_string());
+    }
+    if spec.function_body.trim().is_empty() {
+        errors.push("function_body is empty".to_string());
+    }
+
+    for param in &spec.params {
+        if !is_identifier(&param.name) {
+            errors.push(format!("param name '{}' is not a valid identifier", param.name));
+        }
+        if !PARAM_TYPES.contains(&param.param_type.as_str()) {
+            errors.push(format!(
+                "param '{}' has unsupported type '{}' (expected number|text|select)",
+                param.name, param.param_type
+            ));
+        }
+    }
+
+    let helpers = spec.helpers.as_deref().unwrap_or("");
+    for (label, code) in [("function_body", spec.function_body.as_str()), ("helpers", helpers)] {
+        for pattern in FORBIDDEN_PATTERNS {
+            if code.contains(pattern) {
+                errors.push(format!("{} uses forbidden construct '{}'", label, pattern.trim()));
+            }
+        }
+        if !balanced_delimiters(code) {
+            errors.push(format!("{} has unbalanced delimiters", label));
+        }
+    }
+
+    let prefix = format!("scan_{}_", id);
+    for name in helper_fn_names(helpers) {
+        if !name.starts_with(&prefix) {
+            errors.push(format!("helper fn '{}' must be prefixed with '{}'", name, prefix));
+        }
+    }
+
+    errors
+}
+
+/// Write `specs` into a scratch copy of the crate and run `cargo check` on it.
+/// Returns `Ok(None)` when it builds, `Ok(Some(diagnostics))` when it doesn't.
+/// Nothing in the real source tree is touched.
+pub fn compile_check(specs: &[GeneratedScanSpec]) -> anyhow::Result<Option<String>> {
+    let project = std::env::current_dir()?;
+    let scratch = std::env::temp_dir().join(format!(
+        "retro-validate-{}-{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_nanos()
+    ));
+    let scratch_src = scratch.join("src");
+    fs::create_dir_all(&scratch_src)?;
+
+    let result = (|| {
+        for file in ["Cargo.toml", "Cargo.lock"] {
+            let from = project.join(file);
+            if from.exists() {
+                fs::copy(&from, scratch.join(file))?;
+            }
+        }
+        for entry in fs::read_dir(project.join("src"))? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("rs") {
+                if let Some(name) = path.file_name() {
+                    fs::copy(&path, scratch_src.join(name))?;
+                }
+            }
+        }
+        write_generated_rs(&scratch_src.join("generated.rs"), specs)?;
+
+        // Separate target dir so checks don't contend with the dev build lock
+        let output = std::process::Command::new("cargo")
+            .args(["check", "--quiet", "--message-format", "short"])
+            .current_dir(&scratch)
+            .env("CARGO_TARGET_DIR", project.join("target").join("validate"))
+            .output()?;
+
+        if output.status.success() {
+            Ok(None)
+        } else {
+            Ok(Some(String::from_utf8_lossy(&output.stderr).into_owned()))
+        }
+    })();
+
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn balanced_delimiters(code: &str) -> bool {
+    let mut stack = Vec::new();
+    for ch in code.chars() {
+        let open = match ch {
+            '(' | '[' | '{' => {
+                stack.push(ch);
+                continue;
+            }
+            ')' => '(',
+            ']' => '[',
+            '}' => '{',
+            _ => continue,
+        };
+        if stack.pop() != Some(open) {
+            return false;
+        }
+    }
+    stack.is_empty()
+}
+
+fn helper_fn_names(helpers: &str) -> Vec<&str> {
+    helpers
+        .split("fn ")
+        .skip(1)
+        .filter_map(|rest| {
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))?;
+            Some(&rest[..end])
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
 }
 
 pub fn normalize_scan_id(id: &str) -> String {
@@ -188,6 +499,7 @@ pub fn generated_paths() -> (PathBuf, PathBuf) {
     (PathBuf::from(GENERATED_SCANS_PATH), PathBuf::from(GENERATED_RS_PATH))
 }
 
+#[allow(dead_code)]
 pub fn spec_to_scan_type(spec: &GeneratedScanSpec) -> ScanType {
     ScanType {
         id: spec.id.clone(),
@@ -201,7 +513,151 @@ pub fn spec_to_scan_type(spec: &GeneratedScanSpec) -> ScanType {
                 param_type: p.param_type.clone(),
                 default: p.default.clone(),
                 description: p.description.clone(),
+                min: p.min,
+                max: p.max,
+                integer: false,
             })
             .collect(),
+        default_lookback_days: spec.default_lookback_days,
+        version: Some(spec.version),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(body: &str, helpers: Option<&str>) -> GeneratedScanSpec {
+        GeneratedScanSpec {
+            id: "My Scan".into(),
+            name: "My Scan".into(),
+            description: "test".into(),
+            params: vec![GeneratedParam {
+                name: "period".into(),
+                param_type: "number".into(),
+                default: 14.into(),
+                description: "Period".into(),
+                min: None,
+                max: None,
+            }],
+            function_body: body.into(),
+            helpers: helpers.map(String::from),
+            default_lookback_days: None,
+            version: 1,
+            created_at: None,
+            updated_at: None,
+            history: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_validate_spec_accepts_clean_spec() {
+        let s = spec(
+            "let p = params.get(\"period\").and_then(|v| v.as_u64()).unwrap_or(14) as usize;\nabove(&rsi(&data.close, p), 70.0)",
+            Some("fn scan_my_scan_helper(x: f64) -> f64 { x }"),
+        );
+        assert!(validate_spec(&s).is_empty());
+    }
+
+    #[test]
+    fn test_validate_spec_rejects_problems() {
+        let s = spec("unsafe { std::process::exit(1) }", Some("fn helper() {}"));
+        let errors = validate_spec(&s);
+        assert!(errors.iter().any(|e| e.contains("unsafe")));
+        assert!(errors.iter().any(|e| e.contains("std::process")));
+        assert!(errors.iter().any(|e| e.contains("helper fn 'helper'")));
+
+        let s = spec("vec![false; data.close.len()", None);
+        assert!(validate_spec(&s).iter().any(|e| e.contains("unbalanced")));
+    }
+
+    #[test]
+    fn test_versions_and_legacy_specs() {
+        // Specs saved before versioning load as version 1
+        let legacy = r#"[{"id": "old_scan", "name": "Old Scan", "description": "old", "params": [],
+            "function_body": "vec![false; data.close.len()]", "helpers": null}]"#;
+        let mut specs: Vec<GeneratedScanSpec> = serde_json::from_str(legacy).unwrap();
+        assert_eq!((specs[0].version, specs[0].created_at.as_deref()), (1, None));
+
+        for i in 0..MAX_KEPT_VERSIONS + 2 {
+            let edit = GeneratedScanEdit { description: Some(format!("edit {}", i)), ..Default::default() };
+            let next = edit.apply(&specs[0]);
+            upsert_spec(&mut specs, next);
+        }
+        let current = &specs[0];
+        assert_eq!(specs.len(), 1);
+        assert_eq!(current.version, MAX_KEPT_VERSIONS as u32 + 3);
+        assert_eq!(current.description, format!("edit {}", MAX_KEPT_VERSIONS + 1));
+        assert_eq!(current.history.len(), MAX_KEPT_VERSIONS);
+        assert!(current.history.iter().all(|s| s.history.is_empty()));
+        assert!(current.at_version(1).is_none());
+        assert_eq!(current.at_version(4).unwrap().description, "edit 2");
+
+        // Reverting re-saves an old version on top
+        let reverted = current.at_version(4).cloned().unwrap();
+        upsert_spec(&mut specs, reverted);
+        assert_eq!((specs[0].version, specs[0].description.as_str()), (MAX_KEPT_VERSIONS as u32 + 4, "edit 2"));
+
+        upsert_spec(&mut specs, spec("vec![true; data.close.len()]", None));
+        assert_eq!(specs[1].version, 1);
+        assert!(specs[1].created_at.is_some());
+    }
+
+    #[test]
+    fn test_concurrent_saves_and_rollback() {
+        let dir = std::env::temp_dir().join(format!("retro-generated-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (json_path, rs_path) = (dir.join("generated_scans.json"), dir.join("generated.rs"));
+        let body = "vec![false; data.close.len()]";
+
+        // Writers share a lock as the server does; a reader polls throughout
+        // and must always find a complete store that never shrinks
+        let lock = std::sync::Mutex::new(());
+        let done = std::sync::atomic::AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let reader = scope.spawn(|| {
+                let mut seen = 0;
+                while !done.load(Ordering::Relaxed) {
+                    let count = load_specs(&json_path).expect("store readable mid-write").len();
+                    assert!(count >= seen, "store lost specs: {} -> {}", seen, count);
+                    seen = count;
+                }
+            });
+            let writers: Vec<_> = (0..8)
+                .map(|i| {
+                    let (lock, json_path, rs_path) = (&lock, &json_path, &rs_path);
+                    scope.spawn(move || {
+                        let spec = GeneratedScanSpec { id: format!("scan {}", i), ..spec(body, None) };
+                        let _guard = lock.lock().unwrap();
+                        save_generated(json_path, rs_path, spec).unwrap();
+                    })
+                })
+                .collect();
+            for writer in writers {
+                writer.join().unwrap();
+            }
+            done.store(true, Ordering::Relaxed);
+            reader.join().unwrap();
+        });
+
+        let specs = load_specs(&json_path).unwrap();
+        let mut ids: Vec<&str> = specs.iter().map(|s| s.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, (0..8).map(|i| format!("scan_{}", i)).collect::<Vec<_>>());
+        let rs = fs::read_to_string(&rs_path).unwrap();
+        assert!(ids.iter().all(|id| rs.contains(&format!("fn scan_{}(", id))));
+        // No temp files left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 4);
+
+        // Replacing a spec keeps the others, and rollback brings back the old one
+        let changed = GeneratedScanSpec { id: "scan 3".into(), ..spec("vec![true; data.close.len()]", None) };
+        save_generated(&json_path, &rs_path, changed).unwrap();
+        assert!(fs::read_to_string(&rs_path).unwrap().contains("vec![true"));
+        let restored = rollback_generated(&json_path, &rs_path).unwrap().unwrap();
+        assert_eq!(restored.len(), 8);
+        assert_eq!(fs::read_to_string(&rs_path).unwrap(), rs);
+        assert!(rollback_generated(&json_path, &rs_path).unwrap().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,142 @@
+//! Scan history - an append-only JSONL audit trail of executed scans
+//!
+//! Handlers hand entries to a writer thread over a channel, so logging never
+//! blocks or fails a scan.
+
+use crate::scanner::ScanQuery;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+pub const DEFAULT_HISTORY_PATH: &str = "./data/scan_history.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// RFC 3339, UTC
+    pub timestamp: String,
+    /// The query as run, after defaults were applied; can be posted back to `/api/scan`
+    pub query: ScanQuery,
+    pub total_matches: usize,
+    pub tickers_with_matches: usize,
+    pub scan_time_ms: u64,
+}
+
+pub struct ScanHistory {
+    path: PathBuf,
+    tx: mpsc::UnboundedSender<HistoryEntry>,
+}
+
+impl ScanHistory {
+    /// Start the writer thread appending to `path`
+    pub fn spawn(path: PathBuf) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel::<HistoryEntry>();
+        let writer_path = path.clone();
+
+        std::thread::Builder::new()
+            .name("scan-history".into())
+            .spawn(move || {
+                while let Some(entry) = rx.blocking_recv() {
+                    if let Err(e) = append_entry(&writer_path, &entry) {
+                        tracing::warn!("Could not write scan history to {:?}: {}", writer_path, e);
+                    }
+                }
+            })
+            .expect("failed to spawn scan history writer");
+
+        Self { path, tx }
+    }
+
+    /// `RETRO_SCAN_HISTORY_PATH`, or `./data/scan_history.jsonl`
+    pub fn from_env() -> Self {
+        let path = std::env::var("RETRO_SCAN_HISTORY_PATH")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_HISTORY_PATH.to_string());
+        Self::spawn(PathBuf::from(path))
+    }
+
+    /// Queue an entry; never blocks
+    pub fn record(&self, entry: HistoryEntry) {
+        if self.tx.send(entry).is_err() {
+            tracing::warn!("Scan history writer has stopped; entry dropped");
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn append_entry(path: &Path, entry: &HistoryEntry) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    OpenOptions::new().create(true).append(true).open(path)?.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Up to `limit` most recent entries, newest first. A missing file is empty
+/// history; unparseable lines are skipped.
+pub fn read_recent(path: &Path, limit: usize) -> anyhow::Result<Vec<HistoryEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let raw = fs::read_to_string(path)?;
+    Ok(raw
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .take(limit)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn entry(scan_type: &str, total_matches: usize) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: "2024-01-05T12:00:00Z".into(),
+            query: ScanQuery {
+                scan_type: scan_type.into(),
+                ..Default::default()
+            },
+            total_matches,
+            tickers_with_matches: 1,
+            scan_time_ms: 3,
+        }
+    }
+
+    #[test]
+    fn test_history_round_trip_newest_first() {
+        let path = std::env::temp_dir().join(format!("retro-history-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let history = ScanHistory::spawn(path.clone());
+        for (i, scan) in ["golden_cross", "rsi_oversold", "ema_cross"].iter().enumerate() {
+            history.record(entry(scan, i));
+        }
+
+        // Writes are asynchronous
+        let mut recent = Vec::new();
+        for _ in 0..100 {
+            recent = read_recent(&path, 2).unwrap();
+            if recent.first().is_some_and(|e| e.query.scan_type == "ema_cross") {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let scans: Vec<&str> = recent.iter().map(|e| e.query.scan_type.as_str()).collect();
+        assert_eq!(scans, ["ema_cross", "rsi_oversold"]);
+        assert_eq!(recent[0].total_matches, 2);
+
+        fs::remove_file(&path).unwrap();
+        assert!(read_recent(&path, 10).unwrap().is_empty());
+    }
+}
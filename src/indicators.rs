@@ -1,46 +1,91 @@
 //! Technical indicators - optimized for speed
-//! All functions operate on slices and return Vec<f64> or Vec<bool>
+//! All functions operate on slices and return Vec<f64> or Vec<bool>. The
+//! hot ones also have an `_into` variant writing into a caller's buffer, so
+//! bulk scans can reuse allocations (see `scratch`).
+//!
+//! They are also the toolkit for generated scans, so the ones no built-in
+//! scan calls yet are marked `allow(dead_code)` rather than removed.
 
-/// Simple Moving Average - O(n) using rolling sum
+/// Simple Moving Average - O(n) using rolling sum. Like `ema`, leading NaNs
+/// are skipped, so another indicator's output can be smoothed.
 #[inline]
 pub fn sma(data: &[f64], period: usize) -> Vec<f64> {
+    let mut result = Vec::new();
+    sma_into(data, period, &mut result);
+    result
+}
+
+/// `sma` into `result`, replacing its contents
+#[inline]
+pub fn sma_into(data: &[f64], period: usize, result: &mut Vec<f64>) {
     let n = data.len();
-    let mut result = vec![f64::NAN; n];
+    result.clear();
+    result.resize(n, f64::NAN);
     
-    if n < period || period == 0 {
-        return result;
+    let start = data.iter().position(|v| !v.is_nan()).unwrap_or(n);
+    if n - start < period || period == 0 {
+        return;
     }
     
-    let mut sum: f64 = data[..period].iter().sum();
-    result[period - 1] = sum / period as f64;
+    let mut sum: f64 = data[start..start + period].iter().sum();
+    result[start + period - 1] = sum / period as f64;
     
-    for i in period..n {
+    for i in (start + period)..n {
         sum += data[i] - data[i - period];
         result[i] = sum / period as f64;
     }
-    
-    result
 }
 
-/// Exponential Moving Average
+/// Exponential Moving Average. Leading NaNs (e.g. another indicator's
+/// warm-up) are skipped, so EMAs of EMAs like the MACD signal line work.
 #[inline]
 pub fn ema(data: &[f64], period: usize) -> Vec<f64> {
+    let mut result = Vec::new();
+    ema_into(data, period, &mut result);
+    result
+}
+
+/// `ema` into `result`, replacing its contents
+#[inline]
+pub fn ema_into(data: &[f64], period: usize, result: &mut Vec<f64>) {
     let n = data.len();
-    let mut result = vec![f64::NAN; n];
+    result.clear();
+    result.resize(n, f64::NAN);
     
-    if n < period || period == 0 {
-        return result;
+    let start = data.iter().position(|v| !v.is_nan()).unwrap_or(n);
+    if n - start < period || period == 0 {
+        return;
     }
     
     let multiplier = 2.0 / (period as f64 + 1.0);
     
     // First EMA = SMA
-    let first_sma: f64 = data[..period].iter().sum::<f64>() / period as f64;
-    result[period - 1] = first_sma;
+    let first_sma: f64 = data[start..start + period].iter().sum::<f64>() / period as f64;
+    result[start + period - 1] = first_sma;
     
-    for i in period..n {
+    for i in (start + period)..n {
         result[i] = (data[i] - result[i - 1]) * multiplier + result[i - 1];
     }
+}
+
+/// Weighted Moving Average - linear weights, the newest bar weighing
+/// `period` and the oldest 1. Leading NaNs are skipped as in `sma`.
+#[inline]
+pub fn wma(data: &[f64], period: usize) -> Vec<f64> {
+    let n = data.len();
+    let mut result = vec![f64::NAN; n];
+    
+    let start = data.iter().position(|v| !v.is_nan()).unwrap_or(n);
+    if n - start < period || period == 0 {
+        return result;
+    }
+    
+    let denominator = (period * (period + 1) / 2) as f64;
+    for i in (start + period - 1)..n {
+        let window = &data[i + 1 - period..=i];
+        let weighted: f64 = window.iter().enumerate().map(|(k, v)| (k + 1) as f64 * v).sum();
+        result[i] = weighted / denominator;
+    }
     
     result
 }
@@ -48,49 +93,53 @@ pub fn ema(data: &[f64], period: usize) -> Vec<f64> {
 /// Relative Strength Index
 #[inline]
 pub fn rsi(data: &[f64], period: usize) -> Vec<f64> {
+    let mut result = Vec::new();
+    rsi_into(data, period, &mut result);
+    result
+}
+
+/// `rsi` into `result`, replacing its contents. Gains and losses are taken
+/// bar by bar rather than collected first, so nothing else is allocated.
+#[inline]
+pub fn rsi_into(data: &[f64], period: usize, result: &mut Vec<f64>) {
     let n = data.len();
-    let mut result = vec![f64::NAN; n];
+    result.clear();
+    result.resize(n, f64::NAN);
     
     if n < period + 1 || period == 0 {
-        return result;
+        return;
     }
     
-    // Calculate gains and losses
-    let mut gains = vec![0.0; n];
-    let mut losses = vec![0.0; n];
-    
-    for i in 1..n {
+    // Gain and loss of the move into bar i
+    let change = |i: usize| {
         let change = data[i] - data[i - 1];
         if change > 0.0 {
-            gains[i] = change;
+            (change, 0.0)
         } else {
-            losses[i] = -change;
+            (0.0, -change)
         }
-    }
-    
-    // First average (SMA)
-    let mut avg_gain: f64 = gains[1..=period].iter().sum::<f64>() / period as f64;
-    let mut avg_loss: f64 = losses[1..=period].iter().sum::<f64>() / period as f64;
-    
-    result[period] = if avg_loss == 0.0 {
-        100.0
-    } else {
-        100.0 - (100.0 / (1.0 + avg_gain / avg_loss))
     };
-    
-    // Smoothed average (Wilder's method)
-    for i in (period + 1)..n {
-        avg_gain = (avg_gain * (period - 1) as f64 + gains[i]) / period as f64;
-        avg_loss = (avg_loss * (period - 1) as f64 + losses[i]) / period as f64;
-        
-        result[i] = if avg_loss == 0.0 {
+    let rsi_of = |avg_gain: f64, avg_loss: f64| {
+        if avg_loss == 0.0 {
             100.0
         } else {
             100.0 - (100.0 / (1.0 + avg_gain / avg_loss))
-        };
-    }
+        }
+    };
     
-    result
+    // First average (SMA)
+    let (mut avg_gain, mut avg_loss) = (1..=period).map(change).fold((0.0, 0.0), |(g, l), (dg, dl)| (g + dg, l + dl));
+    avg_gain /= period as f64;
+    avg_loss /= period as f64;
+    result[period] = rsi_of(avg_gain, avg_loss);
+    
+    // Smoothed average (Wilder's method)
+    for (i, value) in result.iter_mut().enumerate().skip(period + 1) {
+        let (gain, loss) = change(i);
+        avg_gain = (avg_gain * (period - 1) as f64 + gain) / period as f64;
+        avg_loss = (avg_loss * (period - 1) as f64 + loss) / period as f64;
+        *value = rsi_of(avg_gain, avg_loss);
+    }
 }
 
 /// On-Balance Volume
@@ -112,6 +161,37 @@ pub fn obv(close: &[f64], volume: &[f64]) -> Vec<f64> {
     result
 }
 
+/// Chande Momentum Oscillator - 100 * (gains - losses) / (gains + losses)
+/// over the last `period` changes, in -100..100. NaN during warm-up and on
+/// flat windows (zero denominator).
+#[inline]
+pub fn cmo(data: &[f64], period: usize) -> Vec<f64> {
+    let n = data.len();
+    let mut result = vec![f64::NAN; n];
+    
+    if n < period + 1 || period == 0 {
+        return result;
+    }
+    
+    // Summed per window (not rolled) so values don't depend on where the series starts
+    for (i, out) in result.iter_mut().enumerate().skip(period) {
+        let (mut up, mut down) = (0.0, 0.0);
+        for w in data[(i - period)..=i].windows(2) {
+            let change = w[1] - w[0];
+            if change > 0.0 {
+                up += change;
+            } else {
+                down -= change;
+            }
+        }
+        if up + down > 0.0 {
+            *out = 100.0 * (up - down) / (up + down);
+        }
+    }
+    
+    result
+}
+
 /// MACD Line
 #[inline]
 pub fn macd(data: &[f64], fast: usize, slow: usize) -> Vec<f64> {
@@ -145,215 +225,1158 @@ pub fn macd_histogram(data: &[f64], fast: usize, slow: usize, signal: usize) ->
         .collect()
 }
 
-/// Average True Range
+/// Elder Ray bull power - high minus the EMA of close
 #[inline]
-pub fn atr(high: &[f64], low: &[f64], close: &[f64], period: usize) -> Vec<f64> {
-    let n = high.len();
-    let mut tr = vec![0.0; n];
-    
-    tr[0] = high[0] - low[0];
-    
-    for i in 1..n {
-        let hl = high[i] - low[i];
-        let hc = (high[i] - close[i - 1]).abs();
-        let lc = (low[i] - close[i - 1]).abs();
-        tr[i] = hl.max(hc).max(lc);
-    }
-    
-    sma(&tr, period)
+pub fn bull_power(high: &[f64], close: &[f64], ema_period: usize) -> Vec<f64> {
+    high.iter().zip(ema(close, ema_period)).map(|(h, e)| h - e).collect()
 }
 
-/// Bollinger Bands - returns (middle, upper, lower)
+/// Elder Ray bear power - low minus the EMA of close
 #[inline]
-pub fn bollinger(data: &[f64], period: usize, num_std: f64) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
-    let n = data.len();
-    let middle = sma(data, period);
-    let mut upper = vec![f64::NAN; n];
-    let mut lower = vec![f64::NAN; n];
-    
-    for i in (period - 1)..n {
-        let slice = &data[(i + 1 - period)..=i];
-        let mean = middle[i];
-        let variance: f64 = slice.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / period as f64;
-        let std_dev = variance.sqrt();
-        
-        upper[i] = mean + num_std * std_dev;
-        lower[i] = mean - num_std * std_dev;
-    }
-    
-    (middle, upper, lower)
+pub fn bear_power(low: &[f64], close: &[f64], ema_period: usize) -> Vec<f64> {
+    low.iter().zip(ema(close, ema_period)).map(|(l, e)| l - e).collect()
 }
 
-/// Rolling Maximum
+/// Elder Impulse System - +1 (green) when both the EMA and the MACD histogram
+/// rose on the bar, -1 (red) when both fell, 0 (blue) otherwise. NaN until
+/// both have a previous value.
 #[inline]
-pub fn rolling_max(data: &[f64], period: usize) -> Vec<f64> {
-    let n = data.len();
-    let mut result = vec![f64::NAN; n];
+pub fn elder_impulse(close: &[f64], ema_period: usize, fast: usize, slow: usize, signal: usize) -> Vec<f64> {
+    let trend = ema(close, ema_period);
+    let hist = macd_histogram(close, fast, slow, signal);
+    let mut result = vec![f64::NAN; close.len()];
     
-    for i in (period - 1)..n {
-        result[i] = data[(i + 1 - period)..=i]
-            .iter()
-            .fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+    for i in 1..close.len() {
+        let ema_slope = trend[i] - trend[i - 1];
+        let hist_slope = hist[i] - hist[i - 1];
+        if ema_slope.is_nan() || hist_slope.is_nan() {
+            continue;
+        }
+        result[i] = if ema_slope > 0.0 && hist_slope > 0.0 {
+            1.0
+        } else if ema_slope < 0.0 && hist_slope < 0.0 {
+            -1.0
+        } else {
+            0.0
+        };
     }
     
     result
 }
 
-/// Rolling Minimum
+/// Detrended Price Oscillator - the price `period / 2 + 1` bars back minus
+/// the current `period`-bar SMA, which strips the trend and leaves the
+/// shorter cycles. Charts often plot it centered, moved back those same bars,
+/// which leaves NaN at both ends and needs bars not yet seen; here each value
+/// stays on the bar it first becomes known. NaN for the first
+/// `max(period - 1, period / 2 + 1)` bars.
 #[inline]
-pub fn rolling_min(data: &[f64], period: usize) -> Vec<f64> {
-    let n = data.len();
-    let mut result = vec![f64::NAN; n];
+pub fn dpo(data: &[f64], period: usize) -> Vec<f64> {
+    let shift = period / 2 + 1;
+    let average = sma(data, period);
     
-    for i in (period - 1)..n {
-        result[i] = data[(i + 1 - period)..=i]
-            .iter()
-            .fold(f64::INFINITY, |a, &b| a.min(b));
-    }
-    
-    result
+    (0..data.len())
+        .map(|i| if i >= shift { data[i - shift] - average[i] } else { f64::NAN })
+        .collect()
 }
 
-/// Standard Deviation
+/// TRIX - 1-bar percent change of a triple-smoothed EMA. Each EMA starts
+/// where the previous one's warm-up ends, so the first value is at bar
+/// `3 * (period - 1) + 1`.
 #[inline]
-pub fn stddev(data: &[f64], period: usize) -> Vec<f64> {
-    let n = data.len();
-    let mut result = vec![f64::NAN; n];
-    let means = sma(data, period);
+pub fn trix(data: &[f64], period: usize) -> Vec<f64> {
+    let triple = ema(&ema(&ema(data, period), period), period);
+    let mut result = vec![f64::NAN; triple.len()];
     
-    for i in (period - 1)..n {
-        let slice = &data[(i + 1 - period)..=i];
-        let mean = means[i];
-        let variance: f64 = slice.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / period as f64;
-        result[i] = variance.sqrt();
+    for i in 1..triple.len() {
+        let prev = triple[i - 1];
+        if prev.is_finite() && prev != 0.0 {
+            result[i] = (triple[i] - prev) / prev * 100.0;
+        }
     }
     
     result
 }
 
-/// Volume Weighted Average Price (intraday approximation)
+/// Ehlers' Fisher Transform - returns (fisher, trigger). The median price
+/// `(high + low) / 2` is placed in its `period`-bar range as -1..1, smoothed
+/// (0.33 new, 0.67 prior) and clamped to ±0.999 so the log stays finite;
+/// Fisher is `0.5 * ln((1 + v) / (1 - v))` plus half its prior value, and the
+/// trigger is Fisher one bar back. Fisher starts at bar `period - 1`, the
+/// trigger a bar later. A flat window counts as mid-range.
 #[inline]
-pub fn vwap(high: &[f64], low: &[f64], close: &[f64], volume: &[f64]) -> Vec<f64> {
+pub fn fisher_transform(high: &[f64], low: &[f64], period: usize) -> (Vec<f64>, Vec<f64>) {
     let n = high.len();
-    let mut result = vec![0.0; n];
-    
-    let mut cum_vol = 0.0;
-    let mut cum_tp_vol = 0.0;
-    
-    for i in 0..n {
-        let typical_price = (high[i] + low[i] + close[i]) / 3.0;
-        cum_vol += volume[i];
-        cum_tp_vol += typical_price * volume[i];
-        
-        result[i] = if cum_vol > 0.0 { cum_tp_vol / cum_vol } else { typical_price };
+    let mut fisher = vec![f64::NAN; n];
+    let mut trigger = vec![f64::NAN; n];
+    if period == 0 || n < period {
+        return (fisher, trigger);
     }
     
-    result
+    let median: Vec<f64> = high.iter().zip(low).map(|(h, l)| (h + l) / 2.0).collect();
+    let highest = rolling_max(&median, period);
+    let lowest = rolling_min(&median, period);
+    let (mut value, mut prior) = (0.0, 0.0);
+    for i in (period - 1)..n {
+        let range = highest[i] - lowest[i];
+        let position = if range > 0.0 { (median[i] - lowest[i]) / range } else { 0.5 };
+        value = (0.66 * (position - 0.5) + 0.67 * value).clamp(-0.999, 0.999);
+        fisher[i] = 0.5 * ((1.0 + value) / (1.0 - value)).ln() + 0.5 * prior;
+        if i >= period {
+            trigger[i] = prior;
+        }
+        prior = fisher[i];
+    }
+    (fisher, trigger)
 }
 
-// ============================================
-// CONDITION DETECTION
-// ============================================
+/// Classic KST settings (Pring): ROC periods, their SMA smoothing, weights,
+/// and the signal line's SMA period
+pub const KST_ROC: [usize; 4] = [10, 15, 20, 30];
+pub const KST_SMOOTH: [usize; 4] = [10, 10, 10, 15];
+pub const KST_WEIGHTS: [f64; 4] = [1.0, 2.0, 3.0, 4.0];
+pub const KST_SIGNAL: usize = 9;
 
-/// Crossed above: A crosses above B
+/// Know Sure Thing - returns (kst, signal): the weighted sum of four
+/// SMA-smoothed rates of change, and an SMA of that sum. KST starts once the
+/// slowest leg is smoothed, at bar `max(roc + smooth) - 1` (44 with the
+/// classic settings), and the signal `signal - 1` bars later.
 #[inline]
-pub fn crossed_above(a: &[f64], b: &[f64]) -> Vec<bool> {
-    let n = a.len();
-    let mut result = vec![false; n];
-    
-    for i in 1..n {
-        if !a[i].is_nan() && !b[i].is_nan() && !a[i-1].is_nan() && !b[i-1].is_nan() {
-            result[i] = a[i] > b[i] && a[i - 1] <= b[i - 1];
+pub fn kst(
+    data: &[f64],
+    roc: [usize; 4],
+    smooth: [usize; 4],
+    weights: [f64; 4],
+    signal: usize,
+) -> (Vec<f64>, Vec<f64>) {
+    let mut line = vec![0.0; data.len()];
+    for k in 0..4 {
+        let leg = sma(&pct_change(data, roc[k]), smooth[k]);
+        for (total, v) in line.iter_mut().zip(leg) {
+            *total += weights[k] * v;
         }
     }
+    let signal_line = sma(&line, signal);
+    (line, signal_line)
+}
+
+/// Coppock Curve settings, in months as Coppock defined them: the two ROC
+/// periods and the WMA over their sum
+pub const COPPOCK_ROC_LONG: usize = 14;
+pub const COPPOCK_ROC_SHORT: usize = 11;
+pub const COPPOCK_WMA: usize = 10;
+
+/// Coppock Curve - a WMA of the sum of two rates of change, meant for
+/// monthly bars. Starts at bar `max(roc_long, roc_short) + wma_period - 1`
+/// (23 with the classic settings).
+#[inline]
+pub fn coppock(data: &[f64], roc_long: usize, roc_short: usize, wma_period: usize) -> Vec<f64> {
+    let sum: Vec<f64> = pct_change(data, roc_long)
+        .iter()
+        .zip(pct_change(data, roc_short))
+        .map(|(long, short)| long + short)
+        .collect();
+    wma(&sum, wma_period)
+}
+
+/// Percentage Price Oscillator - the MACD line as a percentage of the slow
+/// EMA, so levels compare across price ranges. NaN where the slow EMA is
+/// NaN or zero.
+#[inline]
+pub fn ppo(data: &[f64], fast: usize, slow: usize) -> Vec<f64> {
+    let ema_fast = ema(data, fast);
+    let ema_slow = ema(data, slow);
     
-    result
+    ema_fast
+        .iter()
+        .zip(ema_slow.iter())
+        .map(|(&f, &s)| if s.is_finite() && s != 0.0 { (f - s) / s * 100.0 } else { f64::NAN })
+        .collect()
 }
 
-/// Crossed below: A crosses below B
+/// PPO Signal Line
 #[inline]
-pub fn crossed_below(a: &[f64], b: &[f64]) -> Vec<bool> {
-    let n = a.len();
-    let mut result = vec![false; n];
+pub fn ppo_signal(data: &[f64], fast: usize, slow: usize, signal: usize) -> Vec<f64> {
+    ema(&ppo(data, fast, slow), signal)
+}
+
+/// PPO Histogram
+#[inline]
+pub fn ppo_histogram(data: &[f64], fast: usize, slow: usize, signal: usize) -> Vec<f64> {
+    let ppo_line = ppo(data, fast, slow);
+    let signal_line = ema(&ppo_line, signal);
+    
+    ppo_line
+        .iter()
+        .zip(signal_line.iter())
+        .map(|(p, s)| p - s)
+        .collect()
+}
+
+/// True Range - max(high, prev close) - min(low, prev close); the first bar
+/// has no previous close and uses high - low
+#[inline]
+pub fn true_range(high: &[f64], low: &[f64], close: &[f64]) -> Vec<f64> {
+    let n = high.len();
+    let mut tr = vec![0.0; n];
+    
+    if n == 0 {
+        return tr;
+    }
+    
+    tr[0] = high[0] - low[0];
     
     for i in 1..n {
-        if !a[i].is_nan() && !b[i].is_nan() && !a[i-1].is_nan() && !b[i-1].is_nan() {
-            result[i] = a[i] < b[i] && a[i - 1] >= b[i - 1];
-        }
+        let hl = high[i] - low[i];
+        let hc = (high[i] - close[i - 1]).abs();
+        let lc = (low[i] - close[i - 1]).abs();
+        tr[i] = hl.max(hc).max(lc);
     }
     
-    result
+    tr
 }
 
-/// Higher high: value > rolling max of previous N periods
+/// Average True Range
 #[inline]
-pub fn higher_high(data: &[f64], lookback: usize) -> Vec<bool> {
-    let n = data.len();
-    let mut result = vec![false; n];
-    let prev_max = rolling_max(data, lookback);
+pub fn atr(high: &[f64], low: &[f64], close: &[f64], period: usize) -> Vec<f64> {
+    sma(&true_range(high, low, close), period)
+}
+
+/// Ultimate Oscillator - buying pressure (close - min(low, prev close)) over
+/// true range, averaged across three windows weighted 4/2/1, in 0..100.
+/// Standard periods are 7/14/28. NaN until `long` bars have a previous close,
+/// and on windows with zero range.
+#[inline]
+pub fn ultimate_oscillator(
+    high: &[f64],
+    low: &[f64],
+    close: &[f64],
+    short: usize,
+    medium: usize,
+    long: usize,
+) -> Vec<f64> {
+    let n = close.len();
+    let mut result = vec![f64::NAN; n];
+    let longest = short.max(medium).max(long);
     
-    for i in lookback..n {
-        if !data[i].is_nan() && !prev_max[i - 1].is_nan() {
-            result[i] = data[i] > prev_max[i - 1];
+    if short == 0 || medium == 0 || long == 0 || n < longest + 1 {
+        return result;
+    }
+    
+    let tr = true_range(high, low, close);
+    let bp: Vec<f64> = (0..n)
+        .map(|i| if i == 0 { 0.0 } else { close[i] - low[i].min(close[i - 1]) })
+        .collect();
+    
+    // Summed per window (not rolled) so values don't depend on where the series starts
+    let average = |i: usize, period: usize| {
+        let range = (i + 1 - period)..=i;
+        let tr_sum: f64 = tr[range.clone()].iter().sum();
+        (tr_sum > 0.0).then(|| bp[range].iter().sum::<f64>() / tr_sum)
+    };
+    
+    for (i, out) in result.iter_mut().enumerate().skip(longest) {
+        if let (Some(s), Some(m), Some(l)) = (average(i, short), average(i, medium), average(i, long)) {
+            *out = 100.0 * (4.0 * s + 2.0 * m + l) / 7.0;
         }
     }
     
     result
 }
 
-/// Lower low: value < rolling min of previous N periods
+/// Vortex Indicator - returns (VI+, VI-): the sums of |high - prior low| and
+/// |low - prior high| over `period` bars, each divided by the summed true
+/// range. NaN until `period` bars have a prior bar, and on zero-range windows.
 #[inline]
-pub fn lower_low(data: &[f64], lookback: usize) -> Vec<bool> {
-    let n = data.len();
-    let mut result = vec![false; n];
-    let prev_min = rolling_min(data, lookback);
+pub fn vortex(high: &[f64], low: &[f64], close: &[f64], period: usize) -> (Vec<f64>, Vec<f64>) {
+    let n = close.len();
+    let mut plus = vec![f64::NAN; n];
+    let mut minus = vec![f64::NAN; n];
     
-    for i in lookback..n {
-        if !data[i].is_nan() && !prev_min[i - 1].is_nan() {
-            result[i] = data[i] < prev_min[i - 1];
+    if period == 0 || n < period + 1 {
+        return (plus, minus);
+    }
+    
+    let tr = true_range(high, low, close);
+    let vm_plus: Vec<f64> = (0..n).map(|i| if i == 0 { 0.0 } else { (high[i] - low[i - 1]).abs() }).collect();
+    let vm_minus: Vec<f64> = (0..n).map(|i| if i == 0 { 0.0 } else { (low[i] - high[i - 1]).abs() }).collect();
+    
+    // Summed per window (not rolled) so values don't depend on where the series starts
+    for i in period..n {
+        let range = (i + 1 - period)..=i;
+        let tr_sum: f64 = tr[range.clone()].iter().sum();
+        if tr_sum > 0.0 {
+            plus[i] = vm_plus[range.clone()].iter().sum::<f64>() / tr_sum;
+            minus[i] = vm_minus[range].iter().sum::<f64>() / tr_sum;
         }
     }
     
-    result
+    (plus, minus)
 }
 
-/// Percent change
+/// Mass Index - the `sum_period`-bar sum of EMA(high - low) / EMA(EMA(high - low)),
+/// Dorsey's range-expansion gauge (standard 9/25; a flat range sums to
+/// `sum_period`). The double EMA is first valid at bar `2 * (ema_period - 1)`,
+/// so the index starts at bar `2 * (ema_period - 1) + sum_period - 1`. NaN on
+/// windows where the double EMA is zero.
 #[inline]
-pub fn pct_change(data: &[f64], periods: usize) -> Vec<f64> {
-    let n = data.len();
+pub fn mass_index(high: &[f64], low: &[f64], ema_period: usize, sum_period: usize) -> Vec<f64> {
+    let n = high.len();
     let mut result = vec![f64::NAN; n];
     
-    for i in periods..n {
-        if data[i - periods] != 0.0 {
-            result[i] = (data[i] - data[i - periods]) / data[i - periods] * 100.0;
-        }
+    if sum_period == 0 {
+        return result;
+    }
+    
+    let range: Vec<f64> = high.iter().zip(low).map(|(h, l)| h - l).collect();
+    let single = ema(&range, ema_period);
+    let double = ema(&single, ema_period);
+    let ratio: Vec<f64> = single
+        .iter()
+        .zip(&double)
+        .map(|(&s, &d)| if d != 0.0 { s / d } else { f64::NAN })
+        .collect();
+    
+    // Summed per window, so a NaN ratio only spoils the windows holding it
+    for i in (sum_period - 1)..n {
+        result[i] = ratio[(i + 1 - sum_period)..=i].iter().sum();
     }
     
     result
 }
 
-/// Volume ratio: current volume / average volume
+/// Chandelier Exit - returns (long, short): highest high over `period` minus
+/// ATR × `atr_mult`, and lowest low over `period` plus ATR × `atr_mult`
 #[inline]
-pub fn volume_ratio(volume: &[f64], period: usize) -> Vec<f64> {
-    let avg = sma(volume, period);
+pub fn chandelier_exit(
+    high: &[f64],
+    low: &[f64],
+    close: &[f64],
+    period: usize,
+    atr_mult: f64,
+) -> (Vec<f64>, Vec<f64>) {
+    let n = close.len();
+    if period == 0 || n < period {
+        return (vec![f64::NAN; n], vec![f64::NAN; n]);
+    }
     
-    volume
+    let atr_vals = atr(high, low, close, period);
+    let highest = rolling_max(high, period);
+    let lowest = rolling_min(low, period);
+    
+    let long = (0..n).map(|i| highest[i] - atr_vals[i] * atr_mult).collect();
+    let short = (0..n).map(|i| lowest[i] + atr_vals[i] * atr_mult).collect();
+    (long, short)
+}
+
+/// Long chandelier stop anchored at bar `entry`: highest high since entry
+/// minus ATR × `atr_mult`, ratcheting up only. NaN before entry and while ATR
+/// warms up.
+#[inline]
+pub fn chandelier_stop_from(
+    high: &[f64],
+    low: &[f64],
+    close: &[f64],
+    entry: usize,
+    period: usize,
+    atr_mult: f64,
+) -> Vec<f64> {
+    let n = close.len();
+    let mut result = vec![f64::NAN; n];
+    if period == 0 {
+        return result;
+    }
+    
+    let atr_vals = atr(high, low, close, period);
+    let mut highest = f64::NEG_INFINITY;
+    let mut stop = f64::NAN;
+    
+    for i in entry..n {
+        highest = highest.max(high[i]);
+        let level = highest - atr_vals[i] * atr_mult;
+        if !level.is_nan() {
+            stop = if stop.is_nan() { level } else { stop.max(level) };
+        }
+        result[i] = stop;
+    }
+    
+    result
+}
+
+/// Pivot point formula
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PivotMethod {
+    Classic,
+    Fibonacci,
+    Camarilla,
+}
+
+impl PivotMethod {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "classic" | "standard" => Some(PivotMethod::Classic),
+            "fibonacci" | "fib" => Some(PivotMethod::Fibonacci),
+            "camarilla" => Some(PivotMethod::Camarilla),
+            _ => None,
+        }
+    }
+}
+
+/// Pivot levels per bar, each derived from the previous bar
+#[derive(Debug, Clone)]
+pub struct PivotLevels {
+    pub pivot: Vec<f64>,
+    pub r1: Vec<f64>,
+    pub r2: Vec<f64>,
+    pub r3: Vec<f64>,
+    pub s1: Vec<f64>,
+    pub s2: Vec<f64>,
+    pub s3: Vec<f64>,
+}
+
+impl PivotLevels {
+    /// Level by name: `pivot`, `r1`-`r3` or `s1`-`s3`
+    pub fn level(&self, name: &str) -> Option<&[f64]> {
+        match name.trim().to_lowercase().as_str() {
+            "pivot" | "p" => Some(&self.pivot),
+            "r1" => Some(&self.r1),
+            "r2" => Some(&self.r2),
+            "r3" => Some(&self.r3),
+            "s1" => Some(&self.s1),
+            "s2" => Some(&self.s2),
+            "s3" => Some(&self.s3),
+            _ => None,
+        }
+    }
+}
+
+/// Pivot points - bar `i` gets the levels from bar `i - 1`'s high, low and
+/// close, so a daily series gives today's pivots from yesterday's bar (pass
+/// resampled bars for weekly/monthly pivots). P = (H+L+C)/3 for every method.
+/// Classic: R1/S1 = 2P - L / 2P - H, R2/S2 = P ± (H-L), R3/S3 = H + 2(P-L) /
+/// L - 2(H-P). Fibonacci: P ± 0.382, 0.618 and 1.0 of the range. Camarilla:
+/// C ± 1.1/12, 1.1/6 and 1.1/4 of the range. The first bar is NaN.
+#[inline]
+pub fn pivot_points(high: &[f64], low: &[f64], close: &[f64], method: PivotMethod) -> PivotLevels {
+    let n = close.len();
+    let mut levels = PivotLevels {
+        pivot: vec![f64::NAN; n],
+        r1: vec![f64::NAN; n],
+        r2: vec![f64::NAN; n],
+        r3: vec![f64::NAN; n],
+        s1: vec![f64::NAN; n],
+        s2: vec![f64::NAN; n],
+        s3: vec![f64::NAN; n],
+    };
+    
+    for i in 1..n {
+        let (h, l, c) = (high[i - 1], low[i - 1], close[i - 1]);
+        let p = (h + l + c) / 3.0;
+        let range = h - l;
+        
+        let ([r1, r2, r3], [s1, s2, s3]) = match method {
+            PivotMethod::Classic => (
+                [2.0 * p - l, p + range, h + 2.0 * (p - l)],
+                [2.0 * p - h, p - range, l - 2.0 * (h - p)],
+            ),
+            PivotMethod::Fibonacci => (
+                [0.382, 0.618, 1.0].map(|k| p + k * range),
+                [0.382, 0.618, 1.0].map(|k| p - k * range),
+            ),
+            PivotMethod::Camarilla => (
+                [12.0, 6.0, 4.0].map(|d| c + range * 1.1 / d),
+                [12.0, 6.0, 4.0].map(|d| c - range * 1.1 / d),
+            ),
+        };
+        
+        levels.pivot[i] = p;
+        levels.r1[i] = r1;
+        levels.r2[i] = r2;
+        levels.r3[i] = r3;
+        levels.s1[i] = s1;
+        levels.s2[i] = s2;
+        levels.s3[i] = s3;
+    }
+    
+    levels
+}
+
+/// Fibonacci retracement ratios reported by `fib_levels`
+pub const FIB_RATIOS: [f64; 5] = [0.236, 0.382, 0.5, 0.618, 0.786];
+
+/// Swing high/low of the last `lookback` bars and the retracement prices between them
+#[derive(Debug, Clone)]
+pub struct FibLevels {
+    pub swing_high: f64,
+    pub swing_low: f64,
+    /// Bar indices of the swing high and low (the latest, if repeated)
+    pub high_index: usize,
+    pub low_index: usize,
+    /// (ratio, price) for each of `FIB_RATIOS`
+    pub levels: Vec<(f64, f64)>,
+}
+
+impl FibLevels {
+    /// The swing low came first, so levels retrace down from the high
+    pub fn is_uptrend(&self) -> bool {
+        self.low_index <= self.high_index
+    }
+
+    /// The (ratio, price) level closest to `price`
+    pub fn nearest(&self, price: f64) -> Option<(f64, f64)> {
+        self.levels
+            .iter()
+            .copied()
+            .min_by(|a, b| (a.1 - price).abs().total_cmp(&(b.1 - price).abs()))
+    }
+}
+
+/// Fibonacci retracements over the last `lookback` bars. In an uptrend (swing
+/// low before the high) level r sits at high - r × range; in a downtrend at
+/// low + r × range. None with fewer than `lookback` bars.
+pub fn fib_levels(high: &[f64], low: &[f64], lookback: usize) -> Option<FibLevels> {
+    let n = high.len().min(low.len());
+    if lookback == 0 || n < lookback {
+        return None;
+    }
+    let swing_high = *rolling_max(&high[..n], lookback).last()?;
+    let swing_low = *rolling_min(&low[..n], lookback).last()?;
+    if swing_high.is_nan() || swing_low.is_nan() {
+        return None;
+    }
+    let start = n - lookback;
+    let high_index = start + high[start..n].iter().rposition(|&h| h == swing_high)?;
+    let low_index = start + low[start..n].iter().rposition(|&l| l == swing_low)?;
+
+    let range = swing_high - swing_low;
+    let uptrend = low_index <= high_index;
+    let levels = FIB_RATIOS
+        .iter()
+        .map(|&r| (r, if uptrend { swing_high - r * range } else { swing_low + r * range }))
+        .collect();
+
+    Some(FibLevels { swing_high, swing_low, high_index, low_index, levels })
+}
+
+/// Bollinger Bands - returns (middle, upper, lower)
+#[inline]
+pub fn bollinger(data: &[f64], period: usize, num_std: f64) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let n = data.len();
+    let middle = sma(data, period);
+    let mut upper = vec![f64::NAN; n];
+    let mut lower = vec![f64::NAN; n];
+    
+    for i in (period - 1)..n {
+        let slice = &data[(i + 1 - period)..=i];
+        let mean = middle[i];
+        let variance: f64 = slice.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / period as f64;
+        let std_dev = variance.sqrt();
+        
+        upper[i] = mean + num_std * std_dev;
+        lower[i] = mean - num_std * std_dev;
+    }
+    
+    (middle, upper, lower)
+}
+
+/// Bollinger bandwidth: (upper - lower) / middle × 100, so it compares across
+/// price levels. NaN during the warm-up or where the middle band is zero.
+#[inline]
+pub fn bollinger_bandwidth(middle: &[f64], upper: &[f64], lower: &[f64]) -> Vec<f64> {
+    middle
+        .iter()
+        .zip(upper)
+        .zip(lower)
+        .map(|((&m, &u), &l)| if m != 0.0 { (u - l) / m * 100.0 } else { f64::NAN })
+        .collect()
+}
+
+/// Donchian Channel - returns (upper, lower): the highest high and lowest
+/// low of the last `period` bars, this bar included
+#[inline]
+pub fn donchian(high: &[f64], low: &[f64], period: usize) -> (Vec<f64>, Vec<f64>) {
+    (rolling_max(high, period), rolling_min(low, period))
+}
+
+/// Narrow Range - true on bars whose high-low range is the smallest of the
+/// last `n` bars, this bar included (ties count, so NR7 = `n` of 7). False
+/// until `n` bars are in.
+#[inline]
+pub fn narrowest_range(high: &[f64], low: &[f64], n: usize) -> Vec<bool> {
+    let range: Vec<f64> = high.iter().zip(low).map(|(h, l)| h - l).collect();
+    let narrowest = rolling_min(&range, n.max(1));
+    range.iter().zip(&narrowest).map(|(&r, &min)| r <= min).collect()
+}
+
+/// Inside Bar - true when the bar's high and low are within the prior bar's
+/// (equal highs or lows count)
+#[inline]
+pub fn inside_bar(high: &[f64], low: &[f64]) -> Vec<bool> {
+    (0..high.len())
+        .map(|i| i > 0 && high[i] <= high[i - 1] && low[i] >= low[i - 1])
+        .collect()
+}
+
+/// Rolling Maximum
+#[inline]
+pub fn rolling_max(data: &[f64], period: usize) -> Vec<f64> {
+    let n = data.len();
+    let mut result = vec![f64::NAN; n];
+    
+    for i in (period - 1)..n {
+        result[i] = data[(i + 1 - period)..=i]
+            .iter()
+            .fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+    }
+    
+    result
+}
+
+/// Rolling Minimum
+#[inline]
+pub fn rolling_min(data: &[f64], period: usize) -> Vec<f64> {
+    let n = data.len();
+    let mut result = vec![f64::NAN; n];
+    
+    for i in (period - 1)..n {
+        result[i] = data[(i + 1 - period)..=i]
+            .iter()
+            .fold(f64::INFINITY, |a, &b| a.min(b));
+    }
+    
+    result
+}
+
+/// Maximum over `starts[i]..=i` per bar, for windows that vary in length
+/// (see `calendar::Lookback::window_starts`); NaN where `starts[i]` is None
+#[inline]
+pub fn window_max(data: &[f64], starts: &[Option<usize>]) -> Vec<f64> {
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, start)| match start {
+            Some(start) => data[*start..=i].iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b)),
+            None => f64::NAN,
+        })
+        .collect()
+}
+
+/// Minimum over `starts[i]..=i` per bar; NaN where `starts[i]` is None
+#[inline]
+pub fn window_min(data: &[f64], starts: &[Option<usize>]) -> Vec<f64> {
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, start)| match start {
+            Some(start) => data[*start..=i].iter().fold(f64::INFINITY, |a, &b| a.min(b)),
+            None => f64::NAN,
+        })
+        .collect()
+}
+
+/// Running maximum of everything up to and including each bar; NaNs are
+/// skipped (NaN only before the first value)
+#[inline]
+pub fn cumulative_max(data: &[f64]) -> Vec<f64> {
+    let mut peak = f64::NAN;
+    data.iter()
+        .map(|&v| {
+            if v > peak || peak.is_nan() {
+                peak = if v.is_nan() { peak } else { v };
+            }
+            peak
+        })
+        .collect()
+}
+
+/// Drawdown - percent below the running peak (0 at a new high, 25 when 25%
+/// under it). NaN before the first value and while the peak isn't positive.
+#[inline]
+pub fn drawdown(data: &[f64]) -> Vec<f64> {
+    percent_below(data, &cumulative_max(data))
+}
+
+/// Drawdown from the highest value of the last `period` bars (this one
+/// included) rather than the all-time peak; NaN until `period` bars in
+#[inline]
+pub fn rolling_drawdown(data: &[f64], period: usize) -> Vec<f64> {
+    percent_below(data, &rolling_max(data, period))
+}
+
+/// Max Drawdown - the deepest peak-to-trough fall, in percent, within each
+/// trailing window of `period` bars. NaN until the window is full.
+#[inline]
+pub fn max_drawdown(data: &[f64], period: usize) -> Vec<f64> {
+    let n = data.len();
+    let mut result = vec![f64::NAN; n];
+
+    for i in (period - 1)..n {
+        let mut peak = f64::NAN;
+        let mut deepest = f64::NAN;
+        for &v in &data[(i + 1 - period)..=i] {
+            if v > peak || peak.is_nan() {
+                peak = if v.is_nan() { peak } else { v };
+            }
+            if peak > 0.0 {
+                deepest = deepest.max((peak - v) / peak * 100.0);
+            }
+        }
+        result[i] = deepest;
+    }
+
+    result
+}
+
+fn percent_below(data: &[f64], peak: &[f64]) -> Vec<f64> {
+    data.iter()
+        .zip(peak)
+        .map(|(&v, &peak)| if peak > 0.0 { (peak - v) / peak * 100.0 } else { f64::NAN })
+        .collect()
+}
+
+/// Standard Deviation
+#[inline]
+pub fn stddev(data: &[f64], period: usize) -> Vec<f64> {
+    let n = data.len();
+    let mut result = vec![f64::NAN; n];
+    let means = sma(data, period);
+    
+    for i in (period - 1)..n {
+        let slice = &data[(i + 1 - period)..=i];
+        let mean = means[i];
+        let variance: f64 = slice.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / period as f64;
+        result[i] = variance.sqrt();
+    }
+    
+    result
+}
+
+/// Volume Weighted Average Price (intraday approximation)
+#[inline]
+pub fn vwap(high: &[f64], low: &[f64], close: &[f64], volume: &[f64]) -> Vec<f64> {
+    let n = high.len();
+    let mut result = vec![0.0; n];
+    
+    let mut cum_vol = 0.0;
+    let mut cum_tp_vol = 0.0;
+    
+    for i in 0..n {
+        let typical_price = (high[i] + low[i] + close[i]) / 3.0;
+        cum_vol += volume[i];
+        cum_tp_vol += typical_price * volume[i];
+        
+        result[i] = if cum_vol > 0.0 { cum_tp_vol / cum_vol } else { typical_price };
+    }
+    
+    result
+}
+
+/// Elder's Force Index - EMA of (close change × volume); first bar and EMA warm-up are NaN
+#[inline]
+pub fn force_index(close: &[f64], volume: &[f64], period: usize) -> Vec<f64> {
+    let n = close.len();
+    let mut result = vec![f64::NAN; n];
+    
+    if n < 2 {
+        return result;
+    }
+    
+    let raw: Vec<f64> = (1..n)
+        .map(|i| (close[i] - close[i - 1]) * volume[i])
+        .collect();
+    result[1..].copy_from_slice(&ema(&raw, period));
+    
+    result
+}
+
+/// Volume scale for Ease of Movement's box ratio
+const EOM_VOLUME_SCALE: f64 = 100_000_000.0;
+
+/// Ease of Movement - midpoint move divided by box ratio (volume / range),
+/// smoothed by SMA. Bars with zero volume or zero range contribute 0 rather
+/// than NaN, which would poison the rolling sum; the first bar is NaN.
+#[inline]
+pub fn ease_of_movement(high: &[f64], low: &[f64], volume: &[f64], period: usize) -> Vec<f64> {
+    let n = high.len();
+    let mut result = vec![f64::NAN; n];
+    
+    if n < 2 {
+        return result;
+    }
+    
+    let raw: Vec<f64> = (1..n)
+        .map(|i| {
+            let range = high[i] - low[i];
+            if volume[i] == 0.0 || range == 0.0 {
+                return 0.0;
+            }
+            let distance = (high[i] + low[i]) / 2.0 - (high[i - 1] + low[i - 1]) / 2.0;
+            let box_ratio = (volume[i] / EOM_VOLUME_SCALE) / range;
+            distance / box_ratio
+        })
+        .collect();
+    result[1..].copy_from_slice(&sma(&raw, period));
+    
+    result
+}
+
+// ============================================
+// CONDITION DETECTION
+// ============================================
+
+/// Crossed above: A crosses above B
+#[inline]
+#[allow(dead_code)]
+pub fn crossed_above(a: &[f64], b: &[f64]) -> Vec<bool> {
+    let mut result = Vec::new();
+    crossed_above_into(a, b, &mut result);
+    result
+}
+
+/// `crossed_above` into `result`, replacing its contents
+#[inline]
+pub fn crossed_above_into(a: &[f64], b: &[f64], result: &mut Vec<bool>) {
+    let n = a.len();
+    result.clear();
+    result.resize(n, false);
+    
+    for i in 1..n {
+        if !a[i].is_nan() && !b[i].is_nan() && !a[i-1].is_nan() && !b[i-1].is_nan() {
+            result[i] = a[i] > b[i] && a[i - 1] <= b[i - 1];
+        }
+    }
+}
+
+/// Crossed below: A crosses below B
+#[inline]
+#[allow(dead_code)]
+pub fn crossed_below(a: &[f64], b: &[f64]) -> Vec<bool> {
+    let mut result = Vec::new();
+    crossed_below_into(a, b, &mut result);
+    result
+}
+
+/// `crossed_below` into `result`, replacing its contents
+#[inline]
+pub fn crossed_below_into(a: &[f64], b: &[f64], result: &mut Vec<bool>) {
+    let n = a.len();
+    result.clear();
+    result.resize(n, false);
+    
+    for i in 1..n {
+        if !a[i].is_nan() && !b[i].is_nan() && !a[i-1].is_nan() && !b[i-1].is_nan() {
+            result[i] = a[i] < b[i] && a[i - 1] >= b[i - 1];
+        }
+    }
+}
+
+/// Crossed above a constant level: `crossed_above` against `level` on every
+/// bar, without building that series
+#[inline]
+#[allow(dead_code)]
+pub fn crossed_above_level(data: &[f64], level: f64) -> Vec<bool> {
+    let mut result = Vec::new();
+    crossed_above_level_into(data, level, &mut result);
+    result
+}
+
+/// `crossed_above_level` into `result`, replacing its contents
+#[inline]
+pub fn crossed_above_level_into(data: &[f64], level: f64, result: &mut Vec<bool>) {
+    result.clear();
+    result.resize(data.len(), false);
+    if level.is_nan() {
+        return;
+    }
+    for i in 1..data.len() {
+        if !data[i].is_nan() && !data[i - 1].is_nan() {
+            result[i] = data[i] > level && data[i - 1] <= level;
+        }
+    }
+}
+
+/// Crossed below a constant level
+#[inline]
+#[allow(dead_code)]
+pub fn crossed_below_level(data: &[f64], level: f64) -> Vec<bool> {
+    let mut result = Vec::new();
+    crossed_below_level_into(data, level, &mut result);
+    result
+}
+
+/// `crossed_below_level` into `result`, replacing its contents
+#[inline]
+pub fn crossed_below_level_into(data: &[f64], level: f64, result: &mut Vec<bool>) {
+    result.clear();
+    result.resize(data.len(), false);
+    if level.is_nan() {
+        return;
+    }
+    for i in 1..data.len() {
+        if !data[i].is_nan() && !data[i - 1].is_nan() {
+            result[i] = data[i] < level && data[i - 1] >= level;
+        }
+    }
+}
+
+/// Higher high: value > rolling max of previous N periods
+#[inline]
+pub fn higher_high(data: &[f64], lookback: usize) -> Vec<bool> {
+    let n = data.len();
+    let mut result = vec![false; n];
+    let prev_max = rolling_max(data, lookback);
+    
+    for i in lookback..n {
+        if !data[i].is_nan() && !prev_max[i - 1].is_nan() {
+            result[i] = data[i] > prev_max[i - 1];
+        }
+    }
+    
+    result
+}
+
+/// Lower low: value < rolling min of previous N periods
+#[inline]
+pub fn lower_low(data: &[f64], lookback: usize) -> Vec<bool> {
+    let n = data.len();
+    let mut result = vec![false; n];
+    let prev_min = rolling_min(data, lookback);
+    
+    for i in lookback..n {
+        if !data[i].is_nan() && !prev_min[i - 1].is_nan() {
+            result[i] = data[i] < prev_min[i - 1];
+        }
+    }
+    
+    result
+}
+
+/// `higher_high` over varying windows: value > the max of the window that
+/// ends on the bar before
+#[inline]
+pub fn window_higher_high(data: &[f64], starts: &[Option<usize>]) -> Vec<bool> {
+    let prev_max = window_max(data, starts);
+    (0..data.len())
+        .map(|i| i > 0 && !data[i].is_nan() && !prev_max[i - 1].is_nan() && data[i] > prev_max[i - 1])
+        .collect()
+}
+
+/// `lower_low` over varying windows: value < the min of the window that
+/// ends on the bar before
+#[inline]
+pub fn window_lower_low(data: &[f64], starts: &[Option<usize>]) -> Vec<bool> {
+    let prev_min = window_min(data, starts);
+    (0..data.len())
+        .map(|i| i > 0 && !data[i].is_nan() && !prev_min[i - 1].is_nan() && data[i] < prev_min[i - 1])
+        .collect()
+}
+
+/// Percent change
+#[inline]
+pub fn pct_change(data: &[f64], periods: usize) -> Vec<f64> {
+    let n = data.len();
+    let mut result = vec![f64::NAN; n];
+    
+    for i in periods..n {
+        if data[i - periods] != 0.0 {
+            result[i] = (data[i] - data[i - periods]) / data[i - periods] * 100.0;
+        }
+    }
+    
+    result
+}
+
+/// Means, covariance and variances of `a` and `b` over `a.len()` points;
+/// None if any value is NaN
+fn window_moments(a: &[f64], b: &[f64]) -> Option<(f64, f64, f64)> {
+    if a.iter().chain(b).any(|v| v.is_nan()) {
+        return None;
+    }
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    Some((cov, var_a, var_b))
+}
+
+/// Rolling Pearson correlation of two aligned series (typically returns).
+/// NaN when the window holds a NaN or either side is flat.
+#[inline]
+pub fn rolling_correlation(a: &[f64], b: &[f64], period: usize) -> Vec<f64> {
+    let n = a.len().min(b.len());
+    let mut result = vec![f64::NAN; n];
+    if period < 2 {
+        return result;
+    }
+    
+    for (i, out) in result.iter_mut().enumerate().skip(period - 1) {
+        let range = (i + 1 - period)..=i;
+        if let Some((cov, var_a, var_b)) = window_moments(&a[range.clone()], &b[range]) {
+            if var_a > 0.0 && var_b > 0.0 {
+                *out = cov / (var_a * var_b).sqrt();
+            }
+        }
+    }
+    
+    result
+}
+
+/// Rolling beta of asset returns to benchmark returns: cov / var(benchmark).
+/// NaN when the window holds a NaN or the benchmark is flat.
+#[inline]
+pub fn rolling_beta(asset_returns: &[f64], bench_returns: &[f64], period: usize) -> Vec<f64> {
+    let n = asset_returns.len().min(bench_returns.len());
+    let mut result = vec![f64::NAN; n];
+    if period < 2 {
+        return result;
+    }
+    
+    for (i, out) in result.iter_mut().enumerate().skip(period - 1) {
+        let range = (i + 1 - period)..=i;
+        if let Some((cov, _, var_b)) = window_moments(&asset_returns[range.clone()], &bench_returns[range]) {
+            if var_b > 0.0 {
+                *out = cov / var_b;
+            }
+        }
+    }
+    
+    result
+}
+
+/// Volume ratio: current volume / average volume
+#[inline]
+pub fn volume_ratio(volume: &[f64], period: usize) -> Vec<f64> {
+    let avg = sma(volume, period);
+    
+    volume
         .iter()
         .zip(avg.iter())
         .map(|(v, a)| if *a > 0.0 && !a.is_nan() { v / a } else { f64::NAN })
         .collect()
 }
 
+/// Volume z-score: (volume - mean) / std of the previous `period` bars. The
+/// current bar is left out of its own baseline so a spike can't dampen
+/// itself. NaN until `period` prior bars exist, or when they're all equal.
+#[inline]
+pub fn volume_zscore(volume: &[f64], period: usize) -> Vec<f64> {
+    let n = volume.len();
+    let mut result = vec![f64::NAN; n];
+    if period == 0 {
+        return result;
+    }
+    let means = sma(volume, period);
+    let stds = stddev(volume, period);
+
+    for i in period..n {
+        let (mean, std) = (means[i - 1], stds[i - 1]);
+        if std > 0.0 {
+            result[i] = (volume[i] - mean) / std;
+        }
+    }
+
+    result
+}
+
+/// Percentile rank (0-100) of each value among the previous `period` values:
+/// the share strictly below it. NaN until `period` prior values exist.
+#[inline]
+pub fn percentile_rank(data: &[f64], period: usize) -> Vec<f64> {
+    let n = data.len();
+    let mut result = vec![f64::NAN; n];
+    if period == 0 {
+        return result;
+    }
+
+    for i in period..n {
+        let window = &data[(i - period)..i];
+        if data[i].is_nan() || window.iter().any(|v| v.is_nan()) {
+            continue;
+        }
+        let below = window.iter().filter(|&&v| v < data[i]).count();
+        result[i] = below as f64 / period as f64 * 100.0;
+    }
+
+    result
+}
+
+/// Close location: where the close sits in the bar's range, 0 at the low to
+/// 1 at the high. A zero-range bar is 0.5.
+#[inline]
+pub fn close_location(high: &[f64], low: &[f64], close: &[f64]) -> Vec<f64> {
+    high.iter()
+        .zip(low)
+        .zip(close)
+        .map(|((&h, &l), &c)| if h > l { (c - l) / (h - l) } else if h == l { 0.5 } else { f64::NAN })
+        .collect()
+}
+
+/// Consecutive closes in one direction ending on each bar: +3 after three
+/// higher closes in a row, -2 after two lower. An unchanged close (or the
+/// first bar) is 0.
+#[inline]
+pub fn streak(close: &[f64]) -> Vec<f64> {
+    let mut result = vec![0.0_f64; close.len()];
+    for i in 1..close.len() {
+        result[i] = if close[i] > close[i - 1] {
+            result[i - 1].max(0.0) + 1.0
+        } else if close[i] < close[i - 1] {
+            result[i - 1].min(0.0) - 1.0
+        } else {
+            0.0
+        };
+    }
+    result
+}
+
+/// Average daily range: the mean of (high - low) / close × 100 over `period`
+/// bars. NaN until `period` bars exist or where a close is zero.
+#[inline]
+pub fn adr_pct(high: &[f64], low: &[f64], close: &[f64], period: usize) -> Vec<f64> {
+    let range_pct: Vec<f64> = high
+        .iter()
+        .zip(low)
+        .zip(close)
+        .map(|((&h, &l), &c)| if c != 0.0 { (h - l) / c * 100.0 } else { f64::NAN })
+        .collect();
+    sma(&range_pct, period)
+}
+
 /// Is above threshold
 #[inline]
+#[allow(dead_code)]
 pub fn above(data: &[f64], threshold: f64) -> Vec<bool> {
-    data.iter().map(|&v| !v.is_nan() && v > threshold).collect()
+    let mut result = Vec::new();
+    above_into(data, threshold, &mut result);
+    result
+}
+
+/// `above` into `result`, replacing its contents
+#[inline]
+pub fn above_into(data: &[f64], threshold: f64, result: &mut Vec<bool>) {
+    result.clear();
+    result.extend(data.iter().map(|&v| !v.is_nan() && v > threshold));
 }
 
 /// Is below threshold
 #[inline]
+#[allow(dead_code)]
 pub fn below(data: &[f64], threshold: f64) -> Vec<bool> {
-    data.iter().map(|&v| !v.is_nan() && v < threshold).collect()
+    let mut result = Vec::new();
+    below_into(data, threshold, &mut result);
+    result
+}
+
+/// `below` into `result`, replacing its contents
+#[inline]
+pub fn below_into(data: &[f64], threshold: f64, result: &mut Vec<bool>) {
+    result.clear();
+    result.extend(data.iter().map(|&v| !v.is_nan() && v < threshold));
 }
 
 /// AND two boolean vectors
@@ -378,6 +1401,90 @@ mod tests {
         let result = sma(&data, 3);
         assert!((result[2] - 2.0).abs() < 0.001);
         assert!((result[4] - 4.0).abs() < 0.001);
+        
+        let padded = sma(&[f64::NAN, f64::NAN, 1.0, 2.0, 3.0], 2);
+        assert!(padded[..3].iter().all(|v| v.is_nan()));
+        assert_eq!(padded[3..], [1.5, 2.5]);
+    }
+    
+    #[test]
+    fn test_kst() {
+        let data: Vec<f64> = (0..80).map(|i| 100.0 * 1.01_f64.powi(i)).collect();
+        let (line, signal) = kst(&data, KST_ROC, KST_SMOOTH, KST_WEIGHTS, KST_SIGNAL);
+        assert!(line[..44].iter().all(|v| v.is_nan()) && line[44].is_finite());
+        assert!(signal[..52].iter().all(|v| v.is_nan()) && signal[52].is_finite());
+        // Steady 1% growth: each leg's ROC is constant, so KST is their weighted sum
+        let roc = |p: i32| (1.01_f64.powi(p) - 1.0) * 100.0;
+        let expected = roc(10) + 2.0 * roc(15) + 3.0 * roc(20) + 4.0 * roc(30);
+        assert!((line[60] - expected).abs() < 1e-9 && (signal[60] - expected).abs() < 1e-9);
+        
+        let (short, _) = kst(&data, [1, 2, 3, 4], [1, 1, 1, 1], [1.0, 0.0, 0.0, 0.0], 1);
+        assert!(short[3].is_nan() && (short[4] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_window_extremes_match_fixed_lookbacks() {
+        let data: Vec<f64> = (0..60).map(|i| (i as f64 * 0.7).sin() * 10.0 + i as f64 * 0.1).collect();
+        let starts: Vec<Option<usize>> = (0..data.len()).map(|i| (i + 1 >= 5).then(|| i + 1 - 5)).collect();
+        let same = |a: &[f64], b: &[f64]| a.iter().zip(b).all(|(x, y)| x == y || (x.is_nan() && y.is_nan()));
+        assert!(same(&window_max(&data, &starts), &rolling_max(&data, 5)));
+        assert!(same(&window_min(&data, &starts), &rolling_min(&data, 5)));
+        assert_eq!(window_higher_high(&data, &starts), higher_high(&data, 5));
+        assert_eq!(window_lower_low(&data, &starts), lower_low(&data, 5));
+        
+        // A window that grows from the first bar
+        let growing: Vec<Option<usize>> = vec![Some(0); 4];
+        assert_eq!(window_max(&[1.0, 3.0, 2.0, 4.0], &growing), [1.0, 3.0, 3.0, 4.0]);
+        assert_eq!(window_higher_high(&[1.0, 3.0, 2.0, 4.0], &growing), [false, true, false, true]);
+    }
+
+    #[test]
+    fn test_fisher_transform() {
+        let high: Vec<f64> = (0..30).map(|i| 101.0 + (i as f64 * 0.5).sin() * 5.0).collect();
+        let low: Vec<f64> = high.iter().map(|h| h - 2.0).collect();
+        let (fisher, trigger) = fisher_transform(&high, &low, 10);
+        assert!(fisher[..9].iter().all(|v| v.is_nan()) && fisher[9..].iter().all(|v| v.is_finite()));
+        assert!(trigger[9].is_nan() && trigger[10..] == fisher[9..29]);
+        
+        // Pinned at the top of its range the value clamps instead of reaching infinity
+        let rising: Vec<f64> = (0..200).map(|i| i as f64).collect();
+        let (fisher, _) = fisher_transform(&rising, &rising, 5);
+        let limit = 0.5 * (1.999_f64 / 0.001).ln() * 2.0;
+        assert!(fisher[199].is_finite() && (fisher[199] - limit).abs() < 1e-6);
+        // A flat window sits mid-range, at zero
+        assert_eq!(fisher_transform(&[5.0; 8], &[5.0; 8], 3).0[7], 0.0);
+    }
+
+    #[test]
+    fn test_bollinger_bandwidth() {
+        let data = [1.0, 3.0, 1.0, 3.0];
+        let (middle, upper, lower) = bollinger(&data, 2, 2.0);
+        let width = bollinger_bandwidth(&middle, &upper, &lower);
+        // Mean 2, population std 1, so the bands span 4 around a middle of 2
+        assert!(width[0].is_nan());
+        assert_eq!(width[1..], [200.0; 3]);
+        assert!(bollinger_bandwidth(&[0.0], &[1.0], &[-1.0])[0].is_nan());
+    }
+
+    #[test]
+    fn test_streak() {
+        let close = [10.0, 11.0, 12.0, 13.0, 12.0, 11.0, 11.0, f64::NAN, 12.0];
+        assert_eq!(streak(&close), [0.0, 1.0, 2.0, 3.0, -1.0, -2.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_wma_and_coppock() {
+        let result = wma(&[f64::NAN, 1.0, 2.0, 3.0, 6.0], 3);
+        assert!(result[..3].iter().all(|v| v.is_nan()));
+        // (1 + 2*2 + 3*3) / 6, then (2 + 2*3 + 3*6) / 6
+        assert!((result[3] - 14.0 / 6.0).abs() < 1e-9 && (result[4] - 26.0 / 6.0).abs() < 1e-9);
+        
+        let data: Vec<f64> = (0..40).map(|i| 100.0 * 1.01_f64.powi(i)).collect();
+        let curve = coppock(&data, COPPOCK_ROC_LONG, COPPOCK_ROC_SHORT, COPPOCK_WMA);
+        assert!(curve[..23].iter().all(|v| v.is_nan()) && curve[23].is_finite());
+        // Steady growth: both ROCs are constant, and so is their weighted average
+        let roc = |p: i32| (1.01_f64.powi(p) - 1.0) * 100.0;
+        assert!((curve[30] - (roc(14) + roc(11))).abs() < 1e-9);
     }
 
     #[test]
@@ -390,4 +1497,407 @@ mod tests {
         assert!(result[2]);
         assert!(!result[3]);
     }
+
+    #[test]
+    fn test_level_crosses_match_constant_series() {
+        for _ in 0..20 {
+            let data = crate::data::generate_sample_data(300);
+            let mut values = rsi(&data.close, 14);
+            // Gaps mid-series, and a bar sitting exactly on the level
+            for i in (40..300).step_by(37) {
+                values[i] = f64::NAN;
+            }
+            values[100] = 50.0;
+            for level in [30.0, 50.0, 70.0, values[150], f64::NAN] {
+                let line = vec![level; values.len()];
+                assert_eq!(crossed_above_level(&values, level), crossed_above(&values, &line));
+                assert_eq!(crossed_below_level(&values, level), crossed_below(&values, &line));
+            }
+        }
+        assert!(crossed_above_level(&[], 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_macd_signal_skips_macd_warmup() {
+        let data: Vec<f64> = (0..40).map(|i| 100.0 + (i * i) as f64).collect();
+        let hist = macd_histogram(&data, 3, 6, 2);
+        // MACD starts at bar 5, its 2-bar signal at bar 6
+        assert!(hist[..6].iter().all(|v| v.is_nan()));
+        assert!(hist[6..].iter().all(|v| v.is_finite()));
+        // Accelerating prices keep MACD above its signal line
+        assert!(hist[7..].iter().all(|&v| v > 0.0));
+    }
+    
+    #[test]
+    fn test_force_index() {
+        let close = vec![10.0, 11.0, 10.5, 12.0];
+        let volume = vec![100.0, 200.0, 100.0, 50.0];
+        // Raw force: 200, -50, 75
+        let fi = force_index(&close, &volume, 2);
+        assert!(fi[0].is_nan());
+        assert!(fi[1].is_nan());
+        assert!((fi[2] - 75.0).abs() < 1e-9);
+        assert!((fi[3] - (75.0 + (75.0 - 75.0) * 2.0 / 3.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ease_of_movement() {
+        let high = vec![10.0, 12.0, 12.0, 13.0];
+        let low = vec![8.0, 10.0, 12.0, 11.0];
+        let volume = vec![1e8, 2e8, 1e8, 0.0];
+        // Raw: midpoint +2 over box ratio 2/2 = 2; zero range -> 0; zero volume -> 0
+        let eom = ease_of_movement(&high, &low, &volume, 1);
+        assert!(eom[0].is_nan());
+        assert!((eom[1] - 2.0).abs() < 1e-9);
+        assert_eq!(eom[2], 0.0);
+        assert_eq!(eom[3], 0.0);
+        assert!(ease_of_movement(&high, &low, &volume, 2).iter().skip(2).all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn test_ultimate_oscillator() {
+        let high = vec![10.0, 11.0, 12.0, 11.5, 13.0];
+        let low = vec![9.0, 10.0, 10.5, 10.0, 11.0];
+        let close = vec![9.5, 10.5, 11.5, 11.0, 12.5];
+        
+        // From bar 1, TR: 1.5, 1.5, 1.5, 2.0; BP: 1.0, 1.0, 1.0, 1.5
+        assert_eq!(true_range(&high, &low, &close)[1..], [1.5, 1.5, 1.5, 2.0]);
+        
+        let result = ultimate_oscillator(&high, &low, &close, 1, 2, 3);
+        assert!(result[..3].iter().all(|v| v.is_nan()));
+        // Every window at bar 3 has BP/TR = 2/3
+        assert!((result[3] - 200.0 / 3.0).abs() < 1e-9);
+        // bar 4: short 1.5/2.0, medium 2.5/3.5, long 3.5/5.0
+        let expected = 100.0 * (4.0 * 0.75 + 2.0 * (2.5 / 3.5) + 0.7) / 7.0;
+        assert!((result[4] - expected).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_vortex() {
+        let high = vec![10.0, 11.0, 12.0, 11.5, 13.0];
+        let low = vec![9.0, 10.0, 10.5, 10.0, 11.0];
+        let close = vec![9.5, 10.5, 11.5, 11.0, 12.5];
+        
+        // From bar 1, VM+: 2, 2, 1, 3; VM-: 0, 0.5, 2, 0.5; TR: 1.5, 1.5, 1.5, 2.0
+        let (plus, minus) = vortex(&high, &low, &close, 2);
+        assert!(plus[..2].iter().chain(&minus[..2]).all(|v| v.is_nan()));
+        let expected = [(4.0 / 3.0, 0.5 / 3.0), (1.0, 2.5 / 3.0), (4.0 / 3.5, 2.5 / 3.5)];
+        for (i, (p, m)) in expected.into_iter().enumerate() {
+            assert!((plus[i + 2] - p).abs() < 1e-9 && (minus[i + 2] - m).abs() < 1e-9);
+        }
+        
+        let flat = vec![10.0; 5];
+        assert!(vortex(&flat, &flat, &flat, 2).0.iter().all(|v| v.is_nan()));
+    }
+    
+    #[test]
+    fn test_mass_index() {
+        // A constant range gives a ratio of 1 on every bar, so the index is sum_period
+        let high = vec![11.0; 40];
+        let low = vec![10.0; 40];
+        let mi = mass_index(&high, &low, 4, 5);
+        // Double EMA from bar 2 * 3 = 6, so the first full sum is at bar 10
+        assert!(mi[..10].iter().all(|v| v.is_nan()));
+        assert!(mi[10..].iter().all(|&v| (v - 5.0).abs() < 1e-12));
+        
+        // A widening range pulls the single EMA above the double one
+        let wide: Vec<f64> = (0..40).map(|i| if i >= 30 { 14.0 } else { 11.0 }).collect();
+        let mi = mass_index(&wide, &low, 4, 5);
+        assert!(mi[30] > 5.0 && mi[30] > mi[29]);
+        assert!(mass_index(&low, &low, 4, 5).iter().all(|v| v.is_nan()));
+    }
+    
+    #[test]
+    fn test_chandelier_exit() {
+        let high = vec![10.0, 12.0, 11.0, 15.0, 13.0];
+        let low = vec![9.0, 10.0, 9.0, 12.0, 12.0];
+        let close = vec![9.5, 11.0, 10.0, 14.0, 12.5];
+        
+        // TR: 1, 2.5, 2, 5, 2 -> 2-bar ATR from bar 1: 1.75, 2.25, 3.5, 3.5
+        let (long, short) = chandelier_exit(&high, &low, &close, 2, 2.0);
+        assert!(long[0].is_nan() && short[0].is_nan());
+        assert_eq!(long[1..], [12.0 - 3.5, 12.0 - 4.5, 15.0 - 7.0, 15.0 - 7.0]);
+        assert_eq!(short[1..], [9.0 + 3.5, 9.0 + 4.5, 9.0 + 7.0, 12.0 + 7.0]);
+        
+        // Anchored at bar 2: highest high since entry, never lowered
+        let stop = chandelier_stop_from(&high, &low, &close, 2, 2, 1.0);
+        assert!(stop[..2].iter().all(|v| v.is_nan()));
+        assert_eq!(stop[2..], [11.0 - 2.25, 15.0 - 3.5, 15.0 - 3.5]);
+        
+        // Levels of 7.5 and 8.0 after a wider ATR don't pull the stop back down
+        let stop = chandelier_stop_from(&high, &low, &close, 1, 2, 2.0);
+        assert_eq!(stop[1..], [8.5, 8.5, 8.5, 8.5]);
+    }
+    
+    #[test]
+    fn test_pivot_points() {
+        let high = vec![12.0, 0.0];
+        let low = vec![9.0, 0.0];
+        let close = vec![12.0, 0.0];
+        
+        let classic = pivot_points(&high, &low, &close, PivotMethod::Classic);
+        assert!(classic.pivot[0].is_nan());
+        // From bar 0: P = 11, range 3
+        assert_eq!(
+            (classic.pivot[1], classic.r1[1], classic.r2[1], classic.s1[1], classic.s2[1]),
+            (11.0, 13.0, 14.0, 10.0, 8.0)
+        );
+        
+        let fib = pivot_points(&high, &low, &close, PivotMethod::Fibonacci);
+        assert!((fib.r1[1] - (11.0 + 0.382 * 3.0)).abs() < 1e-12);
+        assert!((fib.s2[1] - (11.0 - 0.618 * 3.0)).abs() < 1e-12);
+        assert_eq!(fib.level("S2").map(|l| l[1]), Some(fib.s2[1]));
+        assert_eq!(fib.r3[1], 14.0);
+        assert!(fib.level("r4").is_none());
+        // H + 2(P - L), L - 2(H - P)
+        assert_eq!((classic.r3[1], classic.s3[1]), (16.0, 7.0));
+        
+        let cam = pivot_points(&high, &low, &close, PivotMethod::Camarilla);
+        assert_eq!(cam.pivot[1], 11.0);
+        assert!((cam.r1[1] - (12.0 + 3.3 / 12.0)).abs() < 1e-12);
+        assert!((cam.s3[1] - (12.0 - 3.3 / 4.0)).abs() < 1e-12);
+    }
+    
+    #[test]
+    fn test_rolling_correlation_and_beta() {
+        let bench = vec![1.0, -1.0, 2.0, -2.0, 1.0];
+        let doubled: Vec<f64> = bench.iter().map(|r| r * 2.0).collect();
+        let inverse: Vec<f64> = bench.iter().map(|r| -r).collect();
+        
+        let corr = rolling_correlation(&doubled, &bench, 3);
+        assert!(corr[..2].iter().all(|v| v.is_nan()));
+        assert!(corr[2..].iter().all(|v| (v - 1.0).abs() < 1e-12));
+        assert!((rolling_correlation(&inverse, &bench, 3)[4] + 1.0).abs() < 1e-12);
+        
+        let beta = rolling_beta(&doubled, &bench, 3);
+        assert!(beta[2..].iter().all(|v| (v - 2.0).abs() < 1e-12));
+        
+        // NaN inside the window, or a flat benchmark, gives NaN
+        let gappy = vec![1.0, f64::NAN, 2.0, -2.0, 1.0];
+        let beta = rolling_beta(&gappy, &bench, 3);
+        assert!(beta[2..4].iter().all(|v| v.is_nan()) && beta[4].is_finite());
+        assert!(rolling_beta(&bench, &[0.5; 5], 3)[4].is_nan());
+    }
+    
+    #[test]
+    fn test_volume_zscore_and_percentile_rank() {
+        let volume = [100.0, 200.0, 100.0, 200.0, 450.0, 150.0];
+        let z = volume_zscore(&volume, 4);
+        assert!(z[..4].iter().all(|v| v.is_nan()));
+        // Previous four: mean 150, std 50
+        assert!((z[4] - 6.0).abs() < 1e-12);
+        // 200, 100, 200, 450: mean 237.5, std ~129.3
+        assert!((z[5] + 0.6767).abs() < 1e-3);
+        assert!(volume_zscore(&[5.0, 5.0, 5.0, 9.0], 3)[3].is_nan());
+
+        let rank = percentile_rank(&volume, 4);
+        assert!(rank[3].is_nan());
+        assert_eq!(rank[4], 100.0);
+        // 150 beats 100 only
+        assert_eq!(rank[5], 25.0);
+    }
+
+    #[test]
+    fn test_close_location() {
+        let loc = close_location(&[12.0, 10.0, 10.0, f64::NAN], &[8.0, 10.0, 11.0, 9.0], &[11.0, 10.0, 10.5, 9.5]);
+        assert_eq!(loc[..2], [0.75, 0.5]);
+        // Inverted or missing bars are undefined
+        assert!(loc[2].is_nan() && loc[3].is_nan());
+    }
+
+    #[test]
+    fn test_adr_pct() {
+        let high = vec![11.0, 10.5, 21.0, 10.0];
+        let low = vec![9.0, 9.5, 19.0, 10.0];
+        let close = vec![10.0, 10.0, 20.0, 0.0];
+        // Ranges: 20%, 10%, 10%, undefined
+        let adr = adr_pct(&high, &low, &close, 2);
+        assert!(adr[0].is_nan());
+        assert!((adr[1] - 15.0).abs() < 1e-9);
+        assert!((adr[2] - 10.0).abs() < 1e-9);
+        assert!(adr[3].is_nan());
+    }
+
+    #[test]
+    fn test_fib_levels() {
+        // Low of 100 on bar 2, high of 200 on bar 4: an uptrend
+        let high = vec![150.0, 140.0, 120.0, 160.0, 200.0, 190.0];
+        let low = vec![130.0, 110.0, 100.0, 130.0, 170.0, 180.0];
+        let fib = fib_levels(&high, &low, 5).unwrap();
+        assert_eq!((fib.swing_high, fib.swing_low), (200.0, 100.0));
+        assert_eq!((fib.low_index, fib.high_index), (2, 4));
+        assert!(fib.is_uptrend());
+        assert!((fib.levels[1].1 - 161.8).abs() < 1e-9);
+        assert_eq!(fib.levels[2], (0.5, 150.0));
+        assert_eq!(fib.nearest(185.0).unwrap().0, 0.236);
+
+        // Reversed, the high comes first and levels climb from the low
+        let (rh, rl): (Vec<f64>, Vec<f64>) = (high.iter().rev().copied().collect(), low.iter().rev().copied().collect());
+        let down = fib_levels(&rh, &rl, 6).unwrap();
+        assert!(!down.is_uptrend());
+        assert!((down.levels[0].1 - 123.6).abs() < 1e-9);
+
+        assert!(fib_levels(&high, &low, 7).is_none());
+    }
+
+    #[test]
+    fn test_trix_warmup() {
+        let data: Vec<f64> = (0..30).map(|i| 100.0 + i as f64).collect();
+        let t = trix(&data, 4);
+        // Triple EMA is first valid at bar 3 * 3 = 9, so TRIX at bar 10
+        assert!(t[..10].iter().all(|v| v.is_nan()));
+        assert!(t[10..].iter().all(|&v| v.is_finite() && v > 0.0));
+
+        // Leading NaNs shift the whole warm-up
+        let mut padded = vec![f64::NAN; 5];
+        padded.extend(&data);
+        let shifted = trix(&padded, 4);
+        assert!(shifted[..15].iter().all(|v| v.is_nan()));
+        assert!((shifted[15] - t[10]).abs() < 1e-12);
+
+        let falling: Vec<f64> = data.iter().rev().copied().collect();
+        assert!(trix(&falling, 4)[10..].iter().all(|&v| v < 0.0));
+        assert!(trix(&data[..9], 4).iter().all(|v| v.is_nan()));
+    }
+
+    #[test]
+    fn test_dpo() {
+        // A straight line detrends to a constant: (period - 1) / 2 - shift
+        let line: Vec<f64> = (0..20).map(|i| 100.0 + i as f64).collect();
+        let d = dpo(&line, 6);
+        assert!(d[..5].iter().all(|v| v.is_nan()));
+        assert!(d[5..].iter().all(|&v| (v - (2.5 - 4.0)).abs() < 1e-9));
+        
+        // Short periods wait for the shift rather than the SMA
+        let d = dpo(&line, 2);
+        assert!(d[1].is_nan() && (d[2] - (0.5 - 2.0)).abs() < 1e-9);
+        assert!(dpo(&line[..3], 6).iter().all(|v| v.is_nan()));
+    }
+    
+    #[test]
+    fn test_ppo() {
+        let data: Vec<f64> = (0..40).map(|i| 100.0 + (i * i) as f64).collect();
+        let p = ppo(&data, 3, 6);
+        let m = macd(&data, 3, 6);
+        let slow = ema(&data, 6);
+        assert!(p[..5].iter().all(|v| v.is_nan()));
+        for i in 5..data.len() {
+            assert!((p[i] - m[i] / slow[i] * 100.0).abs() < 1e-9);
+        }
+        let hist = ppo_histogram(&data, 3, 6, 2);
+        assert!(hist[..6].iter().all(|v| v.is_nan()));
+        assert!(hist[6..].iter().all(|v| v.is_finite()));
+        assert_eq!(ppo_signal(&data, 3, 6, 2)[6], ema(&p, 2)[6]);
+
+        // Same shape at 10x the price gives the same PPO, unlike MACD
+        let scaled: Vec<f64> = data.iter().map(|v| v * 10.0).collect();
+        assert!((ppo(&scaled, 3, 6)[20] - p[20]).abs() < 1e-9);
+
+        // A zero slow EMA gives NaN rather than infinity
+        assert!(ppo(&[0.0; 10], 2, 3)[5].is_nan());
+    }
+
+    #[test]
+    fn test_donchian() {
+        let high = vec![10.0, 12.0, 11.0, 9.0];
+        let low = vec![8.0, 9.0, 7.0, 8.5];
+        let (upper, lower) = donchian(&high, &low, 2);
+        assert!(upper[0].is_nan() && lower[0].is_nan());
+        assert_eq!(upper[1..], [12.0, 12.0, 11.0]);
+        assert_eq!(lower[1..], [8.0, 7.0, 7.0]);
+    }
+
+    #[test]
+    fn test_narrowest_range_and_inside_bar() {
+        // Ranges: 4, 3, 2, 2, 5, 1
+        let high = vec![14.0, 13.0, 12.0, 12.5, 15.0, 13.0];
+        let low = vec![10.0, 10.0, 10.0, 10.5, 10.0, 12.0];
+        assert_eq!(narrowest_range(&high, &low, 3), [false, false, true, true, false, true]);
+        assert_eq!(inside_bar(&high, &low), [false, true, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_elder_ray_and_impulse() {
+        let close = vec![10.0, 11.0, 12.0, 13.0];
+        let high = vec![10.5, 11.5, 13.0, 13.5];
+        let low = vec![9.5, 10.5, 11.0, 12.0];
+        // 2-bar EMA from bar 1: 10.5, 11.5, 12.5
+        assert_eq!(bull_power(&high, &close, 2)[1..], [1.0, 1.5, 1.0]);
+        assert_eq!(bear_power(&low, &close, 2)[1..], [0.0, -0.5, -0.5]);
+        assert!(bull_power(&high, &close, 2)[0].is_nan());
+
+        let data = crate::data::generate_sample_data(300);
+        let impulse = elder_impulse(&data.close, 13, 12, 26, 9);
+        let trend = ema(&data.close, 13);
+        let hist = macd_histogram(&data.close, 12, 26, 9);
+        // The histogram starts at bar 33, so its slope at 34
+        assert!(impulse[..34].iter().all(|v| v.is_nan()));
+        for i in 34..data.len() {
+            let (e, h) = (trend[i] - trend[i - 1], hist[i] - hist[i - 1]);
+            let expected = match (e > 0.0, h > 0.0, e < 0.0, h < 0.0) {
+                (true, true, _, _) => 1.0,
+                (_, _, true, true) => -1.0,
+                _ => 0.0,
+            };
+            assert_eq!(impulse[i], expected, "bar {}", i);
+        }
+        // Accelerating rise (green), a stall where the EMA still rises but the
+        // histogram falls (blue), then a sell-off (red)
+        let mut prices: Vec<f64> = (0..20).map(|i| 100.0 + (i * i) as f64).collect();
+        prices.extend([461.0; 3]);
+        prices.extend((1..8).map(|i| 461.0 - 20.0 * i as f64));
+        let impulse = elder_impulse(&prices, 3, 3, 6, 2);
+        assert!(impulse[..7].iter().all(|v| v.is_nan()));
+        assert!(impulse[7..20].iter().all(|&v| v == 1.0));
+        assert!(impulse[20..23].iter().all(|&v| v == 0.0));
+        assert!(impulse[23..25].iter().all(|&v| v == -1.0));
+    }
+
+    #[test]
+    fn test_cumulative_max_and_drawdown() {
+        let data = vec![f64::NAN, 100.0, 120.0, f64::NAN, 90.0, 130.0, 117.0];
+        let peak = cumulative_max(&data);
+        assert!(peak[0].is_nan());
+        assert_eq!(peak[1..], [100.0, 120.0, 120.0, 120.0, 130.0, 130.0]);
+
+        let dd = drawdown(&data);
+        assert!(dd[0].is_nan() && dd[3].is_nan());
+        assert_eq!(dd[1], 0.0);
+        assert_eq!(dd[2], 0.0);
+        assert!((dd[4] - 25.0).abs() < 1e-9);
+        assert_eq!(dd[5], 0.0);
+        assert!((dd[6] - 10.0).abs() < 1e-9);
+        assert!(drawdown(&[0.0, 0.0]).iter().all(|v| v.is_nan()));
+    }
+
+    #[test]
+    fn test_rolling_and_max_drawdown() {
+        let data = vec![100.0, 80.0, 90.0, 60.0, 66.0, 72.0];
+        let dd = rolling_drawdown(&data, 3);
+        assert!(dd[..2].iter().all(|v| v.is_nan()));
+        // Highs of the trailing 3 bars: 100, 90, 90, 72
+        assert!((dd[2] - 10.0).abs() < 1e-9);
+        assert!((dd[3] - 100.0 / 3.0).abs() < 1e-9);
+        assert!((dd[4] - 100.0 * 24.0 / 90.0).abs() < 1e-9);
+        assert_eq!(dd[5], 0.0);
+
+        let mdd = max_drawdown(&data, 3);
+        assert!(mdd[1].is_nan());
+        // 100 -> 80 inside the first window; 90 -> 60 inside the next two
+        assert!((mdd[2] - 20.0).abs() < 1e-9);
+        assert!((mdd[3] - 100.0 / 3.0).abs() < 1e-9);
+        assert!((mdd[4] - 100.0 / 3.0).abs() < 1e-9);
+        assert_eq!(mdd[5], 0.0);
+    }
+
+    #[test]
+    fn test_cmo() {
+        let data = vec![1.0, 2.0, 3.0, 2.0, 2.0, 2.0];
+        let result = cmo(&data, 2);
+        assert!(result[1].is_nan());
+        assert_eq!(result[2], 100.0);
+        assert_eq!(result[3], 0.0);
+        assert_eq!(result[4], -100.0);
+        assert!(result[5].is_nan());
+    }
 }
@@ -0,0 +1,123 @@
+//! Background scan jobs - scans started with `POST /api/scan?async=true`,
+//! followed over `GET /api/scan/progress/:job_id`
+//!
+//! Finished jobs are kept for `FINISHED_JOB_TTL` so a client that connects
+//! late still gets the result, then dropped when the next job starts.
+
+use crate::scanner::ScanProgress;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const FINISHED_JOB_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone)]
+pub enum JobStatus {
+    Running,
+    /// The response `/api/scan` would have sent
+    Done(Arc<serde_json::Value>),
+    Failed(String),
+}
+
+/// Progress event payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ProgressSnapshot {
+    pub scanned: usize,
+    pub total: usize,
+    pub matches_so_far: usize,
+}
+
+#[derive(Debug)]
+pub struct ScanJob {
+    pub progress: Arc<ScanProgress>,
+    status: Mutex<(JobStatus, Option<Instant>)>,
+}
+
+impl ScanJob {
+    fn new() -> Self {
+        Self {
+            progress: Arc::new(ScanProgress::default()),
+            status: Mutex::new((JobStatus::Running, None)),
+        }
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.status.lock().unwrap_or_else(|e| e.into_inner()).0.clone()
+    }
+
+    pub fn finish(&self, outcome: Result<serde_json::Value, String>) {
+        let status = match outcome {
+            Ok(result) => JobStatus::Done(Arc::new(result)),
+            Err(e) => JobStatus::Failed(e),
+        };
+        *self.status.lock().unwrap_or_else(|e| e.into_inner()) = (status, Some(Instant::now()));
+    }
+
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        ProgressSnapshot {
+            scanned: self.progress.scanned.load(Ordering::Relaxed),
+            total: self.progress.total.load(Ordering::Relaxed),
+            matches_so_far: self.progress.matches.load(Ordering::Relaxed),
+        }
+    }
+
+    fn expired(&self, now: Instant) -> bool {
+        let finished_at = self.status.lock().unwrap_or_else(|e| e.into_inner()).1;
+        finished_at.is_some_and(|at| now.duration_since(at) > FINISHED_JOB_TTL)
+    }
+}
+
+/// Job id → job
+#[derive(Debug, Default)]
+pub struct ScanJobs {
+    jobs: Mutex<HashMap<String, Arc<ScanJob>>>,
+    seq: AtomicU64,
+}
+
+impl ScanJobs {
+    /// Register a new running job, dropping expired ones
+    pub fn start(&self) -> (String, Arc<ScanJob>) {
+        let id = format!(
+            "{}-{}",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%3f"),
+            self.seq.fetch_add(1, Ordering::Relaxed)
+        );
+        let job = Arc::new(ScanJob::new());
+
+        let now = Instant::now();
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.retain(|_, job| !job.expired(now));
+        jobs.insert(id.clone(), job.clone());
+        (id, job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<ScanJob>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner()).get(id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_lifecycle() {
+        let jobs = ScanJobs::default();
+        let (id, job) = jobs.start();
+        let (other, _) = jobs.start();
+        assert_ne!(id, other);
+        assert!(matches!(jobs.get(&id).unwrap().status(), JobStatus::Running));
+
+        job.progress.total.store(10, Ordering::Relaxed);
+        job.progress.scanned.fetch_add(4, Ordering::Relaxed);
+        assert_eq!(job.snapshot(), ProgressSnapshot { scanned: 4, total: 10, matches_so_far: 0 });
+
+        job.finish(Ok(serde_json::json!({ "matches": [] })));
+        assert!(matches!(job.status(), JobStatus::Done(_)));
+        assert!(!job.expired(Instant::now()));
+        assert!(job.expired(Instant::now() + FINISHED_JOB_TTL + Duration::from_secs(1)));
+        assert!(jobs.get("nope").is_none());
+    }
+}
@@ -1,6 +1,8 @@
 //! LLM bridge for clarifications and code generation.
 
 use crate::generated_store::GeneratedScanSpec;
+use crate::scan_types::ScanType;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -11,7 +13,7 @@ const DEFAULT_VERSION: &str = "2023-06-01";
 const DEFAULT_API_URL: &str = "https://api.anthropic.com/v1/messages";
 const DEFAULT_INFERENCE_GEO: &str = "us";
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ClarifyQuestion {
     pub id: String,
     pub label: Option<String>,
@@ -25,20 +27,75 @@ pub struct ClarifyQuestion {
     pub placeholder: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ClarifyResponse {
     pub title: Option<String>,
     pub message: Option<String>,
     pub questions: Vec<ClarifyQuestion>,
 }
 
+/// What compile produced: a new scan to save, or an existing scan that
+/// already answers the query
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CompileResponse {
-    pub scan: GeneratedScanSpec,
+#[serde(rename_all = "snake_case")]
+pub enum CompileResponse {
+    /// `{"scan": ...}` is the shape from before reuse was possible
+    #[serde(alias = "scan")]
+    NewScan(GeneratedScanSpec),
+    UseExisting {
+        scan_type: String,
+        #[serde(default)]
+        params: HashMap<String, Value>,
+    },
+}
+
+/// The running system as the prompts describe it, so the model reuses
+/// existing scans and doesn't ask about data we don't have
+#[derive(Debug, Clone, Default)]
+pub struct PromptContext {
+    /// Listed scans, built-in and generated
+    pub scans: Vec<ScanType>,
+    pub ticker_count: usize,
+    pub min_date: Option<String>,
+    pub max_date: Option<String>,
+}
+
+impl PromptContext {
+    fn data_section(&self) -> String {
+        let coverage = match (&self.min_date, &self.max_date) {
+            (Some(from), Some(to)) => format!("from {} to {}", from, to),
+            _ => "over an unknown date range".to_string(),
+        };
+        let mut out = String::new();
+        out.push_str("Available data:\n");
+        out.push_str(&format!("- Daily OHLCV bars for {} tickers, {}.\n", self.ticker_count, coverage));
+        out.push_str("- Fields per bar: date, open, high, low, close, volume. Tickers may be filtered by sector.\n");
+        out.push_str("- There is no intraday, fundamental, options, news or sentiment data.\n");
+        out
+    }
+
+    fn catalog_section(&self) -> String {
+        let mut out = String::new();
+        out.push_str("Existing scans (id: description; params):\n");
+        for scan in &self.scans {
+            let params: Vec<String> = scan
+                .params
+                .iter()
+                .map(|p| format!("{} {} = {}", p.name, p.param_type, p.default))
+                .collect();
+            out.push_str(&format!("- {}: {}; {}\n", scan.id, scan.description, params.join(", ")));
+        }
+        out
+    }
+}
+
+/// Whether an API key is set, so NL requests can go to the LLM at all
+pub fn is_configured() -> bool {
+    env::var("ANTHROPIC_API_KEY").is_ok_and(|key| !key.trim().is_empty())
 }
 
-pub fn clarify(query: &str) -> anyhow::Result<ClarifyResponse> {
-    let prompt = clarify_prompt();
+pub fn clarify(query: &str, context: &PromptContext) -> anyhow::Result<ClarifyResponse> {
+    let prompt = clarify_prompt(context);
     let user = format!("Query:\n{}", query);
     let raw = anthropic_call(&prompt, &user)?;
     let value = parse_json_from_text(&raw)?;
@@ -46,8 +103,8 @@ pub fn clarify(query: &str) -> anyhow::Result<ClarifyResponse> {
     Ok(resp)
 }
 
-pub fn compile(query: &str, answers: &HashMap<String, Value>) -> anyhow::Result<GeneratedScanSpec> {
-    let prompt = compile_prompt();
+pub fn compile(query: &str, answers: &HashMap<String, Value>, context: &PromptContext) -> anyhow::Result<CompileResponse> {
+    let prompt = compile_prompt(context);
     let answers_json = if answers.is_empty() {
         "none".to_string()
     } else {
@@ -57,10 +114,23 @@ pub fn compile(query: &str, answers: &HashMap<String, Value>) -> anyhow::Result<
     let raw = anthropic_call(&prompt, &user)?;
     let value = parse_json_from_text(&raw)?;
     let resp: CompileResponse = serde_json::from_value(value)?;
-    Ok(resp.scan)
+    Ok(resp)
 }
 
 fn anthropic_call(system: &str, user: &str) -> anyhow::Result<String> {
+    let start = std::time::Instant::now();
+    let result = anthropic_request(system, user);
+
+    metrics::histogram!("llm_call_duration_seconds").record(start.elapsed().as_secs_f64());
+    metrics::counter!("llm_calls_total").increment(1);
+    if result.is_err() {
+        metrics::counter!("llm_call_failures_total").increment(1);
+    }
+
+    result
+}
+
+fn anthropic_request(system: &str, user: &str) -> anyhow::Result<String> {
     let api_key = env::var("ANTHROPIC_API_KEY")
         .map_err(|_| anyhow::anyhow!("ANTHROPIC_API_KEY is not set"))?;
     let model = env::var("ANTHROPIC_MODEL").unwrap_or_else(|_| DEFAULT_MODEL.to_string());
@@ -138,7 +208,7 @@ fn parse_json_from_text(text: &str) -> anyhow::Result<Value> {
     Ok(value)
 }
 
-fn clarify_prompt() -> String {
+fn clarify_prompt(context: &PromptContext) -> String {
     let mut prompt = String::new();
     prompt.push_str("You are a trading scan assistant. Convert a natural language query into clarifying questions.\n");
     prompt.push_str("Return ONLY JSON with the schema:\n");
@@ -148,22 +218,55 @@ fn clarify_prompt() -> String {
     prompt.push_str("- Keep 0-6 questions.\n");
     prompt.push_str("- Use ids that can be used as param names in Rust.\n");
     prompt.push_str("- If no questions, return an empty questions array and still provide title/message.\n");
+    prompt.push_str("- Only ask about what the data below can answer; never offer intraday timeframes or fundamental filters.\n");
+    prompt.push_str("- If an existing scan below fits the query, ask only about its params.\n");
+    prompt.push_str(&context.data_section());
+    prompt.push_str(&context.catalog_section());
     prompt
 }
 
-fn compile_prompt() -> String {
+fn compile_prompt(context: &PromptContext) -> String {
     let mut prompt = String::new();
     prompt.push_str("You are a Rust scan code generator for a stock scanner.\n");
-    prompt.push_str("Output ONLY JSON with schema:\n");
-    prompt.push_str("{\"scan\": {\"id\": string, \"name\": string, \"description\": string, \"params\": [{\"name\": string, \"param_type\": \"number|text|select\", \"default\": any, \"description\": string}], \"function_body\": string, \"helpers\"?: string}}\n");
+    prompt.push_str("If an existing scan below answers the query, reuse it instead of writing code. Output ONLY JSON:\n");
+    prompt.push_str("{\"use_existing\": {\"scan_type\": string, \"params\": {<param name>: value}}}\n");
+    prompt.push_str("Otherwise output ONLY JSON with schema:\n");
+    prompt.push_str("{\"new_scan\": {\"id\": string, \"name\": string, \"description\": string, \"params\": [{\"name\": string, \"param_type\": \"number|text|select\", \"default\": any, \"description\": string, \"min\"?: number, \"max\"?: number}], \"function_body\": string, \"helpers\"?: string, \"default_lookback_days\"?: number}}\n");
     prompt.push_str("The function_body must be valid Rust inside:\n");
     prompt.push_str("fn scan_<id>(data: &TickerData, params: &HashMap<String, Value>) -> Vec<bool> { ... }\n");
     prompt.push_str("Constraints:\n");
     prompt.push_str("- data has fields: date (YYYY-MM-DD), open, high, low, close, volume as Vec<f64>.\n");
     prompt.push_str("- Return Vec<bool> with length data.close.len().\n");
     prompt.push_str("- Use params by reading from the HashMap. Provide defaults if missing.\n");
-    prompt.push_str("- Use functions from crate::indicators (sma, ema, rsi, obv, macd, macd_signal, macd_histogram, atr, bollinger, rolling_max, rolling_min, stddev, vwap, crossed_above, crossed_below, higher_high, lower_low, pct_change, volume_ratio, above, below, and, or).\n");
+    prompt.push_str("- Use functions from crate::indicators (sma, ema, rsi, cmo, ultimate_oscillator, obv, macd, macd_signal, macd_histogram, ppo, ppo_signal, ppo_histogram, trix, bull_power, bear_power, elder_impulse, true_range, atr, vortex, chandelier_exit, bollinger, donchian, pivot_points, rolling_max, rolling_min, cumulative_max, drawdown, stddev, vwap, force_index, ease_of_movement, crossed_above, crossed_below, crossed_above_level, crossed_below_level, higher_high, lower_low, pct_change, volume_ratio, volume_zscore, percentile_rank, adr_pct, close_location, rolling_correlation, rolling_beta, above, below, and, or).\n");
+    prompt.push_str("- To test a series crossing a fixed value, use crossed_above_level/crossed_below_level(&series, level), not a vec![level; n].\n");
     prompt.push_str("- Avoid unsafe.\n");
     prompt.push_str("- helpers is optional extra Rust code; if used, prefix helper function names with scan_<id>_.\n");
+    prompt.push_str(&context.data_section());
+    prompt.push_str(&context.catalog_section());
     prompt
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_response_shapes() {
+        let reuse: CompileResponse = serde_json::from_value(serde_json::json!({
+            "use_existing": {"scan_type": "rsi_oversold", "params": {"threshold": 25}}
+        }))
+        .unwrap();
+        assert!(matches!(reuse, CompileResponse::UseExisting { ref scan_type, ref params }
+            if scan_type == "rsi_oversold" && params["threshold"] == 25));
+
+        let spec = serde_json::json!({
+            "id": "x", "name": "X", "description": "", "params": [],
+            "function_body": "vec![false; data.close.len()]", "helpers": null
+        });
+        for key in ["new_scan", "scan"] {
+            let resp: CompileResponse = serde_json::from_value(serde_json::json!({ key: spec })).unwrap();
+            assert!(matches!(resp, CompileResponse::NewScan(s) if s.id == "x"));
+        }
+    }
+}
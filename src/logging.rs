@@ -0,0 +1,137 @@
+//! Log setup - `RUST_LOG` filter directives and the `RETRO_LOG_FORMAT` switch
+//!
+//! `RUST_LOG` takes `tracing_subscriber`'s env-filter syntax, so noisy modules
+//! can be turned down on their own (`info,retro::data=warn`). Without it,
+//! everything logs at INFO as before.
+
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::EnvFilter;
+
+/// Filter used when `RUST_LOG` is unset or invalid
+const DEFAULT_FILTER: &str = "info";
+
+/// Output format, from `RETRO_LOG_FORMAT`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// One line per event (the default)
+    #[default]
+    Text,
+    /// Multi-line, for reading in a terminal
+    Pretty,
+    /// One JSON object per line, for log shippers. Event fields sit at the
+    /// top level next to `timestamp`, `level` and `target`; `spans` holds
+    /// the enclosing spans' fields, outermost first.
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "text" | "full" => Some(Self::Text),
+            "pretty" => Some(Self::Pretty),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A subscriber writing `format` to `writer`, filtered by `filter`
+/// (env-filter directives; INFO when None or invalid)
+pub fn subscriber<W>(format: LogFormat, filter: Option<&str>, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let filter = filter
+        .and_then(|directives| EnvFilter::try_new(directives).ok())
+        .unwrap_or_else(|| EnvFilter::new(DEFAULT_FILTER));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(writer);
+    match format {
+        LogFormat::Text => Box::new(builder.finish()),
+        LogFormat::Pretty => Box::new(builder.pretty().finish()),
+        LogFormat::Json => Box::new(builder.json().flatten_event(true).with_span_list(true).with_current_span(false).finish()),
+    }
+}
+
+/// Install the global subscriber from `RUST_LOG` and `RETRO_LOG_FORMAT`,
+/// logging to stdout; call once at startup
+pub fn init() {
+    let format = std::env::var("RETRO_LOG_FORMAT").ok();
+    let parsed = format.as_deref().map(LogFormat::parse);
+    let filter = std::env::var("RUST_LOG").ok();
+    let invalid_filter = filter.as_deref().is_some_and(|f| EnvFilter::try_new(f).is_err());
+
+    tracing::subscriber::set_global_default(subscriber(
+        parsed.flatten().unwrap_or_default(),
+        filter.as_deref(),
+        std::io::stdout,
+    ))
+    .expect("failed to install the log subscriber");
+
+    if let (Some(None), Some(format)) = (parsed, format) {
+        tracing::warn!("Unknown RETRO_LOG_FORMAT {:?}; expected text, pretty or json", format);
+    }
+    if invalid_filter {
+        tracing::warn!("Invalid RUST_LOG {:?}; logging at {}", filter.unwrap_or_default(), DEFAULT_FILTER);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects everything written, for inspecting log output
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Capture {
+        type Writer = Capture;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_json_lines_and_filter() {
+        let capture = Capture::default();
+        let subscriber = subscriber(LogFormat::Json, Some("info,retro::data=warn"), capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("scan", scan_type = "golden_cross", tickers = 3);
+            span.in_scope(|| tracing::info!(matches = 2, "Scan complete"));
+            tracing::info!(target: "retro::data", "Progress: 500 loaded");
+            tracing::warn!(target: "retro::data", "Failed to load \"X\"");
+        });
+
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        // The data module's INFO line is filtered out
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[0]["message"], "Scan complete");
+        assert_eq!(lines[0]["matches"], 2);
+        assert_eq!(lines[0]["spans"][0]["scan_type"], "golden_cross");
+        assert_eq!(lines[0]["spans"][0]["tickers"], 3);
+        assert_eq!(lines[1]["message"], "Failed to load \"X\"");
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(LogFormat::parse("JSON"), Some(LogFormat::Json));
+        assert_eq!(LogFormat::parse("pretty"), Some(LogFormat::Pretty));
+        assert_eq!(LogFormat::parse("xml"), None);
+    }
+}
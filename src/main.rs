@@ -1,25 +1,108 @@
 mod indicators;
+mod auth;
+mod backtest;
+mod benchmark;
+mod breadth;
+mod calendar;
+mod composite;
+mod error;
+mod events;
 mod scanner;
 mod server;
 mod data;
+mod resample;
+mod mtf;
+mod overlays;
 mod generated;
 mod generated_store;
+mod history;
+mod jobs;
+mod run_history;
+mod saved_scans;
+mod scan_limit;
 mod scan_types;
+mod scratch;
+mod registry;
 mod llm;
+mod logging;
+mod nl_rules;
+mod openapi;
+mod telemetry;
+mod rate_limit;
 
-use tracing_subscriber;
+use server::ServerConfig;
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: retro [--data-dir DIR] [--frontend-dir DIR] [--bind ADDR] [--port PORT]";
+
+/// Apply command-line flags over `config`
+fn parse_args(mut config: ServerConfig, args: impl IntoIterator<Item = String>) -> Result<ServerConfig, String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Both `--port 8080` and `--port=8080`
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+        if flag == "-h" || flag == "--help" {
+            return Err(USAGE.to_string());
+        }
+        let value = inline
+            .or_else(|| args.next())
+            .ok_or_else(|| format!("{} needs a value\n{}", flag, USAGE))?;
+        match flag.as_str() {
+            "--data-dir" => config.data_dir = PathBuf::from(value),
+            "--frontend-dir" => config.frontend_dir = PathBuf::from(value),
+            "--bind" => config.bind_addr = value.parse().map_err(|_| format!("--bind: invalid address {:?}", value))?,
+            "--port" => config.port = value.parse().map_err(|_| format!("--port: invalid port {:?}", value))?,
+            _ => return Err(format!("Unknown argument: {}\n{}", flag, USAGE)),
+        }
+    }
+    Ok(config)
+}
 
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
 
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .init();
+    let config = match parse_args(ServerConfig::from_env(), std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    logging::init();
 
     tracing::info!("🚀 RETRO Scanner starting...");
 
     // Start the server
-    server::run().await;
+    server::run(config).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let config = parse_args(ServerConfig::default(), args(&["--data-dir", "/srv/ohlcv", "--port=8080"])).unwrap();
+        assert_eq!(config.data_dir, PathBuf::from("/srv/ohlcv"));
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.frontend_dir, ServerConfig::default().frontend_dir);
+
+        let config = parse_args(ServerConfig::default(), args(&["--bind", "127.0.0.1", "--frontend-dir", "ui"])).unwrap();
+        assert_eq!(config.bind_addr.to_string(), "127.0.0.1");
+        assert_eq!(config.frontend_dir, PathBuf::from("ui"));
+
+        for bad in [&["--port", "99999"][..], &["--port"], &["--bind", "localhost"], &["--verbose", "1"], &["--help"]] {
+            assert!(parse_args(ServerConfig::default(), args(bad)).is_err(), "{:?}", bad);
+        }
+        assert_eq!(parse_args(ServerConfig::default(), Vec::new()).unwrap(), ServerConfig::default());
+    }
 }
@@ -0,0 +1,254 @@
+//! Multi-timeframe confirmation - gate daily signals on a higher-timeframe trend
+//!
+//! A daily bar is confirmed by the last *completed* higher-timeframe bar before
+//! it: the bucket preceding the one the daily bar falls in. The in-progress
+//! week (or month) is never consulted, so confirmation can't look ahead.
+
+use crate::data::TickerData;
+use crate::generated::ScanFn;
+use crate::indicators::{macd_histogram, sma};
+use crate::resample::{bars_to_data, resample_bars, Bar, Interval};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// `confirm` block of a scan query
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Confirm {
+    #[serde(default = "default_interval")]
+    pub interval: Interval,
+    pub condition: ConfirmCondition,
+}
+
+fn default_interval() -> Interval {
+    Interval::Weekly
+}
+
+/// Condition evaluated on each higher-timeframe bar
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConfirmCondition {
+    /// Close above its simple moving average (e.g. weekly close > 30-week SMA)
+    CloseAboveSma {
+        #[serde(default = "default_sma_period")]
+        period: usize,
+    },
+    /// MACD histogram above zero
+    MacdHistogramPositive {
+        #[serde(default = "default_fast")]
+        fast: usize,
+        #[serde(default = "default_slow")]
+        slow: usize,
+        #[serde(default = "default_signal")]
+        signal: usize,
+    },
+}
+
+fn default_sma_period() -> usize {
+    30
+}
+
+fn default_fast() -> usize {
+    12
+}
+
+fn default_slow() -> usize {
+    26
+}
+
+fn default_signal() -> usize {
+    9
+}
+
+impl Confirm {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval == Interval::Daily {
+            return Err("confirm.interval must be weekly or monthly".into());
+        }
+        let periods: &[usize] = match &self.condition {
+            ConfirmCondition::CloseAboveSma { period } => &[*period],
+            ConfirmCondition::MacdHistogramPositive { fast, slow, signal } => &[*fast, *slow, *signal],
+        };
+        if periods.contains(&0) {
+            return Err("confirm periods must be at least 1".into());
+        }
+        Ok(())
+    }
+
+    /// Per daily bar: whether the last completed higher-timeframe bar meets
+    /// the condition. Assumes `data` is sorted by date.
+    pub fn mask(&self, data: &TickerData) -> Vec<bool> {
+        let bars = resample_bars(data, self.interval);
+        let close: Vec<f64> = bars.iter().map(|b| b.close).collect();
+
+        let passed: Vec<bool> = match &self.condition {
+            ConfirmCondition::CloseAboveSma { period } => close
+                .iter()
+                .zip(sma(&close, *period))
+                .map(|(c, avg)| *c > avg)
+                .collect(),
+            ConfirmCondition::MacdHistogramPositive { fast, slow, signal } => {
+                macd_histogram(&close, *fast, *slow, *signal)
+                    .iter()
+                    .map(|h| *h > 0.0)
+                    .collect()
+            }
+        };
+
+        spread_completed(&bars, &passed, data.len())
+    }
+}
+
+/// `confirm_with` block of a scan query: any registered scan, run on the
+/// resampled series
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConfirmWith {
+    pub scan_type: String,
+    #[serde(default)]
+    pub params: HashMap<String, Value>,
+    #[serde(default = "default_interval")]
+    pub timeframe: Interval,
+}
+
+impl ConfirmWith {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.timeframe == Interval::Daily {
+            return Err("confirm_with.timeframe must be weekly or monthly".into());
+        }
+        Ok(())
+    }
+
+    /// Per daily bar: whether `func` matched on the last completed resampled bar
+    pub fn mask(&self, data: &TickerData, func: ScanFn) -> Vec<bool> {
+        let bars = resample_bars(data, self.timeframe);
+        let matched = func(&bars_to_data(data, &bars), &self.params);
+        spread_completed(&bars, &matched, data.len())
+    }
+}
+
+/// Map a per-bucket mask onto the source bars, each source bar taking the
+/// value of the bucket before its own
+fn spread_completed(bars: &[Bar], passed: &[bool], len: usize) -> Vec<bool> {
+    let mut mask = vec![false; len];
+    for (k, bar) in bars.iter().enumerate().skip(1) {
+        if passed.get(k - 1).copied().unwrap_or(false) {
+            mask[bar.start_idx..=bar.end_idx].fill(true);
+        }
+    }
+    mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daily(bars: &[(&str, f64)]) -> TickerData {
+        let close: Vec<f64> = bars.iter().map(|b| b.1).collect();
+        TickerData::from_columns(
+            bars.iter().map(|b| b.0.to_string()).collect(),
+            close.clone(),
+            close.clone(),
+            close.clone(),
+            close,
+            vec![100.0; bars.len()],
+        )
+    }
+
+    #[test]
+    fn test_confirms_from_last_completed_week() {
+        let data = daily(&[
+            // Week A: closes at 10
+            ("2024-12-23", 10.0),
+            ("2024-12-24", 10.0),
+            ("2024-12-26", 10.0),
+            ("2024-12-27", 10.0),
+            // Week B spans the new year with Jan 1 off: closes at 12
+            ("2024-12-30", 10.0),
+            ("2024-12-31", 11.0),
+            ("2025-01-02", 9.0),
+            ("2025-01-03", 12.0),
+            // Week C: closes at 11
+            ("2025-01-06", 14.0),
+            ("2025-01-07", 14.0),
+            ("2025-01-10", 11.0),
+            // Week D, Monday only: closes at 13
+            ("2025-01-13", 13.0),
+        ]);
+        let confirm = Confirm {
+            interval: Interval::Weekly,
+            // Weekly close above the 2-week SMA: this week closed above last week
+            condition: ConfirmCondition::CloseAboveSma { period: 2 },
+        };
+
+        let mask = confirm.mask(&data);
+
+        // A has no prior week; B looks at A (SMA not ready); C looks at B (12 > 11);
+        // D looks at C (11 < 11.5). Week C's own close never gates its own days,
+        // even though its first days trade at 14.
+        let expected = [
+            false, false, false, false, //
+            false, false, false, false, //
+            true, true, true, //
+            false,
+        ];
+        assert_eq!(mask, expected);
+    }
+
+    #[test]
+    fn test_confirm_with_runs_scan_on_resampled_bars() {
+        // Fires on weekly bars that closed above their open
+        fn up_bar(data: &TickerData, _: &HashMap<String, Value>) -> Vec<bool> {
+            data.close.iter().zip(&data.open).map(|(c, o)| c > o).collect()
+        }
+
+        let data = daily(&[
+            // Week A: 10 -> 12 (up)
+            ("2024-03-04", 10.0),
+            ("2024-03-08", 12.0),
+            // Week B: 12 -> 9 (down), Good Friday off
+            ("2024-03-25", 12.0),
+            ("2024-03-28", 9.0),
+            // Week C: 9 -> 11 (up)
+            ("2024-04-01", 9.0),
+            ("2024-04-05", 11.0),
+            // Week D
+            ("2024-04-08", 11.0),
+        ]);
+        let confirm = ConfirmWith {
+            scan_type: "up_bar".into(),
+            params: HashMap::new(),
+            timeframe: Interval::Weekly,
+        };
+
+        // B follows A (up), C follows B (down), D follows C (up)
+        assert_eq!(confirm.mask(&data, up_bar), [false, false, true, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_monthly_macd_and_validation() {
+        // Strictly rising month-end closes: the MACD histogram turns positive
+        let bars: Vec<(String, f64)> = (0..48)
+            .flat_map(|m| {
+                let (y, mo) = (2020 + m / 12, m % 12 + 1);
+                [(format!("{}-{:02}-01", y, mo), 100.0), (format!("{}-{:02}-15", y, mo), 100.0 + (m * m) as f64)]
+            })
+            .collect();
+        let refs: Vec<(&str, f64)> = bars.iter().map(|(d, c)| (d.as_str(), *c)).collect();
+        let data = daily(&refs);
+
+        let confirm = Confirm {
+            interval: Interval::Monthly,
+            condition: ConfirmCondition::MacdHistogramPositive { fast: 3, slow: 6, signal: 2 },
+        };
+        let mask = confirm.mask(&data);
+
+        // Both days of a month share the previous month's verdict
+        assert!(mask.chunks(2).all(|m| m[0] == m[1]));
+        assert!(!mask[0] && mask[mask.len() - 1]);
+
+        assert!(confirm.validate().is_ok());
+        let daily_interval = Confirm { interval: Interval::Daily, ..confirm };
+        assert!(daily_interval.validate().is_err());
+    }
+}
@@ -0,0 +1,469 @@
+//! Rule-based NL parsing - the offline fallback for `/api/nl/*`
+//!
+//! Recognizes a useful subset of phrasings ("rsi below 25", "golden cross in
+//! the last month", "volume 3x average", "macd cross up and price above 200
+//! sma") without the LLM. Each clause maps to a built-in scan and, where it
+//! can be written as one, a `composite` filter: a lone clause runs its scan
+//! directly, several are ANDed as a composite. A clause that isn't understood
+//! fails the whole parse rather than being silently dropped.
+
+use crate::composite;
+use crate::llm::{ClarifyQuestion, ClarifyResponse};
+use crate::registry::ScanRegistry;
+use crate::scanner::ScanQuery;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Words that carry no meaning for a scan
+const FILLER: &[&str] = &[
+    "a", "all", "an", "any", "are", "companies", "find", "get", "give", "had", "has", "have", "having", "is",
+    "list", "me", "of", "on", "show", "stock", "stocks", "that", "the", "ticker", "tickers", "when", "where",
+    "which", "whose", "with",
+];
+
+/// Words that split a query into clauses
+const CONJUNCTIONS: &[&str] = &["and", ",", "plus", "while", "also"];
+
+const BELOW: &[&str] = &["below", "under", "beneath", "<", "less", "lower"];
+const ABOVE: &[&str] = &["above", "over", ">", "greater", "exceeds", "exceeding", "higher"];
+const CROSS: &[&str] = &["cross", "crosses", "crossed", "crossing", "crossover", "crossovers"];
+const DOWN: &[&str] = &["down", "below", "under", "bearish", "negative"];
+
+/// What one clause asked for
+struct Condition {
+    /// A scan that answers the clause on its own
+    scan: Option<(String, HashMap<String, Value>)>,
+    /// The clause as a `composite` filter, so it can be ANDed with others
+    filter: Option<Value>,
+}
+
+impl Condition {
+    fn scan(id: &str, params: &[(&str, Value)]) -> Self {
+        let params = params.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+        Condition { scan: Some((id.to_string(), params)), filter: None }
+    }
+
+    fn filter(filter: Value) -> Self {
+        Condition { scan: None, filter: Some(filter) }
+    }
+
+    fn with_filter(mut self, filter: Value) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+}
+
+/// Parse `query` into a runnable scan query, or `None` if any part of it
+/// isn't recognized
+pub fn parse(query: &str, registry: &ScanRegistry) -> Option<ScanQuery> {
+    let mut tokens = tokenize(query);
+    let mut scan_query = ScanQuery::default();
+    take_time_window(&mut tokens, &mut scan_query);
+
+    let mut conditions = Vec::new();
+    for clause in tokens.split(|t| CONJUNCTIONS.contains(&t.as_str())) {
+        let words: Vec<&str> = clause.iter().map(String::as_str).filter(|t| !FILLER.contains(t)).collect();
+        if words.is_empty() {
+            continue;
+        }
+        conditions.push(parse_clause(&words, registry)?);
+    }
+
+    let (scan_type, params) = match conditions.as_mut_slice() {
+        [] => return None,
+        [single] if single.scan.is_some() => single.scan.take()?,
+        _ => {
+            let filters: Option<Vec<Value>> = conditions.into_iter().map(|c| c.filter).collect();
+            ("composite".to_string(), HashMap::from([("filters".to_string(), Value::Array(filters?))]))
+        }
+    };
+    scan_query.scan_type = registry.resolve(&scan_type).ok()?.meta.id.clone();
+    scan_query.params = params;
+    Some(scan_query)
+}
+
+/// Clarifying questions for a recognized query: the matched scan's params,
+/// defaulting to what the query said. A composite has none to ask about.
+pub fn clarify(query: &str, registry: &ScanRegistry) -> Option<ClarifyResponse> {
+    let parsed = parse(query, registry)?;
+    let meta = &registry.resolve(&parsed.scan_type).ok()?.meta;
+
+    if parsed.scan_type == "composite" {
+        let filters = composite::parse_filters(&parsed.params["filters"]).ok()?;
+        let described: Vec<String> = filters.iter().map(|f| f.to_string()).collect();
+        return Some(ClarifyResponse {
+            title: Some(meta.name.clone()),
+            message: Some(format!("Understood as: {}", described.join(" AND "))),
+            questions: Vec::new(),
+        });
+    }
+
+    let questions = meta
+        .params
+        .iter()
+        .map(|p| ClarifyQuestion {
+            id: p.name.clone(),
+            label: Some(p.description.clone()),
+            // Built-in selects don't list their options, so ask for text
+            qtype: if p.param_type == "number" { "number" } else { "text" }.to_string(),
+            options: None,
+            default: Some(parsed.params.get(&p.name).unwrap_or(&p.default).clone()),
+            min: p.min,
+            max: p.max,
+            step: None,
+            placeholder: None,
+        })
+        .collect();
+    Some(ClarifyResponse {
+        title: Some(meta.name.clone()),
+        message: Some(format!("Understood as the {} scan: {}", meta.name, meta.description)),
+        questions,
+    })
+}
+
+/// Lowercased words, with numbers split from units ("200-day" → "200 day")
+/// and comparison signs and commas as their own tokens
+fn tokenize(query: &str) -> Vec<String> {
+    let mut text = query.to_lowercase();
+    for (from, to) in [
+        ("<", " < "),
+        (">", " > "),
+        (",", " , "),
+        (";", " , "),
+        ("&", " and "),
+        ("-", " "),
+        ("/", " "),
+        ("$", " "),
+        ("(", " "),
+        (")", " "),
+        ("?", " "),
+        ("!", " "),
+        ("'s", ""),
+    ] {
+        text = text.replace(from, to);
+    }
+    text.split_whitespace()
+        .map(|t| t.trim_end_matches('.').to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// A numeric token, ignoring a trailing `x` or `%` ("3x", "5%")
+fn number(token: &str) -> Option<f64> {
+    match token {
+        "twice" | "double" => Some(2.0),
+        "triple" => Some(3.0),
+        _ => token.trim_end_matches(['x', '%']).parse().ok().filter(|n: &f64| n.is_finite()),
+    }
+}
+
+/// Days in a time unit word, singular or plural
+fn unit_days(token: &str) -> Option<u32> {
+    match token.trim_end_matches('s') {
+        "day" | "session" => Some(1),
+        "week" => Some(7),
+        "month" => Some(30),
+        "quarter" => Some(91),
+        "year" => Some(365),
+        _ => None,
+    }
+}
+
+/// Remove a time window ("in the last month", "past 10 days", "this year",
+/// "since 2020", "in 2022") from `tokens` and apply it to `query`
+fn take_time_window(tokens: &mut Vec<String>, query: &mut ScanQuery) {
+    let mut i = 0;
+    while i < tokens.len() {
+        let word = tokens[i].as_str();
+        let next = tokens.get(i + 1).map(String::as_str);
+        let span = match word {
+            "last" | "past" | "previous" | "this" => {
+                let (count, unit_at) = match next.and_then(number) {
+                    Some(n) => (n, i + 2),
+                    None => (1.0, i + 1),
+                };
+                tokens.get(unit_at).and_then(|t| unit_days(t)).map(|days| {
+                    query.lookback_days = Some((count * days as f64).round().max(1.0) as u32);
+                    unit_at + 1
+                })
+            }
+            "today" => {
+                query.lookback_days = Some(1);
+                Some(i + 1)
+            }
+            "since" | "in" | "during" => match next.and_then(|t| t.parse::<i32>().ok()) {
+                Some(year) if (1900..=2100).contains(&year) => {
+                    query.date_from = Some(format!("{}-01-01", year));
+                    if word != "since" {
+                        query.date_to = Some(format!("{}-12-31", year));
+                    }
+                    Some(i + 2)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+
+        match span {
+            Some(end) => {
+                // Take the lead-in too ("in the", "over the", "within the")
+                let mut start = i;
+                while start > 0 && ["in", "over", "within", "during", "the", "for"].contains(&tokens[start - 1].as_str()) {
+                    start -= 1;
+                }
+                tokens.drain(start..end);
+                i = start;
+            }
+            None => i += 1,
+        }
+    }
+}
+
+fn has(words: &[&str], any: &[&str]) -> bool {
+    words.iter().any(|w| any.contains(w))
+}
+
+/// Index and direction of the first comparison word
+fn comparison(words: &[&str]) -> Option<(usize, bool)> {
+    words.iter().enumerate().find_map(|(i, w)| {
+        if BELOW.contains(w) {
+            Some((i, false))
+        } else if ABOVE.contains(w) {
+            Some((i, true))
+        } else {
+            None
+        }
+    })
+}
+
+/// A number followed by `unit` ("20 day" → 20)
+fn number_before(words: &[&str], units: &[&str]) -> Option<f64> {
+    words.windows(2).find(|w| units.contains(&w[1])).and_then(|w| number(w[0]))
+}
+
+fn parse_clause(words: &[&str], registry: &ScanRegistry) -> Option<Condition> {
+    let crossing = has(words, CROSS);
+
+    if has(words, &["golden"]) {
+        let filter = json!({"indicator": "sma", "period": 50, "op": "crosses_above", "value": {"indicator": "sma", "period": 200}});
+        return Some(Condition::scan("golden_cross", &[]).with_filter(filter));
+    }
+    if has(words, &["death"]) {
+        let filter = json!({"indicator": "sma", "period": 50, "op": "crosses_below", "value": {"indicator": "sma", "period": 200}});
+        return Some(Condition::scan("death_cross", &[]).with_filter(filter));
+    }
+    if has(words, &["macd"]) {
+        let (id, op) = if has(words, DOWN) {
+            ("macd_cross_down", "crosses_below")
+        } else if crossing || has(words, &["up", "above", "bullish", "positive"]) {
+            ("macd_cross_up", "crosses_above")
+        } else {
+            return None;
+        };
+        let filter = json!({"indicator": "macd_histogram", "op": op, "value": 0});
+        return Some(Condition::scan(id, &[]).with_filter(filter));
+    }
+    if has(words, &["rsi"]) {
+        return rsi_clause(words, crossing);
+    }
+    if has(words, &["volume", "vol"]) {
+        return volume_clause(words);
+    }
+    if has(words, &["high", "highs"]) {
+        return high_clause(words);
+    }
+    if has(words, &["bollinger"]) && has(words, &["squeeze"]) {
+        return Some(Condition::scan("bollinger_squeeze", &[]));
+    }
+
+    let moving_average = has(words, &["sma", "ema", "ma", "dma", "moving"]);
+    let price = has(words, &["price", "close", "closes", "closing", "trading", "priced"]);
+    if moving_average && crossing && !price && has(words, &["ema"]) {
+        let mut periods: Vec<u64> = words.iter().filter_map(|w| number(w)).map(|n| n as u64).collect();
+        periods.sort_unstable();
+        let mut params = vec![("direction", Value::from(if has(words, DOWN) { "down" } else { "up" }))];
+        if let [fast, slow] = periods[..] {
+            params.extend([("fast", Value::from(fast)), ("slow", Value::from(slow))]);
+        }
+        return Some(Condition::scan("ema_cross", &params));
+    }
+    if moving_average {
+        let (_, up) = comparison(words)?;
+        let period = words.iter().find_map(|w| number(w)).map_or(200, |n| n as u64);
+        let indicator = if has(words, &["ema", "exponential"]) { "ema" } else { "sma" };
+        let op = match (crossing, up) {
+            (true, true) => "crosses_above",
+            (true, false) => "crosses_below",
+            (false, true) => ">",
+            (false, false) => "<",
+        };
+        return Some(Condition::filter(
+            json!({"indicator": "close", "op": op, "value": {"indicator": indicator, "period": period}}),
+        ));
+    }
+    if price {
+        let (at, up) = comparison(words)?;
+        let level = words[at + 1..].iter().find_map(|w| number(w))?;
+        return Some(Condition::filter(json!({"indicator": "close", "op": if up { ">" } else { "<" }, "value": level})));
+    }
+
+    // Any scan named outright ("turtle", "vortex cross", "pivot bounce")
+    let phrase = words.join(" ");
+    registry
+        .list()
+        .into_iter()
+        .find(|s| s.name.to_lowercase() == phrase || s.id.replace('_', " ") == phrase)
+        .map(|s| Condition::scan(&s.id, &[]))
+}
+
+/// "rsi below 25", "14 day rsi over 70", "rsi crosses under 30", "rsi oversold"
+fn rsi_clause(words: &[&str], crossing: bool) -> Option<Condition> {
+    let (oversold, threshold, period) = if has(words, &["oversold", "overbought"]) {
+        let oversold = has(words, &["oversold"]);
+        let threshold = words.iter().find_map(|w| number(w)).unwrap_or(if oversold { 30.0 } else { 70.0 });
+        (oversold, threshold, None)
+    } else {
+        let (at, up) = comparison(words)?;
+        let threshold = words[at + 1..].iter().find_map(|w| number(w))?;
+        (!up, threshold, words[..at].iter().find_map(|w| number(w)))
+    };
+
+    let (id, op) = match (oversold, crossing) {
+        (true, true) => ("rsi_oversold", "crosses_below"),
+        (true, false) => ("rsi_oversold", "<"),
+        (false, true) => ("rsi_overbought", "crosses_above"),
+        (false, false) => ("rsi_overbought", ">"),
+    };
+    let mut params = vec![("threshold", Value::from(threshold))];
+    let mut filter = json!({"indicator": "rsi", "op": op, "value": threshold});
+    if let Some(period) = period {
+        params.push(("period", Value::from(period as u64)));
+        filter["period"] = Value::from(period as u64);
+    }
+    Some(Condition::scan(id, &params).with_filter(filter))
+}
+
+/// "volume 3x average", "volume over twice the 50 day average", "volume spike"
+fn volume_clause(words: &[&str]) -> Option<Condition> {
+    let multiplier = words
+        .iter()
+        .filter(|w| w.ends_with('x') || ["twice", "double", "triple"].contains(w))
+        .find_map(|w| number(w))
+        .or_else(|| number_before(words, &["times", "x"]))
+        .or_else(|| has(words, &["spike", "spikes", "surge", "unusual", "heavy"]).then_some(2.0))?;
+    let period = number_before(words, &["day", "days", "session", "sessions"]).map(|n| n as u64);
+
+    let mut params = vec![("multiplier", Value::from(multiplier))];
+    let mut filter = json!({"indicator": "volume_ratio", "op": ">", "value": multiplier});
+    if let Some(period) = period {
+        params.push(("period", Value::from(period)));
+        filter["period"] = Value::from(period);
+    }
+    Some(Condition::scan("volume_spike", &params).with_filter(filter))
+}
+
+/// "new 52 week high", "breaking out to a 100 day high", "within 5% of the 52 week high"
+fn high_clause(words: &[&str]) -> Option<Condition> {
+    let lookback = if let Some(n) = number_before(words, &["week", "weeks", "wk"]) {
+        n * 252.0 / 52.0
+    } else if let Some(n) = number_before(words, &["month", "months"]) {
+        n * 21.0
+    } else if let Some(n) = number_before(words, &["year", "years"]) {
+        n * 252.0
+    } else {
+        number_before(words, &["day", "days"]).unwrap_or(252.0)
+    }
+    .round() as u64;
+
+    if has(words, &["within", "near", "close"]) {
+        let pct = words
+            .iter()
+            .find(|w| w.ends_with('%'))
+            .and_then(|w| number(w))
+            .or_else(|| number_before(words, &["percent", "pct"]));
+        let mut params = vec![("lookback", Value::from(lookback))];
+        params.extend(pct.map(|p| ("within_pct", Value::from(p))));
+        return Some(Condition::scan("near_high", &params));
+    }
+    Some(Condition::scan("price_breakout", &[("lookback", Value::from(lookback))]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(query: &str) -> ScanQuery {
+        parse(query, &ScanRegistry::load()).unwrap_or_else(|| panic!("not parsed: {}", query))
+    }
+
+    fn filters(query: &ScanQuery) -> Vec<String> {
+        assert_eq!(query.scan_type, "composite");
+        composite::parse_filters(&query.params["filters"]).unwrap().iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn test_single_scan_phrasings() {
+        let q = parsed("RSI below 25");
+        assert_eq!((q.scan_type.as_str(), &q.params["threshold"]), ("rsi_oversold", &json!(25.0)));
+
+        let q = parsed("stocks where the 7-day RSI is over 80");
+        assert_eq!(q.scan_type, "rsi_overbought");
+        assert_eq!((&q.params["threshold"], &q.params["period"]), (&json!(80.0), &json!(7)));
+
+        let q = parsed("golden cross in the last month");
+        assert_eq!((q.scan_type.as_str(), q.lookback_days), ("golden_cross", Some(30)));
+
+        let q = parsed("Volume 3x average");
+        assert_eq!((q.scan_type.as_str(), &q.params["multiplier"]), ("volume_spike", &json!(3.0)));
+
+        let q = parsed("volume more than 2.5 times the 50-day average over the past 2 weeks");
+        assert_eq!((&q.params["multiplier"], &q.params["period"]), (&json!(2.5), &json!(50)));
+        assert_eq!(q.lookback_days, Some(14));
+
+        let q = parsed("MACD crosses down since 2020");
+        assert_eq!((q.scan_type.as_str(), q.date_from.as_deref()), ("macd_cross_down", Some("2020-01-01")));
+
+        let q = parsed("new 52-week highs this year");
+        assert_eq!((q.scan_type.as_str(), &q.params["lookback"]), ("price_breakout", &json!(252)));
+        assert_eq!(q.lookback_days, Some(365));
+
+        let q = parsed("within 3% of the 52 week high");
+        assert_eq!((q.scan_type.as_str(), &q.params["within_pct"]), ("near_high", &json!(3.0)));
+
+        let q = parsed("12/26 EMA crossover bearish in 2022");
+        assert_eq!(q.scan_type, "ema_cross");
+        assert_eq!((&q.params["fast"], &q.params["slow"], &q.params["direction"]), (&json!(12), &json!(26), &json!("down")));
+        assert_eq!((q.date_from.as_deref(), q.date_to.as_deref()), (Some("2022-01-01"), Some("2022-12-31")));
+
+        assert_eq!(parsed("turtle").scan_type, "turtle");
+    }
+
+    #[test]
+    fn test_combined_phrasings() {
+        let q = parsed("macd cross up and price above 200 sma");
+        assert_eq!(filters(&q), ["macd_histogram crosses_above 0", "close > sma_200"]);
+
+        let q = parsed("RSI under 30, price closes above the 50 day EMA & volume twice average");
+        assert_eq!(filters(&q), ["rsi < 30", "close > ema_50", "volume_ratio > 2"]);
+
+        let q = parsed("price crosses below the 20 day moving average and price under $10 in the last 5 days");
+        assert_eq!(filters(&q), ["close crosses_below sma_20", "close < 10"]);
+        assert_eq!(q.lookback_days, Some(5));
+
+        let q = parsed("price above 200 sma");
+        assert_eq!(filters(&q), ["close > sma_200"]);
+    }
+
+    #[test]
+    fn test_unrecognized_and_clarify() {
+        let registry = ScanRegistry::load();
+        for query in ["", "cheap stocks", "rsi below 30 and pe ratio under 10", "new highs and turtle"] {
+            assert!(parse(query, &registry).is_none(), "{}", query);
+        }
+
+        let resp = clarify("rsi below 25", &registry).unwrap();
+        let threshold = resp.questions.iter().find(|q| q.id == "threshold").unwrap();
+        assert_eq!((threshold.qtype.as_str(), threshold.default.as_ref()), ("number", Some(&json!(25.0))));
+        assert!(clarify("golden cross and rsi below 40", &registry).unwrap().questions.is_empty());
+    }
+}
@@ -0,0 +1,324 @@
+//! OpenAPI 3.1 document for the HTTP API, served at `/api/openapi.json`
+//!
+//! Schemas come from the DTOs' `JsonSchema` derives: request bodies and query
+//! strings as the server deserializes them, responses as it serializes them.
+//! A type whose two shapes differ (a field optional in one, always present in
+//! the other) gets an `...Output` schema for the response side. Scan `params`
+//! are described per scan type from the registry, so generated scans are
+//! included too.
+
+use crate::composite::Filter;
+use crate::scan_types::{ScanParam, ScanType};
+use crate::scanner::ScanQuery;
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, SchemaGenerator};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+const SCHEMAS: &str = "#/components/schemas/";
+
+struct Operation {
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    parameters: Vec<Value>,
+    request: Option<Value>,
+    response: Option<Value>,
+}
+
+/// Routes documented so far, and the schemas they reference
+pub struct ApiDoc {
+    requests: SchemaGenerator,
+    responses: SchemaGenerator,
+    operations: Vec<Operation>,
+}
+
+impl ApiDoc {
+    pub fn new() -> Self {
+        let settings = SchemaSettings::draft2020_12().with(|s| {
+            s.definitions_path = "/components/schemas".into();
+            s.meta_schema = None;
+        });
+        Self {
+            requests: settings.clone().for_deserialize().into_generator(),
+            responses: settings.for_serialize().into_generator(),
+            operations: Vec::new(),
+        }
+    }
+
+    /// Start documenting a route; `{name}` segments become path parameters.
+    /// The other builder methods describe the route added last.
+    pub fn route(&mut self, method: &'static str, path: &'static str, summary: &'static str) -> &mut Self {
+        let parameters = path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+            .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+            .collect();
+        self.operations.push(Operation { method, path, summary, parameters, request: None, response: None });
+        self
+    }
+
+    fn last(&mut self) -> &mut Operation {
+        self.operations.last_mut().expect("route() before describing it")
+    }
+
+    /// Each field of `T` as a query-string parameter
+    pub fn query<T: JsonSchema>(&mut self) -> &mut Self {
+        let schema = self.requests.root_schema_for::<T>().to_value();
+        let required: Vec<&Value> = schema.get("required").and_then(Value::as_array).into_iter().flatten().collect();
+        let parameters: Vec<Value> = schema
+            .get("properties")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .map(|(name, property)| {
+                let mut property = property.clone();
+                let description = property.as_object_mut().and_then(|p| p.remove("description"));
+                let mut parameter = json!({
+                    "name": name,
+                    "in": "query",
+                    "required": required.contains(&&Value::from(name.as_str())),
+                    "schema": property,
+                });
+                if let Some(description) = description {
+                    parameter["description"] = description;
+                }
+                parameter
+            })
+            .collect();
+        self.last().parameters.extend(parameters);
+        self
+    }
+
+    /// JSON request body
+    pub fn body<T: JsonSchema>(&mut self) -> &mut Self {
+        let schema = self.requests.subschema_for::<T>().to_value();
+        self.last().request = Some(schema);
+        self
+    }
+
+    /// A `ScanQuery` body whose `params` follow its `scan_type` (see `ScanRequest`)
+    pub fn scan_body(&mut self) -> &mut Self {
+        self.last().request = Some(json!({ "$ref": format!("{}ScanRequest", SCHEMAS) }));
+        self
+    }
+
+    /// JSON response body
+    pub fn returns<T: JsonSchema>(&mut self) -> &mut Self {
+        let schema = self.responses.subschema_for::<T>().to_value();
+        self.last().response = Some(schema);
+        self
+    }
+
+    /// The finished document, with a `ScanRequest` variant per scan type
+    pub fn into_document(mut self, scan_types: &[ScanType]) -> Value {
+        let scan_query = self.requests.subschema_for::<ScanQuery>().to_value();
+        let filter = self.requests.subschema_for::<Filter>().to_value();
+
+        let mut schemas = Map::new();
+        let mut variants = Vec::new();
+        let mut mapping = Map::new();
+        for scan in scan_types {
+            let params_name = format!("ScanParams.{}", scan.id);
+            schemas.insert(params_name.clone(), params_schema(scan, &filter));
+
+            let request_name = format!("ScanRequest.{}", scan.id);
+            schemas.insert(
+                request_name.clone(),
+                json!({
+                    "title": scan.name,
+                    "description": scan.description,
+                    "allOf": [scan_query],
+                    "properties": {
+                        "scan_type": { "const": scan.id },
+                        "params": { "$ref": format!("{}{}", SCHEMAS, params_name) },
+                    },
+                }),
+            );
+            let reference = format!("{}{}", SCHEMAS, request_name);
+            variants.push(json!({ "$ref": reference }));
+            mapping.insert(scan.id.clone(), reference.into());
+        }
+        schemas.insert(
+            "ScanRequest".into(),
+            json!({
+                "description": "A scan query; `params` depends on `scan_type` (see /api/scan-types)",
+                "oneOf": variants,
+                "discriminator": { "propertyName": "scan_type", "mapping": mapping },
+            }),
+        );
+        schemas.insert(
+            "Error".into(),
+            json!({
+                "type": "object",
+                "properties": { "error": { "type": "string" } },
+                "required": ["error"],
+            }),
+        );
+
+        // Response-side schemas that differ from the request side are renamed
+        let requests = self.requests.take_definitions(true);
+        let mut responses = self.responses.take_definitions(true);
+        let renames: HashMap<String, String> = responses
+            .iter()
+            .filter(|&(name, schema)| requests.get(name).is_some_and(|other| other != schema))
+            .map(|(name, _)| (format!("{}{}", SCHEMAS, name), format!("{}{}Output", SCHEMAS, name)))
+            .collect();
+        for schema in responses.values_mut() {
+            rename_refs(schema, &renames);
+        }
+        for operation in &mut self.operations {
+            if let Some(response) = &mut operation.response {
+                rename_refs(response, &renames);
+            }
+        }
+        schemas.extend(requests);
+        for (name, schema) in responses {
+            let name = match renames.contains_key(&format!("{}{}", SCHEMAS, name)) {
+                true => format!("{}Output", name),
+                false => name,
+            };
+            schemas.insert(name, schema);
+        }
+
+        let mut paths = Map::new();
+        for operation in self.operations {
+            let mut doc = json!({
+                "summary": operation.summary,
+                "responses": {
+                    "200": match operation.response {
+                        Some(schema) => json!({ "description": "OK", "content": { "application/json": { "schema": schema } } }),
+                        None => json!({ "description": "OK" }),
+                    },
+                    "default": {
+                        "description": "Error",
+                        "content": { "application/json": { "schema": { "$ref": format!("{}Error", SCHEMAS) } } },
+                    },
+                },
+            });
+            if !operation.parameters.is_empty() {
+                doc["parameters"] = operation.parameters.into();
+            }
+            if let Some(schema) = operation.request {
+                doc["requestBody"] = json!({ "required": true, "content": { "application/json": { "schema": schema } } });
+            }
+            let path = paths.entry(operation.path).or_insert_with(|| json!({}));
+            path[operation.method] = doc;
+        }
+
+        json!({
+            "openapi": "3.1.0",
+            "info": {
+                "title": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+                "description": env!("CARGO_PKG_DESCRIPTION"),
+            },
+            "paths": paths,
+            "components": { "schemas": schemas },
+        })
+    }
+}
+
+/// Object schema of one scan type's params, from its declared `ScanParam`s.
+/// Undeclared params (e.g. `custom`'s `conditions`) are still accepted.
+fn params_schema(scan: &ScanType, filter: &Value) -> Value {
+    let property = |param: &ScanParam| {
+        let mut schema = match param.param_type.as_str() {
+            "number" => json!({ "type": "number" }),
+            "lookback" => json!({ "type": ["integer", "string"], "pattern": "^\\s*\\d+\\s*([dwmyDWMY]|[mM][oO])?\\s*$" }),
+            "series" => json!({
+                "type": ["string", "object"],
+                "properties": { "indicator": { "type": "string" }, "period": { "type": "integer", "minimum": 1 } },
+                "required": ["indicator"],
+            }),
+            "filters" => json!({ "type": "array", "items": filter }),
+            _ => json!({ "type": "string" }),
+        };
+        schema["description"] = param.description.clone().into();
+        schema["default"] = param.default.clone();
+        if let Some(min) = param.min {
+            schema["minimum"] = min.into();
+        }
+        if let Some(max) = param.max {
+            schema["maximum"] = max.into();
+        }
+        schema
+    };
+    json!({
+        "type": "object",
+        "properties": scan.params.iter().map(|p| (p.name.clone(), property(p))).collect::<Map<_, _>>(),
+    })
+}
+
+fn rename_refs(value: &mut Value, renames: &HashMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match (key.as_str(), &*value) {
+                    ("$ref", Value::String(target)) => {
+                        if let Some(renamed) = renames.get(target) {
+                            *value = renamed.clone().into();
+                        }
+                    }
+                    _ => rename_refs(value, renames),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| rename_refs(item, renames)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::ScanRegistry;
+    use crate::scanner::ScanResult;
+
+    /// Every `$ref` in `value`
+    fn refs<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    match (key.as_str(), value) {
+                        ("$ref", Value::String(target)) => out.push(target),
+                        _ => refs(value, out),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|item| refs(item, out)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_document_resolves_and_describes_params() {
+        let registry = ScanRegistry::load();
+        let mut doc = ApiDoc::new();
+        doc.route("post", "/api/scan", "Run a scan").scan_body().returns::<ScanResult>();
+        doc.route("get", "/api/scan-types", "Scan types").returns::<Vec<ScanType>>();
+        let doc = doc.into_document(&registry.list());
+
+        let mut targets = Vec::new();
+        refs(&doc, &mut targets);
+        assert!(!targets.is_empty());
+        for target in targets {
+            let name = target.strip_prefix(SCHEMAS).unwrap();
+            assert!(doc["components"]["schemas"].get(name).is_some(), "dangling {}", target);
+        }
+
+        let schemas = &doc["components"]["schemas"];
+        assert_eq!(
+            schemas["ScanRequest"]["discriminator"]["mapping"]["rsi_oversold"],
+            format!("{}ScanRequest.rsi_oversold", SCHEMAS)
+        );
+        let threshold = &schemas["ScanParams.rsi_oversold"]["properties"]["threshold"];
+        assert_eq!((threshold["type"].as_str(), threshold["maximum"].as_f64()), (Some("number"), Some(100.0)));
+        assert_eq!(schemas["ScanParams.composite"]["properties"]["filters"]["type"], "array");
+
+        // Fields the server may leave out aren't required of responses
+        let required = schemas["ScanResult"]["required"].as_array().unwrap();
+        assert!(required.contains(&"matches".into()) && !required.contains(&"run_id".into()));
+        assert!(doc["paths"]["/api/scan"]["post"]["requestBody"].is_object());
+    }
+}
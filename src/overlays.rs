@@ -0,0 +1,237 @@
+//! Indicator overlays - named indicator series for charting
+//!
+//! Dispatches an indicator name plus string params (straight from a query
+//! string) to the functions in `indicators`, so the UI can draw overlays
+//! without reimplementing them.
+
+use crate::data::TickerData;
+use crate::indicators::*;
+// The hot ones on pooled buffers, shadowing the glob, since composite scans
+// compute their series through here
+use crate::scratch::{ema, rsi, sma, volume_ratio};
+use std::collections::HashMap;
+
+/// Indicator names accepted by `compute`
+pub const OVERLAY_NAMES: &[&str] = &[
+    "sma",
+    "ema",
+    "rsi",
+    "cmo",
+    "stddev",
+    "rolling_max",
+    "rolling_min",
+    "drawdown",
+    "pct_change",
+    "macd",
+    "macd_histogram",
+    "ppo",
+    "elder_ray",
+    "elder_impulse",
+    "trix",
+    "dpo",
+    "kst",
+    "fisher",
+    "bollinger",
+    "donchian",
+    "atr",
+    "true_range",
+    "chandelier_exit",
+    "vortex",
+    "ultimate_oscillator",
+    "obv",
+    "vwap",
+    "force_index",
+    "ease_of_movement",
+    "volume_ratio",
+    "volume_zscore",
+    "adr_pct",
+    "close_location",
+    "streak",
+];
+
+/// Compute indicator `name` over all of `data`. Returns one or more named
+/// series aligned to `data`'s bars (e.g. `macd`, `signal`, `histogram`).
+/// Single-input indicators read `source` (close by default).
+pub fn compute(
+    data: &TickerData,
+    name: &str,
+    params: &HashMap<String, String>,
+) -> Result<Vec<(&'static str, Vec<f64>)>, String> {
+    let period = |default| usize_param(params, "period", default);
+    let source = || source_values(data, params.get("source").map_or("close", String::as_str));
+    let (h, l, c, v) = (&data.high[..], &data.low[..], &data.close[..], &data.volume[..]);
+
+    let series = match name {
+        "sma" => vec![("sma", sma(source()?, period(20)?))],
+        "ema" => vec![("ema", ema(source()?, period(20)?))],
+        "rsi" => vec![("rsi", rsi(source()?, period(14)?))],
+        "cmo" => vec![("cmo", cmo(source()?, period(14)?))],
+        "stddev" => vec![("stddev", stddev(source()?, period(20)?))],
+        "rolling_max" => vec![("rolling_max", rolling_max(source()?, period(20)?))],
+        "rolling_min" => vec![("rolling_min", rolling_min(source()?, period(20)?))],
+        "drawdown" => {
+            let values = source()?;
+            vec![("peak", cumulative_max(values)), ("drawdown", drawdown(values))]
+        }
+        "pct_change" => vec![("pct_change", pct_change(source()?, period(1)?))],
+        "macd" => {
+            let fast = usize_param(params, "fast", 12)?;
+            let slow = usize_param(params, "slow", 26)?;
+            let signal = usize_param(params, "signal", 9)?;
+            let values = source()?;
+            vec![
+                ("macd", macd(values, fast, slow)),
+                ("signal", macd_signal(values, fast, slow, signal)),
+                ("histogram", macd_histogram(values, fast, slow, signal)),
+            ]
+        }
+        // Alone, so composite filters can test MACD/signal crosses against 0
+        "macd_histogram" => {
+            let fast = usize_param(params, "fast", 12)?;
+            let slow = usize_param(params, "slow", 26)?;
+            let signal = usize_param(params, "signal", 9)?;
+            vec![("histogram", macd_histogram(source()?, fast, slow, signal))]
+        }
+        "ppo" => {
+            let fast = usize_param(params, "fast", 12)?;
+            let slow = usize_param(params, "slow", 26)?;
+            let signal = usize_param(params, "signal", 9)?;
+            let values = source()?;
+            vec![
+                ("ppo", ppo(values, fast, slow)),
+                ("signal", ppo_signal(values, fast, slow, signal)),
+                ("histogram", ppo_histogram(values, fast, slow, signal)),
+            ]
+        }
+        "elder_ray" => {
+            let ema_period = period(13)?;
+            vec![("bull_power", bull_power(h, c, ema_period)), ("bear_power", bear_power(l, c, ema_period))]
+        }
+        "elder_impulse" => {
+            let fast = usize_param(params, "fast", 12)?;
+            let slow = usize_param(params, "slow", 26)?;
+            let signal = usize_param(params, "signal", 9)?;
+            vec![("impulse", elder_impulse(c, period(13)?, fast, slow, signal))]
+        }
+        "trix" => vec![("trix", trix(source()?, period(15)?))],
+        "dpo" => vec![("dpo", dpo(source()?, period(20)?))],
+        "kst" => {
+            let signal = usize_param(params, "signal", KST_SIGNAL)?;
+            let (line, signal) = kst(source()?, KST_ROC, KST_SMOOTH, KST_WEIGHTS, signal);
+            vec![("kst", line), ("signal", signal)]
+        }
+        "fisher" => {
+            let (fisher, trigger) = fisher_transform(h, l, period(10)?);
+            vec![("fisher", fisher), ("trigger", trigger)]
+        }
+        "bollinger" => {
+            let (middle, upper, lower) = bollinger(source()?, period(20)?, f64_param(params, "std", 2.0)?);
+            vec![("middle", middle), ("upper", upper), ("lower", lower)]
+        }
+        "donchian" => {
+            let (upper, lower) = donchian(h, l, period(20)?);
+            vec![("upper", upper), ("lower", lower)]
+        }
+        "atr" => vec![("atr", atr(h, l, c, period(14)?))],
+        "true_range" => vec![("true_range", true_range(h, l, c))],
+        "chandelier_exit" => {
+            let (long, short) = chandelier_exit(h, l, c, period(22)?, f64_param(params, "multiplier", 3.0)?);
+            vec![("long", long), ("short", short)]
+        }
+        "vortex" => {
+            let (plus, minus) = vortex(h, l, c, period(14)?);
+            vec![("vi_plus", plus), ("vi_minus", minus)]
+        }
+        "ultimate_oscillator" => {
+            let short = usize_param(params, "short", 7)?;
+            let medium = usize_param(params, "medium", 14)?;
+            let long = usize_param(params, "long", 28)?;
+            vec![("ultimate_oscillator", ultimate_oscillator(h, l, c, short, medium, long))]
+        }
+        "obv" => vec![("obv", obv(c, v))],
+        "vwap" => vec![("vwap", vwap(h, l, c, v))],
+        "force_index" => vec![("force_index", force_index(c, v, period(13)?))],
+        "ease_of_movement" => vec![("ease_of_movement", ease_of_movement(h, l, v, period(14)?))],
+        "volume_ratio" => vec![("volume_ratio", volume_ratio(v, period(20)?))],
+        "volume_zscore" => vec![("volume_zscore", volume_zscore(v, period(20)?))],
+        "adr_pct" => vec![("adr_pct", adr_pct(h, l, c, period(20)?))],
+        "close_location" => vec![("close_location", close_location(h, l, c))],
+        "streak" => vec![("streak", streak(source()?))],
+        _ => {
+            return Err(format!(
+                "Unknown indicator: {} (expected one of {})",
+                name,
+                OVERLAY_NAMES.join(", ")
+            ))
+        }
+    };
+
+    Ok(series)
+}
+
+fn source_values<'a>(data: &'a TickerData, source: &str) -> Result<&'a [f64], String> {
+    match source {
+        "open" => Ok(&data.open),
+        "high" => Ok(&data.high),
+        "low" => Ok(&data.low),
+        "close" => Ok(&data.close),
+        "volume" => Ok(&data.volume),
+        other => Err(format!("Unknown source: {} (expected open, high, low, close or volume)", other)),
+    }
+}
+
+fn usize_param(params: &HashMap<String, String>, name: &str, default: usize) -> Result<usize, String> {
+    match params.get(name) {
+        None => Ok(default),
+        Some(raw) => match raw.trim().parse::<usize>() {
+            Ok(n) if n >= 1 => Ok(n),
+            _ => Err(format!("{} must be a positive integer, got {:?}", name, raw)),
+        },
+    }
+}
+
+fn f64_param(params: &HashMap<String, String>, name: &str, default: f64) -> Result<f64, String> {
+    match params.get(name) {
+        None => Ok(default),
+        Some(raw) => raw
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|x| x.is_finite())
+            .ok_or_else(|| format!("{} must be a number, got {:?}", name, raw)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::generate_sample_data;
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_dispatches_to_indicators() {
+        let data = generate_sample_data(120);
+
+        let series = compute(&data, "sma", &params(&[("period", "50")])).unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].0, "sma");
+        assert!(series[0].1[48].is_nan());
+        assert_eq!(series[0].1[49..], sma(&data.close, 50)[49..]);
+
+        let macd = compute(&data, "macd", &HashMap::new()).unwrap();
+        let names: Vec<&str> = macd.iter().map(|s| s.0).collect();
+        assert_eq!(names, ["macd", "signal", "histogram"]);
+        assert!(macd.iter().all(|s| s.1.len() == data.len()));
+
+        let high = compute(&data, "rolling_max", &params(&[("source", "high"), ("period", "5")])).unwrap();
+        assert_eq!(high[0].1[10], rolling_max(&data.high, 5)[10]);
+
+        assert!(compute(&data, "nope", &HashMap::new()).is_err());
+        assert!(compute(&data, "rsi", &params(&[("period", "0")])).is_err());
+        assert!(compute(&data, "bollinger", &params(&[("std", "wide")])).is_err());
+        assert!(compute(&data, "ema", &params(&[("source", "hl2")])).is_err());
+    }
+}
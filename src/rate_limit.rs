@@ -0,0 +1,244 @@
+//! Per-client token-bucket rate limiting
+//!
+//! Separate budgets for LLM routes (which spend API credits) and scan routes
+//! (which burn CPU). Both are disabled unless configured via env vars.
+
+use crate::error::ApiError;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Buckets are pruned once the map grows past this many clients
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+/// Token bucket per client IP: `per_minute` burst capacity, refilled continuously
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_minute: u32,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `None` when the env var is unset or 0 (limiting disabled)
+    pub fn from_env(key: &str) -> Option<Self> {
+        std::env::var(key)
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .filter(|&n| n > 0)
+            .map(Self::new)
+    }
+
+    pub fn per_minute(&self) -> u32 {
+        self.per_minute
+    }
+
+    /// Take a token for `ip`, or return how long until one is available
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let capacity = self.per_minute as f64;
+        let refill_per_sec = capacity / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if buckets.len() > MAX_TRACKED_CLIENTS {
+            // Drop clients whose buckets have fully refilled; they're equivalent to new ones
+            buckets.retain(|_, b| {
+                b.tokens + now.duration_since(b.last).as_secs_f64() * refill_per_sec < capacity
+            });
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: capacity,
+            last: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.last = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / refill_per_sec))
+        }
+    }
+}
+
+/// Configured limiters, one per route class
+#[derive(Debug, Default)]
+pub struct RateLimits {
+    pub llm: Option<RateLimiter>,
+    pub scan: Option<RateLimiter>,
+}
+
+impl RateLimits {
+    pub fn from_env() -> Self {
+        Self {
+            llm: RateLimiter::from_env("RETRO_LLM_RATE_LIMIT_PER_MIN")
+                .or_else(|| RateLimiter::from_env("LLM_RATE_LIMIT_PER_MIN")),
+            scan: RateLimiter::from_env("RETRO_SCAN_RATE_LIMIT_PER_MIN"),
+        }
+    }
+
+    fn for_path(&self, path: &str) -> Option<&RateLimiter> {
+        if path.starts_with("/api/nl/") {
+            self.llm.as_ref()
+        } else if SCAN_ROUTES.iter().any(|route| route_matches(route, path)) {
+            self.scan.as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+/// Routes that scan or compute across the whole universe, sharing the scan
+/// budget; `*` stands for one path segment
+const SCAN_ROUTES: &[&str] = &[
+    "/api/scan",
+    "/api/backtest",
+    "/api/saved-scans/*/run",
+    "/api/ticker/*/scan",
+    "/api/correlation",
+    "/api/breadth",
+];
+
+fn route_matches(route: &str, path: &str) -> bool {
+    let (mut route, mut path) = (route.split('/'), path.split('/'));
+    loop {
+        match (route.next(), path.next()) {
+            (None, None) => return true,
+            (Some("*"), Some(segment)) if !segment.is_empty() => {}
+            (Some(a), Some(b)) if a == b => {}
+            _ => return false,
+        }
+    }
+}
+
+/// Middleware applying the budget matching the request path; 429 + Retry-After when exhausted
+pub async fn enforce(State(limits): State<Arc<RateLimits>>, req: Request, next: Next) -> Response {
+    if let Some(limiter) = limits.for_path(req.uri().path()) {
+        let ip = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ci| ci.0.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+        if let Err(retry_after) = limiter.check(ip) {
+            let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            return (
+                [(header::RETRY_AFTER, secs.to_string())],
+                ApiError::TooManyRequests(format!(
+                    "Rate limit exceeded ({} requests/min). Retry in {}s",
+                    limiter.per_minute(),
+                    secs
+                )),
+            )
+                .into_response();
+        }
+    }
+
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::post, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_bucket_exhausts_and_refills() {
+        let limiter = RateLimiter::new(3);
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let t0 = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at(ip, t0).is_ok());
+        }
+        let retry = limiter.check_at(ip, t0).unwrap_err();
+        assert!(retry.as_secs_f64() > 19.0 && retry.as_secs_f64() <= 20.0);
+
+        // Other clients have their own bucket
+        assert!(limiter.check_at(other, t0).is_ok());
+
+        // 3/min refills one token every 20s
+        assert!(limiter.check_at(ip, t0 + Duration::from_secs(20)).is_ok());
+        assert!(limiter.check_at(ip, t0 + Duration::from_secs(20)).is_err());
+    }
+
+    #[test]
+    fn test_scan_budget_covers_scan_like_routes() {
+        let limits = RateLimits { llm: None, scan: Some(RateLimiter::new(1)) };
+        for path in [
+            "/api/scan",
+            "/api/backtest",
+            "/api/saved-scans/abc/run",
+            "/api/ticker/AAPL/scan",
+            "/api/correlation",
+            "/api/breadth",
+        ] {
+            assert!(limits.for_path(path).is_some(), "{}", path);
+        }
+        for path in ["/api/scan/history", "/api/scan/progress/job", "/api/ticker/AAPL", "/api/ticker//scan", "/api/tickers"] {
+            assert!(limits.for_path(path).is_none(), "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_returns_429_past_threshold() {
+        let limits = Arc::new(RateLimits {
+            llm: Some(RateLimiter::new(2)),
+            scan: None,
+        });
+        let app = Router::new()
+            .route("/api/nl/clarify", post(|| async { "ok" }))
+            .route("/api/scan", post(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(limits, enforce));
+
+        let send = |path: &'static str| {
+            let app = app.clone();
+            async move {
+                app.oneshot(Request::post(path).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+            }
+        };
+
+        assert_eq!(send("/api/nl/clarify").await.status(), StatusCode::OK);
+        assert_eq!(send("/api/nl/clarify").await.status(), StatusCode::OK);
+
+        let limited = send("/api/nl/clarify").await;
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(limited.headers().contains_key(header::RETRY_AFTER));
+
+        // Unlimited route class is unaffected
+        for _ in 0..5 {
+            assert_eq!(send("/api/scan").await.status(), StatusCode::OK);
+        }
+    }
+}
@@ -0,0 +1,418 @@
+//! Scan registry - single source of truth for scan metadata and dispatch
+//!
+//! Built once at startup from the built-in table in `scanner` plus the
+//! generated scans, then shared via `AppState`.
+
+use crate::data::TickerData;
+use crate::generated::{self, ScanFn};
+use crate::generated_store::normalize_scan_id;
+use crate::scan_types::{ScanParam, ScanType};
+use crate::scanner::{ScanOutput, BUILTIN_SCANS};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Bars of history a scan needs before the first bar it can match, given its
+/// params. Declared only when the mask at bar `i` depends on nothing older than
+/// `i - warmup` (windowed indicators); recursive ones like EMA, RSI and OBV
+/// depend on all history and never qualify.
+pub type WarmupFn = fn(&HashMap<String, Value>) -> usize;
+
+/// Fewest bars a ticker needs for a scan to be meaningful, given its params.
+/// Shorter tickers are skipped and counted rather than scanned.
+pub type MinBarsFn = fn(&HashMap<String, Value>) -> usize;
+
+/// The full form of a scan whose `func` is just its `.mask`; the scanner
+/// prefers it when present, for scores and `explain`
+pub type OutputFn = fn(&TickerData, &HashMap<String, Value>) -> ScanOutput;
+
+/// Output of a scan that compares each ticker to a benchmark series, given
+/// the benchmark's bars as well
+pub type BenchmarkOutputFn = fn(&TickerData, &TickerData, &HashMap<String, Value>) -> ScanOutput;
+
+/// Default value of a built-in param, in a form usable from a `const` table
+#[derive(Debug, Clone, Copy)]
+pub enum ParamDefault {
+    Int(i64),
+    Float(f64),
+    Text(&'static str),
+    EmptyList,
+}
+
+impl ParamDefault {
+    fn to_value(self) -> Value {
+        match self {
+            ParamDefault::Int(n) => n.into(),
+            ParamDefault::Float(x) => x.into(),
+            ParamDefault::Text(s) => s.into(),
+            ParamDefault::EmptyList => Value::Array(Vec::new()),
+        }
+    }
+}
+
+/// Declarative param description for built-in scans
+#[derive(Debug, Clone, Copy)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub param_type: &'static str,
+    pub default: ParamDefault,
+    pub description: &'static str,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Only whole numbers are accepted
+    pub integer: bool,
+}
+
+impl ParamSpec {
+    pub const fn number(
+        name: &'static str,
+        default: ParamDefault,
+        description: &'static str,
+        min: Option<f64>,
+        max: Option<f64>,
+    ) -> Self {
+        Self { name, param_type: "number", default, description, min, max, integer: false }
+    }
+
+    /// A period or count: a number that must be whole
+    pub const fn integer(
+        name: &'static str,
+        default: i64,
+        description: &'static str,
+        min: Option<f64>,
+        max: Option<f64>,
+    ) -> Self {
+        Self { name, param_type: "number", default: ParamDefault::Int(default), description, min, max, integer: true }
+    }
+
+    pub const fn text(name: &'static str, default: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            param_type: "text",
+            default: ParamDefault::Text(default),
+            description,
+            min: None,
+            max: None,
+            integer: false,
+        }
+    }
+
+    pub const fn select(name: &'static str, default: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            param_type: "select",
+            default: ParamDefault::Text(default),
+            description,
+            min: None,
+            max: None,
+            integer: false,
+        }
+    }
+
+    /// A bar count or calendar span like `"6m"` (see `calendar::Lookback`);
+    /// `min` bounds the count either way
+    pub const fn lookback(name: &'static str, default: ParamDefault, description: &'static str, min: Option<f64>) -> Self {
+        Self { name, param_type: "lookback", default, description, min, max: None, integer: false }
+    }
+
+    /// One indicator series, as `"ema(9)"` or `{indicator, period}` (see
+    /// `composite::SeriesRef`)
+    pub const fn series(name: &'static str, default: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            param_type: "series",
+            default: ParamDefault::Text(default),
+            description,
+            min: None,
+            max: None,
+            integer: false,
+        }
+    }
+
+    /// A `composite` filter list (see `composite::Filter`)
+    pub const fn filters(name: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            param_type: "filters",
+            default: ParamDefault::EmptyList,
+            description,
+            min: None,
+            max: None,
+            integer: false,
+        }
+    }
+
+    fn to_scan_param(self) -> ScanParam {
+        ScanParam {
+            name: self.name.into(),
+            param_type: self.param_type.into(),
+            default: self.default.to_value(),
+            description: self.description.into(),
+            min: self.min,
+            max: self.max,
+            integer: self.integer,
+        }
+    }
+}
+
+/// One row of the built-in scan table
+pub struct BuiltinScan {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub params: &'static [ParamSpec],
+    pub default_lookback_days: Option<u32>,
+    pub func: ScanFn,
+    pub warmup: Option<WarmupFn>,
+    /// Defaults to one bar past the warm-up, or 1 without one
+    pub min_bars: Option<MinBarsFn>,
+    /// Leading bars of a ticker's history where the scan's indicators are
+    /// still settling. Defaults to one less than `min_bars`.
+    pub warmup_bars: Option<WarmupFn>,
+    pub output: Option<OutputFn>,
+    /// Preferred over `output` when the scan needs a benchmark (its
+    /// `benchmark` param, SPY by default); `func` then matches nothing
+    pub benchmark_output: Option<BenchmarkOutputFn>,
+    /// Listed by `/api/scan-types`; unlisted scans are still runnable by id
+    pub listed: bool,
+}
+
+/// A runnable scan and the metadata describing it
+#[derive(Clone)]
+pub struct ScanDefinition {
+    pub meta: ScanType,
+    pub func: ScanFn,
+    /// Lets the scanner run on just the requested date range plus warm-up
+    pub warmup: Option<WarmupFn>,
+    pub min_bars: Option<MinBarsFn>,
+    pub warmup_bars: Option<WarmupFn>,
+    /// Mask plus per-bar series; `func` must return the same mask
+    pub output: Option<OutputFn>,
+    pub benchmark_output: Option<BenchmarkOutputFn>,
+    pub listed: bool,
+}
+
+impl From<&BuiltinScan> for ScanDefinition {
+    fn from(scan: &BuiltinScan) -> Self {
+        Self {
+            meta: ScanType {
+                id: scan.id.into(),
+                name: scan.name.into(),
+                description: scan.description.into(),
+                params: scan.params.iter().map(|p| p.to_scan_param()).collect(),
+                default_lookback_days: scan.default_lookback_days,
+                version: None,
+            },
+            func: scan.func,
+            warmup: scan.warmup,
+            min_bars: scan.min_bars,
+            warmup_bars: scan.warmup_bars,
+            output: scan.output,
+            benchmark_output: scan.benchmark_output,
+            listed: scan.listed,
+        }
+    }
+}
+
+impl ScanDefinition {
+    /// Bars of history a ticker needs for this scan under `params`
+    pub fn min_bars(&self, params: &HashMap<String, Value>) -> usize {
+        match (self.min_bars, self.warmup) {
+            (Some(min_bars), _) => min_bars(params),
+            (None, Some(warmup)) => warmup(params) + 1,
+            (None, None) => 1,
+        }
+    }
+
+    /// Bars at the start of a ticker's history that can't hold a trustworthy
+    /// match; the scanner drops matches there unless the query sets `include_warmup`
+    pub fn warmup_bars(&self, params: &HashMap<String, Value>) -> usize {
+        match self.warmup_bars {
+            Some(warmup_bars) => warmup_bars(params),
+            None => self.min_bars(params).saturating_sub(1),
+        }
+    }
+}
+
+/// Scan id → definition, preserving registration order for listing
+#[derive(Default)]
+pub struct ScanRegistry {
+    scans: Vec<ScanDefinition>,
+    index: HashMap<String, usize>,
+}
+
+impl ScanRegistry {
+    /// Built-in scans followed by generated ones
+    pub fn load() -> Self {
+        let mut registry = Self::default();
+
+        for scan in BUILTIN_SCANS {
+            registry.register(scan.into());
+        }
+
+        for meta in generated::list_scan_types() {
+            match generated::get_scan(&meta.id) {
+                Some(func) => registry.register(ScanDefinition {
+                    meta,
+                    func,
+                    warmup: None,
+                    min_bars: None,
+                    warmup_bars: None,
+                    output: None,
+                    benchmark_output: None,
+                    listed: true,
+                }),
+                None => tracing::warn!("Generated scan {} has no implementation", meta.id),
+            }
+        }
+
+        registry
+    }
+
+    /// Add a scan, replacing any existing definition with the same id
+    pub fn register(&mut self, def: ScanDefinition) {
+        match self.index.get(&def.meta.id) {
+            Some(&i) => self.scans[i] = def,
+            None => {
+                self.index.insert(def.meta.id.clone(), self.scans.len());
+                self.scans.push(def);
+            }
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ScanDefinition> {
+        self.index.get(id).map(|&i| &self.scans[i])
+    }
+
+    /// Look up a client-supplied `scan_type`, tolerating case, hyphens and
+    /// spaces (`RSI-Oversold` → `rsi_oversold`); `meta.id` is the canonical
+    /// id. Unknown ids get an error naming the closest scans.
+    pub fn resolve(&self, scan_type: &str) -> Result<&ScanDefinition, String> {
+        if let Some(def) = self.get(scan_type).or_else(|| self.get(&normalize_scan_id(scan_type))) {
+            return Ok(def);
+        }
+        let normalized = normalize_scan_id(scan_type);
+
+        let suggestions = self.suggestions(&normalized);
+        Err(if suggestions.is_empty() {
+            format!("Unknown scan type: {} (see /api/scan-types)", scan_type)
+        } else {
+            format!("Unknown scan type: {} (did you mean {}?)", scan_type, suggestions.join(", "))
+        })
+    }
+
+    /// Up to three listed ids within a small edit distance of `id`, or that
+    /// it's a prefix of, closest first
+    fn suggestions(&self, id: &str) -> Vec<&str> {
+        let max_distance = (id.len() / 3).max(2);
+        let mut close: Vec<(usize, &str)> = self
+            .scans
+            .iter()
+            .filter(|s| s.listed)
+            .map(|s| s.meta.id.as_str())
+            .filter_map(|candidate| {
+                let distance = levenshtein(id, candidate);
+                let prefix = id.len() >= 3 && candidate.starts_with(id);
+                (distance <= max_distance || prefix).then_some((if prefix { 0 } else { distance }, candidate))
+            })
+            .collect();
+        close.sort();
+        close.into_iter().take(3).map(|(_, candidate)| candidate).collect()
+    }
+
+    /// Metadata for every listed scan, in registration order
+    pub fn list(&self) -> Vec<ScanType> {
+        self.scans
+            .iter()
+            .filter(|s| s.listed)
+            .map(|s| s.meta.clone())
+            .collect()
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_matches_previous_scan_list() {
+        let registry = ScanRegistry::load();
+
+        // Listing order and defaults from the former hand-written list
+        let expected: &[(&str, &[(&str, Value)])] = &[
+            ("golden_cross", &[]),
+            ("death_cross", &[]),
+            ("ema_cross", &[("fast", 12.into()), ("slow", 26.into()), ("direction", "up".into())]),
+            ("rsi_oversold", &[("period", 14.into()), ("threshold", 30.into())]),
+            ("rsi_overbought", &[("period", 14.into()), ("threshold", 70.into())]),
+            ("obv_breakout", &[("lookback", 20.into())]),
+            ("volume_spike", &[("period", 20.into()), ("multiplier", 2.0.into())]),
+            ("macd_cross_up", &[("fast", 12.into()), ("slow", 26.into()), ("signal", 9.into())]),
+            ("price_breakout", &[("lookback", 252.into())]),
+            (
+                "monthly_gap_drop",
+                &[("gap_pct", 5.0.into()), ("candle", "any".into()), ("event_on", "start".into())],
+            ),
+            ("bullish_divergence", &[("lookback", 20.into())]),
+            (
+                "consolidation_breakout",
+                &[("period", 30.into()), ("range_pct", 5.0.into()), ("volume_multiplier", 1.5.into())],
+            ),
+            (
+                "bullish_engulfing_oversold",
+                &[("rsi_period", 14.into()), ("rsi_threshold", 30.into()), ("lookback", 5.into())],
+            ),
+        ];
+
+        // Scans added since are appended after these
+        let listed = registry.list();
+        assert!(listed.len() >= expected.len());
+        for (meta, (id, params)) in listed.iter().zip(expected) {
+            assert_eq!(meta.id, *id);
+            let actual: Vec<(&str, &Value)> =
+                meta.params.iter().map(|p| (p.name.as_str(), &p.default)).collect();
+            let wanted: Vec<(&str, &Value)> = params.iter().map(|(n, v)| (*n, v)).collect();
+            assert_eq!(actual, wanted, "params for {}", id);
+        }
+
+        // Previously dispatchable but not listed
+        for id in ["bollinger_squeeze", "macd_cross_down", "bearish_divergence", "custom"] {
+            let def = registry.get(id).unwrap();
+            assert!(!def.listed);
+        }
+        assert!(registry.get("nope").is_none());
+    }
+
+    #[test]
+    fn test_resolve_normalizes_and_suggests() {
+        let registry = ScanRegistry::load();
+        for input in ["rsi_oversold", "RSI_Oversold", "rsi-oversold", " RSI Oversold "] {
+            assert_eq!(registry.resolve(input).unwrap().meta.id, "rsi_oversold", "{:?}", input);
+        }
+        assert_eq!(registry.resolve("MACD-Cross-Down").unwrap().meta.id, "macd_cross_down");
+
+        let err = registry.resolve("rsi_oversould").err().unwrap();
+        assert!(err.contains("did you mean rsi_oversold"), "{}", err);
+        let err = registry.resolve("golden").err().unwrap();
+        assert!(err.contains("golden_cross"), "{}", err);
+        let err = registry.resolve("zzzzzzzz").err().unwrap();
+        assert!(!err.contains("did you mean"), "{}", err);
+
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+}
@@ -0,0 +1,232 @@
+//! Resampling - aggregate daily bars into coarser intervals
+
+use crate::data::{TickerData, Timeframe};
+use chrono::{Datelike, NaiveDate};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Bar interval for aggregation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Interval {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Interval {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "daily" | "day" | "d" | "1d" => Some(Interval::Daily),
+            "weekly" | "week" | "w" | "1w" => Some(Interval::Weekly),
+            "monthly" | "month" | "m" | "1m" => Some(Interval::Monthly),
+            _ => None,
+        }
+    }
+
+    /// Next coarser interval, if any
+    pub fn coarser(self) -> Option<Self> {
+        match self {
+            Interval::Daily => Some(Interval::Weekly),
+            Interval::Weekly => Some(Interval::Monthly),
+            Interval::Monthly => None,
+        }
+    }
+
+    /// Bucket key for a day; bars sharing a key are aggregated together
+    fn bucket_key(self, day: NaiveDate) -> (i32, u32) {
+        match self {
+            Interval::Daily => (day.year(), day.ordinal()),
+            Interval::Weekly => {
+                let week = day.iso_week();
+                (week.year(), week.week())
+            }
+            Interval::Monthly => (day.year(), day.month()),
+        }
+    }
+}
+
+/// An aggregated bar spanning `start_idx..=end_idx` of the source series
+#[derive(Debug, Clone, Copy)]
+pub struct Bar {
+    pub start_idx: usize,
+    pub end_idx: usize,
+    pub open: f64,
+    pub close: f64,
+    pub high: f64,
+    pub low: f64,
+    pub volume: f64,
+}
+
+/// Aggregate consecutive bars sharing a bucket: open of first, close of last,
+/// max high, min low, summed volume. `data` must be sorted by date, as
+/// `TickerData::from_columns` leaves it; out-of-order bars would split buckets.
+pub fn resample_bars(data: &TickerData, interval: Interval) -> Vec<Bar> {
+    debug_assert!(data.is_sorted(), "resample_bars needs bars sorted by date");
+    if data.is_empty() {
+        return Vec::new();
+    }
+    let n = data.len();
+
+    let mut bars = Vec::new();
+
+    let mut current_key = interval.bucket_key(data.days[0]);
+    let mut start_idx = 0usize;
+    let mut open = data.open[0];
+    let mut high = data.high[0];
+    let mut low = data.low[0];
+    let mut volume = data.volume[0];
+
+    for i in 1..n {
+        let key = interval.bucket_key(data.days[i]);
+        if key != current_key {
+            let end_idx = i - 1;
+            bars.push(Bar {
+                start_idx,
+                end_idx,
+                open,
+                close: data.close[end_idx],
+                high,
+                low,
+                volume,
+            });
+
+            current_key = key;
+            start_idx = i;
+            open = data.open[i];
+            high = data.high[i];
+            low = data.low[i];
+            volume = data.volume[i];
+        } else {
+            high = high.max(data.high[i]);
+            low = low.min(data.low[i]);
+            volume += data.volume[i];
+        }
+    }
+
+    let end_idx = n - 1;
+    bars.push(Bar {
+        start_idx,
+        end_idx,
+        open,
+        close: data.close[end_idx],
+        high,
+        low,
+        volume,
+    });
+
+    bars
+}
+
+/// Map one value per aggregated bar back onto the source bars it spans
+pub fn expand(bars: &[Bar], values: &[f64], len: usize) -> Vec<f64> {
+    let mut out = vec![f64::NAN; len];
+    for (bar, &value) in bars.iter().zip(values) {
+        out[bar.start_idx..=bar.end_idx].fill(value);
+    }
+    out
+}
+
+/// Resample into a new `TickerData`; each bar is dated by its first source
+/// bar's day. Daily bars stay as they are; intraday ones aggregate per day.
+pub fn resample(data: &TickerData, interval: Interval) -> TickerData {
+    if interval == Interval::Daily && data.timeframe == Timeframe::Daily {
+        return data.clone();
+    }
+
+    bars_to_data(data, &resample_bars(data, interval))
+}
+
+/// Build a `TickerData` from bars produced by `resample_bars` on `data`.
+/// The result is daily or coarser, so intraday times are dropped from dates.
+pub fn bars_to_data(data: &TickerData, bars: &[Bar]) -> TickerData {
+    let date = |b: &Bar| match data.timeframe {
+        Timeframe::Daily => data.date[b.start_idx].clone(),
+        Timeframe::Intraday => data.days[b.start_idx].format("%Y-%m-%d").to_string(),
+    };
+    TickerData {
+        date: bars.iter().map(date).collect(),
+        days: bars.iter().map(|b| data.days[b.start_idx]).collect(),
+        open: bars.iter().map(|b| b.open).collect(),
+        high: bars.iter().map(|b| b.high).collect(),
+        low: bars.iter().map(|b| b.low).collect(),
+        close: bars.iter().map(|b| b.close).collect(),
+        volume: bars.iter().map(|b| b.volume).collect(),
+        events: data.events.clone(),
+        timeframe: Timeframe::Daily,
+        adj_close: data.adj_close.as_ref().map(|adj| bars.iter().map(|b| adj[b.end_idx]).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(dates: &[&str]) -> TickerData {
+        let n = dates.len();
+        TickerData::from_columns(
+            dates.iter().map(|d| d.to_string()).collect(),
+            (0..n).map(|i| i as f64 + 1.0).collect(),
+            (0..n).map(|i| i as f64 + 2.0).collect(),
+            (0..n).map(|i| i as f64).collect(),
+            (0..n).map(|i| i as f64 + 1.5).collect(),
+            vec![100.0; n],
+        )
+    }
+
+    #[test]
+    fn test_weekly_resample() {
+        // Thu, Fri | Mon, Tue, Wed
+        let data = sample(&["2024-01-04", "2024-01-05", "2024-01-08", "2024-01-09", "2024-01-10"]);
+        let weekly = resample(&data, Interval::Weekly);
+        assert_eq!(weekly.len(), 2);
+        assert_eq!(weekly.date, vec!["2024-01-04", "2024-01-08"]);
+        assert_eq!(weekly.open[1], 3.0);
+        assert_eq!(weekly.close[1], 5.5);
+        assert_eq!(weekly.high[1], 6.0);
+        assert_eq!(weekly.low[1], 2.0);
+        assert_eq!(weekly.volume[0], 200.0);
+    }
+
+    #[test]
+    fn test_monthly_resample() {
+        // Mixed vendor formats still bucket by calendar month
+        let data = sample(&["2024-01-30", "2024-1-31T00:00:00", "2024-02-01"]);
+        let bars = resample_bars(&data, Interval::Monthly);
+        assert_eq!(bars.len(), 2);
+        assert_eq!((bars[0].start_idx, bars[0].end_idx), (0, 1));
+        assert_eq!((bars[1].start_idx, bars[1].end_idx), (2, 2));
+    }
+
+    #[test]
+    fn test_monthly_resample_timestamped_unsorted() {
+        let data = sample(&[
+            "2024-02-01T09:30:00",
+            " 2024-01-31 16:00:00 ",
+            "2024-01-02T09:30:00Z",
+            "2024-03-01T00:00:00",
+            "2024-02-29T16:00:00",
+        ]);
+        assert!(data.is_sorted());
+        // Opens were 1..=5 in file order, so they show where each row went
+        assert_eq!(data.open, vec![3.0, 2.0, 1.0, 5.0, 4.0]);
+
+        let bars = resample_bars(&data, Interval::Monthly);
+        let spans: Vec<_> = bars.iter().map(|b| (b.start_idx, b.end_idx)).collect();
+        assert_eq!(spans, [(0, 1), (2, 3), (4, 4)]);
+        assert_eq!((bars[0].open, bars[0].close), (3.0, 2.5));
+        assert_eq!((bars[1].open, bars[1].close), (1.0, 5.5));
+    }
+
+    #[test]
+    fn test_intraday_resamples_to_days() {
+        let data = sample(&["2024-01-02T14:30:00Z", "2024-01-02T14:31:00Z", "2024-01-03T14:30:00Z"]);
+        assert_eq!(data.timeframe, Timeframe::Intraday);
+
+        let daily = resample(&data, Interval::Daily);
+        assert_eq!(daily.timeframe, Timeframe::Daily);
+        assert_eq!(daily.date, vec!["2024-01-02", "2024-01-03"]);
+        assert_eq!((daily.open[0], daily.close[0], daily.volume[0]), (1.0, 2.5, 200.0));
+        assert_eq!(resample(&data, Interval::Weekly).date, vec!["2024-01-02"]);
+    }
+}
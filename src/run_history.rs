@@ -0,0 +1,347 @@
+//! Recorded scan runs - per-ticker match summaries of scans run with
+//! `record: true`, one JSON file per run, so two runs can be diffed
+//!
+//! Like the scan history log, files are written by a background thread; the
+//! in-memory index is updated once a run is on disk. Only the newest `keep`
+//! runs of each scan type are kept.
+
+use crate::data::TickerData;
+use crate::scanner::{ScanMatch, ScanQuery};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+pub const DEFAULT_RUN_HISTORY_DIR: &str = "./data/history";
+pub const DEFAULT_RUNS_KEPT_PER_SCAN: usize = 20;
+
+/// A ticker's matches within one run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TickerSummary {
+    pub ticker: String,
+    pub matches: usize,
+    pub first_date: String,
+    pub last_date: String,
+    /// Bars loaded for the ticker when the run was recorded (absent in older runs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bars: Option<usize>,
+}
+
+impl TickerSummary {
+    /// One summary per ticker in `matches`, in ticker order, with its bar
+    /// count from the scanned `data`
+    pub fn from_matches(matches: &[ScanMatch], data: &HashMap<String, Arc<TickerData>>) -> Vec<TickerSummary> {
+        let mut by_ticker: BTreeMap<&str, TickerSummary> = BTreeMap::new();
+        for m in matches {
+            let summary = by_ticker.entry(&m.ticker).or_insert_with(|| TickerSummary {
+                ticker: m.ticker.clone(),
+                matches: 0,
+                first_date: m.date.clone(),
+                last_date: m.date.clone(),
+                bars: data.get(&m.ticker).map(|d| d.len()),
+            });
+            summary.matches += 1;
+            if m.date < summary.first_date {
+                summary.first_date = m.date.clone();
+            }
+            if m.date > summary.last_date {
+                summary.last_date = m.date.clone();
+            }
+        }
+        by_ticker.into_values().collect()
+    }
+}
+
+/// Index entry for a recorded run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub id: String,
+    /// RFC 3339, UTC
+    pub timestamp: String,
+    pub scan_type: String,
+    pub total_matches: usize,
+    pub tickers_with_matches: usize,
+    pub scan_time_ms: u64,
+}
+
+/// A recorded run as stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRun {
+    #[serde(flatten)]
+    pub summary: RunSummary,
+    pub query: ScanQuery,
+    /// Sorted by ticker
+    pub tickers: Vec<TickerSummary>,
+}
+
+/// Tickers that appeared in or dropped out of a scan's results between two runs
+#[derive(Debug, Clone, Serialize)]
+pub struct RunDiff {
+    pub scan_type: String,
+    pub from: String,
+    pub to: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl RunDiff {
+    pub fn between(from: &RecordedRun, to: &RecordedRun) -> Result<Self, String> {
+        if from.summary.scan_type != to.summary.scan_type {
+            return Err(format!(
+                "Runs are of different scans ({} vs {})",
+                from.summary.scan_type, to.summary.scan_type
+            ));
+        }
+        let before: BTreeSet<&str> = from.tickers.iter().map(|t| t.ticker.as_str()).collect();
+        let after: BTreeSet<&str> = to.tickers.iter().map(|t| t.ticker.as_str()).collect();
+
+        Ok(Self {
+            scan_type: to.summary.scan_type.clone(),
+            from: from.summary.id.clone(),
+            to: to.summary.id.clone(),
+            added: after.difference(&before).map(|t| t.to_string()).collect(),
+            removed: before.difference(&after).map(|t| t.to_string()).collect(),
+            unchanged: before.intersection(&after).count(),
+        })
+    }
+}
+
+pub struct RunHistory {
+    dir: PathBuf,
+    /// Oldest first
+    index: Arc<Mutex<Vec<RunSummary>>>,
+    tx: mpsc::UnboundedSender<RecordedRun>,
+    seq: AtomicU64,
+}
+
+impl RunHistory {
+    /// Index the runs already in `dir` and start the writer thread
+    pub fn spawn(dir: PathBuf, keep_per_scan: usize) -> Self {
+        let index = Arc::new(Mutex::new(load_index(&dir)));
+        let (tx, mut rx) = mpsc::unbounded_channel::<RecordedRun>();
+        let (writer_dir, writer_index) = (dir.clone(), index.clone());
+
+        std::thread::Builder::new()
+            .name("run-history".into())
+            .spawn(move || {
+                while let Some(run) = rx.blocking_recv() {
+                    if let Err(e) = write_run(&writer_dir, &run) {
+                        tracing::warn!("Could not record scan run {}: {}", run.summary.id, e);
+                        continue;
+                    }
+                    let mut index = writer_index.lock().unwrap_or_else(|e| e.into_inner());
+                    index.push(run.summary);
+                    prune(&writer_dir, &mut index, keep_per_scan);
+                }
+            })
+            .expect("failed to spawn run history writer");
+
+        Self { dir, index, tx, seq: AtomicU64::new(0) }
+    }
+
+    /// `RETRO_RUN_HISTORY_DIR` (default `./data/history`), keeping
+    /// `RETRO_RUN_HISTORY_KEEP` runs per scan type (default 20)
+    pub fn from_env() -> Self {
+        let dir = std::env::var("RETRO_RUN_HISTORY_DIR")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_RUN_HISTORY_DIR.to_string());
+        let keep = std::env::var("RETRO_RUN_HISTORY_KEEP")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_RUNS_KEPT_PER_SCAN);
+        Self::spawn(PathBuf::from(dir), keep)
+    }
+
+    /// A fresh run id; ids sort in recording order
+    pub fn next_id(&self) -> String {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed) % 10_000;
+        format!("{}-{:04}", chrono::Utc::now().format("%Y%m%dT%H%M%S%3f"), seq)
+    }
+
+    /// Queue a run for writing; never blocks
+    pub fn record(&self, run: RecordedRun) {
+        if self.tx.send(run).is_err() {
+            tracing::warn!("Run history writer has stopped; run dropped");
+        }
+    }
+
+    /// Recorded runs, newest first, optionally of one scan type
+    pub fn list(&self, scan_type: Option<&str>, limit: usize) -> Vec<RunSummary> {
+        let index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        index
+            .iter()
+            .rev()
+            .filter(|run| scan_type.is_none_or(|t| run.scan_type == t))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Load a run; None if there is no such id
+    pub fn get(&self, id: &str) -> anyhow::Result<Option<RecordedRun>> {
+        if !is_run_id(id) {
+            return Ok(None);
+        }
+        let path = run_path(&self.dir, id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+}
+
+/// Ids are generated by `next_id`; anything else can't name a file here
+fn is_run_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn run_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.json", id))
+}
+
+fn write_run(dir: &Path, run: &RecordedRun) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(run_path(dir, &run.summary.id), serde_json::to_string(run)?)?;
+    Ok(())
+}
+
+/// Summaries of the runs in `dir`, oldest first; unreadable files are skipped
+fn load_index(dir: &Path) -> Vec<RunSummary> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut index: Vec<RunSummary> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                return None;
+            }
+            let run: RecordedRun = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+            Some(run.summary)
+        })
+        .collect();
+    index.sort_by(|a, b| a.id.cmp(&b.id));
+    index
+}
+
+/// Drop (and delete) all but the newest `keep` runs of each scan type
+fn prune(dir: &Path, index: &mut Vec<RunSummary>, keep: usize) {
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    let mut expired = Vec::new();
+    for (i, run) in index.iter().enumerate().rev() {
+        let count = seen.entry(run.scan_type.clone()).or_default();
+        *count += 1;
+        if *count > keep {
+            expired.push(i);
+        }
+    }
+    // Descending, so earlier indices stay valid
+    for i in expired {
+        let run = index.remove(i);
+        if let Err(e) = fs::remove_file(run_path(dir, &run.id)) {
+            tracing::warn!("Could not delete expired scan run {}: {}", run.id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn run(history: &RunHistory, scan_type: &str, tickers: &[&str]) -> String {
+        let id = history.next_id();
+        history.record(RecordedRun {
+            summary: RunSummary {
+                id: id.clone(),
+                timestamp: "2024-01-05T12:00:00Z".into(),
+                scan_type: scan_type.into(),
+                total_matches: tickers.len(),
+                tickers_with_matches: tickers.len(),
+                scan_time_ms: 1,
+            },
+            query: ScanQuery { scan_type: scan_type.into(), ..Default::default() },
+            tickers: tickers
+                .iter()
+                .map(|t| TickerSummary {
+                    ticker: t.to_string(),
+                    matches: 1,
+                    first_date: "2024-01-05".into(),
+                    last_date: "2024-01-05".into(),
+                    bars: None,
+                })
+                .collect(),
+        });
+        id
+    }
+
+    #[test]
+    fn test_record_diff_and_retention() {
+        let dir = std::env::temp_dir().join(format!("retro-runs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let history = RunHistory::spawn(dir.clone(), 2);
+        let first = run(&history, "golden_cross", &["AAPL", "MSFT"]);
+        let second = run(&history, "golden_cross", &["MSFT", "NVDA"]);
+        let other = run(&history, "rsi_oversold", &["AAPL"]);
+        let third = run(&history, "golden_cross", &["NVDA"]);
+
+        // Writes are asynchronous
+        for _ in 0..100 {
+            if history.list(None, 10).len() == 3 && history.list(None, 1)[0].id == third {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let ids: Vec<String> = history.list(Some("golden_cross"), 10).into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, [third.clone(), second.clone()]);
+        assert!(history.get(&first).unwrap().is_none(), "oldest golden_cross run is pruned");
+
+        let (second, third) = (history.get(&second).unwrap().unwrap(), history.get(&third).unwrap().unwrap());
+        let diff = RunDiff::between(&second, &third).unwrap();
+        assert_eq!(diff.added, Vec::<String>::new());
+        assert_eq!(diff.removed, ["MSFT"]);
+        assert_eq!(diff.unchanged, 1);
+        assert!(RunDiff::between(&second, &history.get(&other).unwrap().unwrap()).is_err());
+
+        assert!(history.get("../secrets").unwrap().is_none());
+        // A restart re-reads what's on disk
+        assert_eq!(RunHistory::spawn(dir.clone(), 2).list(None, 10).len(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_ticker_summaries() {
+        let m = |ticker: &str, date: &str| ScanMatch {
+            ticker: ticker.into(),
+            date: date.into(),
+            close: 1.0,
+            volume: 1.0,
+            open: 1.0,
+            high: 1.0,
+            low: 1.0,
+            bar_index: 0,
+            prev_close: None,
+            change_pct: None,
+            indicators: Default::default(),
+            explanation: None,
+        };
+        let data = HashMap::from([("MSFT".to_string(), Arc::new(crate::data::generate_sample_data(30)))]);
+        let summaries = TickerSummary::from_matches(
+            &[m("MSFT", "2024-02-01"), m("AAPL", "2024-03-01"), m("MSFT", "2024-01-01")],
+            &data,
+        );
+        assert_eq!(summaries.len(), 2);
+        assert_eq!((summaries[0].bars, summaries[1].bars), (None, Some(30)));
+        assert_eq!(summaries[1].ticker, "MSFT");
+        assert_eq!(summaries[1].matches, 2);
+        assert_eq!((summaries[1].first_date.as_str(), summaries[1].last_date.as_str()), ("2024-01-01", "2024-02-01"));
+    }
+}
@@ -0,0 +1,139 @@
+//! Saved scans - named `ScanQuery` configurations persisted to JSON
+
+use crate::scanner::ScanQuery;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SAVED_SCANS_PATH: &str = "./data/saved_scans.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedScan {
+    /// Slug derived from the name, unique within the store
+    pub id: String,
+    pub name: String,
+    pub query: ScanQuery,
+    /// RFC 3339, UTC
+    pub created_at: String,
+}
+
+/// In-memory list mirrored to a JSON file on every change
+#[derive(Debug, Default)]
+pub struct SavedScanStore {
+    path: PathBuf,
+    scans: Vec<SavedScan>,
+}
+
+impl SavedScanStore {
+    /// Load from `path`; a missing file is an empty store
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let scans = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(path)?)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            scans,
+        })
+    }
+
+    pub fn list(&self) -> &[SavedScan] {
+        &self.scans
+    }
+
+    pub fn get(&self, id: &str) -> Option<&SavedScan> {
+        self.scans.iter().find(|s| s.id == id)
+    }
+
+    /// Save a new scan under a fresh id and persist
+    pub fn insert(&mut self, name: &str, query: ScanQuery) -> anyhow::Result<SavedScan> {
+        let base = slugify(name);
+        let id = (1..)
+            .map(|n| if n == 1 { base.clone() } else { format!("{}_{}", base, n) })
+            .find(|id| self.get(id).is_none())
+            .expect("unbounded id search");
+
+        let scan = SavedScan {
+            id,
+            name: name.trim().to_string(),
+            query,
+            created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        };
+        self.scans.push(scan.clone());
+        if let Err(e) = self.save() {
+            self.scans.pop();
+            return Err(e);
+        }
+        Ok(scan)
+    }
+
+    /// Remove and persist; false if there was no such scan
+    pub fn remove(&mut self, id: &str) -> anyhow::Result<bool> {
+        let Some(pos) = self.scans.iter().position(|s| s.id == id) else {
+            return Ok(false);
+        };
+        let removed = self.scans.remove(pos);
+        if let Err(e) = self.save() {
+            self.scans.insert(pos, removed);
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.scans)?)?;
+        Ok(())
+    }
+}
+
+/// Lowercase alphanumerics joined by underscores; "scan" if nothing remains
+fn slugify(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    if slug.is_empty() {
+        "scan".into()
+    } else {
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_scans_persist() {
+        let path = std::env::temp_dir().join(format!("retro-saved-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut store = SavedScanStore::load(&path).unwrap();
+        let query = ScanQuery {
+            scan_type: "rsi_oversold".into(),
+            lookback_days: Some(5),
+            ..Default::default()
+        };
+        let first = store.insert("Oversold this week!", query.clone()).unwrap();
+        let second = store.insert("oversold -- this week", query).unwrap();
+        assert_eq!(first.id, "oversold_this_week");
+        assert_eq!(second.id, "oversold_this_week_2");
+        assert_eq!(slugify("  ?? "), "scan");
+
+        let mut reloaded = SavedScanStore::load(&path).unwrap();
+        assert_eq!(reloaded.list().len(), 2);
+        assert_eq!(reloaded.get("oversold_this_week").unwrap().query.lookback_days, Some(5));
+
+        assert!(reloaded.remove("oversold_this_week").unwrap());
+        assert!(!reloaded.remove("oversold_this_week").unwrap());
+        assert_eq!(SavedScanStore::load(&path).unwrap().list().len(), 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
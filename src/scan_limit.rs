@@ -0,0 +1,154 @@
+//! Concurrent scan limit - every scan execution (synchronous, streamed or a
+//! background job), backtest and breadth computation holds a permit, so
+//! simultaneous requests don't each spread Rayon across all cores at once
+//!
+//! Requests beyond the limit wait their turn, or with `RETRO_SCAN_QUEUE=reject`
+//! are turned away with a 429.
+
+use crate::error::ApiError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+pub const DEFAULT_MAX_CONCURRENT_SCANS: usize = 2;
+
+#[derive(Debug)]
+pub struct ScanLimiter {
+    permits: Arc<Semaphore>,
+    max_concurrent: usize,
+    reject_when_busy: bool,
+    waiting: AtomicUsize,
+}
+
+impl ScanLimiter {
+    pub fn new(max_concurrent: usize, reject_when_busy: bool) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+            reject_when_busy,
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// `RETRO_MAX_CONCURRENT_SCANS` (default 2) and `RETRO_SCAN_QUEUE`
+    /// (`queue`, the default, or `reject`)
+    pub fn from_env() -> Self {
+        let max_concurrent = std::env::var("RETRO_MAX_CONCURRENT_SCANS")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_SCANS);
+        let reject_when_busy = std::env::var("RETRO_SCAN_QUEUE")
+            .is_ok_and(|v| v.trim().eq_ignore_ascii_case("reject"));
+        Self::new(max_concurrent, reject_when_busy)
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    pub fn rejects_when_busy(&self) -> bool {
+        self.reject_when_busy
+    }
+
+    /// A permit to run one scan; hold it until the scan is done
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, ApiError> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+        if self.reject_when_busy {
+            metrics::counter!("scans_rejected_total").increment(1);
+            return Err(ApiError::TooManyRequests(format!(
+                "{} scans already running; try again shortly",
+                self.max_concurrent
+            )));
+        }
+
+        let queued = Queued::join(&self.waiting);
+        tracing::info!("Scan queued at position {} ({} running)", queued.position, self.max_concurrent);
+        let permit = self.permits.clone().acquire_owned().await;
+        drop(queued);
+        permit.map_err(|_| ApiError::Internal("scan limiter closed".into()))
+    }
+}
+
+/// A place in the queue, given up when dropped - also when the request
+/// waiting for a permit goes away
+struct Queued<'a> {
+    waiting: &'a AtomicUsize,
+    position: usize,
+}
+
+impl<'a> Queued<'a> {
+    fn join(waiting: &'a AtomicUsize) -> Self {
+        let position = waiting.fetch_add(1, Ordering::Relaxed) + 1;
+        Self { waiting, position }
+    }
+}
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.waiting.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_scans_run_one_at_a_time() {
+        let limiter = Arc::new(ScanLimiter::new(1, false));
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire().await.unwrap();
+                    let start = Instant::now();
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    (start, Instant::now())
+                })
+            })
+            .collect();
+
+        let mut spans = Vec::new();
+        for task in tasks {
+            spans.push(task.await.unwrap());
+        }
+        spans.sort();
+        for pair in spans.windows(2) {
+            assert!(pair[1].0 >= pair[0].1, "scans overlapped");
+        }
+
+        let rejecting = ScanLimiter::new(1, true);
+        let held = rejecting.acquire().await.unwrap();
+        assert!(matches!(rejecting.acquire().await, Err(ApiError::TooManyRequests(_))));
+        drop(held);
+        assert!(rejecting.acquire().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_acquire_leaves_the_queue() {
+        let limiter = Arc::new(ScanLimiter::new(1, false));
+        let held = limiter.acquire().await.unwrap();
+
+        // A client that gives up while queued, e.g. a request timing out
+        let gave_up = tokio::time::timeout(Duration::from_millis(20), limiter.acquire()).await;
+        assert!(gave_up.is_err());
+        assert_eq!(limiter.waiting.load(Ordering::Relaxed), 0);
+
+        // The next one queues first in line
+        let next = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire().await.map(|_| ()) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(limiter.waiting.load(Ordering::Relaxed), 1);
+
+        drop(held);
+        assert!(next.await.unwrap().is_ok());
+        assert_eq!(limiter.waiting.load(Ordering::Relaxed), 0);
+    }
+}
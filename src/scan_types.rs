@@ -1,19 +1,233 @@
 //! Shared scan type metadata
 
+use crate::calendar::Lookback;
+use crate::composite;
+use schemars::JsonSchema;
 use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct ScanType {
     pub id: String,
     pub name: String,
     pub description: String,
     pub params: Vec<ScanParam>,
+    /// Applied as `lookback_days` when a scan request gives no date range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_lookback_days: Option<u32>,
+    /// Generated scans' spec version, for display; none for built-ins
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct ScanParam {
     pub name: String,
     pub param_type: String,
     pub default: serde_json::Value,
     pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// A period or count; fractional values are rejected
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub integer: bool,
+}
+
+/// Coerce a raw param value to the declared type and check its bounds.
+/// Numeric strings are parsed, and integral numbers are stored as integers so
+/// scans reading them with `as_u64()` see the value instead of their default.
+/// `integer` params reject fractions, which scans would otherwise read as
+/// their default.
+pub fn coerce_param(value: &Value, param: &ScanParam) -> Result<Value, String> {
+    match param.param_type.as_str() {
+        "number" => {
+            let n = match value {
+                Value::Number(n) => n.as_f64(),
+                Value::String(s) => s.trim().parse::<f64>().ok(),
+                _ => None,
+            }
+            .filter(|n| n.is_finite())
+            .ok_or_else(|| format!("{}: expected a number, got {}", param.name, value))?;
+
+            if let Some(min) = param.min {
+                if n < min {
+                    return Err(format!("{}: {} is below the minimum {}", param.name, n, min));
+                }
+            }
+            if let Some(max) = param.max {
+                if n > max {
+                    return Err(format!("{}: {} is above the maximum {}", param.name, n, max));
+                }
+            }
+
+            if param.integer && n.fract() != 0.0 {
+                return Err(format!("{}: expected a whole number, got {}", param.name, n));
+            }
+
+            if n.fract() == 0.0 && n >= 0.0 && n <= u64::MAX as f64 {
+                Ok(Value::from(n as u64))
+            } else {
+                Ok(Value::from(n))
+            }
+        }
+        // Bar counts are stored as integers, spans in their canonical form ("6m")
+        "lookback" => {
+            let lookback = Lookback::parse(value).map_err(|e| format!("{}: {}", param.name, e))?;
+            if param.min.is_some_and(|min| (lookback.count() as f64) < min) {
+                return Err(format!("{}: {} is below the minimum {}", param.name, lookback, param.min.unwrap_or_default()));
+            }
+            Ok(match lookback {
+                Lookback::Bars(n) => Value::from(n as u64),
+                span => Value::from(span.to_string()),
+            })
+        }
+        "text" | "select" => match value {
+            Value::String(_) => Ok(value.clone()),
+            Value::Number(n) if param.param_type == "text" => Ok(Value::String(n.to_string())),
+            _ => Err(format!("{}: expected a string, got {}", param.name, value)),
+        },
+        // Stored as the `{indicator, period}` object
+        "series" => {
+            let series = composite::SeriesRef::parse(value).map_err(|e| format!("{}: {}", param.name, e))?;
+            serde_json::to_value(series).map_err(|e| format!("{}: {}", param.name, e))
+        }
+        // A JSON string too, as typed into the UI's text input
+        "filters" => {
+            let value = match value {
+                Value::String(s) => serde_json::from_str(s).map_err(|e| format!("{}: invalid JSON: {}", param.name, e))?,
+                _ => value.clone(),
+            };
+            composite::parse_filters(&value).map(|_| value)
+        }
+        _ => Ok(value.clone()),
+    }
+}
+
+/// Coerce every declared param present in `params`, in place. Omitted (or
+/// null) params are left out so scans fall back to their defaults, except a
+/// `filters` list, which is required; params the scan type doesn't declare
+/// are passed through untouched. A lookback may also be given in calendar
+/// days as `<name>_days_calendar` (`lookback_days_calendar: 126` is
+/// `lookback: "126d"`).
+pub fn coerce_params(params: &mut HashMap<String, Value>, scan_type: &ScanType) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+
+    for param in scan_type.params.iter().filter(|p| p.param_type == "lookback") {
+        let alias = format!("{}_days_calendar", param.name);
+        let Some(days) = params.remove(&alias) else {
+            continue;
+        };
+        match (days.as_u64(), params.contains_key(&param.name)) {
+            (_, true) => errors.push(format!("{}: give either {} or {}, not both", alias, param.name, alias)),
+            (Some(days), false) => {
+                params.insert(param.name.clone(), Value::from(format!("{}d", days)));
+            }
+            (None, false) => errors.push(format!("{}: expected a whole number of days, got {}", alias, days)),
+        }
+    }
+
+    for param in &scan_type.params {
+        match params.get(&param.name) {
+            // A filter list has no useful default
+            None if param.param_type == "filters" => errors.push(format!("{}: required", param.name)),
+            None => {}
+            Some(Value::Null) => {
+                params.remove(&param.name);
+            }
+            Some(value) => match coerce_param(value, param) {
+                Ok(coerced) => {
+                    params.insert(param.name.clone(), coerced);
+                }
+                Err(e) => errors.push(e),
+            },
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number_param(min: Option<f64>, max: Option<f64>) -> ScanParam {
+        ScanParam {
+            name: "period".into(),
+            param_type: "number".into(),
+            default: 14.into(),
+            description: "Period".into(),
+            min,
+            max,
+            integer: true,
+        }
+    }
+
+    #[test]
+    fn test_coerce_numeric_strings() {
+        let p = number_param(Some(1.0), None);
+        assert_eq!(coerce_param(&Value::from("14"), &p).unwrap().as_u64(), Some(14));
+        assert_eq!(coerce_param(&Value::from(14.0), &p).unwrap().as_u64(), Some(14));
+        assert!(coerce_param(&Value::from("2.5"), &p).unwrap_err().contains("whole number"));
+        assert!(coerce_param(&Value::from(14.5), &p).is_err());
+        let threshold = ScanParam { name: "threshold".into(), integer: false, ..p.clone() };
+        assert_eq!(coerce_param(&Value::from("2.5"), &threshold).unwrap().as_f64(), Some(2.5));
+        assert!(coerce_param(&Value::from("abc"), &p).is_err());
+        assert!(coerce_param(&Value::Bool(true), &p).is_err());
+    }
+
+    #[test]
+    fn test_coerce_bounds_and_omissions() {
+        let scan = ScanType {
+            id: "rsi_oversold".into(),
+            name: "RSI".into(),
+            description: String::new(),
+            params: vec![number_param(Some(1.0), Some(100.0))],
+            default_lookback_days: None,
+            version: None,
+        };
+
+        let mut params = HashMap::from([("period".to_string(), Value::from(0))]);
+        let errors = coerce_params(&mut params, &scan).unwrap_err();
+        assert!(errors[0].contains("below the minimum"));
+
+        let mut params = HashMap::from([("period".to_string(), Value::Null)]);
+        assert!(coerce_params(&mut params, &scan).is_ok());
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_coerce_lookbacks() {
+        let scan = ScanType {
+            id: "price_breakout".into(),
+            name: "Breakout".into(),
+            description: String::new(),
+            params: vec![ScanParam { name: "lookback".into(), param_type: "lookback".into(), ..number_param(Some(1.0), None) }],
+            default_lookback_days: None,
+            version: None,
+        };
+        let coerced = |value: Value| {
+            let mut params = HashMap::from([("lookback".to_string(), value)]);
+            coerce_params(&mut params, &scan).map(|_| params["lookback"].clone())
+        };
+        assert_eq!(coerced(Value::from("252")), Ok(Value::from(252)));
+        assert_eq!(coerced(Value::from(" 6 MO")), Ok(Value::from("6m")));
+        assert!(coerced(Value::from("0w")).unwrap_err()[0].contains("below the minimum"));
+        assert!(coerced(Value::from("6q")).is_err());
+
+        let mut params = HashMap::from([("lookback_days_calendar".to_string(), Value::from(126))]);
+        coerce_params(&mut params, &scan).unwrap();
+        assert_eq!(params, HashMap::from([("lookback".to_string(), Value::from("126d"))]));
+        let mut both = HashMap::from([
+            ("lookback".to_string(), Value::from(20)),
+            ("lookback_days_calendar".to_string(), Value::from(126)),
+        ]);
+        assert!(coerce_params(&mut both, &scan).is_err());
+    }
 }
@@ -1,15 +1,35 @@
 //! Scanner - parallel execution engine for stock queries
 
-use crate::data::TickerData;
-use crate::generated;
+use crate::benchmark::{aligned_returns, CorrelationFilter};
+use crate::composite;
+use crate::data::{parse_date_param, parse_timestamp, timestamp_bound, TickerData, Timeframe};
+use crate::calendar::Lookback;
 use crate::indicators::*;
+use crate::mtf::{Confirm, ConfirmWith};
+use crate::generated::ScanFn;
+use crate::registry::{BuiltinScan, ParamDefault::*, ParamSpec, ScanDefinition, ScanRegistry};
+use crate::resample::{expand, resample_bars, Bar, Interval};
+// Built-in scans compute the hot indicators on pooled buffers (shadowing the glob)
+use crate::scratch::{
+    self, above, below, crossed_above, crossed_above_level, crossed_below, crossed_below_level, ema, rsi, sma, volume_ratio,
+};
+use chrono::NaiveDate;
 use rayon::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Generated scans are isolated per ticker with `catch_unwind`, which does
+// nothing when panics abort the process
+#[cfg(panic = "abort")]
+compile_error!("retro must be built with panic = \"unwind\" so a panicking scan only skips its ticker");
 
 /// A single match from a scan
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ScanMatch {
     pub ticker: String,
     pub date: String,
@@ -18,55 +38,583 @@ pub struct ScanMatch {
     pub open: f64,
     pub high: f64,
     pub low: f64,
+    /// Position of the bar in the ticker's loaded history, from 0
+    #[serde(default)]
+    pub bar_index: usize,
+    /// Close of the bar before; null on the ticker's first bar
+    #[serde(default)]
+    pub prev_close: Option<f64>,
+    /// Percent change from `prev_close` to `close`
+    #[serde(default)]
+    pub change_pct: Option<f64>,
     #[serde(flatten)]
     pub indicators: HashMap<String, f64>,
+    /// Why the bar matched, when the query asked to `explain`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<Explanation>,
+}
+
+/// Per-match breakdown of a scan's decision. Empty sections are omitted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Explanation {
+    /// Each leg of a composite scan and whether it held on the bar
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub conditions: BTreeMap<String, bool>,
+    /// Principal series on the bar; null while undefined
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<String, Option<f64>>,
+    /// The same series on the bar before, which shows a cross
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub previous: BTreeMap<String, Option<f64>>,
+    /// Fixed levels the series were compared against
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub thresholds: BTreeMap<String, f64>,
+}
+
+/// What a scan computed for each bar. Scans that only produce a mask convert
+/// via `From<Vec<bool>>`.
+#[derive(Debug, Default)]
+pub struct ScanOutput {
+    pub mask: Vec<bool>,
+    /// Copied into every match's indicators (e.g. a score to rank by); NaN is omitted
+    pub scores: Vec<(String, Vec<f64>)>,
+    /// Principal series, reported by `explain`
+    pub series: Vec<(String, Vec<f64>)>,
+    /// Per-leg masks of a composite scan, reported by `explain`
+    pub conditions: Vec<(String, Vec<bool>)>,
+    /// Levels compared against, reported by `explain`
+    pub thresholds: Vec<(String, f64)>,
+}
+
+impl From<Vec<bool>> for ScanOutput {
+    fn from(mask: Vec<bool>) -> Self {
+        Self { mask, ..Default::default() }
+    }
+}
+
+impl ScanOutput {
+    /// Hand every vector back to this thread's scratch pool
+    fn recycle(self) {
+        scratch::recycle_bool(self.mask);
+        for (_, values) in self.scores.into_iter().chain(self.series) {
+            scratch::recycle_f64(values);
+        }
+        for (_, mask) in self.conditions {
+            scratch::recycle_bool(mask);
+        }
+    }
+
+    fn scores_at(&self, j: usize) -> HashMap<String, f64> {
+        self.scores
+            .iter()
+            .filter(|(_, values)| !values[j].is_nan())
+            .map(|(name, values)| (name.clone(), values[j]))
+            .collect()
+    }
+
+    /// Breakdown for bar `j`; None when the scan reports nothing beyond its mask
+    fn explain(&self, j: usize) -> Option<Explanation> {
+        if self.series.is_empty() && self.conditions.is_empty() && self.thresholds.is_empty() {
+            return None;
+        }
+        let defined = |v: f64| Some(v).filter(|v| !v.is_nan());
+        Some(Explanation {
+            conditions: self.conditions.iter().map(|(name, mask)| (name.clone(), mask[j])).collect(),
+            values: self.series.iter().map(|(name, values)| (name.clone(), defined(values[j]))).collect(),
+            previous: match j.checked_sub(1) {
+                Some(p) => self.series.iter().map(|(name, values)| (name.clone(), defined(values[p]))).collect(),
+                None => BTreeMap::new(),
+            },
+            thresholds: self.thresholds.iter().cloned().collect(),
+        })
+    }
+
+    /// Fold another composite leg in: AND its mask, keep its series and
+    /// thresholds (first occurrence of a name wins)
+    fn and_leg(&mut self, name: &str, leg: ScanOutput) {
+        if self.conditions.is_empty() {
+            self.mask.clear();
+            self.mask.extend_from_slice(&leg.mask);
+        } else {
+            // In place, ending at the shorter of the two like `and`
+            self.mask.truncate(leg.mask.len());
+            self.mask.iter_mut().zip(&leg.mask).for_each(|(m, &l)| *m &= l);
+        }
+        self.conditions.push((name.to_string(), leg.mask));
+        for (key, values) in leg.series {
+            if self.series.iter().any(|(k, _)| *k == key) {
+                scratch::recycle_f64(values);
+            } else {
+                self.series.push((key, values));
+            }
+        }
+        for (key, level) in leg.thresholds {
+            if !self.thresholds.iter().any(|(k, _)| *k == key) {
+                self.thresholds.push((key, level));
+            }
+        }
+    }
 }
 
 /// Scan query definition
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct ScanQuery {
     pub scan_type: String,
     pub params: HashMap<String, serde_json::Value>,
     pub date_from: Option<String>,
     pub date_to: Option<String>,
+    /// Only keep matches within this many days of each ticker's latest bar
+    /// (inclusive). Mutually exclusive with `date_from`/`date_to`.
+    #[serde(default)]
+    pub lookback_days: Option<u32>,
+    /// Drop a match when the scan also matched within the previous N bars, so
+    /// a run of consecutive signals reports only its first bar. Earlier bars
+    /// outside the date range still count.
+    #[serde(default)]
+    pub dedup_within_days: Option<usize>,
+    /// Keep only each ticker's earliest match in the date range (after dedup)
+    #[serde(default)]
+    pub first_only: bool,
+    /// Only keep matches whose rolling beta/correlation to a benchmark is in bounds
+    #[serde(default)]
+    pub correlation_filter: Option<CorrelationFilter>,
+    /// Scan split- and dividend-adjusted prices (see `TickerData::adjusted`).
+    /// Tickers without an adjusted close column are skipped.
+    #[serde(default)]
+    pub adjusted: bool,
+    /// Only scan tickers in this sector (from `metadata.csv`, case-insensitive)
+    #[serde(default)]
+    pub sector: Option<String>,
+    /// Skip tickers whose data ends more than this many calendar days before
+    /// the newest date in the store (not today), e.g. delisted symbols
+    #[serde(default)]
+    pub max_staleness_days: Option<u32>,
+    /// The store's newest date, which `max_staleness_days` counts back from;
+    /// set by the server, since the scanned tickers may be a sector's. Falls
+    /// back to the newest date among the scanned tickers.
+    #[serde(skip)]
+    pub newest_day: Option<NaiveDate>,
+    /// Only keep matches whose last completed higher-timeframe bar passes a condition
+    #[serde(default)]
+    pub confirm: Option<Confirm>,
+    /// Only keep matches where another scan matched the last completed
+    /// resampled bar (e.g. daily RSI oversold while a weekly EMA cross is up)
+    #[serde(default)]
+    pub confirm_with: Option<ConfirmWith>,
+    /// Restrict each serialized match to these fields. Ticker, date and any
+    /// explanation are always kept; names a match doesn't have are ignored.
+    #[serde(default)]
+    pub fields: Option<Vec<String>>,
+    /// Attach an `explanation` to each match, for scans that provide one
+    #[serde(default)]
+    pub explain: bool,
+    /// Only keep matches whose average daily range (`adr_pct` over
+    /// `adr_period` bars, as % of close) is at least / at most this
+    #[serde(default)]
+    pub min_adr_pct: Option<f64>,
+    #[serde(default)]
+    pub max_adr_pct: Option<f64>,
+    /// Bars averaged for the ADR bounds (default 20)
+    #[serde(default)]
+    pub adr_period: Option<usize>,
+    /// Keep a per-ticker summary of the results under `./data/history` for diffing
+    #[serde(default)]
+    pub record: bool,
+    /// Keep matches within the scan's warm-up (its first `warmup_bars` bars of
+    /// each ticker's history), where indicators haven't settled
+    #[serde(default)]
+    pub include_warmup: bool,
+    /// Stop the scan after this long and return what was found, with
+    /// `timed_out` set (the server defaults and caps it, and counts time
+    /// spent queued behind other scans against it)
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Stop scanning any ticker that runs longer than this, dropping its
+    /// results and listing it in `tickers_over_budget`. Checked whenever the
+    /// scan takes a pooled buffer and once its mask is computed.
+    #[serde(default)]
+    pub ticker_budget_ms: Option<u64>,
+}
+
+impl ScanQuery {
+    /// The ADR bounds as (min, max, period), None when neither is set
+    pub fn adr_filter(&self) -> Result<Option<(f64, f64, usize)>, String> {
+        if self.min_adr_pct.is_none() && self.max_adr_pct.is_none() {
+            return Ok(None);
+        }
+        let period = self.adr_period.unwrap_or(20);
+        if period == 0 {
+            return Err("adr_period must be at least 1".into());
+        }
+        let (min, max) = (self.min_adr_pct.unwrap_or(0.0), self.max_adr_pct.unwrap_or(f64::INFINITY));
+        if min.is_nan() || max.is_nan() || min > max {
+            return Err("min_adr_pct must not exceed max_adr_pct".into());
+        }
+        Ok(Some((min, max, period)))
+    }
+
+    /// Parsed `date_from`/`date_to`; an error names the offending field, or
+    /// says the range is backwards
+    pub fn date_range(&self) -> Result<(Option<NaiveDate>, Option<NaiveDate>), String> {
+        let from = parse_date_param("date_from", self.date_from.as_deref())?;
+        let to = parse_date_param("date_to", self.date_to.as_deref())?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(format!("date_from ({}) is after date_to ({})", from, to));
+            }
+        }
+        Ok((from, to))
+    }
 }
 
 /// Scan result with stats
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct ScanResult {
     pub matches: Vec<ScanMatch>,
     pub total_tickers_scanned: usize,
     pub tickers_with_matches: usize,
+    /// Tickers skipped because the scan panicked on them
+    pub scan_errors: usize,
+    /// Tickers skipped for having fewer bars than the scan needs
+    pub tickers_skipped_short_history: usize,
+    /// Tickers skipped by an `adjusted` scan for having no adjusted closes
+    pub tickers_skipped_unadjusted: usize,
+    /// Tickers skipped for data ending too long ago (`max_staleness_days`)
+    pub tickers_skipped_stale: usize,
     pub scan_time_ms: u64,
+    /// The scan was stopped before every ticker was scanned
+    pub cancelled: bool,
+    /// The scan ran past `timeout_ms`; tickers not yet finished were skipped
+    pub timed_out: bool,
+    /// Tickers not scanned because the scan was cancelled or timed out
+    /// (counting any it stopped part way)
+    pub tickers_unscanned: usize,
+    /// Tickers whose results were dropped for exceeding `ticker_budget_ms`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tickers_over_budget: Vec<String>,
+    /// Id of the recorded run, when the query asked to `record`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    /// Why the date range may have found little or nothing (see `range_warnings`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl ScanResult {
+    /// Serialize with each match reduced to `fields` plus ticker/date/explanation,
+    /// for lighter payloads. Unknown field names are ignored.
+    pub fn to_json_with_fields(&self, fields: &[String]) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+
+        if let Some(matches) = value.get_mut("matches").and_then(|m| m.as_array_mut()) {
+            for m in matches.iter_mut() {
+                retain_fields(m, fields);
+            }
+        }
+
+        value
+    }
+}
+
+/// One match as JSON, keeping only `ticker`, `date`, `explanation` and `fields`
+pub fn project_match(m: &ScanMatch, fields: &[String]) -> serde_json::Value {
+    let mut value = serde_json::to_value(m).unwrap_or_default();
+    retain_fields(&mut value, fields);
+    value
+}
+
+fn retain_fields(m: &mut serde_json::Value, fields: &[String]) {
+    if let Some(obj) = m.as_object_mut() {
+        obj.retain(|key, _| {
+            key == "ticker" || key == "date" || key == "explanation" || fields.iter().any(|f| f == key)
+        });
+    }
 }
 
-/// Run a scan across all tickers in parallel
+/// Used when a query has no `timeout_ms`; the server always sets one
+pub const DEFAULT_SCAN_TIMEOUT_MS: u64 = 60_000;
+
+/// Run a scan across all tickers in parallel. Unknown scan ids match nothing.
+/// A scan that panics on one ticker (e.g. a bad generated scan) skips that
+/// ticker instead of aborting the whole run. A `correlation_filter` benchmark
+/// is looked up in `data`.
 pub fn run_scan(
+    registry: &ScanRegistry,
+    data: &HashMap<String, Arc<TickerData>>,
+    query: &ScanQuery,
+) -> ScanResult {
+    let benchmark = benchmark_symbol(registry, query).and_then(|symbol| data.get(&symbol).cloned());
+    run_scan_with(registry, data, query, benchmark)
+}
+
+/// The series a query compares tickers against, uppercased: its
+/// `correlation_filter` benchmark, or the `benchmark` param (SPY by default)
+/// of a scan with a `benchmark_output`. Handlers reject queries naming two.
+pub fn benchmark_symbol(registry: &ScanRegistry, query: &ScanQuery) -> Option<String> {
+    let filter = query.correlation_filter.as_ref().map(|f| f.benchmark.clone());
+    let scan = registry
+        .get(&query.scan_type)
+        .filter(|def| def.benchmark_output.is_some())
+        .map(|_| query.params.get("benchmark").and_then(|v| v.as_str()).unwrap_or("SPY").to_string());
+    filter.or(scan).map(|symbol| symbol.to_uppercase())
+}
+
+/// `run_scan` with the benchmark series supplied by the caller, for when
+/// `data` is a subset (e.g. one sector) that may not include it. Without a
+/// benchmark, a query that needs one (see `benchmark_symbol`) matches nothing.
+pub fn run_scan_with(
+    registry: &ScanRegistry,
+    data: &HashMap<String, Arc<TickerData>>,
+    query: &ScanQuery,
+    benchmark: Option<Arc<TickerData>>,
+) -> ScanResult {
+    scan_tickers(registry, data, query, benchmark, None, None)
+}
+
+/// Live counters of a running scan, readable from another thread
+#[derive(Debug, Default)]
+pub struct ScanProgress {
+    /// Tickers in the universe; set when the scan starts
+    pub total: AtomicUsize,
+    /// Tickers finished, including those skipped
+    pub scanned: AtomicUsize,
+    /// Matches found so far
+    pub matches: AtomicUsize,
+    /// Set from outside to stop the scan; tickers not yet started are skipped
+    pub cancelled: AtomicBool,
+}
+
+/// `run_scan_with`, updating `progress` as each ticker finishes and stopping
+/// early (with a partial, `cancelled` result) once `progress.cancelled` is set
+pub fn run_scan_tracked(
+    registry: &ScanRegistry,
+    data: &HashMap<String, Arc<TickerData>>,
+    query: &ScanQuery,
+    benchmark: Option<Arc<TickerData>>,
+    progress: &ScanProgress,
+) -> ScanResult {
+    scan_tickers(registry, data, query, benchmark, None, Some(progress))
+}
+
+/// Receives each ticker's matches as soon as that ticker is scanned; returns
+/// false to stop the scan (e.g. the client went away)
+pub type MatchSink<'a> = &'a (dyn Fn(Vec<ScanMatch>) -> bool + Sync);
+
+/// `run_scan_with`, handing matches to `sink` as tickers finish rather than
+/// collecting them. The returned result has no `matches` but full counts.
+pub fn run_scan_streaming(
+    registry: &ScanRegistry,
     data: &HashMap<String, Arc<TickerData>>,
     query: &ScanQuery,
+    benchmark: Option<Arc<TickerData>>,
+    sink: MatchSink,
+) -> ScanResult {
+    scan_tickers(registry, data, query, benchmark, Some(sink), None)
+}
+
+fn scan_tickers(
+    registry: &ScanRegistry,
+    data: &HashMap<String, Arc<TickerData>>,
+    query: &ScanQuery,
+    benchmark: Option<Arc<TickerData>>,
+    sink: Option<MatchSink>,
+    progress: Option<&ScanProgress>,
 ) -> ScanResult {
     let start = std::time::Instant::now();
     
     let tickers: Vec<_> = data.keys().cloned().collect();
     let total_tickers = tickers.len();
+    // Rayon workers don't inherit it, so panics below log inside it explicitly
+    let span = tracing::info_span!("scan", scan_type = %query.scan_type, tickers = total_tickers);
+    let _entered = span.enter();
+    if let Some(progress) = progress {
+        progress.total.store(total_tickers, Ordering::Relaxed);
+    }
+    let def = registry.get(&query.scan_type);
+    // Outer None: the confirmation scan is unknown, so nothing can match
+    let confirm_func: Option<Option<ScanFn>> = match &query.confirm_with {
+        Some(confirm) => registry.get(&confirm.scan_type).map(|d| Some(d.func)),
+        None => Some(None),
+    };
+    // An adjusted scan compares against the benchmark's adjusted prices when it has them
+    let benchmark = match (query.adjusted, benchmark) {
+        (true, Some(b)) => Some(b.adjusted().map(Arc::new).unwrap_or(b)),
+        (_, b) => b,
+    };
+    // Outer None: the query needs a benchmark that isn't loaded
+    let benchmark: Option<Option<&TickerData>> = match benchmark_symbol(registry, query) {
+        Some(_) => benchmark.as_deref().map(Some),
+        None => Some(None),
+    };
+    // Handlers reject unparseable dates with a 400 before we get here
+    let date_range = query.date_range().unwrap_or_default();
+    let min_bars = def.map_or(1, |def| def.min_bars(&query.params));
+    let stale_before = query.max_staleness_days.and_then(|days| {
+        let newest = query.newest_day.or_else(|| data.values().filter_map(|d| d.days.last().copied()).max())?;
+        newest.checked_sub_signed(chrono::Duration::days(days as i64))
+    });
+    let is_stale = |d: &TickerData| stale_before.is_some_and(|cutoff| d.days.last().is_none_or(|&last| last < cutoff));
+    let tickers_skipped_stale = match def {
+        Some(_) => data.values().filter(|d| is_stale(d)).count(),
+        None => 0,
+    };
+    let tickers_skipped_short_history = match def {
+        Some(_) => data.values().filter(|d| !is_stale(d) && d.len() < min_bars).count(),
+        None => 0,
+    };
+    let tickers_skipped_unadjusted = match (def, query.adjusted) {
+        (Some(_), true) => {
+            data.values().filter(|d| !is_stale(d) && d.len() >= min_bars && d.adj_close.is_none()).count()
+        }
+        _ => 0,
+    };
     
-    // Parallel scan
-    let results: Vec<Vec<ScanMatch>> = tickers
-        .par_iter()
-        .filter_map(|ticker| {
-            let ticker_data = data.get(ticker)?;
-            scan_single_ticker(ticker, ticker_data, query)
-        })
-        .collect();
+    // Matches handed to the sink, and whether it (or a cancellation) stopped the scan
+    let streamed = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+    // Checked before each ticker, and inside one by the pooled indicators
+    // (see `scratch::check_deadline`)
+    let deadline = start.checked_add(Duration::from_millis(query.timeout_ms.unwrap_or(DEFAULT_SCAN_TIMEOUT_MS)));
+    let timed_out = AtomicBool::new(false);
+    let started = AtomicUsize::new(0);
+    let budget = query.ticker_budget_ms.map(Duration::from_millis);
+    let over_budget = Mutex::new(Vec::new());
+    
+    // Parallel scan; Err marks a ticker the scan panicked on. The benchmark
+    // is fetched once above and shared by reference across workers.
+    let outcomes: Vec<Result<Option<Vec<ScanMatch>>, ()>> = match (def, confirm_func, benchmark) {
+        (Some(def), Some(confirm_func), Some(benchmark)) => {
+            let scan_one = |ticker: &String| {
+                let ticker_data = data.get(ticker).filter(|d| !is_stale(d) && d.len() >= min_bars)?;
+                let adjusted;
+                let ticker_data: &TickerData = match query.adjusted {
+                    true => {
+                        adjusted = ticker_data.adjusted()?;
+                        &adjusted
+                    }
+                    false => ticker_data,
+                };
+                let over_budget_at = budget.and_then(|budget| Instant::now().checked_add(budget));
+                let ticker_deadline = [over_budget_at, deadline].into_iter().flatten().min();
+                let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                    let filters = TickerFilters { confirm_func, benchmark };
+                    scratch::with_deadline(ticker_deadline, || {
+                        scan_single_ticker(ticker, ticker_data, def, filters, query, date_range)
+                    })
+                }));
+                let outcome = match outcome {
+                    Err(payload) if payload.is::<scratch::OutOfTime>() => {
+                        match over_budget_at.is_some() && over_budget_at == ticker_deadline {
+                            true => over_budget.lock().unwrap_or_else(|e| e.into_inner()).push(ticker.clone()),
+                            // Cut off by the scan's timeout: count it as never scanned
+                            false => {
+                                timed_out.store(true, Ordering::Relaxed);
+                                started.fetch_sub(1, Ordering::Relaxed);
+                            }
+                        }
+                        return None;
+                    }
+                    outcome => outcome.map_err(|payload| {
+                        span.in_scope(|| {
+                            tracing::error!(
+                                "Scan {} panicked on {}: {}",
+                                query.scan_type,
+                                ticker,
+                                panic_message(payload.as_ref())
+                            )
+                        });
+                    }),
+                };
+                if let (Some(progress), Ok(Some(matches))) = (progress, &outcome) {
+                    progress.matches.fetch_add(matches.len(), Ordering::Relaxed);
+                }
+                match (outcome, sink) {
+                    (Ok(Some(matches)), Some(sink)) => {
+                        streamed.fetch_add(matches.len(), Ordering::Relaxed);
+                        if !sink(matches) {
+                            stopped.store(true, Ordering::Relaxed);
+                        }
+                        Some(Ok(Some(Vec::new())))
+                    }
+                    (outcome, _) => Some(outcome),
+                }
+            };
+            tickers
+                .par_iter()
+                .filter_map(|ticker| {
+                    let cancelled = progress.is_some_and(|p| p.cancelled.load(Ordering::Relaxed));
+                    if cancelled || stopped.load(Ordering::Relaxed) {
+                        stopped.store(true, Ordering::Relaxed);
+                        return None;
+                    }
+                    if timed_out.load(Ordering::Relaxed) || deadline.is_some_and(|d| Instant::now() >= d) {
+                        timed_out.store(true, Ordering::Relaxed);
+                        return None;
+                    }
+                    started.fetch_add(1, Ordering::Relaxed);
+                    let outcome = scan_one(ticker);
+                    if let Some(progress) = progress {
+                        progress.scanned.fetch_add(1, Ordering::Relaxed);
+                    }
+                    outcome
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+    
+    rayon::broadcast(|_| scratch::clear());
+    
+    let scan_errors = outcomes.iter().filter(|o| o.is_err()).count();
+    let results: Vec<Vec<ScanMatch>> = outcomes.into_iter().filter_map(|o| o.ok().flatten()).collect();
     
     let tickers_with_matches = results.len();
     let matches: Vec<ScanMatch> = results.into_iter().flatten().collect();
+    let total_matches = matches.len() + streamed.into_inner();
+    let (cancelled, timed_out) = (stopped.into_inner(), timed_out.into_inner());
+    let tickers_unscanned = match cancelled || timed_out {
+        true => total_tickers - started.into_inner(),
+        false => 0,
+    };
+    let mut tickers_over_budget = over_budget.into_inner().unwrap_or_else(|e| e.into_inner());
+    tickers_over_budget.sort();
     
-    let scan_time_ms = start.elapsed().as_millis() as u64;
+    let elapsed = start.elapsed();
+    let scan_time_ms = elapsed.as_millis() as u64;
+    
+    let scan_label = if def.is_some() {
+        query.scan_type.clone()
+    } else {
+        "unknown".to_string()
+    };
+    metrics::counter!("scans_total", "scan_type" => scan_label.clone()).increment(1);
+    metrics::counter!("scan_matches_total", "scan_type" => scan_label.clone())
+        .increment(total_matches as u64);
+    if scan_errors > 0 {
+        metrics::counter!("scan_errors_total", "scan_type" => scan_label.clone())
+            .increment(scan_errors as u64);
+    }
+    if timed_out {
+        metrics::counter!("scan_timeouts_total", "scan_type" => scan_label.clone()).increment(1);
+        tracing::warn!("Scan {} timed out with {} of {} tickers unscanned", query.scan_type, tickers_unscanned, total_tickers);
+    }
+    if !tickers_over_budget.is_empty() {
+        metrics::counter!("scan_tickers_over_budget_total", "scan_type" => scan_label.clone())
+            .increment(tickers_over_budget.len() as u64);
+    }
+    metrics::histogram!("scan_duration_seconds", "scan_type" => scan_label)
+        .record(elapsed.as_secs_f64());
     
     tracing::info!(
+        matches = total_matches,
+        tickers_with_matches,
+        skipped_short_history = tickers_skipped_short_history,
+        skipped_unadjusted = tickers_skipped_unadjusted,
+        skipped_stale = tickers_skipped_stale,
+        scan_errors,
+        scan_time_ms,
         "Scan complete: {} matches across {} tickers in {}ms",
-        matches.len(),
+        total_matches,
         tickers_with_matches,
         scan_time_ms
     );
@@ -75,81 +623,1389 @@ pub fn run_scan(
         matches,
         total_tickers_scanned: total_tickers,
         tickers_with_matches,
+        scan_errors,
+        tickers_skipped_short_history,
+        tickers_skipped_unadjusted,
+        tickers_skipped_stale,
         scan_time_ms,
+        cancelled,
+        timed_out,
+        tickers_unscanned,
+        tickers_over_budget,
+        run_id: None,
+        warnings: range_warnings(def, data, query),
+    }
+}
+
+/// Reasons a valid date range can come back empty: no loaded data covers it,
+/// or it ends (or starts) before any ticker has the scan's warm-up bars behind
+/// it, e.g. a 200-day scan over the data's first year
+fn range_warnings(
+    def: Option<&ScanDefinition>,
+    data: &HashMap<String, Arc<TickerData>>,
+    query: &ScanQuery,
+) -> Vec<String> {
+    let (from, to) = query.date_range().unwrap_or_default();
+    if from.is_none() && to.is_none() {
+        return Vec::new();
+    }
+    let first = data.values().filter_map(|d| d.days.first()).min();
+    let last = data.values().filter_map(|d| d.days.last()).max();
+    let (Some(&first), Some(&last)) = (first, last) else {
+        return Vec::new();
+    };
+    let range = |from: Option<NaiveDate>, to: Option<NaiveDate>| {
+        format!("{}..{}", from.map(|d| d.to_string()).unwrap_or_default(), to.map(|d| d.to_string()).unwrap_or_default())
+    };
+
+    if from.is_some_and(|f| f > last) || to.is_some_and(|t| t < first) {
+        return vec![format!("No data covers {}; loaded data spans {} to {}", range(from, to), first, last)];
+    }
+
+    let warmup = def.map_or(0, |def| def.min_bars(&query.params).saturating_sub(1));
+    if warmup == 0 {
+        return Vec::new();
     }
+    // The first bar any ticker can match on
+    match data.values().filter_map(|d| d.days.get(warmup)).min() {
+        Some(&earliest) if to.is_some_and(|t| t < earliest) => vec![format!(
+            "{} ends before the scan's {}-bar warm-up completes; the earliest possible match is {}",
+            range(from, to),
+            warmup,
+            earliest
+        )],
+        Some(&earliest) if from.is_some_and(|f| f < earliest) => vec![format!(
+            "{} starts within the scan's {}-bar warm-up; nothing before {} can match",
+            range(from, to),
+            warmup,
+            earliest
+        )],
+        _ => Vec::new(),
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
+
+/// Shared inputs for the query's post-mask filters, resolved once per scan
+#[derive(Clone, Copy)]
+struct TickerFilters<'a> {
+    confirm_func: Option<ScanFn>,
+    benchmark: Option<&'a TickerData>,
 }
 
 /// Scan a single ticker
 fn scan_single_ticker(
     ticker: &str,
     data: &TickerData,
+    def: &ScanDefinition,
+    filters: TickerFilters,
     query: &ScanQuery,
+    date_range: (Option<NaiveDate>, Option<NaiveDate>),
 ) -> Option<Vec<ScanMatch>> {
-    let mask = match query.scan_type.as_str() {
-        "golden_cross" => scan_golden_cross(data),
-        "death_cross" => scan_death_cross(data),
-        "ema_cross" => scan_ema_cross(data, &query.params),
-        "rsi_oversold" => scan_rsi_oversold(data, &query.params),
-        "rsi_overbought" => scan_rsi_overbought(data, &query.params),
-        "obv_breakout" => scan_obv_breakout(data, &query.params),
-        "volume_spike" => scan_volume_spike(data, &query.params),
-        "bollinger_squeeze" => scan_bollinger_squeeze(data, &query.params),
-        "macd_cross_up" => scan_macd_cross_up(data, &query.params),
-        "macd_cross_down" => scan_macd_cross_down(data, &query.params),
-        "price_breakout" => scan_price_breakout(data, &query.params),
-        "bullish_divergence" => scan_bullish_divergence(data, &query.params),
-        "bearish_divergence" => scan_bearish_divergence(data, &query.params),
-        "consolidation_breakout" => scan_consolidation_breakout(data, &query.params),
-        "bullish_engulfing_oversold" => scan_bullish_engulfing_oversold(data, &query.params),
-        "monthly_gap_drop" => scan_monthly_gap_drop(data, &query.params),
-        "custom" => scan_custom(data, &query.params),
-        _ => {
-            if let Some(scan_fn) = generated::get_scan(&query.scan_type) {
-                scan_fn(data, &query.params)
-            } else {
+    scan_ticker_output(ticker, data, def, filters, query, date_range, false)
+        .map(|scan| {
+            // The next ticker on this worker reuses the output's buffers
+            scan.output.recycle();
+            scan.matches
+        })
+        .filter(|matches| !matches.is_empty())
+}
+
+/// A ticker's matches along with the scan's own per-bar output
+#[derive(Debug)]
+pub struct TickerScan {
+    pub matches: Vec<ScanMatch>,
+    /// The scan's mask and series before any query filters; indexed by bar
+    /// when run over the full history, else from the warm-up start
+    pub output: ScanOutput,
+}
+
+/// Scan one ticker outside a bulk run, e.g. to annotate its chart. The
+/// output covers the ticker's whole history so its mask and series line up
+/// with `data.date`. Errors name an unknown scan, a missing benchmark, too
+/// short a history, or a scan that panicked.
+pub fn scan_ticker(
+    registry: &ScanRegistry,
+    ticker: &str,
+    data: &TickerData,
+    query: &ScanQuery,
+    benchmark: Option<&TickerData>,
+) -> Result<TickerScan, String> {
+    let def = registry
+        .get(&query.scan_type)
+        .ok_or_else(|| format!("Unknown scan type: {}", query.scan_type))?;
+    let confirm_func = match &query.confirm_with {
+        Some(confirm) => Some(
+            registry
+                .get(&confirm.scan_type)
+                .ok_or_else(|| format!("Unknown confirm_with scan type: {}", confirm.scan_type))?
+                .func,
+        ),
+        None => None,
+    };
+    if let (Some(symbol), None) = (benchmark_symbol(registry, query), benchmark) {
+        return Err(format!("Benchmark {} is not loaded", symbol));
+    }
+    let min_bars = def.min_bars(&query.params);
+    if data.len() < min_bars {
+        return Err(format!("{} has {} bars; {} needs at least {}", ticker, data.len(), query.scan_type, min_bars));
+    }
+    
+    let adjusted = match query.adjusted {
+        true => Some(data.adjusted().ok_or_else(|| format!("No adjustment data for {}", ticker))?),
+        false => None,
+    };
+    let data = adjusted.as_ref().unwrap_or(data);
+    let adjusted_benchmark = benchmark.filter(|_| query.adjusted).and_then(TickerData::adjusted);
+    let benchmark = adjusted_benchmark.as_ref().or(benchmark);
+    
+    let date_range = query.date_range()?;
+    let filters = TickerFilters { confirm_func, benchmark };
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        scan_ticker_output(ticker, data, def, filters, query, date_range, true)
+    }))
+    .map_err(|payload| format!("Scan {} failed on {}: {}", query.scan_type, ticker, panic_message(payload.as_ref())))?
+    .ok_or_else(|| format!("{} has no bars", ticker))
+}
+
+/// Without `full_history`, a scan with a warm-up runs on just the range it
+/// needs, and a range past the ticker's history returns None early
+fn scan_ticker_output(
+    ticker: &str,
+    data: &TickerData,
+    def: &ScanDefinition,
+    TickerFilters { confirm_func, benchmark }: TickerFilters,
+    query: &ScanQuery,
+    (date_from, date_to): (Option<NaiveDate>, Option<NaiveDate>),
+    full_history: bool,
+) -> Option<TickerScan> {
+    let last = *data.days.last()?;
+    
+    // lookback_days counts back from this ticker's last bar, not the wall
+    // clock, since data files may lag
+    let lookback_from = query
+        .lookback_days
+        .map(|days| last - chrono::Duration::days(days.saturating_sub(1) as i64));
+    let date_from = lookback_from.or(date_from);
+    
+    // History ends before the range starts: nothing to compute
+    if !full_history && date_from.is_some_and(|from| last < from) {
+        return None;
+    }
+    
+    let dedup = query.dedup_within_days.filter(|&n| n > 0);
+    let warmup_bars = if query.include_warmup { 0 } else { def.warmup_bars(&query.params) };
+    
+    // Intraday bars also honor a time of day in the bounds, compared as
+    // RFC 3339 strings; the day checks below cover bare dates
+    let (time_from, time_to) = match data.timeframe {
+        Timeframe::Intraday => (timestamp_bound(query.date_from.as_deref()), timestamp_bound(query.date_to.as_deref())),
+        Timeframe::Daily => (None, None),
+    };
+    
+    // Scans with a declared warm-up only need the range plus that many
+    // earlier bars (and the dedup window before it); mask index j then maps
+    // to bar offset + j
+    let (offset, end) = match def.warmup {
+        Some(warmup) if !full_history && data.days.is_sorted() => {
+            let first = date_from.map_or(0, |from| data.days.partition_point(|&d| d < from));
+            let end = date_to.map_or(data.len(), |to| data.days.partition_point(|&d| d <= to));
+            if first >= end {
                 return None;
             }
+            (first.saturating_sub(warmup(&query.params) + dedup.unwrap_or(0)), end)
         }
+        _ => (0, data.len()),
+    };
+    
+    let sliced;
+    let input = if offset == 0 && end == data.len() {
+        data
+    } else {
+        sliced = data.slice(offset..end);
+        &sliced
+    };
+    let output = match (def.benchmark_output, benchmark, def.output) {
+        (Some(output), Some(bench), _) => output(input, bench, &query.params),
+        (_, _, Some(output)) => output(input, &query.params),
+        _ => ScanOutput::from((def.func)(input, &query.params)),
     };
+    // Scans that don't use pooled buffers are only checked once they return
+    scratch::check_deadline();
+    
+    // Computed on full history: higher-timeframe indicators need more than the warm-up
+    let confirmed = query.confirm.as_ref().map(|c| c.mask(data));
+    let confirmed_with = query
+        .confirm_with
+        .as_ref()
+        .zip(confirm_func)
+        .map(|(c, func)| c.mask(data, func));
+    let in_band = query
+        .correlation_filter
+        .as_ref()
+        .zip(benchmark)
+        .map(|(f, bench)| f.mask(data, bench));
+    // Handlers reject invalid bounds before we get here
+    let adr_filter = query.adr_filter().ok().flatten();
+    let adr = adr_filter.map(|(_, _, period)| adr_pct(&data.high, &data.low, &data.close, period));
+    let adr_in_range = adr_filter.zip(adr.as_ref()).map(|((min, max, _), adr)| {
+        adr.iter().map(|&v| v >= min && v <= max).collect::<Vec<bool>>()
+    });
     
     // Filter by date range if specified
     let mut matches = Vec::new();
+    let mut prev_match: Option<usize> = None;
     
-    for (i, &matched) in mask.iter().enumerate() {
+    for (j, &matched) in output.mask.iter().enumerate() {
         if !matched {
             continue;
         }
         
-        let date = &data.date[i];
+        let i = offset + j;
+        if i < warmup_bars {
+            continue;
+        }
+        let rejected = [&confirmed, &confirmed_with, &in_band, &adr_in_range]
+            .iter()
+            .any(|mask| mask.as_ref().is_some_and(|m| !m[i]));
+        if rejected {
+            continue;
+        }
+        
+        let repeat = dedup.is_some_and(|n| prev_match.is_some_and(|p| i - p <= n));
+        prev_match = Some(i);
+        if repeat {
+            continue;
+        }
         
-        // Check date range
-        if let Some(ref from) = query.date_from {
-            if date < from {
-                continue;
-            }
+        let day = data.days[i];
+        if date_from.is_some_and(|from| day < from) || date_to.is_some_and(|to| day > to) {
+            continue;
         }
-        if let Some(ref to) = query.date_to {
-            if date > to {
-                continue;
-            }
+        let time = &data.date[i];
+        if time_from.as_ref().is_some_and(|from| time < from) || time_to.as_ref().is_some_and(|to| time > to) {
+            continue;
         }
         
+        let mut indicators = output.scores_at(j);
+        if let Some(adr) = &adr {
+            indicators.insert("adr_pct".into(), adr[i]);
+        }
+        let prev_close = i.checked_sub(1).map(|p| data.close[p]);
         matches.push(ScanMatch {
             ticker: ticker.to_string(),
-            date: date.clone(),
+            date: data.date[i].clone(),
             close: data.close[i],
             volume: data.volume[i],
             open: data.open[i],
             high: data.high[i],
             low: data.low[i],
-            indicators: HashMap::new(),
+            bar_index: i,
+            prev_close,
+            change_pct: prev_close.filter(|&p| p > 0.0).map(|p| (data.close[i] - p) / p * 100.0),
+            indicators,
+            explanation: if query.explain { output.explain(j) } else { None },
         });
+        
+        if query.first_only {
+            break;
+        }
     }
     
-    if matches.is_empty() {
-        None
-    } else {
-        Some(matches)
+    Some(TickerScan { matches, output })
+}
+
+// ============================================
+// BUILT-IN SCAN TABLE
+// ============================================
+
+const PERIOD_MIN: Option<f64> = Some(1.0);
+const NON_NEGATIVE: Option<f64> = Some(0.0);
+const PERCENT_MAX: Option<f64> = Some(100.0);
+
+/// Built-in scans, in `/api/scan-types` listing order
+pub const BUILTIN_SCANS: &[BuiltinScan] = &[
+    BuiltinScan {
+        id: "golden_cross",
+        name: "Golden Cross",
+        description: "50 SMA crosses above 200 SMA",
+        params: &[],
+        default_lookback_days: None,
+        func: |data, _| scan_golden_cross(data).mask,
+        warmup: Some(|_| 200),
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(|data, _| scan_golden_cross(data)),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "death_cross",
+        name: "Death Cross",
+        description: "50 SMA crosses below 200 SMA",
+        params: &[],
+        default_lookback_days: None,
+        func: |data, _| scan_death_cross(data).mask,
+        warmup: Some(|_| 200),
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(|data, _| scan_death_cross(data)),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "ema_cross",
+        name: "EMA Cross",
+        description: "Fast EMA crosses slow EMA",
+        params: &[
+            ParamSpec::integer("fast", 12, "Fast EMA period", PERIOD_MIN, None),
+            ParamSpec::integer("slow", 26, "Slow EMA period", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "Cross direction"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_ema_cross(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "fast", 12).max(param_usize(p, "slow", 26)) + 1),
+        warmup_bars: None,
+        output: Some(scan_ema_cross),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "rsi_oversold",
+        name: "RSI Oversold",
+        description: "RSI crosses below threshold",
+        params: &[
+            ParamSpec::integer("period", 14, "RSI period", PERIOD_MIN, None),
+            ParamSpec::number("threshold", Int(30), "Oversold threshold", NON_NEGATIVE, PERCENT_MAX),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_rsi_oversold(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "period", 14) + 2),
+        warmup_bars: None,
+        output: Some(scan_rsi_oversold),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "rsi_overbought",
+        name: "RSI Overbought",
+        description: "RSI crosses above threshold",
+        params: &[
+            ParamSpec::integer("period", 14, "RSI period", PERIOD_MIN, None),
+            ParamSpec::number("threshold", Int(70), "Overbought threshold", NON_NEGATIVE, PERCENT_MAX),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_rsi_overbought(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "period", 14) + 2),
+        warmup_bars: None,
+        output: Some(scan_rsi_overbought),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "obv_breakout",
+        name: "OBV Breakout",
+        description: "OBV breaks above recent high",
+        params: &[ParamSpec::lookback("lookback", Int(20), "Lookback for resistance (bars, or a span like 1m)", PERIOD_MIN)],
+        default_lookback_days: None,
+        func: scan_obv_breakout,
+        warmup: None,
+        min_bars: Some(|p| lookback_bars(p, "lookback", 20) + 1),
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "volume_spike",
+        name: "Volume Spike",
+        description: "Volume exceeds average by multiplier",
+        params: &[
+            ParamSpec::integer("period", 20, "Average volume period", PERIOD_MIN, None),
+            ParamSpec::number("multiplier", Float(2.0), "Volume multiplier", NON_NEGATIVE, None),
+        ],
+        default_lookback_days: Some(365),
+        func: |data, params| scan_volume_spike(data, params).mask,
+        warmup: Some(|p| param_usize(p, "period", 20)),
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(scan_volume_spike),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "macd_cross_up",
+        name: "MACD Cross Up",
+        description: "MACD crosses above signal line",
+        params: &[
+            ParamSpec::integer("fast", 12, "Fast EMA period", PERIOD_MIN, None),
+            ParamSpec::integer("slow", 26, "Slow EMA period", PERIOD_MIN, None),
+            ParamSpec::integer("signal", 9, "Signal line period", PERIOD_MIN, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_macd_cross_up(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "fast", 12).max(param_usize(p, "slow", 26)) + param_usize(p, "signal", 9)),
+        warmup_bars: Some(|p| param_usize(p, "slow", 26) + param_usize(p, "signal", 9)),
+        output: Some(scan_macd_cross_up),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "price_breakout",
+        name: "Price Breakout",
+        description: "Price breaks above N-day high",
+        params: &[ParamSpec::lookback("lookback", Int(252), "Lookback (252 bars, or a span like 52w)", PERIOD_MIN)],
+        default_lookback_days: None,
+        func: |data, params| scan_price_breakout(data, params).mask,
+        warmup: Some(|p| lookback_warmup(p, "lookback", 252)),
+        min_bars: Some(|p| lookback_bars(p, "lookback", 252) + 1),
+        warmup_bars: None,
+        output: Some(scan_price_breakout),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "monthly_gap_drop",
+        name: "Monthly Gap Drop",
+        description: "Month opens below prior close by a % threshold",
+        params: &[
+            ParamSpec::number("gap_pct", Float(5.0), "Gap-down percent (e.g. 5 = -5%)", NON_NEGATIVE, None),
+            ParamSpec::text("candle", "any", "Monthly candle filter: any | bullish | bearish"),
+            ParamSpec::text("event_on", "start", "Match date: start | end (month)"),
+        ],
+        default_lookback_days: None,
+        func: scan_monthly_gap_drop,
+        warmup: None,
+        min_bars: Some(|_| 2),
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "bullish_divergence",
+        name: "Bullish Divergence",
+        description: "Price lower low + OBV higher high",
+        params: &[ParamSpec::lookback("lookback", Int(20), "Lookback (bars, or a span like 1m)", PERIOD_MIN)],
+        default_lookback_days: None,
+        func: scan_bullish_divergence,
+        warmup: None,
+        min_bars: Some(|p| lookback_bars(p, "lookback", 20) + 1),
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "consolidation_breakout",
+        name: "Consolidation Breakout",
+        description: "Breakout from tight range with volume",
+        params: &[
+            ParamSpec::integer("period", 30, "Consolidation period", PERIOD_MIN, None),
+            ParamSpec::number("range_pct", Float(5.0), "Max range percentage", NON_NEGATIVE, None),
+            ParamSpec::number("volume_multiplier", Float(1.5), "Volume multiplier for breakout", NON_NEGATIVE, None),
+        ],
+        default_lookback_days: None,
+        func: scan_consolidation_breakout,
+        warmup: Some(|p| param_usize(p, "period", 30).max(20)),
+        min_bars: None,
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "bullish_engulfing_oversold",
+        name: "Bullish Engulfing (Oversold)",
+        description: "Green candle engulfs red candle after RSI < 30",
+        params: &[
+            ParamSpec::integer("rsi_period", 14, "RSI period", PERIOD_MIN, None),
+            ParamSpec::number("rsi_threshold", Int(30), "RSI oversold threshold", NON_NEGATIVE, PERCENT_MAX),
+            ParamSpec::integer("lookback", 5, "Days to look back for oversold condition", PERIOD_MIN, None),
+        ],
+        default_lookback_days: None,
+        func: scan_bullish_engulfing_oversold,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "rsi_period", 14) + 2),
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "force_index_cross",
+        name: "Force Index Cross",
+        description: "Elder's Force Index crosses its zero line",
+        params: &[
+            ParamSpec::integer("period", 13, "Force Index EMA period", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "Cross direction"),
+        ],
+        default_lookback_days: None,
+        func: scan_force_index_cross,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "period", 13) + 2),
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "eom_cross",
+        name: "Ease of Movement Cross",
+        description: "Ease of Movement crosses its zero line",
+        params: &[
+            ParamSpec::integer("period", 14, "EMV smoothing period", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "Cross direction"),
+        ],
+        default_lookback_days: None,
+        func: scan_eom_cross,
+        warmup: Some(|p| param_usize(p, "period", 14) + 1),
+        min_bars: None,
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "cmo_extreme",
+        name: "CMO Extreme",
+        description: "Chande Momentum crosses above upper or below lower threshold",
+        params: &[
+            ParamSpec::integer("period", 14, "CMO period", PERIOD_MIN, None),
+            ParamSpec::number("upper", Int(50), "Overbought threshold", Some(-100.0), PERCENT_MAX),
+            ParamSpec::number("lower", Int(-50), "Oversold threshold", Some(-100.0), PERCENT_MAX),
+        ],
+        default_lookback_days: None,
+        func: scan_cmo_extreme,
+        warmup: Some(|p| param_usize(p, "period", 14) + 1),
+        min_bars: None,
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "ultimate_oscillator",
+        name: "Ultimate Oscillator",
+        description: "Ultimate Oscillator (7/14/28) crosses below oversold or above overbought",
+        params: &[
+            ParamSpec::integer("short", 7, "Short period", PERIOD_MIN, None),
+            ParamSpec::integer("medium", 14, "Medium period", PERIOD_MIN, None),
+            ParamSpec::integer("long", 28, "Long period", PERIOD_MIN, None),
+            ParamSpec::number("oversold", Int(30), "Oversold threshold", NON_NEGATIVE, PERCENT_MAX),
+            ParamSpec::number("overbought", Int(70), "Overbought threshold", NON_NEGATIVE, PERCENT_MAX),
+        ],
+        default_lookback_days: None,
+        func: scan_ultimate_oscillator,
+        warmup: Some(|p| {
+            let short = param_usize(p, "short", 7);
+            let medium = param_usize(p, "medium", 14);
+            short.max(medium).max(param_usize(p, "long", 28)) + 1
+        }),
+        min_bars: None,
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "trailing_stop_hit",
+        name: "Trailing Stop Hit",
+        description: "Close crosses below the long chandelier exit (or above the short one)",
+        params: &[
+            ParamSpec::integer("period", 22, "Highest high / ATR period", PERIOD_MIN, None),
+            ParamSpec::number("multiplier", Float(3.0), "ATR multiple", NON_NEGATIVE, None),
+            ParamSpec::select("direction", "long", "Stop side (long or short)"),
+        ],
+        default_lookback_days: None,
+        func: scan_trailing_stop_hit,
+        warmup: Some(|p| param_usize(p, "period", 22) + 1),
+        min_bars: None,
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "near_level",
+        name: "Near Pivot Level",
+        description: "Close within a % of a pivot level, or low pierces it and close reclaims it",
+        params: &[
+            ParamSpec::select("level", "pivot", "Level (s3, s2, s1, pivot, r1, r2, r3)"),
+            ParamSpec::number("within_pct", Float(1.0), "Max distance from level (%)", NON_NEGATIVE, None),
+            ParamSpec::select("timeframe", "daily", "Pivot period (daily, weekly, monthly)"),
+            ParamSpec::select("method", "classic", "Pivot formula (classic, fibonacci or camarilla)"),
+            ParamSpec::select("mode", "near", "near or touched_and_reclaimed"),
+        ],
+        default_lookback_days: None,
+        func: scan_near_level,
+        // Enough daily bars to hold the previous full week or month
+        warmup: Some(|p| match p.get("timeframe").and_then(|v| v.as_str()).and_then(Interval::parse) {
+            Some(Interval::Weekly) => 14,
+            Some(Interval::Monthly) => 62,
+            _ => 1,
+        }),
+        min_bars: None,
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "pivot_bounce",
+        name: "Pivot Bounce",
+        description: "Price tests a support (or resistance) pivot level and closes back on the right side of it",
+        params: &[
+            ParamSpec::select("level", "s1", "Level tested: s1-s3 hold as support, r1-r3 as resistance"),
+            ParamSpec::number("tolerance_pct", Float(0.5), "How close (%) the low/high must come to the level", NON_NEGATIVE, None),
+            ParamSpec::select("timeframe", "daily", "Pivot period (daily, weekly, monthly)"),
+            ParamSpec::select("method", "classic", "Pivot formula (classic, fibonacci or camarilla)"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_pivot_bounce(data, params).mask,
+        warmup: Some(|p| match p.get("timeframe").and_then(|v| v.as_str()).and_then(Interval::parse) {
+            Some(Interval::Weekly) => 14,
+            Some(Interval::Monthly) => 62,
+            _ => 1,
+        }),
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(scan_pivot_bounce),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "unusual_volume",
+        name: "Unusual Volume",
+        description: "Volume z-score, trailing-year percentile or multiple of average above a threshold",
+        params: &[
+            ParamSpec::select("method", "zscore", "zscore, percentile or relative"),
+            ParamSpec::integer("period", 20, "Baseline period (zscore, relative)", PERIOD_MIN, None),
+            ParamSpec::number("zscore", Float(3.0), "Min z-score", None, None),
+            ParamSpec::number("percentile", Float(95.0), "Min percentile rank", NON_NEGATIVE, PERCENT_MAX),
+            ParamSpec::integer("percentile_window", 252, "Percentile lookback", PERIOD_MIN, None),
+            ParamSpec::number("multiplier", Float(2.0), "Min multiple of average volume (relative)", NON_NEGATIVE, None),
+            ParamSpec::number("min_dollar_volume", Float(0.0), "Min close × volume", NON_NEGATIVE, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_unusual_volume(data, params).mask,
+        warmup: Some(|p| match p.get("method").and_then(|v| v.as_str()) {
+            Some("percentile") => param_usize(p, "percentile_window", 252),
+            _ => param_usize(p, "period", 20),
+        }),
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(scan_unusual_volume),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "event_gap_continuation",
+        name: "Event Gap Continuation",
+        description: "Within N bars after an event day that gapped up, with every low since above the gap-day low",
+        params: &[
+            ParamSpec::text("event", "earnings", "Event type (file name under data/events)"),
+            ParamSpec::integer("days_after", 5, "Bars after the event day", PERIOD_MIN, None),
+            ParamSpec::number("min_gap_pct", Float(2.0), "Min gap from prior close to event-day open (%)", NON_NEGATIVE, None),
+        ],
+        default_lookback_days: None,
+        func: scan_event_gap_continuation,
+        warmup: Some(|p| param_usize(p, "days_after", 5) + 1),
+        min_bars: None,
+        warmup_bars: Some(|_| 1),
+        output: None,
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "composite",
+        name: "Composite Filter",
+        description: "Every indicator condition in `filters` holds on the bar",
+        params: &[ParamSpec::filters(
+            "filters",
+            "Conditions ANDed per bar: {indicator, period, op (>, <, crosses_above, crosses_below), value}",
+        )],
+        default_lookback_days: None,
+        func: |data, params| scan_composite(data, params).mask,
+        warmup: None,
+        min_bars: None,
+        warmup_bars: Some(composite::warmup_bars),
+        output: Some(scan_composite),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "adr_expansion",
+        name: "ADR Expansion",
+        description: "Day's range exceeds N× the prior average daily range, closing in the top (or bottom) third",
+        params: &[
+            ParamSpec::integer("period", 20, "ADR period", PERIOD_MIN, None),
+            ParamSpec::number("multiplier", Float(2.0), "Min multiple of ADR", NON_NEGATIVE, None),
+            ParamSpec::select("direction", "up", "up: close in the top third, down: bottom third"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_adr_expansion(data, params).mask,
+        warmup: Some(|p| param_usize(p, "period", 20) + 1),
+        min_bars: Some(|p| param_usize(p, "period", 20) + 2),
+        warmup_bars: None,
+        output: Some(scan_adr_expansion),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "strong_close_breakout",
+        name: "Strong Close Breakout",
+        description: "New N-day high that closes near the top of its range on above-average volume",
+        params: &[
+            ParamSpec::lookback("lookback", Int(20), "Bars (or a span like 1m) whose high must be exceeded", PERIOD_MIN),
+            ParamSpec::number("min_close_location", Float(0.75), "Min close location (0 = low, 1 = high)", NON_NEGATIVE, Some(1.0)),
+            ParamSpec::number("multiplier", Float(1.5), "Min multiple of average volume", NON_NEGATIVE, None),
+            ParamSpec::integer("volume_period", 20, "Average volume period", PERIOD_MIN, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_strong_close_breakout(data, params).mask,
+        warmup: Some(|p| lookback_warmup(p, "lookback", 20).max(param_usize(p, "volume_period", 20))),
+        min_bars: Some(|p| lookback_bars(p, "lookback", 20).max(param_usize(p, "volume_period", 20)) + 1),
+        warmup_bars: None,
+        output: Some(scan_strong_close_breakout),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "trix_zero_cross",
+        name: "TRIX Zero Cross",
+        description: "TRIX (rate of change of a triple-smoothed EMA) crosses its zero line",
+        params: &[
+            ParamSpec::integer("period", 15, "EMA period, applied three times", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "Cross direction"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_trix_zero_cross(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| 3 * param_usize(p, "period", 15)),
+        warmup_bars: None,
+        output: Some(scan_trix_zero_cross),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "mass_index_bulge",
+        name: "Mass Index Bulge",
+        description: "Mass Index reversal bulge: rises above 27, then falls back below 26.5",
+        params: &[
+            ParamSpec::integer("ema_period", 9, "EMA period of the high-low range, applied twice", PERIOD_MIN, None),
+            ParamSpec::integer("sum_period", 25, "Bars of range ratio summed", PERIOD_MIN, None),
+            ParamSpec::number("bulge", Float(27.0), "Level the index must rise above to set up", NON_NEGATIVE, None),
+            ParamSpec::number("trigger", Float(26.5), "Level it then falls below to signal", NON_NEGATIVE, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_mass_index_bulge(data, params).mask,
+        // The setup can date from any earlier bar
+        warmup: None,
+        min_bars: Some(|p| 2 * (param_usize(p, "ema_period", 9).max(1) - 1) + param_usize(p, "sum_period", 25)),
+        warmup_bars: None,
+        output: Some(scan_mass_index_bulge),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "dpo_cross",
+        name: "DPO Zero Cross",
+        description: "Detrended Price Oscillator crosses its zero line",
+        params: &[
+            ParamSpec::integer("period", 20, "SMA period; price is taken period / 2 + 1 bars back", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "Cross direction"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_dpo_cross(data, params).mask,
+        warmup: Some(|p| param_usize(p, "period", 20)),
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(scan_dpo_cross),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "fisher_cross",
+        name: "Fisher Cross",
+        description: "Fisher Transform crosses its one-bar-lag trigger",
+        params: &[
+            ParamSpec::integer("period", 10, "Median price range period", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "Cross direction"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_fisher_cross(data, params).mask,
+        // Recursive smoothing, so no warm-up slicing; the first cross can
+        // come once the trigger has two values
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "period", 10) + 2),
+        warmup_bars: None,
+        output: Some(scan_fisher_cross),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "kst_cross",
+        name: "KST Cross",
+        description: "Know Sure Thing (weighted sum of four smoothed ROCs) crosses its signal line",
+        params: &[
+            ParamSpec::number("roc1", Int(10), "ROC period, leg 1", PERIOD_MIN, None),
+            ParamSpec::number("roc2", Int(15), "ROC period, leg 2", PERIOD_MIN, None),
+            ParamSpec::number("roc3", Int(20), "ROC period, leg 3", PERIOD_MIN, None),
+            ParamSpec::number("roc4", Int(30), "ROC period, leg 4", PERIOD_MIN, None),
+            ParamSpec::number("smooth1", Int(10), "SMA smoothing of leg 1", PERIOD_MIN, None),
+            ParamSpec::number("smooth2", Int(10), "SMA smoothing of leg 2", PERIOD_MIN, None),
+            ParamSpec::number("smooth3", Int(10), "SMA smoothing of leg 3", PERIOD_MIN, None),
+            ParamSpec::number("smooth4", Int(15), "SMA smoothing of leg 4", PERIOD_MIN, None),
+            ParamSpec::number("weight1", Float(1.0), "Weight of leg 1", None, None),
+            ParamSpec::number("weight2", Float(2.0), "Weight of leg 2", None, None),
+            ParamSpec::number("weight3", Float(3.0), "Weight of leg 3", None, None),
+            ParamSpec::number("weight4", Float(4.0), "Weight of leg 4", None, None),
+            ParamSpec::integer("signal", 9, "Signal line SMA period", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "Cross direction"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_kst_cross(data, params).mask,
+        warmup: Some(kst_min_bars),
+        min_bars: Some(kst_min_bars),
+        warmup_bars: None,
+        output: Some(scan_kst_cross),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "coppock_turn",
+        name: "Coppock Turn",
+        description: "Coppock Curve turns up from below zero (long-term buy, on monthly bars by default)",
+        params: &[
+            ParamSpec::integer("roc_long", 14, "Longer ROC period, in timeframe bars", PERIOD_MIN, None),
+            ParamSpec::integer("roc_short", 11, "Shorter ROC period, in timeframe bars", PERIOD_MIN, None),
+            ParamSpec::integer("wma_period", 10, "WMA period over the ROC sum", PERIOD_MIN, None),
+            ParamSpec::select("timeframe", "monthly", "Bars the curve is computed on (daily, weekly, monthly)"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_coppock_turn(data, params).mask,
+        // Resampled buckets shift with the slice start, so it runs on full history
+        warmup: None,
+        min_bars: Some(coppock_min_bars),
+        warmup_bars: None,
+        output: Some(scan_coppock_turn),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "ppo_cross",
+        name: "PPO Cross",
+        description: "Percentage Price Oscillator crosses its signal line",
+        params: &[
+            ParamSpec::integer("fast", 12, "Fast EMA period", PERIOD_MIN, None),
+            ParamSpec::integer("slow", 26, "Slow EMA period", PERIOD_MIN, None),
+            ParamSpec::integer("signal", 9, "Signal line period", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "Cross direction"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_ppo_cross(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "fast", 12).max(param_usize(p, "slow", 26)) + param_usize(p, "signal", 9)),
+        warmup_bars: Some(|p| param_usize(p, "slow", 26) + param_usize(p, "signal", 9)),
+        output: Some(scan_ppo_cross),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "nr_breakout",
+        name: "Narrow Range",
+        description: "Narrowest range of the last N bars (NR4/NR7), or the next bar breaking out of it",
+        params: &[
+            ParamSpec::integer("period", 7, "Bars the range must be the narrowest of (4 = NR4, 7 = NR7)", Some(2.0), None),
+            ParamSpec::select("inside_bar", "any", "any, or required: the NR bar must also be inside the prior bar"),
+            ParamSpec::select("mode", "bar", "bar: the NR bar itself, breakout: the next bar if it breaks the NR bar's range"),
+            ParamSpec::select("direction", "up", "Breakout side: up (above the NR high), down (below its low) or either"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_nr_breakout(data, params).mask,
+        // A breakout looks back to the NR bar, whose inside-bar check looks back one more
+        warmup: Some(|p| param_usize(p, "period", 7) + 1),
+        min_bars: Some(|p| param_usize(p, "period", 7) + 1),
+        warmup_bars: Some(|p| param_usize(p, "period", 7).saturating_sub(1)),
+        output: Some(scan_nr_breakout),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "turtle",
+        name: "Turtle Breakout",
+        description: "Close breaks the prior entry-period Donchian high (entry) or exit-period low (exit)",
+        params: &[
+            ParamSpec::integer("entry_period", 20, "Donchian period for entries", PERIOD_MIN, None),
+            ParamSpec::integer("exit_period", 10, "Donchian period for exits", PERIOD_MIN, None),
+            ParamSpec::select("signal", "entry", "entry: new entry-period high, exit: new exit-period low"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_turtle(data, params).mask,
+        warmup: Some(|p| param_usize(p, "entry_period", 20).max(param_usize(p, "exit_period", 10))),
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(scan_turtle),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "vortex_cross",
+        name: "Vortex Cross",
+        description: "VI+ crosses VI- (Vortex Indicator)",
+        params: &[
+            ParamSpec::integer("period", 14, "Vortex period", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "up: VI+ crosses above VI-, down: below"),
+            ParamSpec::number("min_separation", Float(0.0), "Min |VI+ - VI-| on the cross bar", NON_NEGATIVE, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_vortex_cross(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "period", 14) + 2),
+        warmup_bars: None,
+        output: Some(scan_vortex_cross),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "near_high",
+        name: "Near High",
+        description: "Close within N% of its highest close over the lookback (52 weeks by default)",
+        params: &[
+            ParamSpec::lookback("lookback", Int(252), "Lookback (252 bars, or a span like 52w)", PERIOD_MIN),
+            ParamSpec::number("within_pct", Float(5.0), "Max % below the high", NON_NEGATIVE, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_near_high(data, params).mask,
+        warmup: Some(|p| lookback_warmup(p, "lookback", 252)),
+        min_bars: Some(|p| lookback_bars(p, "lookback", 252) + 1),
+        warmup_bars: None,
+        output: Some(scan_near_high),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "elder_impulse_turn",
+        name: "Elder Impulse Turn",
+        description: "Elder impulse leaves red (-1) for blue or green, or leaves green (+1) going down",
+        params: &[
+            ParamSpec::integer("ema_period", 13, "Trend EMA period", PERIOD_MIN, None),
+            ParamSpec::integer("fast", 12, "MACD fast EMA period", PERIOD_MIN, None),
+            ParamSpec::integer("slow", 26, "MACD slow EMA period", PERIOD_MIN, None),
+            ParamSpec::integer("signal", 9, "MACD signal line period", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "up: out of red, down: out of green"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_elder_impulse_turn(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| {
+            let macd_bars = param_usize(p, "fast", 12).max(param_usize(p, "slow", 26)) + param_usize(p, "signal", 9);
+            macd_bars.max(param_usize(p, "ema_period", 13)) + 2
+        }),
+        warmup_bars: None,
+        output: Some(scan_elder_impulse_turn),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "bear_power_divergence",
+        name: "Bear Power Divergence",
+        description: "Price lower low while Elder bear power is negative and makes a higher low",
+        params: &[
+            ParamSpec::lookback("lookback", Int(20), "Lookback (bars, or a span like 1m)", PERIOD_MIN),
+            ParamSpec::integer("ema_period", 13, "Bear power EMA period", PERIOD_MIN, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_bear_power_divergence(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| lookback_bars(p, "lookback", 20) + param_usize(p, "ema_period", 13)),
+        warmup_bars: None,
+        output: Some(scan_bear_power_divergence),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "beta_filter",
+        name: "Beta Filter",
+        description: "Rolling beta of daily returns to a benchmark above or below a threshold",
+        params: &[
+            ParamSpec::text("benchmark", "SPY", "Benchmark ticker"),
+            ParamSpec::integer("period", 60, "Beta period (returns)", Some(2.0), None),
+            ParamSpec::number("threshold", Float(1.0), "Beta threshold", None, None),
+            ParamSpec::select("direction", "above", "above or below the threshold"),
+        ],
+        default_lookback_days: None,
+        // Without the benchmark there's nothing to compare
+        func: |data, _| vec![false; data.len()],
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "period", 60) + 1),
+        warmup_bars: None,
+        output: None,
+        benchmark_output: Some(scan_beta_filter),
+        listed: true,
+    },
+    BuiltinScan {
+        id: "drawdown_filter",
+        name: "Drawdown",
+        description: "Close at least N% below its all-time (loaded) high, or recovering back above that",
+        params: &[
+            ParamSpec::number("threshold", Float(20.0), "Drawdown %", NON_NEGATIVE, None),
+            ParamSpec::select("mode", "dip", "dip: every bar at least threshold% down, recover: the bar it climbs back under"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_drawdown_filter(data, params).mask,
+        // The peak is over the whole history, so there's no warm-up to slice
+        warmup: None,
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(scan_drawdown_filter),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "deep_drawdown",
+        name: "Deep Drawdown",
+        description: "Close more than N% below its 52-week (or all-time) high",
+        params: &[
+            ParamSpec::number("threshold", Float(30.0), "Drawdown %", NON_NEGATIVE, None),
+            ParamSpec::select("basis", "rolling", "High to measure from: rolling (last `period` bars) or all_time"),
+            ParamSpec::integer("period", 252, "Rolling high window, also used for max drawdown (252 = 52 weeks)", PERIOD_MIN, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_deep_drawdown(data, params).mask,
+        // An all-time peak needs the whole history, so there's no warm-up to slice
+        warmup: None,
+        min_bars: Some(drawdown_min_bars),
+        warmup_bars: None,
+        output: Some(scan_deep_drawdown),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "drawdown_recovery",
+        name: "Drawdown Recovery",
+        description: "Drawdown climbs back under Y% after being at least X% deep",
+        params: &[
+            ParamSpec::number("dip_pct", Float(20.0), "Drawdown % that arms the scan", NON_NEGATIVE, None),
+            ParamSpec::number("recover_pct", Float(10.0), "Drawdown % to recover to (below dip_pct)", NON_NEGATIVE, None),
+            ParamSpec::select("basis", "rolling", "High to measure from: rolling (last `period` bars) or all_time"),
+            ParamSpec::integer("period", 252, "Rolling high window (252 = 52 weeks)", PERIOD_MIN, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_drawdown_recovery(data, params).mask,
+        // The armed state can date from any earlier bar
+        warmup: None,
+        min_bars: Some(drawdown_min_bars),
+        warmup_bars: None,
+        output: Some(scan_drawdown_recovery),
+        benchmark_output: None,
+        listed: true,
+    },
+    // Runnable by id but not shown in the UI list
+    BuiltinScan {
+        id: "bollinger_squeeze",
+        name: "Bollinger Squeeze",
+        description: "Band width below a % of the middle band",
+        params: &[
+            ParamSpec::integer("period", 20, "Bollinger period", PERIOD_MIN, None),
+            ParamSpec::number("std", Float(2.0), "Standard deviations", NON_NEGATIVE, None),
+            ParamSpec::number("squeeze_pct", Float(5.0), "Max band width percent", NON_NEGATIVE, None),
+        ],
+        default_lookback_days: Some(365),
+        func: scan_bollinger_squeeze,
+        warmup: Some(|p| param_usize(p, "period", 20)),
+        min_bars: None,
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: false,
+    },
+    BuiltinScan {
+        id: "macd_cross_down",
+        name: "MACD Cross Down",
+        description: "MACD crosses below signal line",
+        params: &[
+            ParamSpec::integer("fast", 12, "Fast EMA period", PERIOD_MIN, None),
+            ParamSpec::integer("slow", 26, "Slow EMA period", PERIOD_MIN, None),
+            ParamSpec::integer("signal", 9, "Signal line period", PERIOD_MIN, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_macd_cross_down(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "fast", 12).max(param_usize(p, "slow", 26)) + param_usize(p, "signal", 9)),
+        warmup_bars: Some(|p| param_usize(p, "slow", 26) + param_usize(p, "signal", 9)),
+        output: Some(scan_macd_cross_down),
+        benchmark_output: None,
+        listed: false,
+    },
+    BuiltinScan {
+        id: "bearish_divergence",
+        name: "Bearish Divergence",
+        description: "Price higher high + OBV lower low",
+        params: &[ParamSpec::lookback("lookback", Int(20), "Lookback (bars, or a span like 1m)", PERIOD_MIN)],
+        default_lookback_days: None,
+        func: scan_bearish_divergence,
+        warmup: None,
+        min_bars: Some(|p| lookback_bars(p, "lookback", 20) + 1),
+        warmup_bars: None,
+        output: None,
+        benchmark_output: None,
+        listed: false,
+    },
+    BuiltinScan {
+        id: "custom",
+        name: "Custom",
+        description: "AND of built-in conditions listed in `conditions`",
+        params: &[],
+        default_lookback_days: None,
+        func: |data, params| scan_custom(data, params).mask,
+        warmup: None,
+        min_bars: None,
+        warmup_bars: Some(custom_warmup_bars),
+        output: Some(scan_custom),
+        benchmark_output: None,
+        listed: false,
+    },
+    BuiltinScan {
+        id: "crossover",
+        name: "Crossover",
+        description: "One indicator crosses another, e.g. ema(9) over ema(21) or close over sma(50)",
+        params: &[
+            ParamSpec::series("left", "ema(9)", "Series that crosses: an indicator or price column, e.g. ema(9), close"),
+            ParamSpec::series("right", "ema(21)", "Series crossed, e.g. sma(50)"),
+            ParamSpec::select("direction", "up", "Cross direction"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_crossover(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| {
+            let (left, right) = crossover_sides(p);
+            left.warmup_bars().max(right.warmup_bars()) + 1
+        }),
+        warmup_bars: None,
+        output: Some(scan_crossover),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "price_vs_ma",
+        name: "Price vs Moving Average",
+        description: "Close above or below a moving average on every such bar, or crossing it",
+        params: &[
+            ParamSpec::select("ma_type", "sma", "Moving average (sma or ema)"),
+            ParamSpec::integer("period", 200, "Moving average period", PERIOD_MIN, None),
+            ParamSpec::select("relation", "above", "above, below, crosses_above or crosses_below"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_price_vs_ma(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| {
+            let crosses = p.get("relation").and_then(|v| v.as_str()).is_some_and(|r| r.starts_with("crosses"));
+            param_usize(p, "period", 200) + crosses as usize
+        }),
+        warmup_bars: None,
+        output: Some(scan_price_vs_ma),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "big_mover",
+        name: "Big Mover",
+        description: "Bar moved more than N% close to close, open to close, or on the gap",
+        params: &[
+            ParamSpec::number("min_change_pct", Float(5.0), "Min move (%)", NON_NEGATIVE, None),
+            ParamSpec::select("direction", "any", "up, down or any"),
+            ParamSpec::select("basis", "close_to_close", "close_to_close, open_to_close or gap"),
+            ParamSpec::number("min_dollar_volume", Float(0.0), "Min close × volume", NON_NEGATIVE, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_big_mover(data, params).mask,
+        warmup: Some(|_| 1),
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(scan_big_mover),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "streak",
+        name: "Streak",
+        description: "Bar completing N consecutive higher (or lower) closes",
+        params: &[
+            ParamSpec::integer("count", 3, "Consecutive closes in the run", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "up (higher closes) or down (lower closes)"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_streak(data, params).mask,
+        // One bar past the run, to tell it from a longer one
+        warmup: Some(|p| param_usize(p, "count", 3) + 1),
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(scan_streak),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "bb_lower_touch_reversal",
+        name: "Bollinger Lower Band Reversal",
+        description: "Low pierces the lower Bollinger Band but the close finishes back inside, optionally with RSI below a level",
+        params: &[
+            ParamSpec::integer("period", 20, "Bollinger period", PERIOD_MIN, None),
+            ParamSpec::number("std", Float(2.0), "Standard deviations", NON_NEGATIVE, None),
+            ParamSpec::integer("rsi_period", 14, "RSI period", PERIOD_MIN, None),
+            ParamSpec::number("max_rsi", Float(100.0), "Max RSI on the bar (100 for no RSI filter)", NON_NEGATIVE, PERCENT_MAX),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_bb_lower_touch_reversal(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "period", 20).max(param_usize(p, "rsi_period", 14) + 1)),
+        warmup_bars: None,
+        output: Some(scan_bb_lower_touch_reversal),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "bb_upper_breakout",
+        name: "Bollinger Upper Band Breakout",
+        description: "Close crosses above the upper Bollinger Band while the bandwidth is above its own average",
+        params: &[
+            ParamSpec::integer("period", 20, "Bollinger period", PERIOD_MIN, None),
+            ParamSpec::number("std", Float(2.0), "Standard deviations", NON_NEGATIVE, None),
+            ParamSpec::integer("bandwidth_period", 20, "Bars the bandwidth is averaged over", PERIOD_MIN, None),
+            ParamSpec::number("min_expansion", Float(1.0), "Min bandwidth as a multiple of its average", NON_NEGATIVE, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_bb_upper_breakout(data, params).mask,
+        warmup: Some(|p| param_usize(p, "period", 20) + param_usize(p, "bandwidth_period", 20) - 1),
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(scan_bb_upper_breakout),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "bb_walk",
+        name: "Bollinger Band Walk",
+        description: "Close above the upper Bollinger Band (or below the lower) for N bars in a row",
+        params: &[
+            ParamSpec::integer("period", 20, "Bollinger period", PERIOD_MIN, None),
+            ParamSpec::number("std", Float(2.0), "Standard deviations", NON_NEGATIVE, None),
+            ParamSpec::integer("bars", 3, "Consecutive closes outside the band", PERIOD_MIN, None),
+            ParamSpec::select("direction", "up", "up (upper band) or down (lower band)"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_bb_walk(data, params).mask,
+        warmup: Some(|p| param_usize(p, "period", 20) + param_usize(p, "bars", 3) - 1),
+        min_bars: None,
+        warmup_bars: None,
+        output: Some(scan_bb_walk),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "ma_extension",
+        name: "Moving Average Extension",
+        description: "Close stretched more than N% above (or below) its moving average",
+        params: &[
+            ParamSpec::select("ma_type", "ema", "Moving average (sma or ema)"),
+            ParamSpec::integer("period", 20, "Moving average period", PERIOD_MIN, None),
+            ParamSpec::number("threshold_pct", Float(10.0), "Min distance from the average (%)", NON_NEGATIVE, None),
+            ParamSpec::select("direction", "up", "up (above the average) or down (below)"),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_ma_extension(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "period", 20)),
+        warmup_bars: None,
+        output: Some(scan_ma_extension),
+        benchmark_output: None,
+        listed: true,
+    },
+    BuiltinScan {
+        id: "opening_range_breakout",
+        name: "Opening Range Breakout",
+        description: "Intraday close breaking above the session's first N-minute high on volume",
+        params: &[
+            ParamSpec::integer("range_minutes", 30, "Opening range length (minutes)", PERIOD_MIN, None),
+            ParamSpec::number("min_volume_ratio", Float(1.5), "Min volume vs its average", NON_NEGATIVE, None),
+            ParamSpec::integer("volume_period", 20, "Volume average period (bars)", PERIOD_MIN, None),
+            ParamSpec::number("min_gap_pct", Float(0.0), "Min gap up at the session open (%), 0 for any", NON_NEGATIVE, None),
+        ],
+        default_lookback_days: None,
+        func: |data, params| scan_opening_range_breakout(data, params).mask,
+        warmup: None,
+        min_bars: Some(|p| param_usize(p, "volume_period", 20)),
+        warmup_bars: None,
+        output: Some(scan_opening_range_breakout),
+        benchmark_output: None,
+        listed: true,
+    },
+];
+
+/// Warm-up of a `custom` scan: the longest of its conditions' own
+fn custom_warmup_bars(params: &HashMap<String, serde_json::Value>) -> usize {
+    let conditions = params.get("conditions").and_then(|v| v.as_array());
+    conditions
+        .into_iter()
+        .flatten()
+        .filter_map(|c| BUILTIN_SCANS.iter().find(|scan| Some(scan.id) == c.as_str()))
+        .map(|scan| ScanDefinition::from(scan).warmup_bars(params))
+        .max()
+        .unwrap_or(0)
+}
+
+#[inline]
+fn param_usize(params: &HashMap<String, serde_json::Value>, name: &str, default: u64) -> usize {
+    params.get(name).and_then(|v| v.as_u64()).unwrap_or(default) as usize
+}
+
+/// A lookback param (a bar count or a span like "6m"), `default` bars when
+/// absent or unparseable
+fn param_lookback(params: &HashMap<String, serde_json::Value>, name: &str, default: usize) -> Lookback {
+    params
+        .get(name)
+        .and_then(|v| Lookback::parse(v).ok())
+        .unwrap_or(Lookback::Bars(default))
+}
+
+/// The `left` and `right` series of a `crossover` scan, ema(9) and ema(21)
+/// when absent or unparseable
+fn crossover_sides(params: &HashMap<String, serde_json::Value>) -> (composite::SeriesRef, composite::SeriesRef) {
+    let side = |name, indicator: &str, period| {
+        params.get(name).and_then(|v| composite::SeriesRef::parse(v).ok()).unwrap_or(composite::SeriesRef {
+            indicator: indicator.into(),
+            period: Some(period),
+        })
+    };
+    (side("left", "ema", 9), side("right", "ema", 21))
+}
+
+/// Warm-up of a lookback param: its bar count, or for a calendar span the
+/// whole history, since the bars a span covers vary by ticker and date
+fn lookback_warmup(params: &HashMap<String, serde_json::Value>, name: &str, default: usize) -> usize {
+    match param_lookback(params, name, default) {
+        Lookback::Bars(n) => n,
+        _ => usize::MAX / 2,
+    }
+}
+
+/// Bars a lookback param needs: its bar count, or 1 for a calendar span,
+/// whose windows stay empty until the history reaches back far enough
+fn lookback_bars(params: &HashMap<String, serde_json::Value>, name: &str, default: usize) -> usize {
+    match param_lookback(params, name, default) {
+        Lookback::Bars(n) => n,
+        _ => 1,
     }
 }
 
@@ -157,19 +2013,28 @@ fn scan_single_ticker(
 // SCAN IMPLEMENTATIONS
 // ============================================
 
-fn scan_golden_cross(data: &TickerData) -> Vec<bool> {
+/// Mask from crossing `fast` over `slow`, explained by both series
+fn cross_output(mask: Vec<bool>, fast: (&str, Vec<f64>), slow: (&str, Vec<f64>)) -> ScanOutput {
+    ScanOutput {
+        mask,
+        series: vec![(fast.0.to_string(), fast.1), (slow.0.to_string(), slow.1)],
+        ..Default::default()
+    }
+}
+
+fn scan_golden_cross(data: &TickerData) -> ScanOutput {
     let sma_50 = sma(&data.close, 50);
     let sma_200 = sma(&data.close, 200);
-    crossed_above(&sma_50, &sma_200)
+    cross_output(crossed_above(&sma_50, &sma_200), ("sma_50", sma_50), ("sma_200", sma_200))
 }
 
-fn scan_death_cross(data: &TickerData) -> Vec<bool> {
+fn scan_death_cross(data: &TickerData) -> ScanOutput {
     let sma_50 = sma(&data.close, 50);
     let sma_200 = sma(&data.close, 200);
-    crossed_below(&sma_50, &sma_200)
+    cross_output(crossed_below(&sma_50, &sma_200), ("sma_50", sma_50), ("sma_200", sma_200))
 }
 
-fn scan_ema_cross(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+fn scan_ema_cross(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
     let fast = params.get("fast").and_then(|v| v.as_u64()).unwrap_or(12) as usize;
     let slow = params.get("slow").and_then(|v| v.as_u64()).unwrap_or(26) as usize;
     let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
@@ -177,48 +2042,270 @@ fn scan_ema_cross(data: &TickerData, params: &HashMap<String, serde_json::Value>
     let ema_fast = ema(&data.close, fast);
     let ema_slow = ema(&data.close, slow);
     
-    if direction == "up" {
+    let mask = if direction == "up" {
         crossed_above(&ema_fast, &ema_slow)
     } else {
         crossed_below(&ema_fast, &ema_slow)
+    };
+    cross_output(mask, ("ema_fast", ema_fast), ("ema_slow", ema_slow))
+}
+
+fn scan_crossover(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let (left, right) = crossover_sides(params);
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
+
+    let (left_values, right_values) = (left.compute(data), right.compute(data));
+    let mask = if direction == "up" {
+        crossed_above(&left_values, &right_values)
+    } else {
+        crossed_below(&left_values, &right_values)
+    };
+    cross_output(mask, (&left.to_string(), left_values), (&right.to_string(), right_values))
+}
+
+/// The `ma_type` (sma or ema) `period` moving average of close, named like
+/// `sma_200`, and close's percent distance from it (NaN where it isn't positive)
+fn param_ma(
+    data: &TickerData,
+    params: &HashMap<String, serde_json::Value>,
+    (default_type, default_period): (&str, usize),
+) -> (String, Vec<f64>, Vec<f64>) {
+    let period = param_usize(params, "period", default_period as u64);
+    let ma_type = params.get("ma_type").and_then(|v| v.as_str()).unwrap_or(default_type);
+    let (name, ma) = match ma_type {
+        "ema" => (format!("ema_{}", period), ema(&data.close, period)),
+        _ => (format!("sma_{}", period), sma(&data.close, period)),
+    };
+    let pct_from_ma = data
+        .close
+        .iter()
+        .zip(&ma)
+        .map(|(&c, &m)| if m > 0.0 { (c - m) / m * 100.0 } else { f64::NAN })
+        .collect();
+    (name, ma, pct_from_ma)
+}
+
+fn scan_price_vs_ma(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let relation = params.get("relation").and_then(|v| v.as_str()).unwrap_or("above");
+    let (name, ma, pct_from_ma) = param_ma(data, params, ("sma", 200));
+
+    let distance: Vec<f64> = data.close.iter().zip(&ma).map(|(&c, &m)| c - m).collect();
+    let mask = match relation {
+        "below" => below(&distance, 0.0),
+        "crosses_above" => crossed_above(&data.close, &ma),
+        "crosses_below" => crossed_below(&data.close, &ma),
+        _ => above(&distance, 0.0),
+    };
+    ScanOutput {
+        mask,
+        scores: vec![("pct_from_ma".into(), pct_from_ma)],
+        series: vec![("close".into(), data.close.clone()), (name, ma)],
+        ..Default::default()
+    }
+}
+
+fn scan_ma_extension(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let threshold = params.get("threshold_pct").and_then(|v| v.as_f64()).unwrap_or(10.0);
+    let down = params.get("direction").and_then(|v| v.as_str()) == Some("down");
+    let (name, ma, pct_from_ma) = param_ma(data, params, ("ema", 20));
+
+    let mask = if down { below(&pct_from_ma, -threshold) } else { above(&pct_from_ma, threshold) };
+    ScanOutput {
+        mask,
+        scores: vec![("pct_from_ma".into(), pct_from_ma.clone())],
+        series: vec![("close".into(), data.close.clone()), (name, ma), ("pct_from_ma".into(), pct_from_ma)],
+        thresholds: vec![("threshold_pct".into(), if down { -threshold } else { threshold })],
+        ..Default::default()
+    }
+}
+
+/// Each session's opening range high and low, set on the bars after the range
+/// (NaN within it), and the session's open vs the prior session's last close
+/// (%) on all its bars. Sessions are UTC days, as when resampling; a session
+/// that ends inside its range gets none.
+fn opening_ranges(data: &TickerData, range: chrono::Duration) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let n = data.len();
+    let (mut high, mut low, mut gap_pct) = (vec![f64::NAN; n], vec![f64::NAN; n], vec![f64::NAN; n]);
+    let stamps: Vec<_> = data.date.iter().map(|d| parse_timestamp(d)).collect();
+
+    let mut start = 0;
+    while start < n {
+        let end = start + data.days[start..].iter().position(|d| *d != data.days[start]).unwrap_or(n - start);
+        if start > 0 && data.close[start - 1] > 0.0 {
+            let prior = data.close[start - 1];
+            gap_pct[start..end].fill((data.open[start] - prior) / prior * 100.0);
+        }
+        if let Some(cutoff) = stamps[start].map(|t| t + range) {
+            let range_end = start + stamps[start..end].iter().take_while(|t| t.is_some_and(|t| t < cutoff)).count();
+            if range_end < end {
+                high[range_end..end].fill(data.high[start..range_end].iter().copied().fold(f64::NAN, f64::max));
+                low[range_end..end].fill(data.low[start..range_end].iter().copied().fold(f64::NAN, f64::min));
+            }
+        }
+        start = end;
+    }
+    (high, low, gap_pct)
+}
+
+/// Matches a close that crosses above the opening range high, so a session
+/// matches again only after falling back into the range. Daily tickers
+/// never match.
+fn scan_opening_range_breakout(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let range_minutes = param_usize(params, "range_minutes", 30);
+    let min_volume_ratio = params.get("min_volume_ratio").and_then(|v| v.as_f64()).unwrap_or(1.5);
+    let volume_period = param_usize(params, "volume_period", 20);
+    let min_gap = params.get("min_gap_pct").and_then(|v| v.as_f64()).unwrap_or(0.0);
+
+    let n = data.len();
+    if data.timeframe != Timeframe::Intraday {
+        return ScanOutput::from(vec![false; n]);
+    }
+    let (range_high, range_low, gap_pct) = opening_ranges(data, chrono::Duration::minutes(range_minutes as i64));
+    let ratio = volume_ratio(&data.volume, volume_period);
+    let breakout_pct: Vec<f64> = data.close.iter().zip(&range_high).map(|(c, h)| (c - h) / h * 100.0).collect();
+
+    // The bar before a session's first breakout candidate is in its range,
+    // where the high is NaN
+    let mask = (0..n)
+        .map(|i| {
+            let crossed = data.close[i] > range_high[i] && (range_high[i - 1].is_nan() || data.close[i - 1] <= range_high[i - 1]);
+            crossed && ratio[i] >= min_volume_ratio && (min_gap <= 0.0 || gap_pct[i] >= min_gap)
+        })
+        .collect();
+    ScanOutput {
+        mask,
+        scores: vec![
+            ("breakout_pct".into(), breakout_pct),
+            ("volume_ratio".into(), ratio.clone()),
+            ("gap_pct".into(), gap_pct.clone()),
+        ],
+        series: vec![
+            ("close".into(), data.close.clone()),
+            ("range_high".into(), range_high),
+            ("range_low".into(), range_low),
+            ("volume_ratio".into(), ratio),
+            ("gap_pct".into(), gap_pct),
+        ],
+        thresholds: vec![("min_volume_ratio".into(), min_volume_ratio), ("min_gap_pct".into(), min_gap)],
+        ..Default::default()
+    }
+}
+
+/// A bar whose base price (prior close, or the open) is zero or missing gets
+/// no move rather than an infinite one; such bars are counted in the
+/// `big_mover_bad_base_bars_total` metric
+fn scan_big_mover(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let min_change = params.get("min_change_pct").and_then(|v| v.as_f64()).unwrap_or(5.0);
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("any");
+    let basis = params.get("basis").and_then(|v| v.as_str()).unwrap_or("close_to_close");
+    let min_dollar_volume = params.get("min_dollar_volume").and_then(|v| v.as_f64()).unwrap_or(0.0);
+
+    let n = data.len();
+    // (base, price) per bar: the move is price vs base
+    let legs: Vec<Option<(f64, f64)>> = (0..n)
+        .map(|i| match basis {
+            "open_to_close" => Some((data.open[i], data.close[i])),
+            "gap" => (i > 0).then(|| (data.close[i - 1], data.open[i])),
+            _ => (i > 0).then(|| (data.close[i - 1], data.close[i])),
+        })
+        .collect();
+    let bad_base = legs.iter().flatten().filter(|(base, _)| base.is_nan() || *base <= 0.0).count();
+    if bad_base > 0 {
+        metrics::counter!("big_mover_bad_base_bars_total").increment(bad_base as u64);
+    }
+    let change: Vec<f64> = legs
+        .iter()
+        .map(|leg| match *leg {
+            Some((base, price)) if base > 0.0 => (price - base) / base * 100.0,
+            _ => f64::NAN,
+        })
+        .collect();
+
+    let dollar_volume: Vec<f64> = data.close.iter().zip(&data.volume).map(|(c, v)| c * v).collect();
+    let mask = (0..n)
+        .map(|i| {
+            let moved = match direction {
+                "up" => change[i] > min_change,
+                "down" => change[i] < -min_change,
+                _ => change[i].abs() > min_change,
+            };
+            moved && dollar_volume[i] >= min_dollar_volume
+        })
+        .collect();
+    ScanOutput {
+        mask,
+        scores: vec![("move_pct".into(), change.clone()), ("dollar_volume".into(), dollar_volume)],
+        series: vec![("move_pct".into(), change)],
+        thresholds: vec![("min_change_pct".into(), min_change), ("min_dollar_volume".into(), min_dollar_volume)],
+        ..Default::default()
+    }
+}
+
+/// Matches the bar that makes the run `count` long, so a longer run matches
+/// once rather than on every bar past `count`
+fn scan_streak(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let count = param_usize(params, "count", 3) as f64;
+    let target = match params.get("direction").and_then(|v| v.as_str()) {
+        Some("down") => -count,
+        _ => count,
+    };
+    let run = streak(&data.close);
+    ScanOutput {
+        mask: run.iter().map(|&r| r == target).collect(),
+        scores: vec![("streak".into(), run.clone())],
+        series: vec![("close".into(), data.close.clone()), ("streak".into(), run)],
+        ..Default::default()
     }
 }
 
-fn scan_rsi_oversold(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+fn scan_rsi_oversold(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
     let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(14) as usize;
     let threshold = params.get("threshold").and_then(|v| v.as_f64()).unwrap_or(30.0);
     
     let rsi_vals = rsi(&data.close, period);
     
     // Entering oversold (crossing below threshold)
-    let thresh_vec: Vec<f64> = vec![threshold; rsi_vals.len()];
-    crossed_below(&rsi_vals, &thresh_vec)
+    ScanOutput {
+        mask: crossed_below_level(&rsi_vals, threshold),
+        series: vec![("rsi".into(), rsi_vals)],
+        thresholds: vec![("oversold".into(), threshold)],
+        ..Default::default()
+    }
 }
 
-fn scan_rsi_overbought(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+fn scan_rsi_overbought(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
     let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(14) as usize;
     let threshold = params.get("threshold").and_then(|v| v.as_f64()).unwrap_or(70.0);
     
     let rsi_vals = rsi(&data.close, period);
     
     // Entering overbought (crossing above threshold)
-    let thresh_vec: Vec<f64> = vec![threshold; rsi_vals.len()];
-    crossed_above(&rsi_vals, &thresh_vec)
+    ScanOutput {
+        mask: crossed_above_level(&rsi_vals, threshold),
+        series: vec![("rsi".into(), rsi_vals)],
+        thresholds: vec![("overbought".into(), threshold)],
+        ..Default::default()
+    }
 }
 
 fn scan_obv_breakout(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
-    let lookback = params.get("lookback").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+    let windows = param_lookback(params, "lookback", 20).window_starts(&data.days);
     
     let obv_vals = obv(&data.close, &data.volume);
-    higher_high(&obv_vals, lookback)
+    window_higher_high(&obv_vals, &windows)
 }
 
-fn scan_volume_spike(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+fn scan_volume_spike(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
     let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
     let multiplier = params.get("multiplier").and_then(|v| v.as_f64()).unwrap_or(2.0);
     
     let vol_ratio = volume_ratio(&data.volume, period);
-    above(&vol_ratio, multiplier)
+    ScanOutput {
+        mask: above(&vol_ratio, multiplier),
+        series: vec![("volume_ratio".into(), vol_ratio)],
+        thresholds: vec![("multiplier".into(), multiplier)],
+        ..Default::default()
+    }
 }
 
 fn scan_bollinger_squeeze(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
@@ -229,21 +2316,92 @@ fn scan_bollinger_squeeze(data: &TickerData, params: &HashMap<String, serde_json
     let (middle, upper, lower) = bollinger(&data.close, period, std_mult);
     
     // Squeeze = bands narrow (upper - lower) / middle < squeeze_pct%
-    middle
-        .iter()
-        .zip(upper.iter())
-        .zip(lower.iter())
-        .map(|((&m, &u), &l)| {
-            if m.is_nan() || u.is_nan() || l.is_nan() || m == 0.0 {
-                false
-            } else {
-                ((u - l) / m * 100.0) < squeeze_pct
-            }
+    bollinger_bandwidth(&middle, &upper, &lower).iter().map(|&w| w < squeeze_pct).collect()
+}
+
+/// The `period`/`std` Bollinger Bands of close
+fn param_bollinger(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let period = param_usize(params, "period", 20);
+    let std_mult = params.get("std").and_then(|v| v.as_f64()).unwrap_or(2.0);
+    bollinger(&data.close, period, std_mult)
+}
+
+fn scan_bb_lower_touch_reversal(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let max_rsi = params.get("max_rsi").and_then(|v| v.as_f64()).unwrap_or(100.0);
+    let (_, _, lower) = param_bollinger(data, params);
+    let rsi_vals = rsi(&data.close, param_usize(params, "rsi_period", 14));
+
+    let mask = (0..data.len())
+        .map(|i| {
+            let rsi_ok = max_rsi >= 100.0 || rsi_vals[i] <= max_rsi;
+            data.low[i] < lower[i] && data.close[i] > lower[i] && rsi_ok
         })
-        .collect()
+        .collect();
+    ScanOutput {
+        mask,
+        scores: vec![("rsi".into(), rsi_vals.clone())],
+        series: vec![
+            ("low".into(), data.low.clone()),
+            ("close".into(), data.close.clone()),
+            ("lower_band".into(), lower),
+            ("rsi".into(), rsi_vals),
+        ],
+        thresholds: vec![("max_rsi".into(), max_rsi)],
+        ..Default::default()
+    }
+}
+
+fn scan_bb_upper_breakout(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let min_expansion = params.get("min_expansion").and_then(|v| v.as_f64()).unwrap_or(1.0);
+    let (middle, upper, lower) = param_bollinger(data, params);
+    let bandwidth = bollinger_bandwidth(&middle, &upper, &lower);
+    let average = sma(&bandwidth, param_usize(params, "bandwidth_period", 20));
+    let expansion: Vec<f64> = bandwidth
+        .iter()
+        .zip(&average)
+        .map(|(&w, &a)| if a > 0.0 { w / a } else { f64::NAN })
+        .collect();
+
+    let crossed = crossed_above(&data.close, &upper);
+    let mask = crossed.iter().zip(&expansion).map(|(&c, &e)| c && e > min_expansion).collect();
+    ScanOutput {
+        mask,
+        scores: vec![("bandwidth".into(), bandwidth.clone()), ("bandwidth_expansion".into(), expansion.clone())],
+        series: vec![
+            ("close".into(), data.close.clone()),
+            ("upper_band".into(), upper),
+            ("bandwidth".into(), bandwidth),
+            ("bandwidth_expansion".into(), expansion),
+        ],
+        thresholds: vec![("min_expansion".into(), min_expansion)],
+        ..Default::default()
+    }
 }
 
-fn scan_macd_cross_up(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+/// Every bar from the `bars`-th close in a row outside the band onwards, so a
+/// walk shows up for as long as it lasts
+fn scan_bb_walk(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let bars = param_usize(params, "bars", 3) as f64;
+    let down = params.get("direction").and_then(|v| v.as_str()) == Some("down");
+    let (_, upper, lower) = param_bollinger(data, params);
+
+    let (band, name) = if down { (lower, "lower_band") } else { (upper, "upper_band") };
+    let mut run = vec![0.0; data.len()];
+    for i in 0..data.len() {
+        let outside = if down { data.close[i] < band[i] } else { data.close[i] > band[i] };
+        if outside {
+            run[i] = if i > 0 { run[i - 1] } else { 0.0 } + 1.0;
+        }
+    }
+    ScanOutput {
+        mask: run.iter().map(|&r| r >= bars).collect(),
+        scores: vec![("bars_outside".into(), run.clone())],
+        series: vec![("close".into(), data.close.clone()), (name.into(), band), ("bars_outside".into(), run)],
+        ..Default::default()
+    }
+}
+
+fn scan_macd_cross_up(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
     let fast = params.get("fast").and_then(|v| v.as_u64()).unwrap_or(12) as usize;
     let slow = params.get("slow").and_then(|v| v.as_u64()).unwrap_or(26) as usize;
     let signal = params.get("signal").and_then(|v| v.as_u64()).unwrap_or(9) as usize;
@@ -251,10 +2409,10 @@ fn scan_macd_cross_up(data: &TickerData, params: &HashMap<String, serde_json::Va
     let macd_line = macd(&data.close, fast, slow);
     let signal_line = macd_signal(&data.close, fast, slow, signal);
     
-    crossed_above(&macd_line, &signal_line)
+    cross_output(crossed_above(&macd_line, &signal_line), ("macd", macd_line), ("signal", signal_line))
 }
 
-fn scan_macd_cross_down(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+fn scan_macd_cross_down(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
     let fast = params.get("fast").and_then(|v| v.as_u64()).unwrap_or(12) as usize;
     let slow = params.get("slow").and_then(|v| v.as_u64()).unwrap_or(26) as usize;
     let signal = params.get("signal").and_then(|v| v.as_u64()).unwrap_or(9) as usize;
@@ -262,38 +2420,501 @@ fn scan_macd_cross_down(data: &TickerData, params: &HashMap<String, serde_json::
     let macd_line = macd(&data.close, fast, slow);
     let signal_line = macd_signal(&data.close, fast, slow, signal);
     
-    crossed_below(&macd_line, &signal_line)
+    cross_output(crossed_below(&macd_line, &signal_line), ("macd", macd_line), ("signal", signal_line))
+}
+
+fn scan_trix_zero_cross(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(15) as usize;
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
+    
+    let trix_line = trix(&data.close, period);
+    
+    let mask = if direction == "up" {
+        crossed_above_level(&trix_line, 0.0)
+    } else {
+        crossed_below_level(&trix_line, 0.0)
+    };
+    ScanOutput {
+        mask,
+        series: vec![("trix".to_string(), trix_line)],
+        ..Default::default()
+    }
+}
+
+/// Dorsey's reversal bulge: the Mass Index rising above `bulge` sets up, and
+/// its first close back below `trigger` signals. Direction-neutral; the bulge
+/// only says a trend is likely to turn.
+fn scan_mass_index_bulge(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let ema_period = param_usize(params, "ema_period", 9);
+    let sum_period = param_usize(params, "sum_period", 25);
+    let bulge = params.get("bulge").and_then(|v| v.as_f64()).unwrap_or(27.0);
+    let trigger = params.get("trigger").and_then(|v| v.as_f64()).unwrap_or(26.5);
+    
+    let mi = mass_index(&data.high, &data.low, ema_period, sum_period);
+    ScanOutput {
+        mask: rearming_signal(&mi, |v| v > bulge, |v| v < trigger),
+        scores: vec![("mass_index".into(), mi.clone())],
+        series: vec![("mass_index".into(), mi)],
+        thresholds: vec![("bulge".into(), bulge), ("trigger".into(), trigger)],
+        ..Default::default()
+    }
+}
+
+fn scan_fisher_cross(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let period = param_usize(params, "period", 10);
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
+    
+    let (fisher, trigger) = fisher_transform(&data.high, &data.low, period);
+    let mask = if direction == "up" {
+        crossed_above(&fisher, &trigger)
+    } else {
+        crossed_below(&fisher, &trigger)
+    };
+    let mut output = cross_output(mask, ("fisher", fisher.clone()), ("trigger", trigger));
+    output.scores = vec![("fisher".into(), fisher)];
+    output
+}
+
+fn scan_dpo_cross(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let period = param_usize(params, "period", 20);
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
+    
+    let dpo_line = dpo(&data.close, period);
+    
+    let mask = if direction == "up" {
+        crossed_above_level(&dpo_line, 0.0)
+    } else {
+        crossed_below_level(&dpo_line, 0.0)
+    };
+    ScanOutput {
+        mask,
+        scores: vec![("dpo".into(), dpo_line.clone())],
+        series: vec![("dpo".into(), dpo_line)],
+        ..Default::default()
+    }
+}
+
+/// KST settings from `roc1..4`, `smooth1..4`, `weight1..4` and `signal`,
+/// each defaulting to the classic value
+fn kst_settings(params: &HashMap<String, serde_json::Value>) -> ([usize; 4], [usize; 4], [f64; 4], usize) {
+    let leg = |name: &str, k: usize| params.get(&format!("{}{}", name, k + 1));
+    let roc = std::array::from_fn(|k| leg("roc", k).and_then(|v| v.as_u64()).map_or(KST_ROC[k], |v| v as usize));
+    let smooth = std::array::from_fn(|k| leg("smooth", k).and_then(|v| v.as_u64()).map_or(KST_SMOOTH[k], |v| v as usize));
+    let weights = std::array::from_fn(|k| leg("weight", k).and_then(|v| v.as_f64()).unwrap_or(KST_WEIGHTS[k]));
+    (roc, smooth, weights, param_usize(params, "signal", KST_SIGNAL as u64))
+}
+
+/// Up to the first bar a signal-line cross can happen on
+fn kst_min_bars(params: &HashMap<String, serde_json::Value>) -> usize {
+    let (roc, smooth, _, signal) = kst_settings(params);
+    (0..4).map(|k| roc[k] + smooth[k]).max().unwrap_or(0) + signal
+}
+
+fn scan_kst_cross(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let (roc, smooth, weights, signal) = kst_settings(params);
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
+    
+    let (kst_line, signal_line) = kst(&data.close, roc, smooth, weights, signal);
+    let mask = if direction == "up" {
+        crossed_above(&kst_line, &signal_line)
+    } else {
+        crossed_below(&kst_line, &signal_line)
+    };
+    cross_output(mask, ("kst", kst_line), ("signal", signal_line))
+}
+
+/// Coppock params: (roc_long, roc_short, wma_period, timeframe)
+fn coppock_settings(params: &HashMap<String, serde_json::Value>) -> (usize, usize, usize, Interval) {
+    let timeframe = params
+        .get("timeframe")
+        .and_then(|v| v.as_str())
+        .and_then(Interval::parse)
+        .unwrap_or(Interval::Monthly);
+    (
+        param_usize(params, "roc_long", COPPOCK_ROC_LONG as u64),
+        param_usize(params, "roc_short", COPPOCK_ROC_SHORT as u64),
+        param_usize(params, "wma_period", COPPOCK_WMA as u64),
+        timeframe,
+    )
+}
+
+/// Timeframe bars up to the first possible turn; for weekly and monthly
+/// curves only a lower bound on the daily bars needed
+fn coppock_min_bars(params: &HashMap<String, serde_json::Value>) -> usize {
+    let (roc_long, roc_short, wma_period, _) = coppock_settings(params);
+    roc_long.max(roc_short) + wma_period + 2
+}
+
+/// The curve is computed on `timeframe` bars. A turn is reported on the last
+/// daily bar of its period, and each period's value holds from that bar
+/// until the next period's, so nothing shows before the period closes. The
+/// latest, still-open period counts as it stands.
+fn scan_coppock_turn(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let (roc_long, roc_short, wma_period, timeframe) = coppock_settings(params);
+    let bars = (timeframe != Interval::Daily).then(|| resample_bars(data, timeframe));
+    let close: Vec<f64> = match &bars {
+        Some(bars) => bars.iter().map(|b| b.close).collect(),
+        None => data.close.clone(),
+    };
+    
+    let curve = coppock(&close, roc_long, roc_short, wma_period);
+    // Rising off a low that's below zero; NaN comparisons rule out the warm-up
+    let turned: Vec<bool> = (0..curve.len())
+        .map(|k| k >= 2 && curve[k - 1] < 0.0 && curve[k] > curve[k - 1] && curve[k - 1] <= curve[k - 2])
+        .collect();
+    
+    let (mask, curve) = match bars {
+        None => (turned, curve),
+        Some(bars) => {
+            let mut mask = vec![false; data.len()];
+            let mut series = vec![f64::NAN; data.len()];
+            for (k, bar) in bars.iter().enumerate() {
+                mask[bar.end_idx] = turned[k];
+                let until = bars.get(k + 1).map_or(data.len(), |next| next.end_idx);
+                series[bar.end_idx..until].fill(curve[k]);
+            }
+            (mask, series)
+        }
+    };
+    ScanOutput {
+        mask,
+        scores: vec![("coppock".into(), curve.clone())],
+        series: vec![("coppock".into(), curve)],
+        thresholds: vec![("zero".into(), 0.0)],
+        ..Default::default()
+    }
+}
+
+fn scan_ppo_cross(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let fast = params.get("fast").and_then(|v| v.as_u64()).unwrap_or(12) as usize;
+    let slow = params.get("slow").and_then(|v| v.as_u64()).unwrap_or(26) as usize;
+    let signal = params.get("signal").and_then(|v| v.as_u64()).unwrap_or(9) as usize;
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
+    
+    let ppo_line = ppo(&data.close, fast, slow);
+    let signal_line = ema(&ppo_line, signal);
+    
+    let mask = if direction == "up" {
+        crossed_above(&ppo_line, &signal_line)
+    } else {
+        crossed_below(&ppo_line, &signal_line)
+    };
+    cross_output(mask, ("ppo", ppo_line), ("signal", signal_line))
+}
+
+fn scan_price_breakout(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let windows = param_lookback(params, "lookback", 252).window_starts(&data.days); // 52 weeks
+    
+    // The high to beat on each bar: the max of the lookback's closes before it
+    let mut prior_high = vec![f64::NAN; data.len()];
+    if data.len() > 1 {
+        prior_high[1..].copy_from_slice(&window_max(&data.close, &windows)[..data.len() - 1]);
+    }
+    ScanOutput {
+        mask: window_higher_high(&data.close, &windows),
+        series: vec![("close".into(), data.close.clone()), ("prior_high".into(), prior_high)],
+        ..Default::default()
+    }
+}
+
+/// Long-only turtle system: enter on a close above the previous
+/// `entry_period` bars' high, exit on a close below the previous
+/// `exit_period` bars' low. `signal` picks which side the mask reports.
+fn scan_turtle(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let entry_period = params.get("entry_period").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+    let exit_period = params.get("exit_period").and_then(|v| v.as_u64()).unwrap_or(10) as usize;
+    let signal = params.get("signal").and_then(|v| v.as_str()).unwrap_or("entry");
+    let n = data.len();
+    
+    // Channels as of the bar before, so the current bar has to break them
+    let (entry_upper, _) = donchian(&data.high, &data.low, entry_period);
+    let (_, exit_lower) = donchian(&data.high, &data.low, exit_period);
+    let mut entry_high = vec![f64::NAN; n];
+    let mut exit_low = vec![f64::NAN; n];
+    if n > 1 {
+        entry_high[1..].copy_from_slice(&entry_upper[..n - 1]);
+        exit_low[1..].copy_from_slice(&exit_lower[..n - 1]);
+    }
+    
+    let mask = if signal == "exit" {
+        data.close.iter().zip(&exit_low).map(|(&c, &l)| c < l).collect()
+    } else {
+        data.close.iter().zip(&entry_high).map(|(&c, &h)| c > h).collect()
+    };
+    ScanOutput {
+        mask,
+        series: vec![
+            ("close".into(), data.close.clone()),
+            ("entry_high".into(), entry_high),
+            ("exit_low".into(), exit_low),
+        ],
+        ..Default::default()
+    }
+}
+
+/// Narrow-range bars, or the bar after one that trades through its range.
+/// Reports `mode` (0 for the NR bar, 1 for a breakout), the NR bar's range
+/// as `nr_range`, and for breakouts `direction` (1 up, -1 down, 0 when an
+/// outside bar broke both sides). A breakout needs the next bar, so an NR
+/// bar at the end of the data can't match in breakout mode yet.
+fn scan_nr_breakout(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let period = param_usize(params, "period", 7);
+    let require_inside = params.get("inside_bar").and_then(|v| v.as_str()) == Some("required");
+    let breakout = params.get("mode").and_then(|v| v.as_str()) == Some("breakout");
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
+    let n = data.len();
+    
+    let narrow = narrowest_range(&data.high, &data.low, period);
+    let inside = inside_bar(&data.high, &data.low);
+    let nr: Vec<bool> = (0..n).map(|i| narrow[i] && (!require_inside || inside[i])).collect();
+    let range = |i: usize| data.high[i] - data.low[i];
+    
+    let mut nr_range = vec![f64::NAN; n];
+    let mut broke = vec![f64::NAN; n];
+    let mask = if breakout {
+        (0..n)
+            .map(|i| {
+                if i == 0 || !nr[i - 1] {
+                    return false;
+                }
+                let up = data.high[i] > data.high[i - 1];
+                let down = data.low[i] < data.low[i - 1];
+                let hit = match direction {
+                    "down" => down,
+                    "either" => up || down,
+                    _ => up,
+                };
+                if hit {
+                    nr_range[i] = range(i - 1);
+                    broke[i] = up as i32 as f64 - down as i32 as f64;
+                }
+                hit
+            })
+            .collect()
+    } else {
+        for i in (0..n).filter(|&i| nr[i]) {
+            nr_range[i] = range(i);
+        }
+        nr
+    };
+    
+    ScanOutput {
+        mask,
+        scores: vec![
+            ("mode".into(), vec![if breakout { 1.0 } else { 0.0 }; n]),
+            ("nr_range".into(), nr_range),
+            ("direction".into(), broke),
+        ],
+        conditions: vec![("narrow_range".into(), narrow), ("inside_bar".into(), inside)],
+        ..Default::default()
+    }
+}
+
+/// Every bar whose close is within `within_pct`% of the highest close over
+/// the lookback (this bar included, so new highs match too)
+fn scan_near_high(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let windows = param_lookback(params, "lookback", 252).window_starts(&data.days);
+    let within_pct = params.get("within_pct").and_then(|v| v.as_f64()).unwrap_or(5.0);
+    
+    let high = window_max(&data.close, &windows);
+    let pct_below: Vec<f64> = data
+        .close
+        .iter()
+        .zip(&high)
+        .map(|(&c, &h)| if h > 0.0 { (h - c) / h * 100.0 } else { f64::NAN })
+        .collect();
+    ScanOutput {
+        mask: pct_below.iter().map(|&p| p <= within_pct).collect(),
+        scores: vec![("pct_below_high".into(), pct_below)],
+        series: vec![("close".into(), data.close.clone()), ("rolling_high".into(), high)],
+        thresholds: vec![("within_pct".into(), within_pct)],
+        ..Default::default()
+    }
+}
+
+fn scan_beta_filter(
+    data: &TickerData,
+    bench: &TickerData,
+    params: &HashMap<String, serde_json::Value>,
+) -> ScanOutput {
+    let period = param_usize(params, "period", 60);
+    let threshold = params.get("threshold").and_then(|v| v.as_f64()).unwrap_or(1.0);
+    let below = params.get("direction").and_then(|v| v.as_str()) == Some("below");
+    
+    let (asset, market) = aligned_returns(data, bench);
+    let beta = rolling_beta(&asset, &market, period);
+    let mask = beta
+        .iter()
+        .map(|&b| !b.is_nan() && if below { b < threshold } else { b > threshold })
+        .collect();
+    ScanOutput {
+        mask,
+        scores: vec![("beta".into(), beta.clone())],
+        series: vec![("beta".into(), beta)],
+        thresholds: vec![("threshold".into(), threshold)],
+        ..Default::default()
+    }
+}
+
+fn scan_drawdown_filter(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let threshold = params.get("threshold").and_then(|v| v.as_f64()).unwrap_or(20.0);
+    let mode = params.get("mode").and_then(|v| v.as_str()).unwrap_or("dip");
+    
+    let dd = drawdown(&data.close);
+    let mask = if mode == "recover" {
+        (0..dd.len()).map(|i| i > 0 && dd[i - 1] >= threshold && dd[i] < threshold).collect()
+    } else {
+        dd.iter().map(|&d| d >= threshold).collect()
+    };
+    ScanOutput {
+        mask,
+        scores: vec![("drawdown".into(), dd.clone())],
+        series: vec![("peak".into(), cumulative_max(&data.close)), ("drawdown".into(), dd)],
+        thresholds: vec![("threshold".into(), threshold)],
+        ..Default::default()
+    }
+}
+
+/// Rolling bases need a full window; an all-time peak exists from bar one
+fn drawdown_min_bars(params: &HashMap<String, serde_json::Value>) -> usize {
+    match params.get("basis").and_then(|v| v.as_str()) {
+        Some("all_time") => 1,
+        _ => param_usize(params, "period", 252),
+    }
 }
 
-fn scan_price_breakout(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
-    let lookback = params.get("lookback").and_then(|v| v.as_u64()).unwrap_or(252) as usize; // 52 weeks
-    higher_high(&data.close, lookback)
+/// The running peak and drawdown from it for `basis` (`rolling` or `all_time`)
+fn drawdown_basis(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> (Vec<f64>, Vec<f64>) {
+    match params.get("basis").and_then(|v| v.as_str()) {
+        Some("all_time") => (cumulative_max(&data.close), drawdown(&data.close)),
+        _ => {
+            let period = param_usize(params, "period", 252);
+            (rolling_max(&data.close, period), rolling_drawdown(&data.close, period))
+        }
+    }
+}
+
+fn scan_deep_drawdown(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let threshold = params.get("threshold").and_then(|v| v.as_f64()).unwrap_or(30.0);
+    let period = param_usize(params, "period", 252);
+
+    let (peak, dd) = drawdown_basis(data, params);
+    ScanOutput {
+        mask: dd.iter().map(|&d| d > threshold).collect(),
+        scores: vec![("drawdown".into(), dd.clone())],
+        series: vec![
+            ("peak".into(), peak),
+            ("drawdown".into(), dd),
+            ("max_drawdown".into(), max_drawdown(&data.close, period)),
+        ],
+        thresholds: vec![("threshold".into(), threshold)],
+        ..Default::default()
+    }
+}
+
+/// Two-level hysteresis: a value passing `arm` arms the signal, and the first
+/// later value passing `fire` (while not arming) matches and disarms it
+fn rearming_signal(values: &[f64], arm: impl Fn(f64) -> bool, fire: impl Fn(f64) -> bool) -> Vec<bool> {
+    let mut armed = false;
+    values
+        .iter()
+        .map(|&v| {
+            if arm(v) {
+                armed = true;
+            } else if armed && fire(v) {
+                armed = false;
+                return true;
+            }
+            false
+        })
+        .collect()
+}
+
+/// Hysteresis between two levels: a drawdown of at least `dip_pct` arms the
+/// scan, and the first bar back at or under `recover_pct` matches and
+/// disarms it. Bounces that stall between the levels don't match, and the
+/// scan must dip again before the next match.
+fn scan_drawdown_recovery(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let dip_pct = params.get("dip_pct").and_then(|v| v.as_f64()).unwrap_or(20.0);
+    let recover_pct = params.get("recover_pct").and_then(|v| v.as_f64()).unwrap_or(10.0);
+
+    let (peak, dd) = drawdown_basis(data, params);
+    ScanOutput {
+        mask: rearming_signal(&dd, |d| d >= dip_pct, |d| d <= recover_pct),
+        scores: vec![("drawdown".into(), dd.clone())],
+        series: vec![("peak".into(), peak), ("drawdown".into(), dd)],
+        thresholds: vec![("dip_pct".into(), dip_pct), ("recover_pct".into(), recover_pct)],
+        ..Default::default()
+    }
 }
 
 fn scan_bullish_divergence(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
-    let lookback = params.get("lookback").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+    let windows = param_lookback(params, "lookback", 20).window_starts(&data.days);
     
     let obv_vals = obv(&data.close, &data.volume);
     
     // Price lower low + OBV higher high
-    let price_ll = lower_low(&data.close, lookback);
-    let obv_hh = higher_high(&obv_vals, lookback);
+    let price_ll = window_lower_low(&data.close, &windows);
+    let obv_hh = window_higher_high(&obv_vals, &windows);
     
     and(&price_ll, &obv_hh)
 }
 
 fn scan_bearish_divergence(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
-    let lookback = params.get("lookback").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+    let windows = param_lookback(params, "lookback", 20).window_starts(&data.days);
     
     let obv_vals = obv(&data.close, &data.volume);
     
     // Price higher high + OBV lower low
-    let price_hh = higher_high(&data.close, lookback);
-    let obv_ll = lower_low(&obv_vals, lookback);
+    let price_hh = window_higher_high(&data.close, &windows);
+    let obv_ll = window_lower_low(&obv_vals, &windows);
     
     and(&price_hh, &obv_ll)
 }
 
+fn scan_elder_impulse_turn(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let ema_period = params.get("ema_period").and_then(|v| v.as_u64()).unwrap_or(13) as usize;
+    let fast = params.get("fast").and_then(|v| v.as_u64()).unwrap_or(12) as usize;
+    let slow = params.get("slow").and_then(|v| v.as_u64()).unwrap_or(26) as usize;
+    let signal = params.get("signal").and_then(|v| v.as_u64()).unwrap_or(9) as usize;
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
+    
+    let impulse = elder_impulse(&data.close, ema_period, fast, slow, signal);
+    let (from, to_ok): (f64, fn(f64) -> bool) = if direction == "up" {
+        (-1.0, |v| v >= 0.0)
+    } else {
+        (1.0, |v| v <= 0.0)
+    };
+    let mut mask = vec![false; impulse.len()];
+    for i in 1..impulse.len() {
+        mask[i] = impulse[i - 1] == from && to_ok(impulse[i]);
+    }
+    ScanOutput {
+        mask,
+        series: vec![("impulse".into(), impulse)],
+        ..Default::default()
+    }
+}
+
+fn scan_bear_power_divergence(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let windows = param_lookback(params, "lookback", 20).window_starts(&data.days);
+    let ema_period = params.get("ema_period").and_then(|v| v.as_u64()).unwrap_or(13) as usize;
+    
+    let power = bear_power(&data.low, &data.close, ema_period);
+    
+    // Price lower low, bear power still below zero but above its own low
+    let price_ll = window_lower_low(&data.low, &windows);
+    let power_low = window_min(&power, &windows);
+    let mask = (0..data.len())
+        .map(|i| price_ll[i] && i > 0 && power[i] < 0.0 && power[i] > power_low[i - 1])
+        .collect();
+    ScanOutput {
+        mask,
+        series: vec![("low".into(), data.low.clone()), ("bear_power".into(), power)],
+        ..Default::default()
+    }
+}
+
 fn scan_consolidation_breakout(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
     let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(30) as usize;
     let range_pct = params.get("range_pct").and_then(|v| v.as_f64()).unwrap_or(5.0);
@@ -348,8 +2969,8 @@ fn scan_bullish_engulfing_oversold(data: &TickerData, params: &HashMap<String, s
 
         if prev_red && curr_green && engulfs {
             // Check if RSI was below threshold within lookback period
-            let start = if i > lookback { i - lookback } else { 0 };
-            let rsi_was_oversold = (start..i).any(|j| !rsi_vals[j].is_nan() && rsi_vals[j] < rsi_threshold);
+            let start = i.saturating_sub(lookback);
+            let rsi_was_oversold = rsi_vals[start..i].iter().any(|&v| !v.is_nan() && v < rsi_threshold);
 
             if rsi_was_oversold {
                 result[i] = true;
@@ -360,78 +2981,8 @@ fn scan_bullish_engulfing_oversold(data: &TickerData, params: &HashMap<String, s
     result
 }
 
-#[derive(Debug, Clone, Copy)]
-struct MonthlyBar {
-    start_idx: usize,
-    end_idx: usize,
-    open: f64,
-    close: f64,
-    high: f64,
-    low: f64,
-    volume: f64,
-}
-
-#[inline]
-fn month_key(date: &str) -> &str {
-    date.get(0..7).unwrap_or(date)
-}
-
-fn build_monthly_bars(data: &TickerData) -> Vec<MonthlyBar> {
-    let n = data.close.len();
-    if n == 0 {
-        return Vec::new();
-    }
-
-    let mut bars = Vec::new();
-
-    let mut current_month = month_key(&data.date[0]).to_string();
-    let mut start_idx = 0usize;
-    let mut open = data.open[0];
-    let mut high = data.high[0];
-    let mut low = data.low[0];
-    let mut volume = data.volume[0];
-
-    for i in 1..n {
-        let month = month_key(&data.date[i]);
-        if month != current_month {
-            let end_idx = i - 1;
-            let close = data.close[end_idx];
-            bars.push(MonthlyBar {
-                start_idx,
-                end_idx,
-                open,
-                close,
-                high,
-                low,
-                volume,
-            });
-
-            current_month = month.to_string();
-            start_idx = i;
-            open = data.open[i];
-            high = data.high[i];
-            low = data.low[i];
-            volume = data.volume[i];
-        } else {
-            high = high.max(data.high[i]);
-            low = low.min(data.low[i]);
-            volume += data.volume[i];
-        }
-    }
-
-    let end_idx = n - 1;
-    let close = data.close[end_idx];
-    bars.push(MonthlyBar {
-        start_idx,
-        end_idx,
-        open,
-        close,
-        high,
-        low,
-        volume,
-    });
-
-    bars
+fn build_monthly_bars(data: &TickerData) -> Vec<Bar> {
+    resample_bars(data, Interval::Monthly)
 }
 
 /// Monthly gap-down (open below prior month's close by %), optionally filter by candle direction.
@@ -497,38 +3048,2040 @@ fn scan_monthly_gap_drop(data: &TickerData, params: &HashMap<String, serde_json:
 }
 
 /// Custom scan - interprets a simple expression
-fn scan_custom(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
-    // This is a simplified custom scan - in production you'd want a proper expression parser
-    // For now, support combinations of predefined conditions
+fn scan_force_index_cross(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+    let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(13) as usize;
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
     
-    let conditions: Vec<&str> = params
-        .get("conditions")
-        .and_then(|v| v.as_array())
-        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
-        .unwrap_or_default();
+    let fi = force_index(&data.close, &data.volume, period);
+    
+    if direction == "up" {
+        crossed_above_level(&fi, 0.0)
+    } else {
+        crossed_below_level(&fi, 0.0)
+    }
+}
+
+fn scan_vortex_cross(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(14) as usize;
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
+    let min_separation = params.get("min_separation").and_then(|v| v.as_f64()).unwrap_or(0.0);
+    
+    let (plus, minus) = vortex(&data.high, &data.low, &data.close, period);
+    let crossed = if direction == "up" {
+        crossed_above(&plus, &minus)
+    } else {
+        crossed_below(&plus, &minus)
+    };
+    // Skip whipsaw bars where the lines barely parted
+    let mask = crossed
+        .iter()
+        .zip(plus.iter().zip(&minus))
+        .map(|(&c, (&p, &m))| c && (p - m).abs() >= min_separation)
+        .collect();
+    
+    let mut output = cross_output(mask, ("vi_plus", plus), ("vi_minus", minus));
+    output.thresholds.push(("min_separation".into(), min_separation));
+    output
+}
+
+fn scan_eom_cross(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+    let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(14) as usize;
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
     
-    if conditions.is_empty() {
-        return vec![false; data.close.len()];
+    let eom = ease_of_movement(&data.high, &data.low, &data.volume, period);
+    
+    if direction == "up" {
+        crossed_above_level(&eom, 0.0)
+    } else {
+        crossed_below_level(&eom, 0.0)
     }
+}
+
+fn scan_cmo_extreme(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+    let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(14) as usize;
+    let upper = params.get("upper").and_then(|v| v.as_f64()).unwrap_or(50.0);
+    let lower = params.get("lower").and_then(|v| v.as_f64()).unwrap_or(-50.0);
     
-    let mut result: Option<Vec<bool>> = None;
+    let cmo_vals = cmo(&data.close, period);
     
-    for cond in conditions {
-        let cond_result = match cond {
-            "golden_cross" => scan_golden_cross(data),
-            "death_cross" => scan_death_cross(data),
-            "rsi_oversold" => scan_rsi_oversold(data, params),
-            "rsi_overbought" => scan_rsi_overbought(data, params),
-            "volume_spike" => scan_volume_spike(data, params),
-            "price_breakout" => scan_price_breakout(data, params),
-            _ => continue,
-        };
-        
-        result = Some(match result {
-            None => cond_result,
-            Some(r) => and(&r, &cond_result),
-        });
+    or(&crossed_above_level(&cmo_vals, upper), &crossed_below_level(&cmo_vals, lower))
+}
+
+fn scan_ultimate_oscillator(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+    let short = params.get("short").and_then(|v| v.as_u64()).unwrap_or(7) as usize;
+    let medium = params.get("medium").and_then(|v| v.as_u64()).unwrap_or(14) as usize;
+    let long = params.get("long").and_then(|v| v.as_u64()).unwrap_or(28) as usize;
+    let oversold = params.get("oversold").and_then(|v| v.as_f64()).unwrap_or(30.0);
+    let overbought = params.get("overbought").and_then(|v| v.as_f64()).unwrap_or(70.0);
+    
+    let uo = ultimate_oscillator(&data.high, &data.low, &data.close, short, medium, long);
+    
+    or(&crossed_below_level(&uo, oversold), &crossed_above_level(&uo, overbought))
+}
+
+fn scan_trailing_stop_hit(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+    let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(22) as usize;
+    let multiplier = params.get("multiplier").and_then(|v| v.as_f64()).unwrap_or(3.0);
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("long");
+    
+    let (long, short) = chandelier_exit(&data.high, &data.low, &data.close, period, multiplier);
+    
+    if direction == "short" {
+        crossed_above(&data.close, &short)
+    } else {
+        crossed_below(&data.close, &long)
     }
+}
+
+/// One pivot level per bar. For weekly/monthly pivots every bar of a period
+/// gets the levels of the previous completed period.
+fn pivot_level_series(data: &TickerData, timeframe: Interval, method: PivotMethod, level: &str) -> Vec<f64> {
+    let pick = |levels: PivotLevels| levels.level(level).unwrap_or(&levels.pivot).to_vec();
+    
+    if timeframe == Interval::Daily {
+        return pick(pivot_points(&data.high, &data.low, &data.close, method));
+    }
+    
+    let bars = resample_bars(data, timeframe);
+    let high: Vec<f64> = bars.iter().map(|b| b.high).collect();
+    let low: Vec<f64> = bars.iter().map(|b| b.low).collect();
+    let close: Vec<f64> = bars.iter().map(|b| b.close).collect();
+    expand(&bars, &pick(pivot_points(&high, &low, &close, method)), data.len())
+}
+
+fn scan_near_level(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+    let level = params.get("level").and_then(|v| v.as_str()).unwrap_or("pivot");
+    let within_pct = params.get("within_pct").and_then(|v| v.as_f64()).unwrap_or(1.0);
+    let timeframe = params
+        .get("timeframe")
+        .and_then(|v| v.as_str())
+        .and_then(Interval::parse)
+        .unwrap_or(Interval::Daily);
+    let method = params
+        .get("method")
+        .and_then(|v| v.as_str())
+        .and_then(PivotMethod::parse)
+        .unwrap_or(PivotMethod::Classic);
+    let mode = params.get("mode").and_then(|v| v.as_str()).unwrap_or("near");
+    
+    let levels = pivot_level_series(data, timeframe, method, level);
+    
+    (0..data.len())
+        .map(|i| {
+            let lvl = levels[i];
+            if mode == "touched_and_reclaimed" {
+                data.low[i] <= lvl && data.close[i] > lvl
+            } else {
+                lvl > 0.0 && ((data.close[i] - lvl) / lvl).abs() * 100.0 <= within_pct
+            }
+        })
+        .collect()
+}
+
+/// Support levels (s1-s3, pivot) bounce when the low comes within
+/// `tolerance_pct` of the level and the close ends above it; resistance
+/// levels (r1-r3) mirror that with the high and a close below
+fn scan_pivot_bounce(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let level = params.get("level").and_then(|v| v.as_str()).unwrap_or("s1").trim().to_lowercase();
+    let tolerance = params.get("tolerance_pct").and_then(|v| v.as_f64()).unwrap_or(0.5) / 100.0;
+    let timeframe = params
+        .get("timeframe")
+        .and_then(|v| v.as_str())
+        .and_then(Interval::parse)
+        .unwrap_or(Interval::Daily);
+    let method = params
+        .get("method")
+        .and_then(|v| v.as_str())
+        .and_then(PivotMethod::parse)
+        .unwrap_or(PivotMethod::Classic);
+    
+    let levels = pivot_level_series(data, timeframe, method, &level);
+    let resistance = level.starts_with('r');
     
-    result.unwrap_or_else(|| vec![false; data.close.len()])
+    let mask = (0..data.len())
+        .map(|i| {
+            let lvl = levels[i];
+            if lvl.is_nan() || lvl <= 0.0 {
+                return false;
+            }
+            if resistance {
+                data.high[i] >= lvl * (1.0 - tolerance) && data.close[i] < lvl
+            } else {
+                data.low[i] <= lvl * (1.0 + tolerance) && data.close[i] > lvl
+            }
+        })
+        .collect();
+    
+    ScanOutput {
+        mask,
+        scores: vec![("pivot_level".into(), levels.clone())],
+        series: vec![
+            ("low".into(), data.low.clone()),
+            ("high".into(), data.high.clone()),
+            ("close".into(), data.close.clone()),
+            ("pivot_level".into(), levels),
+        ],
+        thresholds: vec![("tolerance_pct".into(), tolerance * 100.0)],
+        ..Default::default()
+    }
+}
+
+/// The score `unusual_volume` thresholds under its `method`: its indicator
+/// name, per-bar values and the threshold a match must exceed
+fn unusual_volume_score(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> (&'static str, Vec<f64>, f64) {
+    let method = params.get("method").and_then(|v| v.as_str()).unwrap_or("zscore");
+    let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+    
+    match method {
+        "percentile" => {
+            let window = params.get("percentile_window").and_then(|v| v.as_u64()).unwrap_or(252) as usize;
+            let percentile = params.get("percentile").and_then(|v| v.as_f64()).unwrap_or(95.0);
+            ("volume_percentile", percentile_rank(&data.volume, window), percentile)
+        }
+        "relative" => {
+            let multiplier = params.get("multiplier").and_then(|v| v.as_f64()).unwrap_or(2.0);
+            ("volume_ratio", volume_ratio(&data.volume, period), multiplier)
+        }
+        _ => {
+            let zscore = params.get("zscore").and_then(|v| v.as_f64()).unwrap_or(3.0);
+            ("volume_zscore", volume_zscore(&data.volume, period), zscore)
+        }
+    }
+}
+
+/// Reports the score and dollar volume on every match, for ranking
+fn scan_unusual_volume(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let min_dollar_volume = params.get("min_dollar_volume").and_then(|v| v.as_f64()).unwrap_or(0.0);
+    
+    let (name, score, threshold) = unusual_volume_score(data, params);
+    let dollar_volume: Vec<f64> = data.close.iter().zip(&data.volume).map(|(c, v)| c * v).collect();
+    
+    ScanOutput {
+        mask: (0..data.len())
+            .map(|i| score[i] > threshold && dollar_volume[i] >= min_dollar_volume)
+            .collect(),
+        scores: vec![(name.into(), score), ("dollar_volume".into(), dollar_volume)],
+        thresholds: vec![(name.into(), threshold), ("min_dollar_volume".into(), min_dollar_volume)],
+        ..Default::default()
+    }
+}
+
+fn scan_event_gap_continuation(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+    let event = params.get("event").and_then(|v| v.as_str()).unwrap_or("earnings");
+    let days_after = params.get("days_after").and_then(|v| v.as_u64()).unwrap_or(5) as usize;
+    let min_gap_pct = params.get("min_gap_pct").and_then(|v| v.as_f64()).unwrap_or(2.0);
+    
+    let n = data.len();
+    let mut result = vec![false; n];
+    let Some(dates) = data.events.get(event) else {
+        return result;
+    };
+    
+    for &date in dates {
+        // Event day: the first bar on or after the event date
+        let k = data.days.partition_point(|&d| d < date);
+        if k == 0 || k >= n {
+            continue;
+        }
+        let prev_close = data.close[k - 1];
+        if prev_close <= 0.0 || (data.open[k] / prev_close - 1.0) * 100.0 < min_gap_pct {
+            continue;
+        }
+        
+        let gap_low = data.low[k];
+        let window = (k + 1)..n.min(k + days_after + 1);
+        for (low, matched) in data.low[window.clone()].iter().zip(&mut result[window]) {
+            if *low <= gap_low {
+                break;
+            }
+            *matched = true;
+        }
+    }
+    
+    result
+}
+
+fn scan_adr_expansion(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let period = params.get("period").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+    let multiplier = params.get("multiplier").and_then(|v| v.as_f64()).unwrap_or(2.0);
+    let direction = params.get("direction").and_then(|v| v.as_str()).unwrap_or("up");
+    
+    let n = data.len();
+    let adr = adr_pct(&data.high, &data.low, &data.close, period);
+    // Baseline: the ADR of the `period` bars before today
+    let mut prior_adr = vec![f64::NAN; n];
+    if n > 1 {
+        prior_adr[1..].copy_from_slice(&adr[..n - 1]);
+    }
+    let range_ratio: Vec<f64> = (0..n)
+        .map(|i| {
+            let range_pct = (data.high[i] - data.low[i]) / data.close[i] * 100.0;
+            if prior_adr[i] > 0.0 { range_pct / prior_adr[i] } else { f64::NAN }
+        })
+        .collect();
+    
+    // A zero-range bar sits at 0.5, in neither third
+    let location = close_location(&data.high, &data.low, &data.close);
+    let mask = (0..n)
+        .map(|i| {
+            let placed = match direction {
+                "down" => location[i] <= 1.0 / 3.0,
+                _ => location[i] >= 2.0 / 3.0,
+            };
+            range_ratio[i] > multiplier && placed
+        })
+        .collect();
+    
+    ScanOutput {
+        mask,
+        scores: vec![("adr_pct".into(), prior_adr), ("range_ratio".into(), range_ratio)],
+        thresholds: vec![("range_ratio".into(), multiplier)],
+        ..Default::default()
+    }
+}
+
+fn scan_strong_close_breakout(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let windows = param_lookback(params, "lookback", 20).window_starts(&data.days);
+    let min_location = params.get("min_close_location").and_then(|v| v.as_f64()).unwrap_or(0.75);
+    let multiplier = params.get("multiplier").and_then(|v| v.as_f64()).unwrap_or(1.5);
+    let volume_period = params.get("volume_period").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+    
+    // The intraday high makes the breakout; the close shows whether it held
+    let breakout = window_higher_high(&data.high, &windows);
+    let location = close_location(&data.high, &data.low, &data.close);
+    let vol_ratio = volume_ratio(&data.volume, volume_period);
+    
+    let mut prior_high = vec![f64::NAN; data.len()];
+    if data.len() > 1 {
+        prior_high[1..].copy_from_slice(&window_max(&data.high, &windows)[..data.len() - 1]);
+    }
+    
+    ScanOutput {
+        mask: (0..data.len())
+            .map(|i| breakout[i] && location[i] >= min_location && vol_ratio[i] > multiplier)
+            .collect(),
+        scores: vec![("close_location".into(), location.clone()), ("volume_ratio".into(), vol_ratio.clone())],
+        series: vec![
+            ("high".into(), data.high.clone()),
+            ("prior_high".into(), prior_high),
+            ("close_location".into(), location),
+            ("volume_ratio".into(), vol_ratio),
+        ],
+        thresholds: vec![("min_close_location".into(), min_location), ("volume_ratio".into(), multiplier)],
+        ..Default::default()
+    }
+}
+
+/// AND of the `filters` conditions; params are validated on the way in, so
+/// an unparseable list (only reachable by calling the scan directly) matches nothing
+fn scan_composite(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    let filters = params.get("filters").map(composite::parse_filters);
+    let Some(Ok(filters)) = filters else {
+        return ScanOutput::from(vec![false; data.len()]);
+    };
+
+    let mut output = ScanOutput::from(scratch::bool_with(|out| {
+        out.clear();
+        out.resize(data.len(), false);
+    }));
+    for filter in &filters {
+        let (mask, series) = filter.evaluate(data);
+        let mut leg = ScanOutput { mask, series, ..Default::default() };
+        if let composite::Operand::Number(level) = filter.value {
+            leg.thresholds.push((filter.to_string(), level));
+        }
+        output.and_leg(&filter.to_string(), leg);
+    }
+    output
+}
+
+fn scan_custom(data: &TickerData, params: &HashMap<String, serde_json::Value>) -> ScanOutput {
+    // This is a simplified custom scan - in production you'd want a proper expression parser
+    // For now, support combinations of predefined conditions
+    
+    let conditions: Vec<&str> = params
+        .get("conditions")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    
+    let mut output = ScanOutput::from(vec![false; data.close.len()]);
+    
+    for cond in conditions {
+        let leg = match cond {
+            "golden_cross" => scan_golden_cross(data),
+            "death_cross" => scan_death_cross(data),
+            "rsi_oversold" => scan_rsi_oversold(data, params),
+            "rsi_overbought" => scan_rsi_overbought(data, params),
+            "volume_spike" => scan_volume_spike(data, params),
+            "price_breakout" => scan_price_breakout(data, params),
+            _ => continue,
+        };
+        output.and_leg(cond, leg);
+    }
+    
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::generate_sample_data;
+    use crate::registry::{ScanDefinition, WarmupFn};
+    use crate::scan_types::ScanType;
+    use chrono::Datelike;
+
+    fn panicking_scan(data: &TickerData, _: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+        // Out-of-bounds on short series, like a careless generated scan
+        let _ = data.close[300];
+        vec![true; data.len()]
+    }
+
+    #[test]
+    fn test_panicking_scan_skips_ticker() {
+        let mut registry = ScanRegistry::default();
+        registry.register(ScanDefinition {
+            meta: ScanType {
+                id: "generated_panic".into(),
+                name: "Panics".into(),
+                description: "Panics on short series".into(),
+                params: vec![],
+                default_lookback_days: None,
+                version: None,
+            },
+            func: panicking_scan,
+            warmup: None,
+            min_bars: None,
+            warmup_bars: None,
+            output: None,
+            benchmark_output: None,
+            listed: true,
+        });
+
+        let mut data = HashMap::new();
+        data.insert("LONG".to_string(), Arc::new(generate_sample_data(400)));
+        data.insert("SHORT".to_string(), Arc::new(generate_sample_data(100)));
+
+        let query = ScanQuery {
+            scan_type: "generated_panic".into(),
+            params: HashMap::new(),
+            ..Default::default()
+        };
+        let result = run_scan(&registry, &data, &query);
+
+        assert_eq!(result.total_tickers_scanned, 2);
+        assert_eq!(result.tickers_with_matches, 1);
+        assert_eq!(result.scan_errors, 1);
+        assert!(result.matches.iter().all(|m| m.ticker == "LONG"));
+    }
+
+    #[test]
+    fn test_lookback_days_relative_to_last_bar() {
+        let mut registry = ScanRegistry::default();
+        registry.register(ScanDefinition {
+            meta: ScanType {
+                id: "every_bar".into(),
+                name: "Every bar".into(),
+                description: String::new(),
+                params: vec![],
+                default_lookback_days: None,
+                version: None,
+            },
+            func: |data, _| vec![true; data.len()],
+            warmup: None,
+            min_bars: None,
+            warmup_bars: None,
+            output: None,
+            benchmark_output: None,
+            listed: true,
+        });
+
+        let ticker = generate_sample_data(50);
+        let last_dates = ticker.date[47..].to_vec();
+        let data = HashMap::from([("AAA".to_string(), Arc::new(ticker))]);
+
+        let query = ScanQuery {
+            scan_type: "every_bar".into(),
+            params: HashMap::new(),
+            lookback_days: Some(3),
+            ..Default::default()
+        };
+        let result = run_scan(&registry, &data, &query);
+
+        let dates: Vec<String> = result.matches.iter().map(|m| m.date.clone()).collect();
+        assert_eq!(dates, last_dates);
+    }
+
+    #[test]
+    fn test_dedup_and_first_only() {
+        // Fires on the 3rd-5th of each month: runs of three consecutive bars
+        fn early_month(data: &TickerData, _: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+            data.days.iter().map(|d| (3..=5).contains(&d.day())).collect()
+        }
+        let mut registry = ScanRegistry::default();
+        for (id, warmup) in [("early_month", None), ("early_month_sliced", Some((|_| 0) as WarmupFn))] {
+            registry.register(ScanDefinition {
+                meta: ScanType {
+                    id: id.into(),
+                    name: id.into(),
+                    description: String::new(),
+                    params: vec![],
+                    default_lookback_days: None,
+                    version: None,
+                },
+                func: early_month,
+                warmup,
+                min_bars: None,
+                warmup_bars: None,
+                output: None,
+                benchmark_output: None,
+                listed: true,
+            });
+        }
+
+        // Daily bars 2020-01-01..2020-04-09
+        let data = HashMap::from([("AAA".to_string(), Arc::new(generate_sample_data(100)))]);
+
+        for scan_type in ["early_month", "early_month_sliced"] {
+            let dates = |query: ScanQuery| -> Vec<String> {
+                let query = ScanQuery { scan_type: scan_type.into(), ..query };
+                run_scan(&registry, &data, &query).matches.into_iter().map(|m| m.date).collect()
+            };
+
+            assert_eq!(
+                dates(ScanQuery { dedup_within_days: Some(2), ..Default::default() }),
+                ["2020-01-03", "2020-02-03", "2020-03-03", "2020-04-03"]
+            );
+
+            // A range starting mid-run still sees the run's earlier bars
+            let from = Some("2020-01-04".to_string());
+            assert_eq!(
+                dates(ScanQuery { date_from: from.clone(), date_to: Some("2020-02-29".into()), ..Default::default() }),
+                ["2020-01-04", "2020-01-05", "2020-02-03", "2020-02-04", "2020-02-05"]
+            );
+            assert_eq!(
+                dates(ScanQuery { date_from: from.clone(), dedup_within_days: Some(1), ..Default::default() }),
+                ["2020-02-03", "2020-03-03", "2020-04-03"]
+            );
+
+            assert_eq!(
+                dates(ScanQuery { date_from: from.clone(), first_only: true, ..Default::default() }),
+                ["2020-01-04"]
+            );
+            assert_eq!(
+                dates(ScanQuery { date_from: from, dedup_within_days: Some(1), first_only: true, ..Default::default() }),
+                ["2020-02-03"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_correlation_filter_uses_shared_benchmark() {
+        let mut registry = ScanRegistry::default();
+        registry.register(ScanDefinition {
+            meta: ScanType {
+                id: "every_bar".into(),
+                name: "Every bar".into(),
+                description: String::new(),
+                params: vec![],
+                default_lookback_days: None,
+                version: None,
+            },
+            func: |data, _| vec![true; data.len()],
+            warmup: None,
+            min_bars: None,
+            warmup_bars: None,
+            output: None,
+            benchmark_output: None,
+            listed: true,
+        });
+
+        let spy = Arc::new(generate_sample_data(100));
+        let data = HashMap::from([("SPY".to_string(), spy.clone()), ("TWIN".to_string(), spy.clone())]);
+        let query = ScanQuery {
+            scan_type: "every_bar".into(),
+            correlation_filter: Some(serde_json::from_value(serde_json::json!({
+                "period": 20, "min_beta": 0.99, "max_beta": 1.01
+            })).unwrap()),
+            ..Default::default()
+        };
+
+        // Beta 1 once the first 20 returns (bars 1..=20) are in
+        let result = run_scan(&registry, &data, &query);
+        assert_eq!(result.matches.len(), 2 * 80);
+
+        // Benchmark outside the scanned universe
+        let twin_only = HashMap::from([("TWIN".to_string(), spy.clone())]);
+        assert!(run_scan(&registry, &twin_only, &query).matches.is_empty());
+        assert_eq!(run_scan_with(&registry, &twin_only, &query, Some(spy)).matches.len(), 80);
+    }
+
+    #[test]
+    fn test_weekly_pivots_come_from_previous_week() {
+        let dates = ["2024-01-04", "2024-01-05", "2024-01-08", "2024-01-10", "2024-01-16"];
+        let n = dates.len();
+        let data = TickerData::from_columns(
+            dates.iter().map(|d| d.to_string()).collect(),
+            vec![10.0; n],
+            vec![11.0, 14.0, 12.0, 13.0, 12.0],
+            vec![9.0, 10.0, 8.0, 9.0, 10.0],
+            vec![10.0, 12.0, 9.0, 12.0, 11.0],
+            vec![100.0; n],
+        );
+
+        let pivot = pivot_level_series(&data, Interval::Weekly, PivotMethod::Classic, "pivot");
+
+        // Week of Jan 1 has no prior week; Jan 8 week uses H 14 L 9 C 12;
+        // the holiday-shortened Jan 15 week uses H 13 L 8 C 12
+        assert!(pivot[0].is_nan() && pivot[1].is_nan());
+        assert_eq!(pivot[2..], [35.0 / 3.0, 35.0 / 3.0, 11.0]);
+
+        let s1 = pivot_level_series(&data, Interval::Weekly, PivotMethod::Classic, "s1");
+        assert_eq!(s1[4], 2.0 * 11.0 - 13.0);
+    }
+
+    #[test]
+    fn test_pivot_bounce() {
+        // Bar 0 sets bar 1's classic pivots: H 12, L 9, C 12 -> P 11, S1 10, R1 13
+        let dates = ["2024-01-02", "2024-01-03", "2024-01-04"];
+        let data = TickerData::from_columns(
+            dates.iter().map(|d| d.to_string()).collect(),
+            vec![12.0, 10.8, 20.0],
+            vec![12.0, 11.0, 20.0],
+            vec![9.0, 10.04, 20.0],
+            vec![12.0, 10.6, 20.0],
+            vec![100.0; 3],
+        );
+
+        let params = |level: &str, tolerance: f64| {
+            HashMap::from([
+                ("level".to_string(), serde_json::json!(level)),
+                ("tolerance_pct".to_string(), serde_json::json!(tolerance)),
+            ])
+        };
+        // Low 10.04 is within 0.5% of S1 = 10 and the close holds above it
+        let output = scan_pivot_bounce(&data, &params("s1", 0.5));
+        assert_eq!(output.mask, [false, true, false]);
+        assert_eq!(output.scores_at(1)["pivot_level"], 10.0);
+        // ...but not within 0.1%
+        assert_eq!(scan_pivot_bounce(&data, &params("s1", 0.1)).mask, [false; 3]);
+        // High 11 never gets within 0.5% of R1 = 13
+        assert_eq!(scan_pivot_bounce(&data, &params("R1", 0.5)).mask, [false; 3]);
+        // The pivot (11) is tested as support, but the close is below it
+        assert_eq!(scan_pivot_bounce(&data, &params("pivot", 0.5)).mask, [false; 3]);
+    }
+
+    #[test]
+    fn test_unusual_volume_reports_score() {
+        let mut data = generate_sample_data(300);
+        data.volume[250] = data.volume[200..250].iter().sum::<f64>();
+        data.close[250] = 0.01;
+        let store = HashMap::from([("T".to_string(), Arc::new(data))]);
+        let registry = ScanRegistry::load();
+
+        let query = ScanQuery {
+            scan_type: "unusual_volume".into(),
+            params: HashMap::from([("zscore".to_string(), serde_json::json!(10.0))]),
+            ..Default::default()
+        };
+        let result = run_scan(&registry, &store, &query);
+        assert_eq!(result.matches.len(), 1);
+        let m = &result.matches[0];
+        assert_eq!(m.date, store["T"].date[250]);
+        assert!(m.indicators["volume_zscore"] > 10.0);
+        assert_eq!(m.indicators["dollar_volume"], m.close * m.volume);
+
+        // The spike is on a one-cent print
+        let mut floored = query.clone();
+        floored.params.insert("min_dollar_volume".into(), serde_json::json!(1_000_000.0));
+        assert!(run_scan(&registry, &store, &floored).matches.is_empty());
+
+        let mut percentile = query;
+        percentile.params.insert("method".into(), serde_json::json!("percentile"));
+        percentile.params.insert("percentile_window".into(), serde_json::json!(200));
+        let result = run_scan(&registry, &store, &percentile);
+        assert!(result.matches.iter().any(|m| m.indicators["volume_percentile"] == 100.0));
+        assert!(result.matches.iter().all(|m| !m.indicators.contains_key("volume_zscore")));
+    }
+
+    #[test]
+    fn test_intraday_range_compares_timestamps() {
+        let dates: Vec<String> = ["03", "04"]
+            .iter()
+            .flat_map(|day| ["14:30", "15:00", "15:30"].map(|time| format!("2024-01-{} {}:00", day, time)))
+            .collect();
+        let data = TickerData::from_columns(dates, vec![10.0; 6], vec![11.0; 6], vec![9.0; 6], vec![10.0; 6], vec![100.0; 6]);
+        assert_eq!(data.timeframe, Timeframe::Intraday);
+        let store = HashMap::from([("T".to_string(), Arc::new(data))]);
+        let registry = ScanRegistry::load();
+
+        let query = |from: &str, to: &str| ScanQuery {
+            scan_type: "composite".into(),
+            params: HashMap::from([(
+                "filters".to_string(),
+                serde_json::json!([{ "indicator": "close", "op": ">", "value": 0 }]),
+            )]),
+            date_from: Some(from.into()),
+            date_to: Some(to.into()),
+            ..Default::default()
+        };
+        let dates = |query: &ScanQuery| -> Vec<String> {
+            run_scan(&registry, &store, query).matches.into_iter().map(|m| m.date).collect()
+        };
+
+        assert_eq!(
+            dates(&query("2024-01-03T15:00:00Z", "2024-01-04T14:30:00Z")),
+            ["2024-01-03T15:00:00Z", "2024-01-03T15:30:00Z", "2024-01-04T14:30:00Z"]
+        );
+        // A bare end date takes the whole day
+        assert_eq!(dates(&query("2024-01-03T15:30:00Z", "2024-01-04")).len(), 4);
+    }
+
+    #[test]
+    fn test_composite_filters() {
+        let registry = ScanRegistry::load();
+        let data = generate_sample_data(600);
+        let params: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "filters": [
+                { "indicator": "rsi", "period": 14, "op": "<", "value": 70 },
+                { "indicator": "close", "op": ">", "value": { "indicator": "sma", "period": 50 } },
+                { "indicator": "volume_ratio", "op": ">", "value": 0.5 },
+            ]
+        }))
+        .unwrap();
+
+        let rsi14 = rsi(&data.close, 14);
+        let sma50 = sma(&data.close, 50);
+        let ratio = volume_ratio(&data.volume, 20);
+        let expected: Vec<bool> =
+            (0..data.len()).map(|i| rsi14[i] < 70.0 && data.close[i] > sma50[i] && ratio[i] > 0.5).collect();
+        let output = scan_composite(&data, &params);
+        assert!(output.mask.iter().any(|&m| m));
+        assert_eq!(output.mask, expected);
+
+        let j = output.mask.iter().position(|&m| m).unwrap();
+        let e = output.explain(j).unwrap();
+        assert_eq!(e.conditions.len(), 3);
+        assert!(e.conditions["close > sma_50"]);
+        assert_eq!(e.thresholds["rsi_14 < 70"], 70.0);
+        assert_eq!(e.values["sma_50"], Some(sma50[j]));
+
+        let meta = &registry.get("composite").unwrap().meta;
+        let mut missing = HashMap::new();
+        assert!(crate::scan_types::coerce_params(&mut missing, meta).is_err());
+        let mut bad = HashMap::from([("filters".to_string(), serde_json::json!([{ "indicator": "rsi" }]))]);
+        assert!(crate::scan_types::coerce_params(&mut bad, meta).is_err());
+        let typed = r#"[{"indicator": "close", "op": "crosses_above", "value": 100}]"#;
+        let mut typed = HashMap::from([("filters".to_string(), serde_json::json!(typed))]);
+        crate::scan_types::coerce_params(&mut typed, meta).unwrap();
+        assert!(typed["filters"].is_array());
+    }
+
+    #[test]
+    fn test_streaming_and_tracked_scans_match_batch_scan() {
+        let registry = ScanRegistry::load();
+        let data: HashMap<String, Arc<TickerData>> = (0..8)
+            .map(|i| (format!("T{}", i), Arc::new(generate_sample_data(300 + i * 10))))
+            .collect();
+        let query = ScanQuery {
+            scan_type: "volume_spike".into(),
+            params: serde_json::from_value(serde_json::json!({ "multiplier": 1.2 })).unwrap(),
+            ..Default::default()
+        };
+        let batch = run_scan(&registry, &data, &query);
+        assert!(batch.tickers_with_matches > 1);
+
+        let progress = ScanProgress::default();
+        let tracked = run_scan_tracked(&registry, &data, &query, None, &progress);
+        assert_eq!(tracked.matches.len(), batch.matches.len());
+        assert_eq!(progress.total.into_inner(), 8);
+        assert_eq!(progress.scanned.into_inner(), 8);
+        assert_eq!(progress.matches.into_inner(), batch.matches.len());
+        assert!(!tracked.cancelled);
+
+        let cancelled = ScanProgress::default();
+        cancelled.cancelled.store(true, Ordering::Relaxed);
+        let partial = run_scan_tracked(&registry, &data, &query, None, &cancelled);
+        assert!(partial.cancelled && partial.matches.is_empty());
+        assert_eq!(cancelled.scanned.into_inner(), 0);
+
+        let received = std::sync::Mutex::new(Vec::new());
+        let sink = |matches: Vec<ScanMatch>| {
+            received.lock().unwrap().extend(matches);
+            true
+        };
+        let streamed = run_scan_streaming(&registry, &data, &query, None, &sink);
+        assert!(streamed.matches.is_empty());
+        assert_eq!(streamed.tickers_with_matches, batch.tickers_with_matches);
+        let mut received = received.into_inner().unwrap();
+        received.sort_by(|a, b| (&a.ticker, &a.date).cmp(&(&b.ticker, &b.date)));
+        let mut expected = batch.matches;
+        expected.sort_by(|a, b| (&a.ticker, &a.date).cmp(&(&b.ticker, &b.date)));
+        assert_eq!(received.len(), expected.len());
+        assert!(received.iter().zip(&expected).all(|(a, b)| a.ticker == b.ticker && a.date == b.date));
+
+        // A sink that refuses the first batch (client gone) stops the scan
+        let calls = AtomicUsize::new(0);
+        let refuse = |_: Vec<ScanMatch>| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            false
+        };
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let stopped = pool.install(|| run_scan_streaming(&registry, &data, &query, None, &refuse));
+        assert_eq!(calls.into_inner(), 1);
+        assert!(stopped.tickers_with_matches < batch.tickers_with_matches);
+        assert!(stopped.cancelled);
+    }
+
+    #[test]
+    fn test_scan_timeout_and_ticker_budget() {
+        // Every bar matches; 20ms per ticker, or a second on the 301-bar one
+        // spent computing pooled indicators, where deadlines are checked
+        fn slow(data: &TickerData, _: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+            std::thread::sleep(Duration::from_millis(20));
+            vec![true; data.len()]
+        }
+        fn one_stuck(data: &TickerData, _: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+            if data.len() == 301 {
+                for _ in 0..200 {
+                    std::thread::sleep(Duration::from_millis(5));
+                    scratch::recycle_f64(sma(&data.close, 20));
+                }
+            }
+            vec![true; data.len()]
+        }
+        let mut registry = ScanRegistry::default();
+        for (id, func) in [("slow", slow as ScanFn), ("one_stuck", one_stuck as ScanFn)] {
+            registry.register(ScanDefinition {
+                meta: ScanType {
+                    id: id.into(),
+                    name: id.into(),
+                    description: String::new(),
+                    params: vec![],
+                    default_lookback_days: None,
+                    version: None,
+                },
+                func,
+                warmup: None,
+                min_bars: None,
+                warmup_bars: None,
+                output: None,
+                benchmark_output: None,
+                listed: true,
+            });
+        }
+        let data: HashMap<String, Arc<TickerData>> =
+            (0..10).map(|i| (format!("T{}", i), Arc::new(generate_sample_data(300 + i)))).collect();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let scan = |query: ScanQuery| pool.install(|| run_scan(&registry, &data, &query));
+
+        let partial = scan(ScanQuery { scan_type: "slow".into(), timeout_ms: Some(50), ..Default::default() });
+        assert!(partial.timed_out && !partial.cancelled);
+        // The deadline passes a few tickers in; the rest never start
+        assert!(partial.tickers_with_matches >= 1 && partial.tickers_unscanned >= 5);
+        assert_eq!(partial.tickers_with_matches + partial.tickers_unscanned, 10);
+
+        let full = scan(ScanQuery { scan_type: "slow".into(), ..Default::default() });
+        assert!(!full.timed_out);
+        assert_eq!((full.tickers_with_matches, full.tickers_unscanned), (10, 0));
+
+        // The stuck ticker is stopped at its budget, not after its second
+        let budgeted = scan(ScanQuery { scan_type: "one_stuck".into(), ticker_budget_ms: Some(20), ..Default::default() });
+        assert_eq!(budgeted.tickers_over_budget, ["T1"]);
+        assert_eq!(budgeted.tickers_with_matches, 9);
+        assert!(budgeted.matches.iter().all(|m| m.ticker != "T1"));
+        assert!(budgeted.scan_time_ms < 500, "{}ms", budgeted.scan_time_ms);
+
+        // So is a ticker still running when the scan times out; it counts as unscanned
+        let cut = scan(ScanQuery { scan_type: "one_stuck".into(), timeout_ms: Some(100), ..Default::default() });
+        assert!(cut.timed_out && cut.tickers_over_budget.is_empty());
+        assert!(cut.scan_time_ms < 500, "{}ms", cut.scan_time_ms);
+        assert!(cut.tickers_unscanned >= 1 && !cut.matches.iter().any(|m| m.ticker == "T1"));
+        assert_eq!(cut.tickers_with_matches + cut.tickers_unscanned, 10);
+    }
+
+    #[test]
+    fn test_scan_ticker_matches_bulk_scan() {
+        let registry = ScanRegistry::load();
+        let data = Arc::new(generate_sample_data(600));
+        let universe = HashMap::from([("AAA".to_string(), data.clone())]);
+        let query = ScanQuery {
+            scan_type: "golden_cross".into(),
+            date_from: Some(data.date[400].clone()),
+            explain: true,
+            ..Default::default()
+        };
+
+        let scan = scan_ticker(&registry, "AAA", &data, &query, None).unwrap();
+        let bulk = run_scan(&registry, &universe, &query).matches;
+        let dates = |matches: &[ScanMatch]| matches.iter().map(|m| m.date.clone()).collect::<Vec<_>>();
+        assert_eq!(dates(&scan.matches), dates(&bulk));
+
+        // Mask and series span the whole history, including bars before the range
+        assert_eq!(scan.output.mask.len(), data.len());
+        assert!(scan.output.series.iter().all(|(_, values)| values.len() == data.len()));
+        assert!(scan.matches.iter().all(|m| scan.output.mask[data.date.iter().position(|d| *d == m.date).unwrap()]));
+
+        let short = data.slice(0..100);
+        assert!(scan_ticker(&registry, "AAA", &short, &query, None).unwrap_err().contains("at least 201"));
+        let unknown = ScanQuery { scan_type: "nope".into(), ..Default::default() };
+        assert!(scan_ticker(&registry, "AAA", &data, &unknown, None).is_err());
+    }
+
+    #[test]
+    fn test_to_json_with_fields() {
+        let registry = ScanRegistry::load();
+        let data: HashMap<String, Arc<TickerData>> =
+            (0..8).map(|i| (format!("T{}", i), Arc::new(generate_sample_data(600)))).collect();
+        let query = ScanQuery { scan_type: "golden_cross".into(), explain: true, ..Default::default() };
+        let result = run_scan(&registry, &data, &query);
+        assert!(!result.matches.is_empty());
+
+        let fields = vec!["close".to_string(), "no_such_field".to_string()];
+        let projected = result.to_json_with_fields(&fields);
+        let matches = projected["matches"].as_array().unwrap();
+        assert_eq!(matches.len(), result.matches.len());
+        for m in matches {
+            let mut keys: Vec<&str> = m.as_object().unwrap().keys().map(|k| k.as_str()).collect();
+            keys.sort();
+            // The unknown name is dropped without error
+            assert_eq!(keys, ["close", "date", "explanation", "ticker"]);
+        }
+        // Result stats are untouched
+        assert_eq!(projected["total_tickers_scanned"], 8);
+
+        let full = serde_json::to_vec(&result).unwrap().len();
+        let slim = serde_json::to_vec(&projected).unwrap().len();
+        let bare = serde_json::to_vec(&result.to_json_with_fields(&[])).unwrap().len();
+        assert!(bare < slim && slim < full, "bare {} / selected {} / full {} bytes", bare, slim, full);
+    }
+
+    #[test]
+    fn test_match_bar_context() {
+        let registry = ScanRegistry::load();
+        let data = Arc::new(generate_sample_data(600));
+        let query = ScanQuery { scan_type: "golden_cross".into(), date_from: Some(data.date[400].clone()), ..Default::default() };
+        let matches = scan_ticker(&registry, "AAA", &data, &query, None).unwrap().matches;
+        assert!(!matches.is_empty());
+        for m in &matches {
+            let i = m.bar_index;
+            assert_eq!((m.date.as_str(), m.prev_close), (data.date[i].as_str(), Some(data.close[i - 1])));
+            let change = (data.close[i] / data.close[i - 1] - 1.0) * 100.0;
+            assert!((m.change_pct.unwrap() - change).abs() < 1e-9);
+        }
+
+        let json = serde_json::to_value(&matches[0]).unwrap();
+        assert_eq!(json["bar_index"], matches[0].bar_index);
+        assert_eq!(json["prev_close"], data.close[matches[0].bar_index - 1]);
+        // A first-bar match serializes a null previous close
+        let first = ScanMatch { bar_index: 0, prev_close: None, change_pct: None, ..matches[0].clone() };
+        assert_eq!(serde_json::to_value(&first).unwrap()["prev_close"], serde_json::Value::Null);
+        // Matches recorded before these fields existed still load
+        let old: ScanMatch = serde_json::from_value(serde_json::json!({
+            "ticker": "AAA", "date": "2024-01-02", "close": 1.0, "volume": 1.0, "open": 1.0, "high": 1.0, "low": 1.0, "rsi": 25.0
+        }))
+        .unwrap();
+        assert_eq!((old.bar_index, old.prev_close, old.indicators["rsi"]), (0, None, 25.0));
+    }
+
+    #[test]
+    fn test_adjusted_scan_sees_through_split() {
+        let registry = ScanRegistry::load();
+        // Adjusted closes fall for 300 bars then climb; a 4:1 split at bar 320
+        // leaves the raw closes 4x higher before it
+        let adj: Vec<f64> = (0..700)
+            .map(|i| if i < 300 { 200.0 - i as f64 / 3.0 } else { 100.0 + (i - 300) as f64 / 2.0 })
+            .collect();
+        let close: Vec<f64> = adj.iter().enumerate().map(|(i, &a)| if i < 320 { a * 4.0 } else { a }).collect();
+        let start = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+        let dates = (0..700).map(|i| (start + chrono::Duration::days(i)).format("%Y-%m-%d").to_string()).collect();
+        let split = Arc::new(TickerData::from_columns_adjusted(
+            dates,
+            close.clone(),
+            close.clone(),
+            close.clone(),
+            close,
+            vec![1000.0; 700],
+            Some(adj),
+        ));
+        let raw_only = Arc::new(generate_sample_data(700));
+
+        let first_match = |adjusted: bool| {
+            let query = ScanQuery { scan_type: "golden_cross".into(), adjusted, ..Default::default() };
+            scan_ticker(&registry, "SPLT", &split, &query, None).unwrap().matches[0].date.clone()
+        };
+        // The raw series waits for the pre-split prices to leave the 200-day SMA
+        let (raw, adjusted) = (first_match(false), first_match(true));
+        assert!(raw > adjusted, "raw {} vs adjusted {}", raw, adjusted);
+
+        let query = ScanQuery { scan_type: "golden_cross".into(), adjusted: true, ..Default::default() };
+        let universe = HashMap::from([("SPLT".to_string(), split.clone()), ("RAW".to_string(), raw_only.clone())]);
+        let result = run_scan(&registry, &universe, &query);
+        assert_eq!(result.tickers_skipped_unadjusted, 1);
+        assert!(result.matches.iter().all(|m| m.ticker == "SPLT"));
+        assert!(result.matches.iter().any(|m| m.date == adjusted));
+        assert!(scan_ticker(&registry, "RAW", &raw_only, &query, None).unwrap_err().contains("No adjustment data"));
+    }
+
+    #[test]
+    fn test_max_staleness_skips_tickers_that_stopped_early() {
+        let registry = ScanRegistry::load();
+        let current = Arc::new(generate_sample_data(600));
+        // Same series, but its data ends 200 days earlier
+        let delisted = Arc::new(current.slice(0..400));
+        let universe = HashMap::from([("CUR".to_string(), current.clone()), ("OLD".to_string(), delisted)]);
+        let params = HashMap::from([(
+            "filters".to_string(),
+            serde_json::json!([{ "indicator": "close", "op": ">", "value": 0 }]),
+        )]);
+        let query = ScanQuery { scan_type: "composite".into(), params, ..Default::default() };
+
+        let all = run_scan(&registry, &universe, &query);
+        assert_eq!((all.tickers_with_matches, all.tickers_skipped_stale), (2, 0));
+
+        let fresh = ScanQuery { max_staleness_days: Some(30), ..query.clone() };
+        let result = run_scan(&registry, &universe, &fresh);
+        assert_eq!((result.tickers_with_matches, result.tickers_skipped_stale), (1, 1));
+        assert!(result.matches.iter().all(|m| m.ticker == "CUR"));
+
+        // Measured from the store's newest date when the server supplies it
+        let newest_day = current.days.last().map(|&d| d + chrono::Duration::days(60));
+        let later = ScanQuery { newest_day, ..fresh };
+        assert_eq!(run_scan(&registry, &universe, &later).tickers_skipped_stale, 2);
+    }
+
+    #[test]
+    fn test_explain_breaks_down_matches() {
+        let registry = ScanRegistry::load();
+        // Several random tickers so both scans reliably find something
+        let data: HashMap<_, _> =
+            (0..8).map(|i| (format!("T{}", i), Arc::new(generate_sample_data(600)))).collect();
+
+        let mut query = ScanQuery {
+            scan_type: "custom".into(),
+            params: serde_json::from_value(serde_json::json!({
+                "conditions": ["rsi_oversold", "volume_spike"],
+                "multiplier": 1.0,
+                "threshold": 40.0,
+            }))
+            .unwrap(),
+            ..Default::default()
+        };
+        assert!(run_scan(&registry, &data, &query).matches.iter().all(|m| m.explanation.is_none()));
+
+        query.explain = true;
+        let result = run_scan(&registry, &data, &query);
+        assert!(!result.matches.is_empty());
+        for m in &result.matches {
+            let e = m.explanation.as_ref().unwrap();
+            assert_eq!(e.conditions, BTreeMap::from([("rsi_oversold".into(), true), ("volume_spike".into(), true)]));
+            assert_eq!(e.thresholds["oversold"], 40.0);
+            // Crossed below the threshold on this bar
+            assert!(e.values["rsi"].unwrap() < 40.0 && e.previous["rsi"].unwrap() >= 40.0);
+            assert!(e.values["volume_ratio"].unwrap() > 1.0);
+        }
+
+        let golden = ScanQuery {
+            scan_type: "golden_cross".into(),
+            explain: true,
+            ..Default::default()
+        };
+        let crosses = run_scan(&registry, &data, &golden).matches;
+        assert!(!crosses.is_empty());
+        for m in crosses {
+            let e = m.explanation.unwrap();
+            assert!(e.values["sma_50"] > e.values["sma_200"]);
+            assert!(e.previous["sma_50"] <= e.previous["sma_200"]);
+            assert!(e.conditions.is_empty() && e.thresholds.is_empty());
+        }
+
+        // Scans with only a mask have nothing to add
+        let plain = ScanQuery { scan_type: "obv_breakout".into(), ..golden };
+        assert!(run_scan(&registry, &data, &plain).matches.iter().all(|m| m.explanation.is_none()));
+    }
+
+    #[test]
+    fn test_adr_expansion_and_adr_filter() {
+        // 1%-range bars around 100, then a 4% bar closing near its high and
+        // one closing near its low
+        let n = 30;
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let dates = (0..n).map(|i| (start + chrono::Duration::days(i as i64)).to_string()).collect();
+        let mut high = vec![100.5; n];
+        let mut low = vec![99.5; n];
+        let mut close = vec![100.0; n];
+        (high[25], low[25], close[25]) = (102.0, 98.0, 101.8);
+        (high[28], low[28], close[28]) = (102.0, 98.0, 98.2);
+        let data = TickerData::from_columns(dates, close.clone(), high, low, close, vec![1000.0; n]);
+
+        let params = |direction: &str| HashMap::from([("direction".to_string(), serde_json::json!(direction))]);
+        let up = scan_adr_expansion(&data, &params("up"));
+        let up_bars: Vec<usize> = (0..n).filter(|&i| up.mask[i]).collect();
+        assert_eq!(up_bars, [25]);
+        let range_ratio = up.scores_at(25)["range_ratio"];
+        assert!((range_ratio - 4.0 / 1.0 / 1.018).abs() < 0.05, "{}", range_ratio);
+        // Bar 28's baseline includes bar 25's wide range, but it still clears 2×
+        let down = scan_adr_expansion(&data, &params("down"));
+        assert_eq!((0..n).filter(|&i| down.mask[i]).collect::<Vec<_>>(), [28]);
+
+        // Universal filter: a wide-ranging ticker passes min_adr_pct, a quiet one doesn't
+        let registry = ScanRegistry::load();
+        let mut wide = generate_sample_data(300);
+        for (h, l) in wide.high.iter_mut().zip(wide.low.iter_mut()) {
+            (*h, *l) = (*h * 1.05, *l * 0.95);
+        }
+        let store = HashMap::from([
+            ("WIDE".to_string(), Arc::new(wide)),
+            ("QUIET".to_string(), Arc::new(generate_sample_data(300))),
+        ]);
+        let mut query = ScanQuery {
+            scan_type: "volume_spike".into(),
+            params: HashMap::from([("multiplier".to_string(), serde_json::json!(1.2))]),
+            min_adr_pct: Some(8.0),
+            ..Default::default()
+        };
+        let result = run_scan(&registry, &store, &query);
+        assert!(!result.matches.is_empty());
+        assert!(result.matches.iter().all(|m| m.ticker == "WIDE" && m.indicators["adr_pct"] >= 8.0));
+
+        query.max_adr_pct = Some(1.0);
+        assert!(query.adr_filter().is_err());
+        query.adr_period = Some(0);
+        query.max_adr_pct = None;
+        assert!(query.adr_filter().is_err());
+    }
+
+    #[test]
+    fn test_vortex_cross() {
+        let data = generate_sample_data(400);
+        let (plus, minus) = vortex(&data.high, &data.low, &data.close, 14);
+
+        let up = scan_vortex_cross(&data, &HashMap::new());
+        assert_eq!(up.mask, crossed_above(&plus, &minus));
+        let down = scan_vortex_cross(&data, &HashMap::from([("direction".to_string(), serde_json::json!("down"))]));
+        assert_eq!(down.mask, crossed_below(&plus, &minus));
+
+        // A separation filter keeps only the crosses that cleared it
+        let gaps: Vec<f64> = (0..data.len()).filter(|&i| up.mask[i]).map(|i| (plus[i] - minus[i]).abs()).collect();
+        let mut sorted = gaps.clone();
+        sorted.sort_by(f64::total_cmp);
+        let cutoff = sorted[sorted.len() / 2];
+        let params = HashMap::from([("min_separation".to_string(), serde_json::json!(cutoff))]);
+        let strict = scan_vortex_cross(&data, &params);
+        assert_eq!(strict.mask.iter().filter(|&&m| m).count(), gaps.iter().filter(|&&g| g >= cutoff).count());
+        assert!((0..data.len()).all(|i| !strict.mask[i] || up.mask[i]));
+        assert_eq!(strict.explain(0).unwrap().thresholds["min_separation"], cutoff);
+    }
+
+    #[test]
+    fn test_near_high() {
+        let close = vec![100.0, 104.0, 110.0, 106.0, 104.0, 103.0, 101.0, 109.0];
+        let n = close.len();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let dates = (0..n).map(|i| (start + chrono::Duration::days(i as i64)).to_string()).collect();
+        let data = TickerData::from_columns(dates, close.clone(), close.clone(), close.clone(), close, vec![1000.0; n]);
+
+        let params = HashMap::from([
+            ("lookback".to_string(), serde_json::json!(4)),
+            ("within_pct".to_string(), serde_json::json!(5.0)),
+        ]);
+        let output = scan_near_high(&data, &params);
+        // 4-bar highs from bar 3: 110, 110, 110, 106, 109. Bars 4 and 5 are
+        // 5.5% and 6.4% off, bar 6 only 4.7% once 110 leaves the window
+        assert_eq!((0..n).filter(|&i| output.mask[i]).collect::<Vec<_>>(), [3, 6, 7]);
+        assert!((output.scores_at(6)["pct_below_high"] - 5.0 / 1.06).abs() < 1e-9);
+        assert_eq!(output.scores_at(7)["pct_below_high"], 0.0);
+    }
+
+    #[test]
+    fn test_calendar_lookbacks_resolve_by_date() {
+        // 40 daily bars climbing to 139, a two-month gap, then 10 bars from 120
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let resumed = NaiveDate::from_ymd_opt(2024, 4, 15).unwrap();
+        let mut dates: Vec<String> = (0..40).map(|i| (start + chrono::Duration::days(i)).to_string()).collect();
+        dates.extend((0..10).map(|i| (resumed + chrono::Duration::days(i)).to_string()));
+        let close: Vec<f64> = (0..40).map(|i| 100.0 + i as f64).chain((0..10).map(|i| 120.0 + i as f64)).collect();
+        let data = Arc::new(TickerData::from_columns(dates, close.clone(), close.clone(), close.clone(), close, vec![1000.0; 50]));
+        let params = |lookback: serde_json::Value| HashMap::from([("lookback".to_string(), lookback)]);
+
+        // 20 bars reach back across the gap to the old highs; a month doesn't
+        assert!(!scan_near_high(&data, &params(serde_json::json!(20))).mask[40]);
+        assert!(scan_near_high(&data, &params(serde_json::json!("1m"))).mask[40]);
+        let breakouts = |lookback| {
+            let mask = scan_price_breakout(&data, &params(lookback)).mask;
+            (0..50).filter(|&i| mask[i]).collect::<Vec<_>>()
+        };
+        assert_eq!(breakouts(serde_json::json!(20)), (20..40).collect::<Vec<_>>());
+        // The prior bar's month must be covered, first true of Feb 1 (bar 31)
+        assert_eq!(breakouts(serde_json::json!("1m")), (32..40).chain(41..50).collect::<Vec<_>>());
+
+        // Through a query: the alias is coerced, and the warm-up covers the whole history
+        let registry = ScanRegistry::load();
+        let mut query = ScanQuery {
+            scan_type: "price_breakout".into(),
+            params: HashMap::from([("lookback_days_calendar".to_string(), serde_json::json!(30))]),
+            date_from: Some("2024-04-16".into()),
+            ..Default::default()
+        };
+        crate::scan_types::coerce_params(&mut query.params, &registry.get("price_breakout").unwrap().meta).unwrap();
+        assert_eq!(query.params["lookback"], "30d");
+        let universe = HashMap::from([("GAP".to_string(), data.clone())]);
+        assert_eq!(run_scan(&registry, &universe, &query).matches.len(), 9);
+    }
+
+    #[test]
+    fn test_elder_impulse_turn_and_bear_power_divergence() {
+        let data = generate_sample_data(400);
+        let impulse = elder_impulse(&data.close, 13, 12, 26, 9);
+        let up = scan_elder_impulse_turn(&data, &HashMap::new());
+        let down = scan_elder_impulse_turn(&data, &HashMap::from([("direction".to_string(), serde_json::json!("down"))]));
+        for i in 1..data.len() {
+            assert_eq!(up.mask[i], impulse[i - 1] == -1.0 && impulse[i] >= 0.0);
+            assert_eq!(down.mask[i], impulse[i - 1] == 1.0 && impulse[i] <= 0.0);
+        }
+        assert!(!up.mask[0] && !down.mask[0]);
+
+        // 3-bar EMA: bar 10 gaps down to a 90 low with the EMA still at 101
+        // (bear power -11); bar 13's 89 low is lower but only 4.1 under its EMA
+        let mut close = vec![110.0; 14];
+        let mut low = vec![109.0; 14];
+        close[10..].fill(92.0);
+        low[10..].copy_from_slice(&[90.0, 91.0, 91.0, 89.0]);
+        let n = close.len();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let dates = (0..n).map(|i| (start + chrono::Duration::days(i as i64)).to_string()).collect();
+        let data = TickerData::from_columns(dates, close.clone(), close.clone(), low, close, vec![1000.0; n]);
+        let params = HashMap::from([
+            ("lookback".to_string(), serde_json::json!(5)),
+            ("ema_period".to_string(), serde_json::json!(3)),
+        ]);
+        let output = scan_bear_power_divergence(&data, &params);
+        assert_eq!((0..n).filter(|&i| output.mask[i]).collect::<Vec<_>>(), [13]);
+        assert!((output.explain(13).unwrap().values["bear_power"].unwrap() + 4.125).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_drawdown_filter() {
+        let close = vec![100.0, 90.0, 75.0, 70.0, 85.0, 79.0, 82.0];
+        let n = close.len();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let dates = (0..n).map(|i| (start + chrono::Duration::days(i as i64)).to_string()).collect();
+        let data = TickerData::from_columns(dates, close.clone(), close.clone(), close.clone(), close, vec![1000.0; n]);
+
+        // Drawdowns: 0, 10, 25, 30, 15, 21, 18
+        let params = |mode: &str| {
+            HashMap::from([
+                ("threshold".to_string(), serde_json::json!(20.0)),
+                ("mode".to_string(), serde_json::json!(mode)),
+            ])
+        };
+        let dip = scan_drawdown_filter(&data, &params("dip"));
+        assert_eq!((0..n).filter(|&i| dip.mask[i]).collect::<Vec<_>>(), [2, 3, 5]);
+        assert!((dip.scores_at(3)["drawdown"] - 30.0).abs() < 1e-9);
+        let recover = scan_drawdown_filter(&data, &params("recover"));
+        assert_eq!((0..n).filter(|&i| recover.mask[i]).collect::<Vec<_>>(), [4, 6]);
+    }
+
+    #[test]
+    fn test_mass_index_bulge() {
+        // Flat 1-point ranges (index at 25), then a burst of wide bars and a
+        // return to narrow ones
+        let n = 120;
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let dates = (0..n).map(|i| (start + chrono::Duration::days(i as i64)).to_string()).collect();
+        let high: Vec<f64> = (0..n).map(|i| if (60..68).contains(&i) { 103.0 } else { 101.0 }).collect();
+        let data = TickerData::from_columns(dates, vec![100.5; n], high, vec![100.0; n], vec![100.5; n], vec![1000.0; n]);
+        
+        let output = scan_mass_index_bulge(&data, &HashMap::new());
+        let mi = &output.series[0].1;
+        assert!((mi[59] - 25.0).abs() < 1e-9);
+        let matches: Vec<usize> = (0..n).filter(|&i| output.mask[i]).collect();
+        assert_eq!(matches.len(), 1);
+        let i = matches[0];
+        let peak = (60..i).find(|&j| mi[j] > 27.0).expect("bulge before the signal");
+        assert!(mi[i] < 26.5 && mi[i - 1] >= 26.5 && peak < i);
+        
+        // Without a bulge there's nothing to signal
+        let params = HashMap::from([("bulge".to_string(), serde_json::json!(100.0))]);
+        assert!(!scan_mass_index_bulge(&data, &params).mask.iter().any(|&m| m));
+    }
+
+    #[test]
+    fn test_deep_drawdown_basis() {
+        let close = vec![200.0, 100.0, 90.0, 80.0, 84.0, 70.0];
+        let n = close.len();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let dates = (0..n).map(|i| (start + chrono::Duration::days(i as i64)).to_string()).collect();
+        let data = TickerData::from_columns(dates, close.clone(), close.clone(), close.clone(), close, vec![1000.0; n]);
+
+        let params = |basis: &str| {
+            HashMap::from([
+                ("threshold".to_string(), serde_json::json!(15.0)),
+                ("basis".to_string(), serde_json::json!(basis)),
+                ("period".to_string(), serde_json::json!(3)),
+            ])
+        };
+        // From the 200 peak every later bar is more than 15% down
+        let all_time = scan_deep_drawdown(&data, &params("all_time"));
+        assert_eq!((0..n).filter(|&i| all_time.mask[i]).collect::<Vec<_>>(), [1, 2, 3, 4, 5]);
+        // 3-bar highs: 200, 100, 90, 84; drawdowns 55, 20, 6.7, 16.7
+        let rolling = scan_deep_drawdown(&data, &params("rolling"));
+        assert_eq!((0..n).filter(|&i| rolling.mask[i]).collect::<Vec<_>>(), [2, 3, 5]);
+        assert!((rolling.scores_at(3)["drawdown"] - 20.0).abs() < 1e-9);
+        assert_eq!(drawdown_min_bars(&params("rolling")), 3);
+        assert_eq!(drawdown_min_bars(&params("all_time")), 1);
+    }
+
+    #[test]
+    fn test_drawdown_recovery_hysteresis() {
+        // All-time drawdowns: 0, 25, 15, 22, 8, 5, 12, 30, 9
+        let close = vec![100.0, 75.0, 85.0, 78.0, 92.0, 95.0, 88.0, 70.0, 91.0];
+        let n = close.len();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let dates = (0..n).map(|i| (start + chrono::Duration::days(i as i64)).to_string()).collect();
+        let data = TickerData::from_columns(dates, close.clone(), close.clone(), close.clone(), close, vec![1000.0; n]);
+
+        let params = HashMap::from([
+            ("dip_pct".to_string(), serde_json::json!(20.0)),
+            ("recover_pct".to_string(), serde_json::json!(10.0)),
+            ("basis".to_string(), serde_json::json!("all_time")),
+        ]);
+        let output = scan_drawdown_recovery(&data, &params);
+        // Bar 1 arms; the stall at 15 (bar 2) and the re-dip (bar 3) don't
+        // match; bar 4 recovers and disarms, so 5 and 6 don't match despite
+        // staying shallow; bar 7 re-arms and bar 8 recovers straight through
+        assert_eq!((0..n).filter(|&i| output.mask[i]).collect::<Vec<_>>(), [4, 8]);
+        assert!((output.scores_at(4)["drawdown"] - 8.0).abs() < 1e-9);
+
+        // With the levels inverted, any shallower bar after an arming one matches
+        let mut loose = params.clone();
+        loose.insert("recover_pct".into(), serde_json::json!(22.0));
+        let output = scan_drawdown_recovery(&data, &loose);
+        assert_eq!((0..n).filter(|&i| output.mask[i]).collect::<Vec<_>>(), [2, 4, 8]);
+    }
+
+    #[test]
+    fn test_nr_breakout_modes() {
+        // Ranges: 4, 3, 2, 2, 5, 5, 1, 0.4
+        let high = vec![14.0, 13.0, 12.0, 12.5, 15.0, 14.0, 13.0, 12.9];
+        let low = vec![10.0, 10.0, 10.0, 10.5, 10.0, 9.0, 12.0, 12.5];
+        let n = high.len();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let dates = (0..n).map(|i| (start + chrono::Duration::days(i as i64)).to_string()).collect();
+        let data = TickerData::from_columns(dates, low.clone(), high, low.clone(), low, vec![1000.0; n]);
+        let params = |pairs: &[(&str, &str)]| {
+            let mut params = HashMap::from([("period".to_string(), serde_json::json!(3))]);
+            params.extend(pairs.iter().map(|(k, v)| (k.to_string(), serde_json::json!(v))));
+            params
+        };
+        let hits = |output: &ScanOutput| (0..n).filter(|&i| output.mask[i]).collect::<Vec<_>>();
+
+        let bars = scan_nr_breakout(&data, &params(&[]));
+        assert_eq!(hits(&bars), [2, 3, 6, 7]);
+        assert_eq!(bars.scores_at(6)["mode"], 0.0);
+        assert_eq!(bars.scores_at(6)["nr_range"], 1.0);
+        assert!(!bars.scores_at(6).contains_key("direction"));
+        // Bar 3 pokes above bar 2's high, so it isn't inside
+        assert_eq!(hits(&scan_nr_breakout(&data, &params(&[("inside_bar", "required")]))), [2, 6, 7]);
+
+        // The bar after each NR bar, judged against that bar's high and low;
+        // bar 7 is the last NR bar and has no next bar to confirm it
+        let up = scan_nr_breakout(&data, &params(&[("mode", "breakout")]));
+        assert_eq!(hits(&up), [3, 4]);
+        assert_eq!(up.scores_at(3)["mode"], 1.0);
+        assert_eq!(up.scores_at(3)["nr_range"], 2.0);
+        assert_eq!(up.scores_at(3)["direction"], 1.0);
+        let down = scan_nr_breakout(&data, &params(&[("mode", "breakout"), ("direction", "down")]));
+        assert_eq!(hits(&down), [4]);
+        // Bar 4 is an outside bar, breaking both sides of bar 3
+        assert_eq!(down.scores_at(4)["direction"], 0.0);
+        let either = scan_nr_breakout(&data, &params(&[("mode", "breakout"), ("direction", "either")]));
+        assert_eq!(hits(&either), [3, 4]);
+    }
+
+    #[test]
+    fn test_turtle_entries_and_exits() {
+        let n = 30;
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let dates = (0..n).map(|i| (start + chrono::Duration::days(i as i64)).to_string()).collect();
+        let (mut high, mut low, mut close) = (vec![101.0; n], vec![99.0; n], vec![100.0; n]);
+        // Bar 12 closes above the 10-bar high
+        (high[12], close[12]) = (103.0, 102.0);
+        // Bar 16 clears 101 but not bar 12's 103, still in the channel
+        (high[16], close[16]) = (102.0, 101.5);
+        // Bar 20 closes under the 5-bar low; bar 21 only under 99, not bar 20's 97
+        (low[20], close[20]) = (97.0, 98.0);
+        (low[21], close[21]) = (98.0, 98.5);
+        let data = TickerData::from_columns(dates, close.clone(), high, low, close, vec![1000.0; n]);
+
+        let params = |signal: &str| {
+            HashMap::from([
+                ("entry_period".to_string(), serde_json::json!(10)),
+                ("exit_period".to_string(), serde_json::json!(5)),
+                ("signal".to_string(), serde_json::json!(signal)),
+            ])
+        };
+        let entries = scan_turtle(&data, &params("entry"));
+        assert_eq!((0..n).filter(|&i| entries.mask[i]).collect::<Vec<_>>(), [12]);
+        assert_eq!(entries.explain(12).unwrap().values["entry_high"], Some(101.0));
+        let exits = scan_turtle(&data, &params("exit"));
+        assert_eq!((0..n).filter(|&i| exits.mask[i]).collect::<Vec<_>>(), [20]);
+    }
+
+    #[test]
+    fn test_strong_close_breakout() {
+        let n = 30;
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let dates = (0..n).map(|i| (start + chrono::Duration::days(i as i64)).to_string()).collect();
+        let (mut high, mut low, mut close, mut volume) = (vec![101.0; n], vec![99.0; n], vec![100.0; n], vec![1000.0; n]);
+        // Bar 22: breaks out and closes at 95% of its range on 3× volume
+        (high[22], low[22], close[22], volume[22]) = (103.0, 99.0, 102.8, 3000.0);
+        // Bar 24: new high that fades to the bottom of the range
+        (high[24], low[24], close[24], volume[24]) = (104.0, 100.0, 100.5, 3000.0);
+        // Bar 26: zero-range bar above every prior high
+        (high[26], low[26], close[26], volume[26]) = (105.0, 105.0, 105.0, 3000.0);
+        // Bar 28: strong close on ordinary volume
+        (high[28], low[28], close[28]) = (106.0, 104.0, 105.9);
+        let data = TickerData::from_columns(dates, close.clone(), high, low, close, volume);
+
+        let params = HashMap::from([("lookback".to_string(), serde_json::json!(10))]);
+        let output = scan_strong_close_breakout(&data, &params);
+        assert_eq!((0..n).filter(|&i| output.mask[i]).collect::<Vec<_>>(), [22]);
+        assert!((output.scores_at(22)["close_location"] - 0.95).abs() < 1e-9);
+        assert_eq!(output.explain(22).unwrap().values["prior_high"], Some(101.0));
+
+        let lenient = HashMap::from([
+            ("lookback".to_string(), serde_json::json!(10)),
+            ("min_close_location".to_string(), serde_json::json!(0.5)),
+            ("multiplier".to_string(), serde_json::json!(0.5)),
+        ]);
+        let output = scan_strong_close_breakout(&data, &lenient);
+        assert_eq!((0..n).filter(|&i| output.mask[i]).collect::<Vec<_>>(), [22, 26, 28]);
+    }
+
+    #[test]
+    fn test_trix_and_ppo_crosses() {
+        let data = generate_sample_data(400);
+        let params = |v: serde_json::Value| -> HashMap<String, serde_json::Value> { serde_json::from_value(v).unwrap() };
+
+        let up = scan_trix_zero_cross(&data, &params(serde_json::json!({ "period": 10 })));
+        let down = scan_trix_zero_cross(&data, &params(serde_json::json!({ "period": 10, "direction": "down" })));
+        let t = trix(&data.close, 10);
+        // Nothing can cross before TRIX's second valid bar
+        assert!(up.mask[..29].iter().chain(&down.mask[..29]).all(|&m| !m));
+        for i in 29..data.len() {
+            assert_eq!(up.mask[i], t[i - 1] <= 0.0 && t[i] > 0.0);
+            assert_eq!(down.mask[i], t[i - 1] >= 0.0 && t[i] < 0.0);
+        }
+        assert_eq!(up.series[0].0, "trix");
+
+        let out = scan_ppo_cross(&data, &HashMap::new());
+        assert_eq!(out.mask, crossed_above(&ppo(&data.close, 12, 26), &ppo_signal(&data.close, 12, 26, 9)));
+        let down = scan_ppo_cross(&data, &params(serde_json::json!({ "direction": "down" })));
+        assert!(out.mask.iter().zip(&down.mask).all(|(a, b)| !(a & b)));
+        assert_eq!(out.series.iter().map(|s| s.0.as_str()).collect::<Vec<_>>(), ["ppo", "signal"]);
+
+        let registry = ScanRegistry::load();
+        assert!(registry.get("trix_zero_cross").is_some() && registry.get("ppo_cross").is_some());
+    }
+
+    #[test]
+    fn test_kst_cross() {
+        let data = generate_sample_data(400);
+        let up = scan_kst_cross(&data, &HashMap::new());
+        let (line, signal) = kst(&data.close, KST_ROC, KST_SMOOTH, KST_WEIGHTS, KST_SIGNAL);
+        assert_eq!(up.mask, crossed_above(&line, &signal));
+        // The signal line starts at bar 52, so the first cross can be at 53
+        assert_eq!(kst_min_bars(&HashMap::new()), 54);
+        assert!(up.mask[..53].iter().all(|&m| !m) && up.mask.iter().any(|&m| m));
+
+        let params = HashMap::from([
+            ("roc4".to_string(), serde_json::json!(40)),
+            ("weight1".to_string(), serde_json::json!(0.5)),
+            ("direction".to_string(), serde_json::json!("down")),
+        ]);
+        let (roc, smooth, weights, signal) = kst_settings(&params);
+        assert_eq!((roc, smooth, weights, signal), ([10, 15, 20, 40], KST_SMOOTH, [0.5, 2.0, 3.0, 4.0], 9));
+        assert_eq!(kst_min_bars(&params), 64);
+        let (line, signal) = kst(&data.close, roc, smooth, weights, signal);
+        assert_eq!(scan_kst_cross(&data, &params).mask, crossed_below(&line, &signal));
+    }
+
+    #[test]
+    fn test_coppock_turn() {
+        let data = generate_sample_data(2000);
+        let daily = HashMap::from([("timeframe".to_string(), serde_json::json!("daily"))]);
+        let curve = coppock(&data.close, COPPOCK_ROC_LONG, COPPOCK_ROC_SHORT, COPPOCK_WMA);
+        let turns = scan_coppock_turn(&data, &daily).mask;
+        assert_eq!(coppock_min_bars(&daily), 26);
+        assert!(turns[..25].iter().all(|&m| !m) && turns.iter().any(|&m| m));
+        for (i, &turned) in turns.iter().enumerate().skip(2) {
+            let expected = curve[i - 1] < 0.0 && curve[i] > curve[i - 1] && curve[i - 1] <= curve[i - 2];
+            assert_eq!(turned, expected, "bar {}", i);
+        }
+        
+        // Monthly by default: turns land on month-end bars, matching the curve
+        // of the monthly closes, and the series only changes on those bars
+        let monthly = scan_coppock_turn(&data, &HashMap::new());
+        let bars = resample_bars(&data, Interval::Monthly);
+        let close: Vec<f64> = bars.iter().map(|b| b.close).collect();
+        let monthly_curve = coppock(&close, COPPOCK_ROC_LONG, COPPOCK_ROC_SHORT, COPPOCK_WMA);
+        let month_ends: Vec<usize> = bars.iter().map(|b| b.end_idx).collect();
+        assert!(monthly.mask.iter().enumerate().filter(|&(_, &m)| m).all(|(i, _)| month_ends.contains(&i)));
+        let series = &monthly.series[0].1;
+        for (k, bar) in bars.iter().enumerate().skip(1) {
+            let same = |a: f64, b: f64| a == b || (a.is_nan() && b.is_nan());
+            assert!(same(series[bar.end_idx], monthly_curve[k]));
+            assert!(same(series[bar.end_idx - 1], monthly_curve[k - 1]));
+        }
+    }
+
+    #[test]
+    fn test_fisher_cross() {
+        let data = generate_sample_data(300);
+        let (fisher, trigger) = fisher_transform(&data.high, &data.low, 10);
+        let up = scan_fisher_cross(&data, &HashMap::new());
+        assert_eq!(up.mask, crossed_above(&fisher, &trigger));
+        assert!(up.mask[..11].iter().all(|&m| !m) && up.mask.iter().any(|&m| m));
+        assert_eq!(ScanRegistry::load().get("fisher_cross").unwrap().min_bars(&HashMap::new()), 12);
+        
+        let down = scan_fisher_cross(&data, &HashMap::from([("direction".to_string(), serde_json::json!("down"))]));
+        assert_eq!(down.mask, crossed_below(&fisher, &trigger));
+        let j = down.mask.iter().position(|&m| m).unwrap();
+        assert_eq!(down.scores_at(j)["fisher"], fisher[j]);
+    }
+
+    #[test]
+    fn test_crossover() {
+        let data = generate_sample_data(300);
+        let registry = ScanRegistry::load();
+        let def = registry.get("crossover").unwrap();
+
+        // Defaults: ema(9) over ema(21)
+        let (fast, slow) = (ema(&data.close, 9), ema(&data.close, 21));
+        let up = scan_crossover(&data, &HashMap::new());
+        assert_eq!(up.mask, crossed_above(&fast, &slow));
+        assert!(up.mask.iter().any(|&m| m));
+        assert_eq!(up.series[0].0, "ema_9");
+        assert_eq!(def.min_bars(&HashMap::new()), 22);
+
+        // A price column against an SMA, given as typed into the UI
+        let mut params = HashMap::from([
+            ("left".to_string(), serde_json::json!("close")),
+            ("right".to_string(), serde_json::json!("sma(50)")),
+            ("direction".to_string(), serde_json::json!("down")),
+        ]);
+        crate::scan_types::coerce_params(&mut params, &def.meta).unwrap();
+        assert_eq!(params["right"], serde_json::json!({ "indicator": "sma", "period": 50 }));
+        let down = scan_crossover(&data, &params);
+        let sma50 = sma(&data.close, 50);
+        assert_eq!(down.mask, crossed_below(&data.close, &sma50));
+        let j = down.mask.iter().position(|&m| m).unwrap();
+        assert_eq!(down.explain(j).unwrap().values["sma_50"], Some(sma50[j]));
+        assert_eq!(def.min_bars(&params), 51);
+
+        let mut bad = HashMap::from([("left".to_string(), serde_json::json!({ "indicator": "wma", "period": 9 }))]);
+        assert!(crate::scan_types::coerce_params(&mut bad, &def.meta).is_err());
+    }
+
+    #[test]
+    fn test_price_vs_ma() {
+        let data = generate_sample_data(400);
+        let registry = ScanRegistry::load();
+        let def = registry.get("price_vs_ma").unwrap();
+        let params = |ma_type: &str, relation: &str| {
+            HashMap::from([
+                ("ma_type".to_string(), serde_json::json!(ma_type)),
+                ("period".to_string(), serde_json::json!(50)),
+                ("relation".to_string(), serde_json::json!(relation)),
+            ])
+        };
+
+        // Every bar above the 200 SMA by default, none during its warm-up
+        let sma200 = sma(&data.close, 200);
+        let above_200 = scan_price_vs_ma(&data, &HashMap::new());
+        let expected: Vec<bool> = (0..data.len()).map(|i| data.close[i] > sma200[i]).collect();
+        assert_eq!(above_200.mask, expected);
+        assert!(!above_200.mask[..199].iter().any(|&m| m));
+        assert_eq!(def.min_bars(&HashMap::new()), 200);
+
+        let ema50 = ema(&data.close, 50);
+        let below_ema = scan_price_vs_ma(&data, &params("ema", "below"));
+        let j = below_ema.mask.iter().position(|&m| m).unwrap();
+        assert!(data.close[j] < ema50[j]);
+        assert!((below_ema.scores_at(j)["pct_from_ma"] - (data.close[j] / ema50[j] - 1.0) * 100.0).abs() < 1e-9);
+        assert_eq!(below_ema.series[1].0, "ema_50");
+
+        let crosses = params("sma", "crosses_above");
+        assert_eq!(scan_price_vs_ma(&data, &crosses).mask, crossed_above(&data.close, &sma(&data.close, 50)));
+        assert_eq!(def.min_bars(&crosses), 51);
+    }
+
+    #[test]
+    fn test_big_mover() {
+        let bars = |open: &[f64], close: &[f64]| {
+            let dates = (1..=close.len()).map(|d| format!("2024-01-{:02}", d)).collect();
+            TickerData::from_columns(dates, open.to_vec(), close.to_vec(), close.to_vec(), close.to_vec(), vec![1000.0; close.len()])
+        };
+        let params = |pairs: &[(&str, serde_json::Value)]| -> HashMap<String, serde_json::Value> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+        };
+        // A zero close (bad data) on bar 2, then a 10% gap up that fades to +4%
+        let data = bars(&[100.0, 100.0, 0.0, 110.0, 99.0], &[100.0, 100.0, 0.0, 104.0, 93.6]);
+
+        let close = scan_big_mover(&data, &HashMap::new());
+        // 100 -> 0 is a -100% move; 0 -> 104 has no base and is skipped
+        assert_eq!(close.mask, [false, false, true, false, true]);
+        assert!(close.series[0].1[3].is_nan());
+        assert!((close.scores_at(4)["move_pct"] + 10.0).abs() < 1e-9);
+
+        let gap = scan_big_mover(&data, &params(&[("basis", "gap".into()), ("direction", "up".into())]));
+        assert_eq!(gap.mask, [false; 5]);
+        let gap = scan_big_mover(
+            &data,
+            &params(&[("basis", "gap".into()), ("direction", "down".into()), ("min_change_pct", 4.0.into())]),
+        );
+        assert_eq!(gap.mask, [false, false, true, false, true]);
+
+        let intraday = params(&[("basis", "open_to_close".into()), ("direction", "down".into())]);
+        assert_eq!(scan_big_mover(&data, &intraday).mask, [false, false, false, true, true]);
+        let mut liquid = intraday;
+        liquid.insert("min_dollar_volume".into(), 100_000.0.into());
+        assert_eq!(scan_big_mover(&data, &liquid).mask, [false, false, false, true, false]);
+    }
+
+    #[test]
+    fn test_streak_scan() {
+        let close = [10.0, 11.0, 12.0, 13.0, 14.0, 13.0, 12.0, 11.0, 12.0];
+        let dates = (1..=close.len()).map(|d| format!("2024-01-{:02}", d)).collect();
+        let data = TickerData::from_columns(dates, close.to_vec(), close.to_vec(), close.to_vec(), close.to_vec(), vec![1.0; 9]);
+
+        let hits = |mask: &[bool]| mask.iter().enumerate().filter(|&(_, &m)| m).map(|(i, _)| i).collect::<Vec<_>>();
+        // Only the bar completing the third higher close, not the fourth
+        let up = scan_streak(&data, &HashMap::new());
+        assert_eq!(hits(&up.mask), [3]);
+        assert_eq!(up.scores_at(3)["streak"], 3.0);
+        let down = HashMap::from([("direction".to_string(), serde_json::json!("down"))]);
+        assert_eq!(hits(&scan_streak(&data, &down).mask), [7]);
+
+        // With a composite filter: a third down day while above the 50 SMA
+        let sample = generate_sample_data(300);
+        let params = HashMap::from([(
+            "filters".to_string(),
+            serde_json::json!([
+                { "indicator": "streak", "op": "<", "value": -2 },
+                { "indicator": "close", "op": ">", "value": { "indicator": "sma", "period": 50 } },
+            ]),
+        )]);
+        let run = streak(&sample.close);
+        let sma50 = sma(&sample.close, 50);
+        let expected: Vec<bool> = (0..sample.len()).map(|i| run[i] < -2.0 && sample.close[i] > sma50[i]).collect();
+        assert_eq!(scan_composite(&sample, &params).mask, expected);
+    }
+
+    #[test]
+    fn test_bollinger_setups() {
+        let data = generate_sample_data(400);
+        let (middle, upper, lower) = bollinger(&data.close, 20, 2.0);
+        let registry = ScanRegistry::load();
+
+        let touch = scan_bb_lower_touch_reversal(&data, &HashMap::new());
+        let expected: Vec<bool> = (0..data.len()).map(|i| data.low[i] < lower[i] && data.close[i] > lower[i]).collect();
+        assert_eq!(touch.mask, expected);
+        let rsi14 = rsi(&data.close, 14);
+        let oversold = HashMap::from([("max_rsi".to_string(), serde_json::json!(40))]);
+        let filtered = scan_bb_lower_touch_reversal(&data, &oversold);
+        assert!((0..data.len()).all(|i| filtered.mask[i] == (expected[i] && rsi14[i] <= 40.0)));
+
+        // Breakouts are upper-band crosses on above-average bandwidth
+        let breakout = scan_bb_upper_breakout(&data, &HashMap::new());
+        let bandwidth = bollinger_bandwidth(&middle, &upper, &lower);
+        let average = sma(&bandwidth, 20);
+        let crossed = crossed_above(&data.close, &upper);
+        for i in 0..data.len() {
+            assert_eq!(breakout.mask[i], crossed[i] && bandwidth[i] > average[i], "bar {}", i);
+        }
+        assert_eq!(registry.get("bb_upper_breakout").unwrap().min_bars(&HashMap::new()), 40);
+        if let Some(j) = breakout.mask.iter().position(|&m| m) {
+            assert_eq!(breakout.scores_at(j)["bandwidth_expansion"], bandwidth[j] / average[j]);
+        }
+
+        // A walk needs `bars` closes in a row above the band, and lasts while they continue
+        let walk = scan_bb_walk(&data, &HashMap::from([("bars".to_string(), serde_json::json!(2))]));
+        for i in 1..data.len() {
+            assert_eq!(walk.mask[i], data.close[i] > upper[i] && data.close[i - 1] > upper[i - 1], "bar {}", i);
+        }
+        let down = HashMap::from([("direction".to_string(), serde_json::json!("down")), ("bars".to_string(), serde_json::json!(1))]);
+        let below: Vec<bool> = (0..data.len()).map(|i| data.close[i] < lower[i]).collect();
+        assert_eq!(scan_bb_walk(&data, &down).mask, below);
+    }
+
+    #[test]
+    fn test_ma_extension() {
+        let data = generate_sample_data(300);
+        let ema20 = ema(&data.close, 20);
+        let pct: Vec<f64> = data.close.iter().zip(&ema20).map(|(&c, &m)| (c - m) / m * 100.0).collect();
+
+        let params = |direction: &str| {
+            HashMap::from([
+                ("threshold_pct".to_string(), serde_json::json!(2)),
+                ("direction".to_string(), serde_json::json!(direction)),
+            ])
+        };
+        let stretched = scan_ma_extension(&data, &params("up"));
+        assert_eq!(stretched.mask, pct.iter().map(|&p| p > 2.0).collect::<Vec<_>>());
+        let j = stretched.mask.iter().position(|&m| m).unwrap();
+        assert!((stretched.scores_at(j)["pct_from_ma"] - pct[j]).abs() < 1e-9);
+        assert_eq!(stretched.series[1].0, "ema_20");
+
+        let washed_out = scan_ma_extension(&data, &params("down"));
+        assert_eq!(washed_out.mask, pct.iter().map(|&p| p < -2.0).collect::<Vec<_>>());
+
+        let sma50 = HashMap::from([("ma_type".to_string(), serde_json::json!("sma")), ("period".to_string(), serde_json::json!(50))]);
+        let output = scan_ma_extension(&data, &sma50);
+        assert!(output.mask[..49].iter().all(|&m| !m));
+        assert_eq!(output.series[1].1[49..], sma(&data.close, 50)[49..]);
+    }
+
+    #[test]
+    fn test_opening_range_breakout() {
+        // Two sessions of 5-minute bars from 14:30, the second gapping up 10%
+        let dates: Vec<String> = ["03", "04"]
+            .iter()
+            .flat_map(|day| (0..12).map(move |k| format!("2024-01-{} {}:{:02}:00", day, 14 + (30 + 5 * k) / 60, (30 + 5 * k) % 60)))
+            .collect();
+        let mut close = vec![10.0; 24];
+        close[8] = 11.5; // above the first range's 10.5 high
+        close[9] = 11.6; // still above: no second cross
+        close[10] = 10.2;
+        close[11] = 11.0; // back in and out again, on light volume
+        close[12..].fill(12.1);
+        close[19] = 12.9;
+        let high: Vec<f64> = close.iter().map(|c| c + 0.5).collect();
+        let low: Vec<f64> = close.iter().map(|c| c - 0.5).collect();
+        let mut volume = vec![100.0; 24];
+        volume[8] = 300.0;
+        volume[19] = 300.0;
+        let data = TickerData::from_columns(dates, close.clone(), high, low, close, volume);
+        assert_eq!(data.timeframe, Timeframe::Intraday);
+
+        let params = |gap: f64| {
+            HashMap::from([
+                ("volume_period".to_string(), serde_json::json!(3)),
+                ("min_gap_pct".to_string(), serde_json::json!(gap)),
+            ])
+        };
+        let output = scan_opening_range_breakout(&data, &params(0.0));
+        let hits: Vec<usize> = (0..24).filter(|&i| output.mask[i]).collect();
+        assert_eq!(hits, [8, 19]);
+        // The first six bars (30 minutes) make each range
+        assert!(output.series[1].1[..6].iter().all(|h| h.is_nan()));
+        assert_eq!((output.series[1].1[6], output.series[2].1[6]), (10.5, 9.5));
+        assert_eq!(output.series[1].1[19], 12.6);
+        let scores = output.scores_at(19);
+        assert!((scores["gap_pct"] - 10.0).abs() < 1e-9);
+        assert!((scores["breakout_pct"] - (12.9 - 12.6) / 12.6 * 100.0).abs() < 1e-9);
+
+        // Only the gapping session passes a gap filter
+        let gapped = scan_opening_range_breakout(&data, &params(5.0));
+        assert_eq!((0..24).filter(|&i| gapped.mask[i]).collect::<Vec<_>>(), [19]);
+
+        let daily = generate_sample_data(100);
+        assert!(!scan_opening_range_breakout(&daily, &params(0.0)).mask.contains(&true));
+    }
+
+    /// Timing for a composite RSI + SMA + volume scan over a large universe:
+    /// `cargo test --release bench_composite -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_composite_scan() {
+        let registry = ScanRegistry::load();
+        let data: HashMap<_, _> =
+            (0..4000).map(|i| (format!("T{}", i), Arc::new(generate_sample_data(1134)))).collect();
+        let params = HashMap::from([(
+            "filters".to_string(),
+            serde_json::json!([
+                { "indicator": "rsi", "period": 14, "op": "<", "value": 70 },
+                { "indicator": "close", "op": ">", "value": { "indicator": "sma", "period": 50 } },
+                { "indicator": "volume_ratio", "period": 20, "op": ">", "value": 0.5 },
+            ]),
+        )]);
+        // Few matches, so the time is mostly the indicators over each full history
+        let query = ScanQuery { scan_type: "composite".into(), params, lookback_days: Some(5), ..Default::default() };
+
+        let runs: Vec<_> = (0..7)
+            .map(|_| {
+                let start = std::time::Instant::now();
+                let result = run_scan(&registry, &data, &query);
+                (start.elapsed(), result.matches.len())
+            })
+            .collect();
+        let mut times: Vec<_> = runs.iter().map(|(t, _)| *t).collect();
+        times.sort();
+        println!("composite scan, 4000 x 1134 bars: best {:?}, median {:?}, {} matches", times[0], times[3], runs[0].1);
+    }
+
+    #[test]
+    fn test_dpo_cross() {
+        let data = generate_sample_data(400);
+        let up = scan_dpo_cross(&data, &HashMap::new());
+        let down = scan_dpo_cross(&data, &HashMap::from([("direction".to_string(), serde_json::json!("down"))]));
+        let d = dpo(&data.close, 20);
+        // DPO starts at bar 19, so the first cross can be at bar 20
+        assert!(up.mask[..20].iter().chain(&down.mask[..20]).all(|&m| !m));
+        for i in 20..data.len() {
+            assert_eq!(up.mask[i], d[i - 1] <= 0.0 && d[i] > 0.0);
+            assert_eq!(down.mask[i], d[i - 1] >= 0.0 && d[i] < 0.0);
+        }
+        assert!(up.mask.iter().any(|&m| m) && down.mask.iter().any(|&m| m));
+        assert_eq!(ScanRegistry::load().get("dpo_cross").unwrap().warmup_bars(&HashMap::new()), 20);
+    }
+
+    #[test]
+    fn test_date_range_checks_and_warnings() {
+        let registry = ScanRegistry::load();
+        // Daily bars from 2020-01-01 to 2020-10-26
+        let data = HashMap::from([("T".to_string(), Arc::new(generate_sample_data(300)))]);
+        let query = |scan_type: &str, from: Option<&str>, to: Option<&str>| ScanQuery {
+            scan_type: scan_type.into(),
+            date_from: from.map(String::from),
+            date_to: to.map(String::from),
+            ..Default::default()
+        };
+
+        let backwards = query("golden_cross", Some("2020-06-01"), Some("2020-05-01")).date_range().unwrap_err();
+        assert!(backwards.contains("is after"), "{}", backwards);
+        let invalid = query("golden_cross", Some("2025-13-45"), None).date_range().unwrap_err();
+        assert!(invalid.contains("date_from") && invalid.contains("YYYY-MM-DD"), "{}", invalid);
+
+        let warnings = |q: ScanQuery| run_scan(&registry, &data, &q).warnings;
+        let outside = warnings(query("rsi_oversold", Some("2030-01-01"), None));
+        assert_eq!(outside, ["No data covers 2030-01-01..; loaded data spans 2020-01-01 to 2020-10-26"]);
+
+        let warmup = registry.get("golden_cross").unwrap().min_bars(&HashMap::new()) - 1;
+        let earliest = (NaiveDate::from_ymd_opt(2020, 1, 1).unwrap() + chrono::Duration::days(warmup as i64)).to_string();
+        let early = warnings(query("golden_cross", None, Some("2020-03-01")));
+        assert!(early.len() == 1 && early[0].contains("ends before") && early[0].contains(&earliest), "{:?}", early);
+        let straddling = warnings(query("golden_cross", Some("2020-02-01"), None));
+        assert!(straddling.len() == 1 && straddling[0].contains("starts within"), "{:?}", straddling);
+
+        assert!(warnings(query("golden_cross", Some("2020-10-01"), None)).is_empty());
+        assert!(warnings(query("golden_cross", None, None)).is_empty());
+        assert!(warnings(query("price_breakout", Some("2020-01-01"), Some("2020-02-01")))[0].contains("ends before"));
+    }
+
+    #[test]
+    fn test_event_gap_continuation() {
+        // (date, open, low, close)
+        let bars: [(&str, f64, f64, f64); 7] = [
+            ("2024-04-30", 100.0, 99.0, 100.0),
+            ("2024-05-01", 100.0, 99.0, 100.0),
+            // Earnings day: gaps 5% and the day's low is 103
+            ("2024-05-02", 105.0, 103.0, 106.0),
+            ("2024-05-03", 106.0, 104.0, 107.0),
+            ("2024-05-06", 107.0, 105.0, 108.0),
+            ("2024-05-07", 104.0, 102.0, 103.0),
+            ("2024-05-08", 104.0, 104.0, 105.0),
+        ];
+        let mut data = TickerData::from_columns(
+            bars.iter().map(|b| b.0.to_string()).collect(),
+            bars.iter().map(|b| b.1).collect(),
+            bars.iter().map(|b| b.3.max(b.1)).collect(),
+            bars.iter().map(|b| b.2).collect(),
+            bars.iter().map(|b| b.3).collect(),
+            vec![1e6; bars.len()],
+        );
+        let earnings = vec![NaiveDate::from_ymd_opt(2024, 5, 2).unwrap()];
+        data.events = Arc::new(HashMap::from([("earnings".to_string(), earnings)]));
+        let store = HashMap::from([("T".to_string(), Arc::new(data))]);
+        let registry = ScanRegistry::load();
+
+        let dates = |params: serde_json::Value| -> Vec<String> {
+            let query = ScanQuery {
+                scan_type: "event_gap_continuation".into(),
+                params: serde_json::from_value(params).unwrap(),
+                ..Default::default()
+            };
+            run_scan(&registry, &store, &query).matches.into_iter().map(|m| m.date).collect()
+        };
+
+        // Held for two bars, then undercut the gap-day low on May 7
+        assert_eq!(dates(serde_json::json!({})), ["2024-05-03", "2024-05-06"]);
+        assert_eq!(dates(serde_json::json!({"days_after": 1})), ["2024-05-03"]);
+        assert!(dates(serde_json::json!({"min_gap_pct": 6.0})).is_empty());
+        assert!(dates(serde_json::json!({"event": "dividends"})).is_empty());
+    }
+
+    #[test]
+    fn test_short_history_is_skipped_not_panicking() {
+        let registry = ScanRegistry::load();
+        let data: HashMap<String, Arc<TickerData>> = HashMap::from([
+            ("SHORT".to_string(), Arc::new(generate_sample_data(10))),
+            ("LONG".to_string(), Arc::new(generate_sample_data(400))),
+        ]);
+
+        for scan in BUILTIN_SCANS {
+            // Scan functions themselves cope with any length
+            for len in 0..=10 {
+                let mask = (scan.func)(&generate_sample_data(len), &HashMap::new());
+                assert_eq!(mask.len(), len, "{}", scan.id);
+            }
+
+            let query = ScanQuery {
+                scan_type: scan.id.into(),
+                ..Default::default()
+            };
+            let min_bars = registry.get(scan.id).unwrap().min_bars(&query.params);
+            let result = run_scan(&registry, &data, &query);
+            assert_eq!(result.scan_errors, 0, "{}", scan.id);
+            assert_eq!(result.tickers_skipped_short_history, usize::from(min_bars > 10), "{}", scan.id);
+            if min_bars > 10 {
+                assert!(result.matches.iter().all(|m| m.ticker == "LONG"), "{}", scan.id);
+            }
+        }
+
+        let query = ScanQuery {
+            scan_type: "rsi_oversold".into(),
+            params: HashMap::from([("period".to_string(), serde_json::json!(5))]),
+            ..Default::default()
+        };
+        assert_eq!(run_scan(&registry, &data, &query).tickers_skipped_short_history, 0);
+    }
+
+    #[test]
+    fn test_beta_filter_uses_benchmark() {
+        let registry = ScanRegistry::load();
+        let bench = generate_sample_data(200);
+        // Every daily return twice the benchmark's: a beta of 2 throughout
+        let mut close = vec![100.0];
+        for i in 1..bench.len() {
+            let last = close[i - 1];
+            close.push(last * (1.0 + 2.0 * (bench.close[i] / bench.close[i - 1] - 1.0)));
+        }
+        let levered = TickerData::from_columns(bench.date.clone(), close.clone(), close.clone(), close.clone(), close, bench.volume.clone());
+        let mut data = HashMap::from([("LEV".to_string(), Arc::new(levered)), ("IDX".to_string(), Arc::new(bench))]);
+
+        let mut query = ScanQuery {
+            scan_type: "beta_filter".into(),
+            params: serde_json::from_value(serde_json::json!({"benchmark": "idx", "period": 20, "threshold": 1.5})).unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(benchmark_symbol(&registry, &query).as_deref(), Some("IDX"));
+        let matches = run_scan(&registry, &data, &query).matches;
+        assert!(matches.iter().all(|m| m.ticker == "LEV" && (m.indicators["beta"] - 2.0).abs() < 1e-9));
+        assert_eq!(matches.len(), 200 - 20);
+
+        query.params.insert("direction".into(), serde_json::json!("below"));
+        let matches = run_scan(&registry, &data, &query).matches;
+        assert!(matches.iter().all(|m| m.ticker == "IDX") && !matches.is_empty());
+
+        // No benchmark loaded: nothing can match
+        data.remove("IDX");
+        assert!(run_scan(&registry, &data, &query).matches.is_empty());
+    }
+
+    #[test]
+    fn test_matches_start_after_warmup_bars() {
+        let registry = ScanRegistry::load();
+        let data = generate_sample_data(400);
+        let index = |m: &ScanMatch| data.date.iter().position(|d| *d == m.date).unwrap();
+
+        for scan in BUILTIN_SCANS.iter().filter(|s| s.listed) {
+            let mut query = ScanQuery { scan_type: scan.id.into(), ..Default::default() };
+            let warmup_bars = ScanDefinition::from(scan).warmup_bars(&query.params);
+            let Ok(strict) = scan_ticker(&registry, "T", &data, &query, None) else {
+                continue;
+            };
+            assert!(strict.matches.iter().all(|m| index(m) >= warmup_bars), "{} matched in its warm-up", scan.id);
+
+            query.include_warmup = true;
+            let all = scan_ticker(&registry, "T", &data, &query, None).unwrap().matches;
+            let settled: Vec<_> = all.iter().filter(|m| index(m) >= warmup_bars).map(|m| &m.date).collect();
+            assert_eq!(settled, strict.matches.iter().map(|m| &m.date).collect::<Vec<_>>(), "{}", scan.id);
+        }
+
+        let params = |value: serde_json::Value| serde_json::from_value(value).unwrap();
+        let macd = registry.get("macd_cross_up").unwrap();
+        assert_eq!(macd.warmup_bars(&HashMap::new()), 35);
+        let custom = registry.get("custom").unwrap();
+        assert_eq!(custom.warmup_bars(&params(serde_json::json!({"conditions": ["rsi_oversold", "golden_cross"]}))), 200);
+
+        // A 200-day EMA is defined from bar 199 on, a bar before it's settled
+        let mut query = ScanQuery {
+            scan_type: "composite".into(),
+            params: params(serde_json::json!({"filters": [{"indicator": "ema", "period": 200, "op": ">", "value": 0}]})),
+            ..Default::default()
+        };
+        assert_eq!(registry.get("composite").unwrap().warmup_bars(&query.params), 200);
+        assert_eq!(scan_ticker(&registry, "T", &data, &query, None).unwrap().matches[0].date, data.date[200]);
+        query.include_warmup = true;
+        assert_eq!(scan_ticker(&registry, "T", &data, &query, None).unwrap().matches[0].date, data.date[199]);
+    }
+
+    #[test]
+    fn test_warmup_slicing_matches_full_scan() {
+        let full = ScanRegistry::load();
+        let mut unsliced = ScanRegistry::default();
+        for scan in BUILTIN_SCANS.iter().filter(|s| s.warmup.is_some()) {
+            let mut def = ScanDefinition::from(scan);
+            def.warmup = None;
+            unsliced.register(def);
+        }
+
+        let data: HashMap<String, Arc<TickerData>> = (0..4)
+            .map(|i| (format!("T{}", i), Arc::new(generate_sample_data(1500 + i * 250))))
+            .collect();
+
+        let params = [
+            HashMap::new(),
+            HashMap::from([
+                ("lookback".to_string(), serde_json::json!(20)),
+                ("period".to_string(), serde_json::json!(10)),
+                ("multiplier".to_string(), serde_json::json!(1.2)),
+                ("range_pct".to_string(), serde_json::json!(15.0)),
+                ("volume_multiplier".to_string(), serde_json::json!(1.0)),
+                ("squeeze_pct".to_string(), serde_json::json!(15.0)),
+                ("timeframe".to_string(), serde_json::json!("weekly")),
+                ("method".to_string(), serde_json::json!("relative")),
+            ]),
+            HashMap::from([
+                ("timeframe".to_string(), serde_json::json!("monthly")),
+                ("mode".to_string(), serde_json::json!("touched_and_reclaimed")),
+                ("level".to_string(), serde_json::json!("s1")),
+                ("method".to_string(), serde_json::json!("percentile")),
+                ("percentile_window".to_string(), serde_json::json!(60)),
+            ]),
+        ];
+        let ranges = [
+            (Some("2022-06-15"), None),
+            (Some("2021-03-01"), Some("2023-01-31")),
+            (None, Some("2021-12-31")),
+            (Some("2030-01-01"), None),
+        ];
+
+        let key = |m: &ScanMatch| (m.ticker.clone(), m.date.clone(), m.close.to_bits());
+        let mut compared = 0;
+        for scan in BUILTIN_SCANS.iter().filter(|s| s.warmup.is_some()) {
+            for params in &params {
+                for (from, to) in ranges {
+                    let query = ScanQuery {
+                        scan_type: scan.id.into(),
+                        params: params.clone(),
+                        date_from: from.map(String::from),
+                        date_to: to.map(String::from),
+                        // The unsliced copies lose their warm-up, and with it their default warmup_bars
+                        include_warmup: true,
+                        ..Default::default()
+                    };
+                    let mut fast: Vec<_> = run_scan(&full, &data, &query).matches.iter().map(key).collect();
+                    let mut slow: Vec<_> = run_scan(&unsliced, &data, &query).matches.iter().map(key).collect();
+                    fast.sort();
+                    slow.sort();
+                    assert_eq!(fast, slow, "{} {:?} {:?}..{:?}", scan.id, params, from, to);
+                    compared += fast.len();
+                }
+            }
+        }
+        assert!(compared > 0);
+    }
 }
@@ -0,0 +1,220 @@
+//! Scratch buffers - per-thread pools of indicator output vectors
+//!
+//! A bulk scan computes the same few series for every ticker. Built-in scans
+//! take their output buffers from the current thread's pool (the `_into`
+//! indicators refill them), and the scanner hands a ticker's vectors back
+//! once its matches are extracted, so each rayon worker reuses a handful of
+//! allocations from one ticker to the next. The pools are emptied when the
+//! scan finishes.
+//!
+//! The functions at the bottom are the hot indicators on pooled buffers, with
+//! the same signatures and results as their `indicators` namesakes.
+//!
+//! The thread also carries the deadline of the ticker being scanned. Every
+//! pooled buffer handed out checks it first, so a ticker that runs past its
+//! time budget stops at its next indicator rather than when it finishes.
+
+use crate::indicators;
+use std::cell::{Cell, RefCell};
+use std::panic;
+use std::time::Instant;
+
+/// Buffers kept per thread and element type; any more returned are dropped
+const MAX_POOLED: usize = 32;
+
+#[derive(Default)]
+struct Pool {
+    floats: Vec<Vec<f64>>,
+    flags: Vec<Vec<bool>>,
+}
+
+thread_local! {
+    static POOL: RefCell<Pool> = RefCell::default();
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Unwind payload of a ticker's scan stopped by `check_deadline`
+#[derive(Debug)]
+pub struct OutOfTime;
+
+/// Run `f` with a deadline for `check_deadline` on this thread; `f` unwinds
+/// with `OutOfTime` once it passes, so callers catch it with `catch_unwind`
+pub fn with_deadline<R>(deadline: Option<Instant>, f: impl FnOnce() -> R) -> R {
+    // Put the previous deadline back even when `f` unwinds
+    struct Restore(Option<Instant>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            DEADLINE.with(|d| d.set(self.0));
+        }
+    }
+    let _restore = Restore(DEADLINE.with(|d| d.replace(deadline)));
+    f()
+}
+
+/// Stop the current scan if its deadline has passed. Unwinds without the
+/// panic hook, so nothing is logged as a panic.
+pub fn check_deadline() {
+    if DEADLINE.with(Cell::get).is_some_and(|deadline| Instant::now() >= deadline) {
+        panic::resume_unwind(Box::new(OutOfTime));
+    }
+}
+
+/// A buffer filled by `fill`, reusing a pooled allocation when there is one
+pub fn f64_with(fill: impl FnOnce(&mut Vec<f64>)) -> Vec<f64> {
+    check_deadline();
+    let mut buffer = POOL.with(|pool| pool.borrow_mut().floats.pop()).unwrap_or_default();
+    fill(&mut buffer);
+    buffer
+}
+
+/// `f64_with` for masks
+pub fn bool_with(fill: impl FnOnce(&mut Vec<bool>)) -> Vec<bool> {
+    check_deadline();
+    let mut buffer = POOL.with(|pool| pool.borrow_mut().flags.pop()).unwrap_or_default();
+    fill(&mut buffer);
+    buffer
+}
+
+/// Return a buffer for reuse on this thread
+pub fn recycle_f64(buffer: Vec<f64>) {
+    POOL.with(|pool| {
+        let floats = &mut pool.borrow_mut().floats;
+        if buffer.capacity() > 0 && floats.len() < MAX_POOLED {
+            floats.push(buffer);
+        }
+    });
+}
+
+/// Return a mask for reuse on this thread
+pub fn recycle_bool(buffer: Vec<bool>) {
+    POOL.with(|pool| {
+        let flags = &mut pool.borrow_mut().flags;
+        if buffer.capacity() > 0 && flags.len() < MAX_POOLED {
+            flags.push(buffer);
+        }
+    });
+}
+
+/// Drop this thread's pooled buffers
+pub fn clear() {
+    POOL.with(|pool| *pool.borrow_mut() = Pool::default());
+}
+
+/// A pooled copy of `values`
+pub fn copy(values: &[f64]) -> Vec<f64> {
+    f64_with(|out| {
+        out.clear();
+        out.extend_from_slice(values);
+    })
+}
+
+pub fn sma(data: &[f64], period: usize) -> Vec<f64> {
+    f64_with(|out| indicators::sma_into(data, period, out))
+}
+
+pub fn ema(data: &[f64], period: usize) -> Vec<f64> {
+    f64_with(|out| indicators::ema_into(data, period, out))
+}
+
+pub fn rsi(data: &[f64], period: usize) -> Vec<f64> {
+    f64_with(|out| indicators::rsi_into(data, period, out))
+}
+
+pub fn crossed_above(a: &[f64], b: &[f64]) -> Vec<bool> {
+    bool_with(|out| indicators::crossed_above_into(a, b, out))
+}
+
+pub fn crossed_below(a: &[f64], b: &[f64]) -> Vec<bool> {
+    bool_with(|out| indicators::crossed_below_into(a, b, out))
+}
+
+pub fn crossed_above_level(data: &[f64], level: f64) -> Vec<bool> {
+    bool_with(|out| indicators::crossed_above_level_into(data, level, out))
+}
+
+pub fn crossed_below_level(data: &[f64], level: f64) -> Vec<bool> {
+    bool_with(|out| indicators::crossed_below_level_into(data, level, out))
+}
+
+pub fn above(data: &[f64], threshold: f64) -> Vec<bool> {
+    bool_with(|out| indicators::above_into(data, threshold, out))
+}
+
+pub fn below(data: &[f64], threshold: f64) -> Vec<bool> {
+    bool_with(|out| indicators::below_into(data, threshold, out))
+}
+
+/// `indicators::volume_ratio`, its average on a pooled buffer too
+pub fn volume_ratio(volume: &[f64], period: usize) -> Vec<f64> {
+    let average = sma(volume, period);
+    let ratio = f64_with(|out| {
+        out.clear();
+        out.extend(volume.iter().zip(&average).map(|(v, a)| if *a > 0.0 && !a.is_nan() { v / a } else { f64::NAN }));
+    });
+    recycle_f64(average);
+    ratio
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_reused_until_cleared() {
+        let first = f64_with(|b| b.extend([1.0, 2.0, 3.0]));
+        let address = first.as_ptr();
+        recycle_f64(first);
+
+        // The pooled allocation comes back, contents and all; `_into` fills clear it
+        let reused = f64_with(|b| {
+            assert_eq!(b[..], [1.0, 2.0, 3.0]);
+            b.clear();
+            b.push(4.0);
+        });
+        assert_eq!((reused.as_ptr(), &reused[..]), (address, &[4.0][..]));
+        recycle_f64(reused);
+
+        clear();
+        assert!(f64_with(|b| assert!(b.is_empty() && b.capacity() == 0)).is_empty());
+        // Unallocated buffers aren't worth keeping
+        recycle_bool(Vec::new());
+        assert_eq!(bool_with(|_| {}).capacity(), 0);
+    }
+
+    #[test]
+    fn test_deadline_stops_pooled_indicators() {
+        let data = crate::data::generate_sample_data(300);
+        let past = Instant::now();
+        let stopped = panic::catch_unwind(|| with_deadline(Some(past), || sma(&data.close, 20)));
+        assert!(stopped.unwrap_err().is::<OutOfTime>());
+        // Restored on the way out
+        assert_eq!(sma(&data.close, 20).len(), 300);
+
+        let later = Instant::now() + std::time::Duration::from_secs(60);
+        assert_eq!(with_deadline(Some(later), || rsi(&data.close, 14).len()), 300);
+        clear();
+    }
+
+    #[test]
+    fn test_pooled_indicators_match() {
+        let data = crate::data::generate_sample_data(300);
+        // A longer buffer left in the pool must not leak into shorter results
+        recycle_f64(vec![7.0; 1000]);
+        recycle_bool(vec![true; 1000]);
+        let slow = indicators::sma(&data.close, 50);
+        assert_eq!(sma(&data.close, 20)[19..], indicators::sma(&data.close, 20)[19..]);
+        assert_eq!(ema(&data.close, 20)[19..], indicators::ema(&data.close, 20)[19..]);
+        assert_eq!(rsi(&data.close, 14)[14..], indicators::rsi(&data.close, 14)[14..]);
+        assert_eq!(crossed_above(&data.close, &slow), indicators::crossed_above(&data.close, &slow));
+        assert_eq!(crossed_below(&data.close, &slow), indicators::crossed_below(&data.close, &slow));
+        assert_eq!(crossed_above_level(&data.close, 100.0), indicators::crossed_above_level(&data.close, 100.0));
+        assert_eq!(crossed_below_level(&data.close, 100.0), indicators::crossed_below_level(&data.close, 100.0));
+        assert_eq!(above(&data.close, 100.0), indicators::above(&data.close, 100.0));
+        assert_eq!(below(&data.close, 100.0), indicators::below(&data.close, 100.0));
+        assert_eq!(copy(&data.close), data.close);
+        let ratio = volume_ratio(&data.volume, 20);
+        assert_eq!(ratio.len(), 300);
+        assert_eq!(ratio[19..], indicators::volume_ratio(&data.volume, 20)[19..]);
+        clear();
+    }
+}
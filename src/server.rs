@@ -1,48 +1,148 @@
 //! Web server - Axum with WebSocket support for streaming results
 
-use crate::data::{DataStore, TickerData};
-use crate::generated;
-use crate::generated_store;
+use crate::auth::{self, ApiKeys};
+use crate::backtest::{run_backtest, BacktestRequest, BacktestResult};
+use crate::benchmark::{aligned_returns, correlation_matrix};
+use crate::breadth::{breadth, BreadthPoint, BREADTH_SMA_PERIOD};
+use crate::error::ApiError;
+use crate::events::EventStore;
+use crate::data::{load_metadata, DEFAULT_MIN_LOAD_BARS, parse_date, parse_date_param, DataStore, DataStoreStats, TickerData, TickerStats, Timeframe};
+use crate::generated_store::{self, GeneratedScanEdit, GeneratedScanSpec};
+use crate::history::{self, HistoryEntry, ScanHistory};
+use crate::jobs::{JobStatus, ScanJobs};
+use crate::run_history::{RecordedRun, RunDiff, RunHistory, RunSummary, TickerSummary};
 use crate::llm;
-use crate::scan_types::{ScanParam, ScanType};
-use crate::scanner::{run_scan, ScanQuery, ScanResult};
+use crate::nl_rules;
+use crate::calendar::Lookback;
+use crate::indicators::{atr, cumulative_max, drawdown, fib_levels, macd_histogram, rolling_beta, rsi, sma, volume_ratio, window_max};
+use crate::openapi::ApiDoc;
+use crate::overlays;
+use crate::registry::ScanRegistry;
+use crate::rate_limit::{self, RateLimits};
+use crate::scan_limit::ScanLimiter;
+use crate::resample::{resample, Interval};
+use crate::scan_types::{self, ScanType};
+use crate::saved_scans::{SavedScan, SavedScanStore, SAVED_SCANS_PATH};
+use crate::scanner::{
+    benchmark_symbol, project_match, run_scan_streaming, run_scan_tracked, run_scan_with, scan_ticker, ScanMatch,
+    ScanProgress, ScanQuery, ScanResult,
+};
+use crate::telemetry;
 use axum::{
-    extract::{Path, Query, State, WebSocketUpgrade},
-    http::StatusCode,
-    response::{Html, IntoResponse, Response},
-    routing::{get, post},
-    Json, Router,
+    body::{Body, Bytes},
+    error_handling::HandleErrorLayer,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    middleware,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
+    routing::{delete, get, post, put},
+    BoxError, Json, Router,
 };
+use chrono::NaiveDate;
+use futures::StreamExt;
+use metrics_exporter_prometheus::PrometheusHandle;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, RwLock};
+use tokio_stream::wrappers::ReceiverStream;
+use tower::timeout::TimeoutLayer;
+use tower::ServiceBuilder;
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::services::ServeDir;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
 
 /// Application state
 pub struct AppState {
     pub data_store: RwLock<DataStore>,
+    pub metrics: PrometheusHandle,
+    pub scan_registry: Arc<ScanRegistry>,
+    pub scan_history: ScanHistory,
+    /// Runs of scans with `record: true`, for `/api/history`
+    pub run_history: RunHistory,
+    /// Scans started with `?async=true`
+    pub scan_jobs: ScanJobs,
+    /// Caps how many scans execute at once, across every scan route
+    pub scan_limiter: ScanLimiter,
+    /// Longest a scan may run (`RETRO_SCAN_TIMEOUT_SECS`, by default just under
+    /// the request timeout); also the default `timeout_ms`
+    pub scan_timeout_ms: u64,
+    pub saved_scans: RwLock<SavedScanStore>,
+    /// Per-client budgets enforced by the `rate_limit::enforce` layer
+    pub rate_limits: Arc<RateLimits>,
+    /// Held while the generated scan store and generated.rs are rewritten
+    pub generated_lock: tokio::sync::Mutex<()>,
+    pub data_dir: PathBuf,
+}
+
+/// Where the server reads data from and listens. Env vars give the defaults;
+/// `main` applies command-line flags over them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    /// OHLCV files; `metadata.csv` and `events/` are read from beside it
     pub data_dir: PathBuf,
+    /// Static UI served at `/`, when the directory exists
+    pub frontend_dir: PathBuf,
+    pub bind_addr: IpAddr,
+    pub port: u16,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("./data/ohlcv"),
+            frontend_dir: PathBuf::from("frontend"),
+            bind_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 3000,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// `RETRO_DATA_DIR`, `RETRO_FRONTEND_DIR`, `RETRO_BIND_ADDR` and
+    /// `RETRO_PORT` over the defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            data_dir: std::env::var_os("RETRO_DATA_DIR").map_or(defaults.data_dir, PathBuf::from),
+            frontend_dir: std::env::var_os("RETRO_FRONTEND_DIR").map_or(defaults.frontend_dir, PathBuf::from),
+            bind_addr: env_or("RETRO_BIND_ADDR", defaults.bind_addr),
+            port: env_or("RETRO_PORT", defaults.port),
+        }
+    }
 }
 
 /// Run the web server
-pub async fn run() {
+pub async fn run(config: ServerConfig) {
+    let metrics = telemetry::install_recorder();
+
     // Initialize data store
-    let data_dir = PathBuf::from("./data/ohlcv");
+    let data_dir = config.data_dir;
     let mut data_store = DataStore::new();
-    
+
     // Try to load data if directory exists
     if data_dir.exists() {
-        if let Err(e) = data_store.load_directory(&data_dir) {
+        tracing::info!("Loading data from {:?}", data_dir);
+        let min_bars = env_or("RETRO_MIN_BARS", DEFAULT_MIN_LOAD_BARS);
+        if let Err(e) = data_store.load_directory(&data_dir, min_bars) {
             tracing::warn!("Could not load data: {}", e);
         }
     } else {
-        tracing::info!("Data directory not found, starting with empty store");
-        tracing::info!("Place parquet/csv files in ./data/ohlcv/ and restart");
-        
+        tracing::info!("Data directory {:?} not found, starting with empty store", data_dir);
+        tracing::info!("Place parquet/csv files in {:?} and restart", data_dir);
+    
         // Generate sample data for demo
         tracing::info!("Generating sample data for demo...");
         for ticker in &["AAPL", "MSFT", "GOOGL", "AMZN", "NVDA", "META", "TSLA", "SPY", "QQQ", "IWM"] {
@@ -51,65 +151,490 @@ pub async fn run() {
             data_store.tickers.push(ticker.to_string());
         }
         data_store.tickers.sort();
+        data_store.refresh_stats();
+        metrics::gauge!("tickers_loaded").set(data_store.stats.ticker_count as f64);
         tracing::info!("Generated {} sample tickers", data_store.tickers.len());
     }
-    
+
+    let metadata_path = data_dir.with_file_name("metadata.csv");
+    if metadata_path.exists() {
+        match load_metadata(&metadata_path) {
+            Ok(metadata) => {
+                tracing::info!("Loaded metadata for {} tickers", metadata.len());
+                data_store.metadata = metadata;
+            }
+            Err(e) => tracing::warn!("Could not load {:?}: {}", metadata_path, e),
+        }
+    }
+
+    let events_dir = data_dir.with_file_name("events");
+    if events_dir.is_dir() {
+        match EventStore::load_dir(&events_dir) {
+            Ok(events) => data_store.set_events(events),
+            Err(e) => tracing::warn!("Could not load {:?}: {}", events_dir, e),
+        }
+    }
+
+    let saved_scans = SavedScanStore::load(&PathBuf::from(SAVED_SCANS_PATH)).unwrap_or_else(|e| {
+        tracing::warn!("Could not load saved scans: {}", e);
+        SavedScanStore::default()
+    });
+
+    let request_timeout = Duration::from_secs(env_or("RETRO_REQUEST_TIMEOUT_SECS", 60));
+    // Scans stop with partial results before the request itself times out
+    let scan_timeout_ms = match std::env::var("RETRO_SCAN_TIMEOUT_SECS").ok().and_then(|v| v.trim().parse::<u64>().ok()) {
+        Some(secs) => secs.saturating_mul(1000),
+        None => scan_timeout_within(request_timeout),
+    };
+    if Duration::from_millis(scan_timeout_ms) >= request_timeout {
+        tracing::warn!(
+            "RETRO_SCAN_TIMEOUT_SECS is not below RETRO_REQUEST_TIMEOUT_SECS; long scans will get a 503 instead of partial results"
+        );
+    }
+
     let state = Arc::new(AppState {
         data_store: RwLock::new(data_store),
+        metrics,
+        scan_registry: Arc::new(ScanRegistry::load()),
+        scan_history: ScanHistory::from_env(),
+        run_history: RunHistory::from_env(),
+        scan_jobs: ScanJobs::default(),
+        scan_limiter: ScanLimiter::from_env(),
+        scan_timeout_ms,
+        saved_scans: RwLock::new(saved_scans),
+        rate_limits: Arc::new(RateLimits::from_env()),
+        generated_lock: tokio::sync::Mutex::new(()),
         data_dir,
     });
-    
+
+    tracing::info!(
+        "Running up to {} scans at once; {} the rest",
+        state.scan_limiter.max_concurrent(),
+        if state.scan_limiter.rejects_when_busy() { "rejecting" } else { "queueing" }
+    );
+
+    let max_body_bytes: usize = env_or("RETRO_MAX_BODY_BYTES", 1024 * 1024);
+
+    // API routes, nested under /api
+    let api = Router::new()
+        .route("/openapi.json", get(openapi_handler))
+        .route("/docs", get(swagger_ui))
+        .route("/tickers", get(get_tickers))
+        .route("/tickers/data", post(get_batch_ticker_data))
+        .route("/correlation", post(correlation_handler))
+        .route("/breadth", get(breadth_handler))
+        .route("/ticker/:ticker", get(get_ticker_data))
+        .route("/ticker/:ticker/resampled", get(get_resampled))
+        .route("/ticker/:ticker/append", post(append_bars_handler))
+        .route("/ticker/:ticker/indicator", get(get_indicator))
+        .route("/ticker/:ticker/scan", post(ticker_scan_handler))
+        .route("/ticker/:ticker/fib", get(get_fib_levels))
+        .route("/ticker/:ticker/drawdown", get(get_drawdown))
+        .route("/ticker/:ticker/beta", get(get_beta))
+        .route("/ticker/:ticker/snapshot", get(get_snapshot))
+        .route("/data/stats", get(get_data_stats))
+        .route("/data/stale", get(get_stale_tickers))
+        .route("/events/:event_type/:ticker", get(get_events))
+        .route("/scan", post(run_scan_handler))
+        .route("/scan/history", get(scan_history_handler))
+        .route("/scan/progress/:job_id", get(scan_progress))
+        .route("/scan/:job_id", delete(cancel_scan_job))
+        .route("/history", get(list_runs))
+        .route("/history/:id", get(get_run))
+        .route("/history/:id/diff/:other_id", get(diff_runs))
+        .route("/saved-scans", get(list_saved_scans).post(create_saved_scan))
+        .route("/saved-scans/:id", get(get_saved_scan).delete(delete_saved_scan))
+        .route("/saved-scans/:id/run", post(run_saved_scan))
+        .route("/backtest", post(backtest_handler))
+        .route("/scan-types", get(get_scan_types))
+        .route("/scan-types/validate", post(validate_scan_spec_handler))
+        .route("/nl/clarify", post(nl_clarify_handler))
+        .route("/nl/compile", post(nl_compile_handler))
+        .route("/generated/rollback", post(rollback_generated_handler))
+        .route("/generated/:id", put(update_generated_handler))
+        .route("/generated/:id/revert", post(revert_generated_handler));
+
+    // Optional API-key auth, on the nest only: static files, /api/health and
+    // /metrics stay open. CORS (outermost) answers preflights without a key.
+    let keys = ApiKeys::from_env();
+    match &keys {
+        Some(_) => tracing::info!("API key authentication enabled"),
+        None => tracing::info!("RETRO_API_KEY not set, API authentication disabled"),
+    }
+
     // Build router
-    let app = Router::new()
-        // API routes
+    let mut app = Router::new()
         .route("/api/health", get(health_check))
-        .route("/api/tickers", get(get_tickers))
-        .route("/api/ticker/:ticker", get(get_ticker_data))
-        .route("/api/scan", post(run_scan_handler))
-        .route("/api/scan-types", get(get_scan_types))
-        .route("/api/nl/clarify", post(nl_clarify_handler))
-        .route("/api/nl/compile", post(nl_compile_handler))
-        // Static files (frontend)
-        .nest_service("/", ServeDir::new("frontend").append_index_html_on_directories(true))
+        .nest("/api", auth::protect(api, keys))
+        .route("/metrics", get(metrics_handler));
+
+    // Static files (frontend), or an API index for headless deployments
+    app = if config.frontend_dir.is_dir() {
+        tracing::info!("Serving frontend from {:?}", config.frontend_dir);
+        app.nest_service("/", ServeDir::new(&config.frontend_dir).append_index_html_on_directories(true))
+    } else {
+        tracing::info!("Frontend directory {:?} not found, serving the API index at /", config.frontend_dir);
+        app.route("/", get(api_index))
+    };
+
+    let mut app = app
+        // Request metrics (route_layer so MatchedPath is available)
+        .route_layer(middleware::from_fn(telemetry::track_http))
         // State
-        .with_state(state)
+        .with_state(state.clone());
+
+    // Per-client rate limits for LLM and scan routes (each disabled unless configured)
+    let rate_limits = state.rate_limits.clone();
+    if rate_limits.llm.is_some() || rate_limits.scan.is_some() {
+        for (name, limiter) in [("LLM", &rate_limits.llm), ("scan", &rate_limits.scan)] {
+            if let Some(limiter) = limiter {
+                tracing::info!("{} rate limit: {} requests/min per client", name, limiter.per_minute());
+            }
+        }
+        app = app.layer(middleware::from_fn_with_state(rate_limits, rate_limit::enforce));
+    }
+
+    let app = app
         // CORS
-        .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any));
-    
+        .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any))
+        // Compression (gzip/deflate/br, negotiated via Accept-Encoding)
+        .layer(CompressionLayer::new());
+    let app = with_request_limits(app, max_body_bytes, request_timeout)
+        // A span per request; its closing event carries status and latency
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(|request: &axum::extract::Request| {
+                    tracing::info_span!("request", method = %request.method(), path = %request.uri().path())
+                })
+                .on_response(DefaultOnResponse::new().level(tracing::Level::INFO).latency_unit(LatencyUnit::Millis)),
+        );
+
     // Run server
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    tracing::info!("🚀 Server running at http://localhost:3000");
-    
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let addr = SocketAddr::new(config.bind_addr, config.port);
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!("Could not listen on {}: {}", addr, e);
+            std::process::exit(1);
+        }
+    };
+    tracing::info!("🚀 Server running at http://{}", addr);
+    // Connect info gives the rate limiter each client's address
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
+
+    tracing::info!("Server stopped");
+}
+
+/// Main endpoints, listed at `/` when there's no frontend
+const API_INDEX: &[(&str, &str, &str)] = &[
+    ("GET", "/api/health", "Health check"),
+    ("GET", "/api/openapi.json", "OpenAPI document"),
+    ("GET", "/api/docs", "Swagger UI"),
+    ("GET", "/api/tickers", "List tickers"),
+    ("GET", "/api/ticker/:ticker", "OHLCV bars for a ticker"),
+    ("GET", "/api/ticker/:ticker/resampled", "Weekly or monthly bars for a ticker"),
+    ("POST", "/api/ticker/:ticker/append", "Append newer OHLCV bars to a loaded ticker"),
+    ("GET", "/api/ticker/:ticker/indicator", "Indicator series for a ticker"),
+    ("POST", "/api/ticker/:ticker/scan", "Run a scan on one ticker"),
+    ("GET", "/api/scan-types", "Available scan types"),
+    ("POST", "/api/scan", "Run a scan"),
+    ("GET", "/api/saved-scans", "Saved scans"),
+    ("POST", "/api/backtest", "Backtest a scan's signals"),
+    ("GET", "/api/breadth", "Daily market breadth across the universe"),
+    ("GET", "/api/data/stats", "Data store statistics"),
+    ("GET", "/api/data/stale", "Tickers whose data ends well before the rest"),
+    ("GET", "/metrics", "Prometheus metrics"),
+];
+
+#[derive(Serialize)]
+struct ApiIndexEndpoint {
+    method: &'static str,
+    path: &'static str,
+    description: &'static str,
+}
+
+#[derive(Serialize)]
+struct ApiIndex {
+    name: &'static str,
+    version: &'static str,
+    description: &'static str,
+    endpoints: Vec<ApiIndexEndpoint>,
+}
+
+async fn api_index() -> Json<ApiIndex> {
+    Json(ApiIndex {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        description: env!("CARGO_PKG_DESCRIPTION"),
+        endpoints: API_INDEX
+            .iter()
+            .map(|&(method, path, description)| ApiIndexEndpoint { method, path, description })
+            .collect(),
+    })
+}
+
+/// The OpenAPI document. Routes without a typed response are listed with
+/// their summary only.
+fn api_doc(registry: &ScanRegistry) -> serde_json::Value {
+    let mut doc = ApiDoc::new();
+    doc.route("get", "/api/health", "Health check with the tickers and bars loaded (also in the x-data-tickers header)")
+        .returns::<HealthResponse>();
+    doc.route("get", "/api/tickers", "List tickers").query::<TickersQuery>().returns::<Vec<String>>();
+    doc.route("post", "/api/tickers/data", "Date-aligned series for several tickers")
+        .body::<BatchTickerRequest>()
+        .returns::<BatchTickerResponse>();
+    doc.route("post", "/api/correlation", "Pairwise return correlations")
+        .body::<CorrelationRequest>()
+        .returns::<CorrelationResponse>();
+    doc.route("get", "/api/breadth", "Daily market breadth").query::<BreadthQuery>().returns::<BreadthResponse>();
+    doc.route("get", "/api/ticker/{ticker}", "OHLCV bars for a ticker")
+        .query::<TickerQuery>()
+        .returns::<TickerResponse>();
+    doc.route("get", "/api/ticker/{ticker}/resampled", "Weekly or monthly bars for a ticker")
+        .query::<ResampledQuery>()
+        .returns::<TickerResponse>();
+    doc.route("post", "/api/ticker/{ticker}/append", "Append newer bars to a loaded ticker")
+        .body::<Vec<OHLCVPoint>>()
+        .returns::<AppendResponse>();
+    doc.route("get", "/api/ticker/{ticker}/indicator", "Indicator series by name (see overlays)")
+        .returns::<IndicatorResponse>();
+    doc.route("post", "/api/ticker/{ticker}/scan", "Run a scan on one ticker")
+        .query::<TickerScanOptions>()
+        .scan_body()
+        .returns::<TickerScanResponse>();
+    doc.route("get", "/api/ticker/{ticker}/fib", "Fibonacci retracements").query::<FibQuery>().returns::<FibResponse>();
+    doc.route("get", "/api/ticker/{ticker}/drawdown", "Running peak and drawdown")
+        .query::<DrawdownQuery>()
+        .returns::<DrawdownResponse>();
+    doc.route("get", "/api/ticker/{ticker}/beta", "Current beta to a benchmark").query::<BetaQuery>().returns::<BetaResponse>();
+    doc.route("get", "/api/ticker/{ticker}/snapshot", "Latest values of common indicators").returns::<SnapshotResponse>();
+    doc.route("get", "/api/data/stats", "Data store statistics");
+    doc.route("get", "/api/data/stale", "Tickers whose data ends more than `days` before the newest date")
+        .query::<StaleQuery>();
+    doc.route("get", "/api/events/{event_type}/{ticker}", "Event dates for a ticker");
+    doc.route("post", "/api/scan", "Run a scan").query::<ScanOptions>().scan_body().returns::<ScanResult>();
+    doc.route("get", "/api/scan/history", "Recent scans");
+    doc.route("get", "/api/scan/progress/{job_id}", "Progress of an async scan");
+    doc.route("delete", "/api/scan/{job_id}", "Cancel an async scan");
+    doc.route("get", "/api/history", "Recorded runs");
+    doc.route("get", "/api/history/{id}", "A recorded run");
+    doc.route("get", "/api/history/{id}/diff/{other_id}", "Diff two recorded runs");
+    doc.route("get", "/api/saved-scans", "Saved scans");
+    doc.route("post", "/api/saved-scans", "Save a scan");
+    doc.route("get", "/api/saved-scans/{id}", "A saved scan");
+    doc.route("delete", "/api/saved-scans/{id}", "Delete a saved scan");
+    doc.route("post", "/api/saved-scans/{id}/run", "Run a saved scan").returns::<ScanResult>();
+    doc.route("post", "/api/backtest", "Backtest a scan's signals");
+    doc.route("get", "/api/scan-types", "Available scan types and their params").returns::<Vec<ScanType>>();
+    doc.route("post", "/api/scan-types/validate", "Validate a generated scan spec");
+    doc.route("post", "/api/nl/clarify", "Questions to pin down a natural-language scan")
+        .body::<NlClarifyRequest>()
+        .returns::<NlClarifyResponse>();
+    doc.route("post", "/api/nl/compile", "Compile a natural-language scan, or pick an existing one")
+        .body::<NlCompileRequest>()
+        .returns::<NlCompileResult>();
+    doc.route("post", "/api/generated/rollback", "Restore the generated scans from their backup");
+    doc.route("put", "/api/generated/{id}", "Edit a generated scan").returns::<GeneratedSaveResponse>();
+    doc.route("post", "/api/generated/{id}/revert", "Revert a generated scan to a kept version")
+        .returns::<GeneratedSaveResponse>();
+    doc.into_document(&registry.list())
+}
+
+async fn openapi_handler(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    Json(api_doc(&state.scan_registry))
+}
+
+/// Swagger UI for `/api/openapi.json`, with its assets from a CDN
+const SWAGGER_UI_HTML: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>retro API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/api/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+async fn swagger_ui() -> Html<&'static str> {
+    Html(SWAGGER_UI_HTML)
+}
+
+/// Read a config value from the environment, falling back to `default`
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Default scan timeout: the request timeout less a tenth of it (at least a
+/// second), left for the tickers in flight to stop and the response to go out
+fn scan_timeout_within(request_timeout: Duration) -> u64 {
+    let request_ms = request_timeout.as_millis() as u64;
+    request_ms.saturating_sub((request_ms / 10).max(1000)).max(1)
+}
+
+/// Bodies over `max_body_bytes` get a 413, and requests still running after
+/// `timeout` a 503
+fn with_request_limits(app: Router, max_body_bytes: usize, timeout: Duration) -> Router {
+    app.layer(RequestBodyLimitLayer::new(max_body_bytes)).layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(handle_timeout_error))
+            .layer(TimeoutLayer::new(timeout)),
+    )
+}
+
+async fn handle_timeout_error(err: BoxError) -> ApiError {
+    if err.is::<tower::timeout::error::Elapsed>() {
+        ApiError::Timeout("Request timed out".into())
+    } else {
+        ApiError::Internal(format!("Unhandled internal error: {}", err))
+    }
+}
+
+/// Resolves on SIGINT (Ctrl-C) or SIGTERM. In-flight requests - including
+/// generated-scan writes - are drained before `serve` returns.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl-C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    let reason = tokio::select! {
+        _ = ctrl_c => "SIGINT",
+        _ = terminate => "SIGTERM",
+    };
+
+    tracing::info!("Received {}, shutting down gracefully...", reason);
 }
 
 // ============================================
 // HANDLERS
 // ============================================
 
-async fn health_check() -> &'static str {
-    "OK"
+#[derive(Serialize, JsonSchema)]
+struct HealthResponse {
+    status: &'static str,
+    /// 0 until data is loaded; scans answer 409 meanwhile
+    tickers_loaded: usize,
+    total_bars: usize,
 }
 
-async fn get_tickers(State(state): State<Arc<AppState>>) -> Json<Vec<String>> {
+async fn health_check(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let store = state.data_store.read().await;
-    Json(store.get_tickers().to_vec())
+    let stats = &store.stats;
+    let summary = format!("tickers={}; bars={}", stats.ticker_count, stats.total_bars);
+    let body = HealthResponse { status: "ok", tickers_loaded: stats.ticker_count, total_bars: stats.total_bars };
+    ([("x-data-tickers", summary)], Json(body))
 }
 
-#[derive(Deserialize)]
+/// 409 for a scan over an empty store, which would otherwise look like a
+/// scan that found nothing
+fn require_data(state: &AppState, store: &DataStore) -> Result<(), ApiError> {
+    if !store.get_tickers().is_empty() {
+        return Ok(());
+    }
+    Err(ApiError::Conflict(format!(
+        "No market data is loaded, so there is nothing to scan. Put parquet or CSV files in {} (RETRO_DATA_DIR or --data-dir) and restart the server",
+        state.data_dir.display()
+    )))
+}
+
+/// 404 for an unknown ticker, saying so when no data is loaded at all
+fn ticker_not_found(store: &DataStore, ticker: &str) -> ApiError {
+    match store.get_tickers().is_empty() {
+        true => ApiError::NotFound(format!("Ticker not found: {} (no market data is loaded)", ticker)),
+        false => ApiError::NotFound(format!("Ticker not found: {}", ticker)),
+    }
+}
+
+async fn metrics_handler(State(state): State<Arc<AppState>>) -> String {
+    state.metrics.render()
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct TickersQuery {
+    sector: Option<String>,
+}
+
+async fn get_tickers(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TickersQuery>,
+) -> Json<Vec<String>> {
+    let store = state.data_store.read().await;
+    let tickers = store.get_tickers().iter();
+    Json(match &query.sector {
+        Some(sector) => tickers.filter(|t| store.in_sector(t, sector)).cloned().collect(),
+        None => tickers.cloned().collect(),
+    })
+}
+
+#[derive(Deserialize, JsonSchema)]
 struct TickerQuery {
     from: Option<String>,
     to: Option<String>,
+    interval: Option<String>,
+    max_points: Option<usize>,
+    /// Split- and dividend-adjusted prices instead of the raw ones
+    #[serde(default)]
+    adjusted: bool,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct TickerResponse {
     ticker: String,
+    interval: Interval,
+    /// `intraday` when the bars are the ticker's own sub-daily ones
+    timeframe: Timeframe,
     data: Vec<OHLCVPoint>,
+    /// Dates of the ticker's first and last bars, whatever the range asked for
+    first_date: Option<String>,
+    last_date: Option<String>,
+    /// Set when the ticker is known but has no bars in the range
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
 }
 
-#[derive(Serialize)]
+impl TickerResponse {
+    /// `points` are `data`'s bars in the requested range (maybe resampled);
+    /// none gets a warning naming the dates the ticker does have
+    fn new(ticker: &str, data: &TickerData, interval: Interval, timeframe: Timeframe, points: Vec<OHLCVPoint>) -> Self {
+        let ticker = ticker.to_uppercase();
+        let (first_date, last_date) = (data.date.first().cloned(), data.date.last().cloned());
+        let warnings = match (&first_date, &last_date) {
+            _ if !points.is_empty() => vec![],
+            (Some(first), Some(last)) => {
+                vec![format!("No bars for {} in the requested range; its data runs from {} to {}", ticker, first, last)]
+            }
+            _ => vec![format!("{} has no bars", ticker)],
+        };
+        TickerResponse { ticker, interval, timeframe, data: points, first_date, last_date, warnings }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 struct OHLCVPoint {
     date: String,
     open: f64,
@@ -119,413 +644,2266 @@ struct OHLCVPoint {
     volume: f64,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct NlClarifyRequest {
     query: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct NlCompileRequest {
     query: String,
     answers: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize)]
-struct NlCompileResponse {
+#[derive(Serialize, JsonSchema)]
+struct GeneratedSaveResponse {
     scan_id: String,
+    /// Version the save created
+    version: u32,
     requires_restart: bool,
     message: String,
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
-    error: String,
+/// An existing scan that already answers an NL query, with the params to run it
+#[derive(Serialize, JsonSchema)]
+struct ExistingScanResponse {
+    scan_id: String,
+    params: HashMap<String, serde_json::Value>,
+    /// Ready for `/api/scan`, including any date window the query named
+    query: ScanQuery,
+    requires_restart: bool,
+    message: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum NlCompileResponse {
+    NewScan(GeneratedSaveResponse),
+    UseExisting(Box<ExistingScanResponse>),
+}
+
+/// Which path answered an NL request
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum NlSource {
+    Llm,
+    /// `nl_rules`, when the LLM is unconfigured or failed
+    Rules,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct NlCompileResult {
+    source: NlSource,
+    #[serde(flatten)]
+    result: NlCompileResponse,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct NlClarifyResponse {
+    source: NlSource,
+    #[serde(flatten)]
+    clarify: llm::ClarifyResponse,
+}
+
+#[derive(Deserialize)]
+struct RevertQuery {
+    version: u32,
+}
+
+/// `data` itself, or its split- and dividend-adjusted copy when `adjusted`
+/// is asked for (422 if the ticker has no adjusted closes)
+fn price_basis(ticker: &str, data: Arc<TickerData>, adjusted: bool) -> Result<Arc<TickerData>, ApiError> {
+    match adjusted {
+        true => data
+            .adjusted()
+            .map(Arc::new)
+            .ok_or_else(|| ApiError::Unprocessable(format!("No adjustment data for {}", ticker.to_uppercase()))),
+        false => Ok(data),
+    }
 }
 
 async fn get_ticker_data(
     State(state): State<Arc<AppState>>,
     Path(ticker): Path<String>,
     Query(query): Query<TickerQuery>,
-) -> Result<Json<TickerResponse>, StatusCode> {
+) -> Result<Json<TickerResponse>, ApiError> {
     let store = state.data_store.read().await;
-    
-    let data = store
-        .get(&ticker)
-        .ok_or(StatusCode::NOT_FOUND)?;
-    
-    let mut points: Vec<OHLCVPoint> = data
-        .date
-        .iter()
-        .enumerate()
-        .filter(|(_, date)| {
-            if let Some(ref from) = query.from {
-                if *date < from {
-                    return false;
-                }
-            }
-            if let Some(ref to) = query.to {
-                if *date > to {
-                    return false;
+
+    let data = store.get(&ticker).ok_or_else(|| ticker_not_found(&store, &ticker))?;
+    let data = price_basis(&ticker, data, query.adjusted)?;
+
+    let mut interval = match query.interval.as_deref() {
+        Some(s) => Interval::parse(s)
+            .ok_or_else(|| ApiError::BadRequest(format!("Unknown interval: {}", s)))?,
+        None => Interval::Daily,
+    };
+
+    let filtered = select_range(&data, query.from.as_deref(), query.to.as_deref())?;
+
+    // Intraday tickers keep their own bars unless an interval is asked for
+    let mut bars = match query.interval {
+        None if filtered.timeframe == Timeframe::Intraday => filtered.clone(),
+        _ => resample(&filtered, interval),
+    };
+
+    // Fall back to coarser intervals, then tail-truncate, to honor max_points
+    if let Some(max_points) = query.max_points {
+        while bars.len() > max_points {
+            match interval.coarser() {
+                Some(next) => {
+                    interval = next;
+                    bars = resample(&filtered, interval);
                 }
+                None => break,
             }
-            true
-        })
-        .map(|(i, date)| OHLCVPoint {
-            date: date.clone(),
-            open: data.open[i],
-            high: data.high[i],
-            low: data.low[i],
-            close: data.close[i],
-            volume: data.volume[i],
+        }
+    }
+
+    let skip = query
+        .max_points
+        .map(|max| bars.len().saturating_sub(max))
+        .unwrap_or(0);
+
+    let points = ohlcv_points(&bars, skip);
+
+    Ok(Json(TickerResponse::new(&ticker, &data, interval, bars.timeframe, points)))
+}
+
+/// Bars of `data` whose day falls within `from..=to`, in date order
+fn select_range(data: &TickerData, from: Option<&str>, to: Option<&str>) -> Result<TickerData, ApiError> {
+    let from = parse_date_param("from", from).map_err(ApiError::BadRequest)?;
+    let to = parse_date_param("to", to).map_err(ApiError::BadRequest)?;
+
+    let mut indices: Vec<usize> = (0..data.len())
+        .filter(|&i| {
+            let day = data.days[i];
+            from.is_none_or(|f| day >= f) && to.is_none_or(|t| day <= t)
         })
         .collect();
-    
-    // Sort by date
-    points.sort_by(|a, b| a.date.cmp(&b.date));
-    
-    Ok(Json(TickerResponse {
-        ticker: ticker.to_uppercase(),
-        data: points,
-    }))
+
+    // Sort by date before aggregating so partial first/last buckets are correct
+    indices.sort_by_key(|&i| data.days[i]);
+    Ok(data.select(&indices))
 }
 
-async fn run_scan_handler(
+fn ohlcv_points(bars: &TickerData, skip: usize) -> Vec<OHLCVPoint> {
+    (skip..bars.len())
+        .map(|i| OHLCVPoint {
+            date: bars.date[i].clone(),
+            open: bars.open[i],
+            high: bars.high[i],
+            low: bars.low[i],
+            close: bars.close[i],
+            volume: bars.volume[i],
+        })
+        .collect()
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ResampledQuery {
+    timeframe: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    /// Split- and dividend-adjusted prices instead of the raw ones
+    #[serde(default)]
+    adjusted: bool,
+}
+
+/// Higher-timeframe bars only, so charts needn't fetch the daily series to aggregate
+async fn get_resampled(
     State(state): State<Arc<AppState>>,
-    Json(query): Json<ScanQuery>,
-) -> Json<ScanResult> {
+    Path(ticker): Path<String>,
+    Query(query): Query<ResampledQuery>,
+) -> Result<Json<TickerResponse>, ApiError> {
+    let timeframe = query
+        .timeframe
+        .as_deref()
+        .ok_or_else(|| ApiError::BadRequest("timeframe is required (weekly or monthly)".to_string()))?;
+    let interval = Interval::parse(timeframe)
+        .ok_or_else(|| ApiError::BadRequest(format!("Unknown timeframe: {}", timeframe)))?;
+
     let store = state.data_store.read().await;
-    let data = store.data.clone();
+    let data = store.get(&ticker).ok_or_else(|| ticker_not_found(&store, &ticker))?;
+    let data = price_basis(&ticker, data, query.adjusted)?;
 
-    // run_scan uses Rayon (blocking), so run it on the blocking thread pool
-    let result = tokio::task::spawn_blocking(move || run_scan(&data, &query))
-        .await
-        .expect("scan task panicked");
+    let filtered = select_range(&data, query.from.as_deref(), query.to.as_deref())?;
+    let bars = resample(&filtered, interval);
 
-    Json(result)
+    Ok(Json(TickerResponse::new(&ticker, &data, interval, bars.timeframe, ohlcv_points(&bars, 0))))
 }
 
-async fn nl_clarify_handler(
-    Json(req): Json<NlClarifyRequest>,
-) -> Result<Json<llm::ClarifyResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let query = req.query;
-    let response = tokio::task::spawn_blocking(move || llm::clarify(&query))
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Clarify task panicked".into(),
-                }),
-            )
-        })?;
+#[derive(Serialize, JsonSchema)]
+struct AppendResponse {
+    ticker: String,
+    /// Rows added; rows for dates already loaded are skipped
+    appended: usize,
+    bars: usize,
+    last_date: Option<String>,
+}
 
-    match response {
-        Ok(payload) => Ok(Json(payload)),
-        Err(e) => Err((
-            StatusCode::NOT_IMPLEMENTED,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
-        )),
+/// `POST /api/ticker/:ticker/append` - add end-of-day (or newer intraday)
+/// bars to a loaded ticker without reloading the data directory
+async fn append_bars_handler(
+    State(state): State<Arc<AppState>>,
+    Path(ticker): Path<String>,
+    Json(rows): Json<Vec<OHLCVPoint>>,
+) -> Result<Json<AppendResponse>, ApiError> {
+    if rows.is_empty() {
+        return Err(ApiError::BadRequest("No bars to append".to_string()));
+    }
+    for row in &rows {
+        if parse_date(&row.date).is_none() {
+            return Err(ApiError::BadRequest(format!("Invalid date: {}", row.date)));
+        }
+        let prices = [row.open, row.high, row.low, row.close];
+        if prices.iter().any(|p| !p.is_finite() || *p <= 0.0) || !row.volume.is_finite() || row.volume < 0.0 {
+            return Err(ApiError::BadRequest(format!("Invalid prices or volume on {}", row.date)));
+        }
+    }
+    let bars = TickerData::from_columns(
+        rows.iter().map(|r| r.date.clone()).collect(),
+        rows.iter().map(|r| r.open).collect(),
+        rows.iter().map(|r| r.high).collect(),
+        rows.iter().map(|r| r.low).collect(),
+        rows.iter().map(|r| r.close).collect(),
+        rows.iter().map(|r| r.volume).collect(),
+    );
+
+    let mut store = state.data_store.write().await;
+    if store.get(&ticker).is_none() {
+        return Err(ticker_not_found(&store, &ticker));
     }
+    let appended = store.append_bars(&ticker, bars).map_err(ApiError::BadRequest)?;
+    let data = store.get(&ticker).expect("checked above");
+
+    Ok(Json(AppendResponse {
+        ticker: ticker.to_uppercase(),
+        appended,
+        bars: data.len(),
+        last_date: data.date.last().cloned(),
+    }))
 }
 
-async fn nl_compile_handler(
-    Json(req): Json<NlCompileRequest>,
-) -> Result<Json<NlCompileResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let query = req.query;
-    let answers = req.answers;
+#[derive(Serialize, JsonSchema)]
+struct IndicatorResponse {
+    ticker: String,
+    name: String,
+    dates: Vec<String>,
+    /// Output name → values aligned to `dates`; warm-up and undefined bars are null
+    series: BTreeMap<&'static str, Vec<Option<f64>>>,
+}
 
-    let spec = tokio::task::spawn_blocking(move || llm::compile(&query, &answers))
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Compile task panicked".into(),
-                }),
-            )
-        })?;
+/// `GET /api/ticker/:ticker/indicator?name=sma&period=50` - computed on full
+/// history, then cut to `from`/`to`, so the first bars of the range aren't warm-up
+async fn get_indicator(
+    State(state): State<Arc<AppState>>,
+    Path(ticker): Path<String>,
+    Query(mut params): Query<HashMap<String, String>>,
+) -> Result<Json<IndicatorResponse>, ApiError> {
+    let name = params
+        .remove("name")
+        .ok_or_else(|| ApiError::BadRequest("name is required".into()))?;
+    let from = parse_date_param("from", params.remove("from").as_deref()).map_err(ApiError::BadRequest)?;
+    let to = parse_date_param("to", params.remove("to").as_deref()).map_err(ApiError::BadRequest)?;
+    let adjusted = match params.remove("adjusted").as_deref() {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(other) => return Err(ApiError::BadRequest(format!("adjusted must be true or false, got {}", other))),
+    };
 
-    let spec = match spec {
-        Ok(spec) => spec,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_IMPLEMENTED,
-                Json(ErrorResponse {
-                    error: e.to_string(),
-                }),
-            ))
-        }
+    let data = {
+        let store = state.data_store.read().await;
+        store.get(&ticker).ok_or_else(|| ticker_not_found(&store, &ticker))?
     };
+    let data = price_basis(&ticker, data, adjusted)?;
 
-    let (json_path, rs_path) = generated_store::generated_paths();
-    let mut specs = generated_store::load_specs(&json_path).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to load generated scans: {}", e),
-            }),
-        )
-    })?;
+    let computed = overlays::compute(&data, &name, &params).map_err(ApiError::BadRequest)?;
 
-    generated_store::upsert_spec(&mut specs, spec.clone());
-    generated_store::save_specs(&json_path, &specs).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to save generated scans: {}", e),
-            }),
-        )
-    })?;
+    let indices: Vec<usize> = (0..data.len())
+        .filter(|&i| {
+            let day = data.days[i];
+            from.is_none_or(|f| day >= f) && to.is_none_or(|t| day <= t)
+        })
+        .collect();
 
-    generated_store::write_generated_rs(&rs_path, &specs).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to write generated Rust: {}", e),
-            }),
-        )
+    Ok(Json(IndicatorResponse {
+        ticker: ticker.to_uppercase(),
+        name,
+        dates: indices.iter().map(|&i| data.date[i].clone()).collect(),
+        series: computed
+            .into_iter()
+            .map(|(key, values)| {
+                let aligned = indices
+                    .iter()
+                    .map(|&i| Some(values[i]).filter(|v| v.is_finite()))
+                    .collect();
+                (key, aligned)
+            })
+            .collect(),
+    }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct FibQuery {
+    lookback: Option<usize>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct FibPoint {
+    date: String,
+    price: f64,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct FibLevel {
+    ratio: f64,
+    price: f64,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct FibResponse {
+    ticker: String,
+    /// Latest bar, which the levels are computed as of
+    date: String,
+    lookback: usize,
+    /// `up` when the swing low came before the high (levels retrace down from the high)
+    direction: &'static str,
+    swing_high: FibPoint,
+    swing_low: FibPoint,
+    levels: Vec<FibLevel>,
+    close: f64,
+    /// Level the latest close is closest to
+    nearest: FibLevel,
+}
+
+/// `GET /api/ticker/:ticker/fib?lookback=120` - retracement levels of the
+/// swing over the last `lookback` bars
+async fn get_fib_levels(
+    State(state): State<Arc<AppState>>,
+    Path(ticker): Path<String>,
+    Query(query): Query<FibQuery>,
+) -> Result<Json<FibResponse>, ApiError> {
+    let lookback = query.lookback.unwrap_or(120);
+    if lookback < 2 {
+        return Err(ApiError::BadRequest("lookback must be at least 2".into()));
+    }
+    let data = {
+        let store = state.data_store.read().await;
+        store.get(&ticker).ok_or_else(|| ticker_not_found(&store, &ticker))?
+    };
+
+    let fib = fib_levels(&data.high, &data.low, lookback).ok_or_else(|| {
+        ApiError::Unprocessable(format!("{} has {} bars, fewer than lookback {}", ticker, data.len(), lookback))
     })?;
+    let last = data.len() - 1;
+    let close = data.close[last];
+    let (ratio, price) = fib.nearest(close).unwrap_or((f64::NAN, f64::NAN));
 
-    Ok(Json(NlCompileResponse {
-        scan_id: generated_store::normalize_scan_id(&spec.id),
-        requires_restart: true,
-        message: "Generated scan saved. Restart the server to load it.".into(),
+    Ok(Json(FibResponse {
+        ticker: ticker.to_uppercase(),
+        date: data.date[last].clone(),
+        lookback,
+        direction: if fib.is_uptrend() { "up" } else { "down" },
+        swing_high: FibPoint { date: data.date[fib.high_index].clone(), price: fib.swing_high },
+        swing_low: FibPoint { date: data.date[fib.low_index].clone(), price: fib.swing_low },
+        levels: fib.levels.iter().map(|&(ratio, price)| FibLevel { ratio, price }).collect(),
+        close,
+        nearest: FibLevel { ratio, price },
     }))
 }
 
-async fn get_scan_types() -> Json<Vec<ScanType>> {
-    let mut scans = vec![
-        ScanType {
-            id: "golden_cross".into(),
-            name: "Golden Cross".into(),
-            description: "50 SMA crosses above 200 SMA".into(),
-            params: vec![],
-        },
-        ScanType {
-            id: "death_cross".into(),
-            name: "Death Cross".into(),
-            description: "50 SMA crosses below 200 SMA".into(),
-            params: vec![],
-        },
-        ScanType {
-            id: "ema_cross".into(),
-            name: "EMA Cross".into(),
-            description: "Fast EMA crosses slow EMA".into(),
-            params: vec![
-                ScanParam {
-                    name: "fast".into(),
-                    param_type: "number".into(),
-                    default: 12.into(),
-                    description: "Fast EMA period".into(),
-                },
-                ScanParam {
-                    name: "slow".into(),
-                    param_type: "number".into(),
-                    default: 26.into(),
-                    description: "Slow EMA period".into(),
-                },
-                ScanParam {
-                    name: "direction".into(),
-                    param_type: "select".into(),
-                    default: "up".into(),
-                    description: "Cross direction".into(),
-                },
-            ],
-        },
-        ScanType {
-            id: "rsi_oversold".into(),
-            name: "RSI Oversold".into(),
-            description: "RSI crosses below threshold".into(),
-            params: vec![
-                ScanParam {
-                    name: "period".into(),
-                    param_type: "number".into(),
-                    default: 14.into(),
-                    description: "RSI period".into(),
-                },
-                ScanParam {
-                    name: "threshold".into(),
-                    param_type: "number".into(),
-                    default: 30.into(),
-                    description: "Oversold threshold".into(),
-                },
-            ],
-        },
-        ScanType {
-            id: "rsi_overbought".into(),
-            name: "RSI Overbought".into(),
-            description: "RSI crosses above threshold".into(),
-            params: vec![
-                ScanParam {
-                    name: "period".into(),
-                    param_type: "number".into(),
-                    default: 14.into(),
-                    description: "RSI period".into(),
-                },
-                ScanParam {
-                    name: "threshold".into(),
-                    param_type: "number".into(),
-                    default: 70.into(),
-                    description: "Overbought threshold".into(),
-                },
-            ],
-        },
-        ScanType {
-            id: "obv_breakout".into(),
-            name: "OBV Breakout".into(),
-            description: "OBV breaks above recent high".into(),
-            params: vec![ScanParam {
-                name: "lookback".into(),
-                param_type: "number".into(),
-                default: 20.into(),
-                description: "Lookback period for resistance".into(),
-            }],
-        },
-        ScanType {
-            id: "volume_spike".into(),
-            name: "Volume Spike".into(),
-            description: "Volume exceeds average by multiplier".into(),
-            params: vec![
-                ScanParam {
-                    name: "period".into(),
-                    param_type: "number".into(),
-                    default: 20.into(),
-                    description: "Average volume period".into(),
-                },
-                ScanParam {
-                    name: "multiplier".into(),
-                    param_type: "number".into(),
-                    default: 2.0.into(),
-                    description: "Volume multiplier".into(),
-                },
-            ],
-        },
-        ScanType {
-            id: "macd_cross_up".into(),
-            name: "MACD Cross Up".into(),
-            description: "MACD crosses above signal line".into(),
-            params: vec![
-                ScanParam {
-                    name: "fast".into(),
-                    param_type: "number".into(),
-                    default: 12.into(),
-                    description: "Fast EMA period".into(),
-                },
-                ScanParam {
-                    name: "slow".into(),
-                    param_type: "number".into(),
-                    default: 26.into(),
-                    description: "Slow EMA period".into(),
-                },
-                ScanParam {
-                    name: "signal".into(),
-                    param_type: "number".into(),
-                    default: 9.into(),
-                    description: "Signal line period".into(),
-                },
-            ],
-        },
-        ScanType {
-            id: "price_breakout".into(),
-            name: "Price Breakout".into(),
-            description: "Price breaks above N-day high".into(),
-            params: vec![ScanParam {
-                name: "lookback".into(),
-                param_type: "number".into(),
-                default: 252.into(),
-                description: "Lookback period (252 = 52 weeks)".into(),
-            }],
-        },
-        ScanType {
-            id: "monthly_gap_drop".into(),
-            name: "Monthly Gap Drop".into(),
-            description: "Month opens below prior close by a % threshold".into(),
-            params: vec![
-                ScanParam {
-                    name: "gap_pct".into(),
-                    param_type: "number".into(),
-                    default: 5.0.into(),
-                    description: "Gap-down percent (e.g. 5 = -5%)".into(),
-                },
-                ScanParam {
-                    name: "candle".into(),
-                    param_type: "text".into(),
-                    default: "any".into(),
-                    description: "Monthly candle filter: any | bullish | bearish".into(),
-                },
-                ScanParam {
-                    name: "event_on".into(),
-                    param_type: "text".into(),
-                    default: "start".into(),
-                    description: "Match date: start | end (month)".into(),
-                },
-            ],
-        },
-        ScanType {
-            id: "bullish_divergence".into(),
-            name: "Bullish Divergence".into(),
-            description: "Price lower low + OBV higher high".into(),
-            params: vec![ScanParam {
-                name: "lookback".into(),
-                param_type: "number".into(),
-                default: 20.into(),
-                description: "Lookback period".into(),
-            }],
-        },
-        ScanType {
-            id: "consolidation_breakout".into(),
-            name: "Consolidation Breakout".into(),
-            description: "Breakout from tight range with volume".into(),
-            params: vec![
-                ScanParam {
-                    name: "period".into(),
-                    param_type: "number".into(),
-                    default: 30.into(),
-                    description: "Consolidation period".into(),
-                },
-                ScanParam {
-                    name: "range_pct".into(),
-                    param_type: "number".into(),
-                    default: 5.0.into(),
-                    description: "Max range percentage".into(),
-                },
-                ScanParam {
-                    name: "volume_multiplier".into(),
-                    param_type: "number".into(),
-                    default: 1.5.into(),
-                    description: "Volume multiplier for breakout".into(),
-                },
-            ],
-        },
-        ScanType {
-            id: "bullish_engulfing_oversold".into(),
-            name: "Bullish Engulfing (Oversold)".into(),
-            description: "Green candle engulfs red candle after RSI < 30".into(),
-            params: vec![
-                ScanParam {
-                    name: "rsi_period".into(),
-                    param_type: "number".into(),
-                    default: 14.into(),
-                    description: "RSI period".into(),
-                },
-                ScanParam {
-                    name: "rsi_threshold".into(),
-                    param_type: "number".into(),
-                    default: 30.into(),
-                    description: "RSI oversold threshold".into(),
-                },
-                ScanParam {
-                    name: "lookback".into(),
-                    param_type: "number".into(),
-                    default: 5.into(),
-                    description: "Days to look back for oversold condition".into(),
-                },
-            ],
-        },
-    ];
+#[derive(Deserialize, JsonSchema)]
+struct DrawdownQuery {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct MaxDrawdown {
+    date: String,
+    drawdown: f64,
+    /// Date of the peak it's measured from
+    peak_date: String,
+}
 
-    scans.extend(generated::list_scan_types());
-    Json(scans)
+#[derive(Serialize, JsonSchema)]
+struct DrawdownResponse {
+    ticker: String,
+    dates: Vec<String>,
+    close: Vec<f64>,
+    /// Running high of close, over the whole history rather than just the range
+    peak: Vec<Option<f64>>,
+    /// Percent below `peak`, 0 at new highs
+    drawdown: Vec<Option<f64>>,
+    /// Deepest point within the range
+    max_drawdown: Option<MaxDrawdown>,
+}
+
+/// `GET /api/ticker/:ticker/drawdown` - the underwater curve, cut to
+/// `from`/`to` after computing on full history
+async fn get_drawdown(
+    State(state): State<Arc<AppState>>,
+    Path(ticker): Path<String>,
+    Query(query): Query<DrawdownQuery>,
+) -> Result<Json<DrawdownResponse>, ApiError> {
+    let from = parse_date_param("from", query.from.as_deref()).map_err(ApiError::BadRequest)?;
+    let to = parse_date_param("to", query.to.as_deref()).map_err(ApiError::BadRequest)?;
+    let data = {
+        let store = state.data_store.read().await;
+        store.get(&ticker).ok_or_else(|| ticker_not_found(&store, &ticker))?
+    };
+
+    let peak = cumulative_max(&data.close);
+    let dd = drawdown(&data.close);
+    let indices: Vec<usize> = (0..data.len())
+        .filter(|&i| {
+            let day = data.days[i];
+            from.is_none_or(|f| day >= f) && to.is_none_or(|t| day <= t)
+        })
+        .collect();
+
+    let max_drawdown = indices
+        .iter()
+        .copied()
+        .filter(|&i| dd[i].is_finite())
+        .max_by(|&a, &b| dd[a].total_cmp(&dd[b]))
+        .map(|i| {
+            let peak_index = data.close[..=i].iter().rposition(|&c| c == peak[i]).unwrap_or(i);
+            MaxDrawdown {
+                date: data.date[i].clone(),
+                drawdown: dd[i],
+                peak_date: data.date[peak_index].clone(),
+            }
+        });
+    let finite = |values: &[f64], i: usize| Some(values[i]).filter(|v| v.is_finite());
+
+    Ok(Json(DrawdownResponse {
+        ticker: ticker.to_uppercase(),
+        dates: indices.iter().map(|&i| data.date[i].clone()).collect(),
+        close: indices.iter().map(|&i| data.close[i]).collect(),
+        peak: indices.iter().map(|&i| finite(&peak, i)).collect(),
+        drawdown: indices.iter().map(|&i| finite(&dd, i)).collect(),
+        max_drawdown,
+    }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct BetaQuery {
+    #[serde(default = "default_beta_benchmark")]
+    benchmark: String,
+    #[serde(default = "default_beta_period")]
+    period: usize,
+}
+
+fn default_beta_benchmark() -> String {
+    "SPY".into()
+}
+
+fn default_beta_period() -> usize {
+    60
+}
+
+#[derive(Serialize, JsonSchema)]
+struct BetaResponse {
+    ticker: String,
+    benchmark: String,
+    period: usize,
+    /// The ticker's last bar
+    date: Option<String>,
+    /// Over the `period` returns ending there; null without enough shared
+    /// dates or when the benchmark was flat
+    beta: Option<f64>,
+}
+
+/// Current beta of a ticker's daily returns to a benchmark's on the same dates
+async fn get_beta(
+    State(state): State<Arc<AppState>>,
+    Path(ticker): Path<String>,
+    Query(query): Query<BetaQuery>,
+) -> Result<Json<BetaResponse>, ApiError> {
+    if query.period < 2 {
+        return Err(ApiError::BadRequest("period must be at least 2".into()));
+    }
+    let (data, bench) = {
+        let store = state.data_store.read().await;
+        let data = store.get(&ticker).ok_or_else(|| ticker_not_found(&store, &ticker))?;
+        let bench = store
+            .get(&query.benchmark)
+            .ok_or_else(|| ApiError::NotFound(format!("Benchmark not found: {}", query.benchmark)))?;
+        (data, bench)
+    };
+
+    let (asset, market) = aligned_returns(&data, &bench);
+    let beta = rolling_beta(&asset, &market, query.period);
+
+    Ok(Json(BetaResponse {
+        ticker: ticker.to_uppercase(),
+        benchmark: query.benchmark.to_uppercase(),
+        period: query.period,
+        date: data.date.last().cloned(),
+        beta: beta.last().copied().filter(|b| b.is_finite()),
+    }))
+}
+
+#[derive(Serialize, JsonSchema)]
+struct SnapshotValue {
+    value: f64,
+    /// Bar the value is from: the last one unless the indicator is NaN there
+    date: String,
+}
+
+/// Each field null when the indicator has no value anywhere in the history
+#[derive(Serialize, JsonSchema)]
+struct SnapshotResponse {
+    ticker: String,
+    /// The ticker's last bar
+    date: String,
+    close: f64,
+    /// Close of the bar before; null when the ticker has one bar
+    prev_close: Option<f64>,
+    /// Percent change from `prev_close` to `close`
+    change_pct: Option<f64>,
+    rsi_14: Option<SnapshotValue>,
+    /// MACD(12, 26) minus its 9-bar signal
+    macd_histogram: Option<SnapshotValue>,
+    atr_14: Option<SnapshotValue>,
+    sma_50: Option<SnapshotValue>,
+    sma_200: Option<SnapshotValue>,
+    /// Volume vs its 20-bar average
+    volume_ratio_20: Option<SnapshotValue>,
+    /// Percent the close is below its highest close of the last 52 weeks
+    pct_below_52w_high: Option<SnapshotValue>,
+}
+
+/// `GET /api/ticker/:ticker/snapshot` - the latest non-NaN value of a fixed
+/// set of indicators, each computed on full history
+async fn get_snapshot(
+    State(state): State<Arc<AppState>>,
+    Path(ticker): Path<String>,
+) -> Result<Json<SnapshotResponse>, ApiError> {
+    let data = {
+        let store = state.data_store.read().await;
+        store.get(&ticker).ok_or_else(|| ticker_not_found(&store, &ticker))?
+    };
+    let last = data.len().checked_sub(1).ok_or_else(|| ApiError::Unprocessable(format!("{} has no bars", ticker)))?;
+
+    let latest = |values: Vec<f64>| {
+        values
+            .iter()
+            .rposition(|v| v.is_finite())
+            .map(|i| SnapshotValue { value: values[i], date: data.date[i].clone() })
+    };
+    let high = window_max(&data.close, &Lookback::Weeks(52).window_starts(&data.days));
+    let pct_below_high = data
+        .close
+        .iter()
+        .zip(&high)
+        .map(|(&c, &h)| if h > 0.0 { (h - c) / h * 100.0 } else { f64::NAN })
+        .collect();
+
+    let prev_close = last.checked_sub(1).map(|p| data.close[p]);
+
+    Ok(Json(SnapshotResponse {
+        ticker: ticker.to_uppercase(),
+        date: data.date[last].clone(),
+        close: data.close[last],
+        prev_close,
+        change_pct: prev_close.filter(|&p| p > 0.0).map(|p| (data.close[last] - p) / p * 100.0),
+        rsi_14: latest(rsi(&data.close, 14)),
+        macd_histogram: latest(macd_histogram(&data.close, 12, 26, 9)),
+        atr_14: latest(atr(&data.high, &data.low, &data.close, 14)),
+        sma_50: latest(sma(&data.close, 50)),
+        sma_200: latest(sma(&data.close, 200)),
+        volume_ratio_20: latest(volume_ratio(&data.volume, 20)),
+        pct_below_52w_high: latest(pct_below_high),
+    }))
+}
+
+const MAX_BATCH_TICKERS: usize = 50;
+
+/// Which dates a batch covers: any ticker's (`union`, the default) or only
+/// those every found ticker has (`intersection`)
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum DateAlign {
+    Union,
+    Intersection,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct BatchTickerRequest {
+    /// Up to 50 tickers; unknown ones are listed in `not_found`
+    tickers: Vec<String>,
+    from: Option<String>,
+    to: Option<String>,
+    /// Any of open, high, low, close and volume (default close)
+    fields: Option<Vec<String>>,
+    /// Rebase price fields to 100 at the first date the found tickers share
+    #[serde(default)]
+    normalize: bool,
+    align: Option<DateAlign>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct BatchTickerResponse {
+    dates: Vec<String>,
+    /// Ticker → field → values aligned to `dates`, null where the ticker has no bar
+    series: HashMap<String, HashMap<String, Vec<Option<f64>>>>,
+    not_found: Vec<String>,
+}
+
+fn field_values<'a>(data: &'a TickerData, field: &str) -> Option<&'a [f64]> {
+    match field {
+        "open" => Some(&data.open),
+        "high" => Some(&data.high),
+        "low" => Some(&data.low),
+        "close" => Some(&data.close),
+        "volume" => Some(&data.volume),
+        _ => None,
+    }
+}
+
+/// Date-aligned series for several tickers. With `normalize`, price fields are
+/// rebased to 100 at the first date every found ticker has data for; that's a
+/// 400 when they share no date, and a column whose base is zero is all null.
+/// Intraday tickers are a 400 too, since bars are aligned by day.
+async fn get_batch_ticker_data(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<BatchTickerRequest>,
+) -> Result<Json<BatchTickerResponse>, ApiError> {
+    if req.tickers.len() > MAX_BATCH_TICKERS {
+        return Err(ApiError::BadRequest(format!(
+            "Too many tickers: {} (max {})",
+            req.tickers.len(),
+            MAX_BATCH_TICKERS
+        )));
+    }
+
+    let fields = req.fields.unwrap_or_else(|| vec!["close".to_string()]);
+    if let Some(field) = fields.iter().find(|f| !matches!(f.as_str(), "open" | "high" | "low" | "close" | "volume")) {
+        return Err(ApiError::BadRequest(format!("Unknown field: {}", field)));
+    }
+
+    let from = parse_date_param("from", req.from.as_deref()).map_err(ApiError::BadRequest)?;
+    let to = parse_date_param("to", req.to.as_deref()).map_err(ApiError::BadRequest)?;
+
+    let store = state.data_store.read().await;
+
+    let mut found: Vec<(String, Arc<TickerData>)> = Vec::new();
+    let mut not_found = Vec::new();
+
+    for ticker in &req.tickers {
+        let ticker = ticker.to_uppercase();
+        if found.iter().any(|(t, _)| *t == ticker) {
+            continue;
+        }
+        match store.get(&ticker) {
+            // Series are aligned by day, which would collapse a session's bars
+            Some(data) if data.timeframe == Timeframe::Intraday => {
+                return Err(ApiError::BadRequest(format!(
+                    "{} has intraday bars; fetch it from /api/ticker/{}",
+                    ticker, ticker
+                )));
+            }
+            Some(data) => found.push((ticker, data)),
+            None => not_found.push(ticker),
+        }
+    }
+
+    // Index each ticker's bars by parsed date within the requested range, so
+    // vendors with different date string formats still line up
+    let indexed: Vec<HashMap<NaiveDate, usize>> = found
+        .iter()
+        .map(|(_, data)| {
+            data.days
+                .iter()
+                .enumerate()
+                .filter(|(_, &day)| {
+                    from.is_none_or(|f| day >= f) && to.is_none_or(|t| day <= t)
+                })
+                .map(|(i, &day)| (day, i))
+                .collect()
+        })
+        .collect();
+
+    let all_dates: BTreeSet<NaiveDate> = indexed.iter().flat_map(|m| m.keys().copied()).collect();
+    let shared = |date: &NaiveDate| indexed.iter().all(|m| m.contains_key(date));
+
+    let dates: Vec<NaiveDate> = match req.align.unwrap_or(DateAlign::Union) {
+        DateAlign::Union => all_dates.into_iter().collect(),
+        DateAlign::Intersection => all_dates.into_iter().filter(|d| shared(d)).collect(),
+    };
+    let base_date = dates.iter().copied().find(|d| shared(d));
+    let rebased = req.normalize && fields.iter().any(|f| f != "volume");
+    if rebased && !found.is_empty() && base_date.is_none() {
+        return Err(ApiError::BadRequest(
+            "normalize needs a date every found ticker has data for in the range".into(),
+        ));
+    }
+
+    let mut series = HashMap::new();
+    for ((ticker, data), by_date) in found.iter().zip(&indexed) {
+        let mut columns = HashMap::new();
+        for field in &fields {
+            let Some(values) = field_values(data, field) else {
+                continue;
+            };
+            let base = match req.normalize && field != "volume" {
+                true => base_date.and_then(|d| by_date.get(&d)).map(|&i| values[i]),
+                false => None,
+            };
+            let column: Vec<Option<f64>> = dates
+                .iter()
+                .map(|d| {
+                    let &i = by_date.get(d)?;
+                    match base {
+                        Some(b) if b == 0.0 || !b.is_finite() => None,
+                        Some(b) => Some(values[i] / b * 100.0),
+                        None => Some(values[i]),
+                    }
+                })
+                .collect();
+            columns.insert(field.clone(), column);
+        }
+        series.insert(ticker.clone(), columns);
+    }
+
+    Ok(Json(BatchTickerResponse {
+        dates: dates.iter().map(|d| d.format("%Y-%m-%d").to_string()).collect(),
+        series,
+        not_found,
+    }))
+}
+
+/// Pairwise correlations grow as n², so the matrix takes fewer tickers than a batch
+const MAX_CORRELATION_TICKERS: usize = 25;
+
+#[derive(Deserialize, JsonSchema)]
+struct CorrelationRequest {
+    tickers: Vec<String>,
+    /// Daily returns correlated, ending at the last day all tickers share
+    #[serde(default = "default_correlation_lookback")]
+    lookback: usize,
+}
+
+fn default_correlation_lookback() -> usize {
+    60
+}
+
+#[derive(Serialize, JsonSchema)]
+struct CorrelationResponse {
+    tickers: Vec<String>,
+    lookback: usize,
+    from: String,
+    to: String,
+    /// Rows and columns follow `tickers`; null where a ticker was flat
+    matrix: Vec<Vec<Option<f64>>>,
+}
+
+async fn correlation_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CorrelationRequest>,
+) -> Result<Json<CorrelationResponse>, ApiError> {
+    let mut tickers: Vec<String> = Vec::new();
+    for ticker in &req.tickers {
+        let ticker = ticker.to_uppercase();
+        if !tickers.contains(&ticker) {
+            tickers.push(ticker);
+        }
+    }
+    if tickers.len() < 2 {
+        return Err(ApiError::BadRequest("Need at least 2 distinct tickers".into()));
+    }
+    if tickers.len() > MAX_CORRELATION_TICKERS {
+        return Err(ApiError::BadRequest(format!(
+            "Too many tickers: {} (max {})",
+            tickers.len(),
+            MAX_CORRELATION_TICKERS
+        )));
+    }
+    if req.lookback < 2 {
+        return Err(ApiError::BadRequest("lookback must be at least 2".into()));
+    }
+
+    let store = state.data_store.read().await;
+    let data = tickers
+        .iter()
+        .map(|t| store.get(t).ok_or_else(|| ticker_not_found(&store, t)))
+        .collect::<Result<Vec<_>, _>>()?;
+    drop(store);
+
+    let series: Vec<&TickerData> = data.iter().map(|d| d.as_ref()).collect();
+    let result = correlation_matrix(&series, req.lookback).ok_or_else(|| {
+        ApiError::Unprocessable(format!(
+            "The tickers share fewer than {} days; lower the lookback",
+            req.lookback + 1
+        ))
+    })?;
+
+    Ok(Json(CorrelationResponse {
+        tickers,
+        lookback: req.lookback,
+        from: result.from.to_string(),
+        to: result.to.to_string(),
+        matrix: result
+            .matrix
+            .into_iter()
+            .map(|row| row.into_iter().map(|v| Some(v).filter(|v| v.is_finite())).collect())
+            .collect(),
+    }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct BreadthQuery {
+    #[serde(default = "default_breadth_lookback")]
+    lookback: usize,
+    from: Option<String>,
+    to: Option<String>,
+    sector: Option<String>,
+}
+
+fn default_breadth_lookback() -> usize {
+    252
+}
+
+#[derive(Serialize, JsonSchema)]
+struct BreadthResponse {
+    lookback: usize,
+    sma_period: usize,
+    series: Vec<BreadthPoint>,
+}
+
+/// Daily new-high/new-low and above-SMA counts across the universe
+async fn breadth_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<BreadthQuery>,
+) -> Result<Json<BreadthResponse>, ApiError> {
+    if query.lookback == 0 {
+        return Err(ApiError::BadRequest("lookback must be at least 1".into()));
+    }
+    let from = parse_date_param("from", query.from.as_deref()).map_err(ApiError::BadRequest)?;
+    let to = parse_date_param("to", query.to.as_deref()).map_err(ApiError::BadRequest)?;
+    let data = state.data_store.read().await.universe(query.sector.as_deref());
+    let permit = state.scan_limiter.acquire().await?;
+
+    let lookback = query.lookback;
+    let series = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        breadth(&data, lookback, (from, to))
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Breadth task failed: {}", e);
+        ApiError::Internal("breadth failed".into())
+    })?;
+
+    Ok(Json(BreadthResponse { lookback, sma_period: BREADTH_SMA_PERIOD, series }))
+}
+
+#[derive(Serialize)]
+struct EventsResponse {
+    event_type: String,
+    ticker: String,
+    dates: Vec<String>,
+}
+
+async fn get_events(
+    State(state): State<Arc<AppState>>,
+    Path((event_type, ticker)): Path<(String, String)>,
+) -> Result<Json<EventsResponse>, ApiError> {
+    let store = state.data_store.read().await;
+    let event_type = event_type.to_lowercase();
+    let ticker = ticker.to_uppercase();
+
+    let dates = store.events.get(&event_type, &ticker).ok_or_else(|| {
+        ApiError::NotFound(format!(
+            "Unknown event type: {} (loaded: {})",
+            event_type,
+            store.events.types().join(", ")
+        ))
+    })?;
+
+    Ok(Json(EventsResponse {
+        dates: dates.iter().map(|d| d.format("%Y-%m-%d").to_string()).collect(),
+        event_type,
+        ticker,
+    }))
+}
+
+#[derive(Deserialize)]
+struct DataStatsQuery {
+    #[serde(default)]
+    per_ticker: bool,
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct DataStatsResponse {
+    #[serde(flatten)]
+    stats: DataStoreStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tickers: Option<Vec<TickerStats>>,
+}
+
+async fn get_data_stats(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DataStatsQuery>,
+) -> Json<DataStatsResponse> {
+    let store = state.data_store.read().await;
+
+    let tickers = query.per_ticker.then(|| {
+        store
+            .ticker_stats
+            .iter()
+            .skip(query.offset.unwrap_or(0))
+            .take(query.limit.unwrap_or(100))
+            .cloned()
+            .collect()
+    });
+
+    Json(DataStatsResponse {
+        stats: store.stats.clone(),
+        tickers,
+    })
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct StaleQuery {
+    /// Calendar days a ticker's data may end before the newest date in the store
+    #[serde(default = "default_stale_days")]
+    days: u32,
+}
+
+fn default_stale_days() -> u32 {
+    30
+}
+
+#[derive(Serialize)]
+struct StaleResponse {
+    /// Newest date in the store, which staleness counts back from
+    newest_date: Option<String>,
+    days: u32,
+    tickers: Vec<TickerStats>,
+}
+
+async fn get_stale_tickers(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StaleQuery>,
+) -> Json<StaleResponse> {
+    let store = state.data_store.read().await;
+    Json(StaleResponse {
+        newest_date: store.newest_day().map(|d| d.format("%Y-%m-%d").to_string()),
+        days: query.days,
+        tickers: store.stale_tickers(query.days),
+    })
+}
+
+/// Validate dates and params, and apply the scan's default lookback
+fn prepare_scan_query(state: &AppState, query: &mut ScanQuery) -> Result<(), ApiError> {
+    validate_scan_query(state, query)?;
+    let has_dates = query.date_from.is_some() || query.date_to.is_some();
+    if !has_dates && query.lookback_days.is_none() {
+        query.lookback_days = state.scan_registry.get(&query.scan_type).and_then(|def| def.meta.default_lookback_days);
+    }
+    Ok(())
+}
+
+/// Validate dates and params, resolving the scan id. Backtests use this
+/// alone: their entries span the whole history unless dates are given.
+fn validate_scan_query(state: &AppState, query: &mut ScanQuery) -> Result<(), ApiError> {
+    let def = state.scan_registry.resolve(&query.scan_type).map_err(ApiError::BadRequest)?;
+    query.scan_type = def.meta.id.clone();
+    query.date_range().map_err(ApiError::BadRequest)?;
+    if let Some(confirm) = &query.confirm {
+        confirm.validate().map_err(ApiError::BadRequest)?;
+    }
+    query.adr_filter().map_err(ApiError::BadRequest)?;
+    if let Some(filter) = &query.correlation_filter {
+        filter.validate().map_err(ApiError::BadRequest)?;
+    }
+    if let Some(confirm) = &mut query.confirm_with {
+        confirm.validate().map_err(ApiError::BadRequest)?;
+        let confirm_def = state
+            .scan_registry
+            .resolve(&confirm.scan_type)
+            .map_err(|e| ApiError::BadRequest(format!("confirm_with: {}", e)))?;
+        confirm.scan_type = confirm_def.meta.id.clone();
+        scan_types::coerce_params(&mut confirm.params, &confirm_def.meta).map_err(|errors| {
+            ApiError::BadRequest(format!("Invalid confirm_with params: {}", errors.join("; ")))
+        })?;
+    }
+    let has_dates = query.date_from.is_some() || query.date_to.is_some();
+    match query.lookback_days {
+        Some(_) if has_dates => {
+            return Err(ApiError::BadRequest(
+                "lookback_days cannot be combined with date_from/date_to".into(),
+            ))
+        }
+        Some(0) => return Err(ApiError::BadRequest("lookback_days must be at least 1".into())),
+        _ => {}
+    }
+    if query.timeout_ms == Some(0) || query.ticker_budget_ms == Some(0) {
+        return Err(ApiError::BadRequest("timeout_ms and ticker_budget_ms must be at least 1".into()));
+    }
+    query.timeout_ms = Some(query.timeout_ms.map_or(state.scan_timeout_ms, |ms| ms.min(state.scan_timeout_ms)));
+
+    scan_types::coerce_params(&mut query.params, &def.meta)
+        .map_err(|errors| ApiError::BadRequest(format!("Invalid params: {}", errors.join("; "))))?;
+    if let (Some(filter), Some(_)) = (&query.correlation_filter, def.benchmark_output) {
+        let scan_benchmark = query.params.get("benchmark").and_then(|v| v.as_str()).unwrap_or("SPY");
+        if !filter.benchmark.eq_ignore_ascii_case(scan_benchmark) {
+            return Err(ApiError::BadRequest(format!(
+                "correlation_filter.benchmark {} differs from the scan's benchmark {}",
+                filter.benchmark, scan_benchmark
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The query's benchmark (see `scanner::benchmark_symbol`), looked up in the
+/// whole store so it needn't be part of the scanned universe
+fn resolve_benchmark(
+    registry: &ScanRegistry,
+    store: &DataStore,
+    query: &ScanQuery,
+) -> Result<Option<Arc<TickerData>>, ApiError> {
+    match benchmark_symbol(registry, query) {
+        Some(symbol) => store
+            .get(&symbol)
+            .map(Some)
+            .ok_or_else(|| ApiError::BadRequest(format!("Benchmark not found: {}", symbol))),
+        None => Ok(None),
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ScanOptions {
+    /// Respond with NDJSON: one match per line as tickers finish, then a summary line
+    #[serde(default)]
+    stream: bool,
+    /// Start the scan in the background and respond with a job id at once
+    #[serde(default, rename = "async")]
+    run_async: bool,
+}
+
+async fn run_scan_handler(
+    State(state): State<Arc<AppState>>,
+    Query(options): Query<ScanOptions>,
+    Json(query): Json<ScanQuery>,
+) -> Result<Response, ApiError> {
+    match (options.stream, options.run_async) {
+        (true, true) => Err(ApiError::BadRequest("stream and async cannot be combined".into())),
+        (true, false) => stream_scan(state, query).await,
+        (false, true) => start_scan_job(state, query).await,
+        (false, false) => execute_scan(&state, query).await,
+    }
+}
+
+type ScanInputs = (HashMap<String, Arc<TickerData>>, Option<Arc<TickerData>>);
+
+/// Validate the query and pick the tickers and benchmark it runs against
+async fn scan_inputs(state: &AppState, query: &mut ScanQuery) -> Result<ScanInputs, ApiError> {
+    prepare_scan_query(state, query)?;
+    let store = state.data_store.read().await;
+    require_data(state, &store)?;
+    let benchmark = resolve_benchmark(&state.scan_registry, &store, query)?;
+    query.newest_day = store.newest_day();
+    Ok((store.universe(query.sector.as_deref()), benchmark))
+}
+
+/// Validate, run and log a scan; shared by `/api/scan` and saved-scan runs
+async fn execute_scan(state: &AppState, mut query: ScanQuery) -> Result<Response, ApiError> {
+    let (data, benchmark) = scan_inputs(state, &mut query).await?;
+    let fields = query.fields.clone();
+    let permit = acquire_scan_permit(state, &mut query).await?;
+    let result = scan_and_log(state, permit, query, data, benchmark, None).await?;
+
+    Ok(match fields {
+        Some(fields) => Json(result.to_json_with_fields(&fields)).into_response(),
+        None => Json(result).into_response(),
+    })
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct TickerScanOptions {
+    /// Include the scan's per-bar mask, aligned to `dates`
+    #[serde(default)]
+    mask: bool,
+    /// Include the scan's principal series (e.g. the RSI), aligned to `dates`
+    #[serde(default)]
+    series: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct TickerScanResponse {
+    ticker: String,
+    scan_type: String,
+    matches: Vec<ScanMatch>,
+    /// Every bar of the ticker's history, when `mask` or `series` is asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dates: Option<Vec<String>>,
+    /// Where the scan itself fired, before the query's date and confirmation filters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mask: Option<Vec<bool>>,
+    /// Null during warm-up; empty for scans that report no series
+    #[serde(skip_serializing_if = "Option::is_none")]
+    series: Option<BTreeMap<String, Vec<Option<f64>>>>,
+}
+
+/// Run a scan on one ticker, for chart markers without scanning the universe
+async fn ticker_scan_handler(
+    State(state): State<Arc<AppState>>,
+    Path(ticker): Path<String>,
+    Query(options): Query<TickerScanOptions>,
+    Json(mut query): Json<ScanQuery>,
+) -> Result<Json<TickerScanResponse>, ApiError> {
+    let (data, benchmark) = {
+        let store = state.data_store.read().await;
+        let data = store.get(&ticker).ok_or_else(|| ticker_not_found(&store, &ticker))?;
+        prepare_scan_query(&state, &mut query)?;
+        (data, resolve_benchmark(&state.scan_registry, &store, &query)?)
+    };
+
+    let ticker = ticker.to_uppercase();
+    let registry = state.scan_registry.clone();
+    let (scan, query, data) = tokio::task::spawn_blocking({
+        let ticker = ticker.clone();
+        move || {
+            let scan = scan_ticker(&registry, &ticker, &data, &query, benchmark.as_deref());
+            (scan, query, data)
+        }
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Ticker scan task failed: {}", e);
+        ApiError::Internal("scan failed".into())
+    })?;
+    let scan = scan.map_err(ApiError::Unprocessable)?;
+
+    let detailed = options.mask || options.series;
+    Ok(Json(TickerScanResponse {
+        ticker,
+        scan_type: query.scan_type,
+        matches: scan.matches,
+        dates: detailed.then(|| data.date.clone()),
+        mask: options.mask.then_some(scan.output.mask),
+        series: options.series.then(|| {
+            scan.output
+                .series
+                .into_iter()
+                .map(|(key, values)| (key, values.into_iter().map(|v| Some(v).filter(|v| v.is_finite())).collect()))
+                .collect()
+        }),
+    }))
+}
+
+/// A scan permit, with the time spent queued for it taken off the query's
+/// `timeout_ms`: a scan that waited behind others still stops before its
+/// request times out, and one that waited past its timeout returns at once
+async fn acquire_scan_permit(state: &AppState, query: &mut ScanQuery) -> Result<OwnedSemaphorePermit, ApiError> {
+    let queued_at = Instant::now();
+    let permit = state.scan_limiter.acquire().await?;
+    let waited = queued_at.elapsed().as_millis() as u64;
+    query.timeout_ms = query.timeout_ms.map(|ms| ms.saturating_sub(waited));
+    Ok(permit)
+}
+
+/// Run a validated scan on the blocking pool, then log (and maybe record) it.
+/// The permit is released when the scan itself finishes, even if the caller
+/// has gone away.
+async fn scan_and_log(
+    state: &AppState,
+    permit: OwnedSemaphorePermit,
+    query: ScanQuery,
+    data: HashMap<String, Arc<TickerData>>,
+    benchmark: Option<Arc<TickerData>>,
+    progress: Option<Arc<ScanProgress>>,
+) -> Result<ScanResult, ApiError> {
+    let registry = state.scan_registry.clone();
+    let logged_query = query.clone();
+
+    // run_scan uses Rayon (blocking), so run it on the blocking thread pool
+    let (mut result, data) = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        let result = match progress {
+            Some(progress) => run_scan_tracked(&registry, &data, &query, benchmark, &progress),
+            None => run_scan_with(&registry, &data, &query, benchmark),
+        };
+        (result, data)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Scan task failed: {}", e);
+        ApiError::Internal("scan failed".into())
+    })?;
+
+    // A partial result would show up as tickers dropping out in a diff
+    if logged_query.record && !result.cancelled && !result.timed_out {
+        let tickers = TickerSummary::from_matches(&result.matches, &data);
+        result.run_id = Some(record_run(state, &logged_query, &result, result.matches.len(), tickers));
+    }
+    state.scan_history.record(HistoryEntry {
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        query: logged_query,
+        total_matches: result.matches.len(),
+        tickers_with_matches: result.tickers_with_matches,
+        scan_time_ms: result.scan_time_ms,
+    });
+    Ok(result)
+}
+
+#[derive(Serialize)]
+struct ScanJobStarted {
+    job_id: String,
+    progress_url: String,
+}
+
+/// `/api/scan?async=true`: validate now, scan in the background
+async fn start_scan_job(state: Arc<AppState>, mut query: ScanQuery) -> Result<Response, ApiError> {
+    let (data, benchmark) = scan_inputs(&state, &mut query).await?;
+    // In reject mode a busy server refuses the job up front
+    let permit = match state.scan_limiter.rejects_when_busy() {
+        true => Some(state.scan_limiter.acquire().await?),
+        false => None,
+    };
+    let (job_id, job) = state.scan_jobs.start();
+
+    tokio::spawn(async move {
+        let fields = query.fields.clone();
+        let permit = match permit {
+            Some(permit) => Ok(permit),
+            None => state.scan_limiter.acquire().await,
+        };
+        let outcome = match permit {
+            Ok(permit) => scan_and_log(&state, permit, query, data, benchmark, Some(job.progress.clone())).await,
+            Err(e) => Err(e),
+        };
+        job.finish(match outcome {
+            Ok(result) => Ok(match fields {
+                Some(fields) => result.to_json_with_fields(&fields),
+                None => serde_json::to_value(&result).unwrap_or_default(),
+            }),
+            Err(e) => Err(e.to_string()),
+        });
+    });
+
+    let progress_url = format!("/api/scan/progress/{}", job_id);
+    Ok((StatusCode::ACCEPTED, Json(ScanJobStarted { job_id, progress_url })).into_response())
+}
+
+#[derive(Serialize)]
+struct ScanJobCancelled {
+    job_id: String,
+    cancelled: bool,
+}
+
+/// Stop a running job; its `done` result then has what was found so far and
+/// `cancelled: true`
+async fn cancel_scan_job(
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<String>,
+) -> Result<(StatusCode, Json<ScanJobCancelled>), ApiError> {
+    let job = state
+        .scan_jobs
+        .get(&job_id)
+        .ok_or_else(|| ApiError::NotFound(format!("Scan job not found: {}", job_id)))?;
+    if !matches!(job.status(), JobStatus::Running) {
+        return Err(ApiError::Unprocessable(format!("Scan job {} has already finished", job_id)));
+    }
+    job.progress.cancelled.store(true, Ordering::Relaxed);
+    Ok((StatusCode::ACCEPTED, Json(ScanJobCancelled { job_id, cancelled: true })))
+}
+
+/// How often a progress stream checks its job
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// SSE: `progress` events (`{scanned, total, matches_so_far}`) whenever the
+/// counts change, then one `done` event with the scan result (or `error`)
+async fn scan_progress(
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<String>,
+) -> Result<Sse<impl futures::Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let job = state
+        .scan_jobs
+        .get(&job_id)
+        .ok_or_else(|| ApiError::NotFound(format!("Scan job not found: {}", job_id)))?;
+
+    let events = futures::stream::unfold(Some((job, None)), |state| async move {
+        let (job, last) = state?;
+        loop {
+            match job.status() {
+                JobStatus::Done(result) => return Some((Event::default().event("done").data(result.to_string()), None)),
+                JobStatus::Failed(error) => {
+                    let data = serde_json::json!({ "error": error }).to_string();
+                    return Some((Event::default().event("error").data(data), None));
+                }
+                JobStatus::Running => {
+                    let snapshot = job.snapshot();
+                    if last != Some(snapshot) {
+                        let data = serde_json::to_string(&snapshot).unwrap_or_default();
+                        return Some((Event::default().event("progress").data(data), Some((job, Some(snapshot)))));
+                    }
+                    tokio::time::sleep(PROGRESS_INTERVAL).await;
+                }
+            }
+        }
+    });
+
+    Ok(Sse::new(events.map(Ok)).keep_alive(KeepAlive::default()))
+}
+
+/// Batches of NDJSON lines in flight to a streaming client; when full, scan
+/// workers wait for the client to catch up
+const STREAM_BUFFER_BATCHES: usize = 64;
+
+/// `/api/scan?stream=true`: each ticker's matches are serialized on the scan
+/// worker and sent as soon as the ticker finishes; the last line is
+/// `{"summary": {...}}`. A client that disconnects stops the scan.
+async fn stream_scan(state: Arc<AppState>, mut query: ScanQuery) -> Result<Response, ApiError> {
+    let (data, benchmark) = scan_inputs(&state, &mut query).await?;
+    let permit = acquire_scan_permit(&state, &mut query).await?;
+    let (tx, rx) = tokio::sync::mpsc::channel::<Bytes>(STREAM_BUFFER_BATCHES);
+
+    tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        let fields = query.fields.as_deref();
+        let sent = AtomicUsize::new(0);
+        let tickers = std::sync::Mutex::new(Vec::new());
+        let sink = |matches: Vec<ScanMatch>| {
+            sent.fetch_add(matches.len(), Ordering::Relaxed);
+            if query.record {
+                tickers.lock().unwrap_or_else(|e| e.into_inner()).extend(TickerSummary::from_matches(&matches, &data));
+            }
+            tx.blocking_send(ndjson_lines(&matches, fields)).is_ok()
+        };
+        let mut result = run_scan_streaming(&state.scan_registry, &data, &query, benchmark, &sink);
+        if query.record && !tx.is_closed() {
+            let mut tickers = tickers.into_inner().unwrap_or_else(|e| e.into_inner());
+            tickers.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+            result.run_id = Some(record_run(&state, &query, &result, sent.load(Ordering::Relaxed), tickers));
+        }
+
+        let mut summary = serde_json::to_value(&result).unwrap_or_default();
+        if let Some(obj) = summary.as_object_mut() {
+            obj.remove("matches");
+            obj.insert("total_matches".into(), sent.load(Ordering::Relaxed).into());
+        }
+        let mut line = serde_json::json!({ "summary": summary }).to_string();
+        line.push('\n');
+        // Err: the client is gone and the scan was cut short, so don't log it
+        if tx.blocking_send(Bytes::from(line)).is_ok() {
+            state.scan_history.record(HistoryEntry {
+                timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                total_matches: sent.into_inner(),
+                tickers_with_matches: result.tickers_with_matches,
+                scan_time_ms: result.scan_time_ms,
+                query,
+            });
+        }
+    });
+
+    let body = Body::from_stream(ReceiverStream::new(rx).map(Ok::<_, Infallible>));
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], body).into_response())
+}
+
+/// Queue a recorded run of `query`; returns its id
+fn record_run(
+    state: &AppState,
+    query: &ScanQuery,
+    result: &ScanResult,
+    total_matches: usize,
+    tickers: Vec<TickerSummary>,
+) -> String {
+    let id = state.run_history.next_id();
+    state.run_history.record(RecordedRun {
+        summary: RunSummary {
+            id: id.clone(),
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            scan_type: query.scan_type.clone(),
+            total_matches,
+            tickers_with_matches: result.tickers_with_matches,
+            scan_time_ms: result.scan_time_ms,
+        },
+        query: query.clone(),
+        tickers,
+    });
+    id
+}
+
+/// One JSON object per line, each reduced to `fields` when given
+fn ndjson_lines(matches: &[ScanMatch], fields: Option<&[String]>) -> Bytes {
+    let mut out = Vec::new();
+    for m in matches {
+        let written = match fields {
+            Some(fields) => serde_json::to_writer(&mut out, &project_match(m, fields)),
+            None => serde_json::to_writer(&mut out, m),
+        };
+        if written.is_ok() {
+            out.push(b'\n');
+        }
+    }
+    Bytes::from(out)
+}
+
+#[derive(Deserialize)]
+struct SaveScanRequest {
+    name: String,
+    query: ScanQuery,
+}
+
+async fn list_saved_scans(State(state): State<Arc<AppState>>) -> Json<Vec<SavedScan>> {
+    Json(state.saved_scans.read().await.list().to_vec())
+}
+
+async fn get_saved_scan(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<SavedScan>, ApiError> {
+    let saved = state.saved_scans.read().await;
+    saved
+        .get(&id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Saved scan not found: {}", id)))
+}
+
+async fn create_saved_scan(
+    State(state): State<Arc<AppState>>,
+    Json(mut req): Json<SaveScanRequest>,
+) -> Result<(StatusCode, Json<SavedScan>), ApiError> {
+    if req.name.trim().is_empty() {
+        return Err(ApiError::BadRequest("name is required".into()));
+    }
+    // Reject what a run would reject, but store the query as given (apart
+    // from the canonical scan id)
+    let mut prepared = req.query.clone();
+    prepare_scan_query(&state, &mut prepared)?;
+    req.query.scan_type = prepared.scan_type;
+
+    let mut saved = state.saved_scans.write().await;
+    let scan = saved
+        .insert(&req.name, req.query)
+        .map_err(|e| ApiError::Internal(format!("Could not save scan: {}", e)))?;
+    Ok((StatusCode::CREATED, Json(scan)))
+}
+
+async fn delete_saved_scan(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let mut saved = state.saved_scans.write().await;
+    match saved.remove(&id) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::NotFound(format!("Saved scan not found: {}", id))),
+        Err(e) => Err(ApiError::Internal(format!("Could not delete scan: {}", e))),
+    }
+}
+
+async fn run_saved_scan(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    let query = state
+        .saved_scans
+        .read()
+        .await
+        .get(&id)
+        .map(|s| s.query.clone())
+        .ok_or_else(|| ApiError::NotFound(format!("Saved scan not found: {}", id)))?;
+    execute_scan(&state, query).await
+}
+
+#[derive(Deserialize)]
+struct ScanHistoryQuery {
+    limit: Option<usize>,
+}
+
+async fn scan_history_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ScanHistoryQuery>,
+) -> Result<Json<Vec<HistoryEntry>>, ApiError> {
+    let limit = query.limit.unwrap_or(50).min(1000);
+    let path = state.scan_history.path().to_path_buf();
+
+    let entries = tokio::task::spawn_blocking(move || history::read_recent(&path, limit))
+        .await
+        .map_err(|_| ApiError::Internal("History task panicked".into()))?
+        .map_err(|e| ApiError::Internal(format!("Could not read scan history: {}", e)))?;
+
+    Ok(Json(entries))
+}
+
+#[derive(Deserialize)]
+struct RunListQuery {
+    scan_type: Option<String>,
+    limit: Option<usize>,
+}
+
+async fn list_runs(State(state): State<Arc<AppState>>, Query(query): Query<RunListQuery>) -> Json<Vec<RunSummary>> {
+    let limit = query.limit.unwrap_or(50).min(1000);
+    // Runs of scans no longer registered are still listed under their own id
+    let scan_type = query.scan_type.map(|t| match state.scan_registry.resolve(&t) {
+        Ok(def) => def.meta.id.clone(),
+        Err(_) => t,
+    });
+    Json(state.run_history.list(scan_type.as_deref(), limit))
+}
+
+async fn load_run(state: Arc<AppState>, id: String) -> Result<RecordedRun, ApiError> {
+    let run = tokio::task::spawn_blocking({
+        let id = id.clone();
+        move || state.run_history.get(&id)
+    })
+    .await
+    .map_err(|_| ApiError::Internal("History task panicked".into()))?
+    .map_err(|e| ApiError::Internal(format!("Could not read scan run {}: {}", id, e)))?;
+    run.ok_or_else(|| ApiError::NotFound(format!("Scan run not found: {}", id)))
+}
+
+async fn get_run(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Json<RecordedRun>, ApiError> {
+    Ok(Json(load_run(state, id).await?))
+}
+
+async fn diff_runs(
+    State(state): State<Arc<AppState>>,
+    Path((id, other_id)): Path<(String, String)>,
+) -> Result<Json<RunDiff>, ApiError> {
+    let (from, to) = (load_run(state.clone(), id).await?, load_run(state, other_id).await?);
+    RunDiff::between(&from, &to).map(Json).map_err(ApiError::Unprocessable)
+}
+
+async fn backtest_handler(
+    State(state): State<Arc<AppState>>,
+    Json(mut req): Json<BacktestRequest>,
+) -> Result<Json<BacktestResult>, ApiError> {
+    if req.exit.is_empty() {
+        return Err(ApiError::BadRequest(
+            "At least one exit rule is required (hold_bars, stop_loss_pct, take_profit_pct, trailing_stop or exit_scan)".into(),
+        ));
+    }
+    if req.sizing.max_positions == 0 {
+        return Err(ApiError::BadRequest("max_positions must be at least 1".into()));
+    }
+    if !req.sizing.initial_capital.is_finite() || req.sizing.initial_capital <= 0.0 {
+        return Err(ApiError::BadRequest("initial_capital must be positive".into()));
+    }
+    if let Some(trail) = req.exit.trailing_stop {
+        if trail.period == 0 || trail.multiplier.is_nan() || trail.multiplier <= 0.0 {
+            return Err(ApiError::BadRequest(
+                "trailing_stop needs period >= 1 and a positive multiplier".into(),
+            ));
+        }
+    }
+    for pct in [req.exit.stop_loss_pct, req.exit.take_profit_pct].into_iter().flatten() {
+        if pct.is_nan() || pct <= 0.0 {
+            return Err(ApiError::BadRequest("stop_loss_pct and take_profit_pct must be positive".into()));
+        }
+    }
+
+    validate_scan_query(&state, &mut req.entry)?;
+
+    if let Some(exit_scan) = &mut req.exit.exit_scan {
+        let def = state
+            .scan_registry
+            .resolve(exit_scan)
+            .map_err(|e| ApiError::BadRequest(format!("exit_scan: {}", e)))?;
+        *exit_scan = def.meta.id.clone();
+        scan_types::coerce_params(&mut req.exit.exit_params, &def.meta).map_err(|errors| {
+            ApiError::BadRequest(format!("Invalid exit_params: {}", errors.join("; ")))
+        })?;
+    }
+
+    let store = state.data_store.read().await;
+    require_data(&state, &store)?;
+    let data = store.universe(req.entry.sector.as_deref());
+    let benchmark = resolve_benchmark(&state.scan_registry, &store, &req.entry)?;
+    drop(store);
+    let registry = state.scan_registry.clone();
+    let permit = acquire_scan_permit(&state, &mut req.entry).await?;
+
+    let result = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        run_backtest(&registry, &data, &req, benchmark)
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Backtest task failed: {}", e);
+        ApiError::Internal("backtest failed".into())
+    })?;
+
+    Ok(Json(result))
+}
+
+/// `POST /api/nl/clarify` - the LLM's questions when it's configured and
+/// answers, else the rule-based parser's
+async fn nl_clarify_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<NlClarifyRequest>,
+) -> Result<Json<NlClarifyResponse>, ApiError> {
+    let llm_error = if llm::is_configured() {
+        let query = req.query.clone();
+        let context = prompt_context(&state).await;
+        match tokio::task::spawn_blocking(move || llm::clarify(&query, &context))
+            .await
+            .map_err(|_| ApiError::Internal("Clarify task panicked".into()))?
+        {
+            Ok(clarify) => return Ok(Json(NlClarifyResponse { source: NlSource::Llm, clarify })),
+            Err(e) => {
+                tracing::warn!("LLM clarify failed, trying the rule-based parser: {}", e);
+                e.to_string()
+            }
+        }
+    } else {
+        "ANTHROPIC_API_KEY is not set".to_string()
+    };
+
+    let clarify = nl_rules::clarify(&req.query, &state.scan_registry).ok_or_else(|| rules_miss(&llm_error))?;
+    Ok(Json(NlClarifyResponse { source: NlSource::Rules, clarify }))
+}
+
+/// Neither the LLM nor the rule-based parser could handle a query
+fn rules_miss(llm_error: &str) -> ApiError {
+    ApiError::LlmUnavailable(format!("{}, and the rule-based parser didn't recognize the query", llm_error))
+}
+
+/// The scan catalog and data coverage the NL prompts describe
+async fn prompt_context(state: &AppState) -> llm::PromptContext {
+    let store = state.data_store.read().await;
+    llm::PromptContext {
+        scans: state.scan_registry.list(),
+        ticker_count: store.stats.ticker_count,
+        min_date: store.stats.min_date.clone(),
+        max_date: store.stats.max_date.clone(),
+    }
+}
+
+/// `POST /api/nl/compile` - a new or existing scan from the LLM when it's
+/// configured and answers, else an existing scan from the rule-based parser
+async fn nl_compile_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<NlCompileRequest>,
+) -> Result<Json<NlCompileResult>, ApiError> {
+    let llm_error = if llm::is_configured() {
+        let (query, answers) = (req.query.clone(), req.answers.clone());
+        let context = prompt_context(&state).await;
+        match tokio::task::spawn_blocking(move || llm::compile(&query, &answers, &context))
+            .await
+            .map_err(|_| ApiError::Internal("Compile task panicked".into()))?
+        {
+            Ok(compiled) => {
+                let result = match compiled {
+                    llm::CompileResponse::NewScan(spec) => {
+                        // One compile at a time, so concurrent saves can't drop each other's specs
+                        let _guard = state.generated_lock.lock().await;
+                        NlCompileResponse::NewScan(save_generated_spec(spec)?)
+                    }
+                    // Nothing to generate, so the generated store is left alone
+                    llm::CompileResponse::UseExisting { scan_type, params } => {
+                        let query = ScanQuery { scan_type, params, ..Default::default() };
+                        NlCompileResponse::UseExisting(Box::new(existing_scan(&state, query)?))
+                    }
+                };
+                return Ok(Json(NlCompileResult { source: NlSource::Llm, result }));
+            }
+            Err(e) => {
+                tracing::warn!("LLM compile failed, trying the rule-based parser: {}", e);
+                e.to_string()
+            }
+        }
+    } else {
+        "ANTHROPIC_API_KEY is not set".to_string()
+    };
+
+    let mut query = nl_rules::parse(&req.query, &state.scan_registry).ok_or_else(|| rules_miss(&llm_error))?;
+    // Answers to the synthesized clarify questions are the scan's params
+    query.params.extend(req.answers);
+    let existing = existing_scan(&state, query)?;
+    Ok(Json(NlCompileResult { source: NlSource::Rules, result: NlCompileResponse::UseExisting(Box::new(existing)) }))
+}
+
+/// Check an NL-suggested existing scan and its params, as `/api/scan` would
+fn existing_scan(state: &AppState, mut query: ScanQuery) -> Result<ExistingScanResponse, ApiError> {
+    let def = state
+        .scan_registry
+        .resolve(&query.scan_type)
+        .map_err(|e| ApiError::Unprocessable(format!("Suggested scan doesn't exist: {}", e)))?;
+    scan_types::coerce_params(&mut query.params, &def.meta)
+        .map_err(|errors| ApiError::Unprocessable(format!("Suggested invalid params: {}", errors.join("; "))))?;
+    query.scan_type = def.meta.id.clone();
+
+    Ok(ExistingScanResponse {
+        scan_id: query.scan_type.clone(),
+        params: query.params.clone(),
+        query,
+        requires_restart: false,
+        message: format!("Reusing the existing {} scan.", def.meta.name),
+    })
+}
+
+/// Validate `spec` and save it as the next version of its scan. Callers hold
+/// `generated_lock`.
+fn save_generated_spec(spec: GeneratedScanSpec) -> Result<GeneratedSaveResponse, ApiError> {
+    let problems = generated_store::validate_spec(&spec);
+    if !problems.is_empty() {
+        return Err(ApiError::Unprocessable(format!(
+            "Generated scan failed validation: {}",
+            problems.join("; ")
+        )));
+    }
+
+    let (json_path, rs_path) = generated_store::generated_paths();
+    let scan_id = generated_store::normalize_scan_id(&spec.id);
+    let specs = generated_store::save_generated(&json_path, &rs_path, spec)
+        .map_err(|e| ApiError::Internal(format!("Failed to save generated scan: {}", e)))?;
+    let version = specs.iter().find(|s| s.id == scan_id).map_or(1, |s| s.version);
+
+    Ok(GeneratedSaveResponse {
+        scan_id,
+        version,
+        requires_restart: true,
+        message: "Generated scan saved. Restart the server to load it.".into(),
+    })
+}
+
+/// The stored spec for generated scan `id`
+fn load_generated_spec(id: &str) -> Result<GeneratedScanSpec, ApiError> {
+    let (json_path, _) = generated_store::generated_paths();
+    let id = generated_store::normalize_scan_id(id);
+    generated_store::load_specs(&json_path)
+        .map_err(|e| ApiError::Internal(format!("Failed to load generated scans: {}", e)))?
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| ApiError::NotFound(format!("Generated scan not found: {}", id)))
+}
+
+/// `PUT /api/generated/:id` - edit a generated scan's description, params,
+/// body or helpers directly, as a new version
+async fn update_generated_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(edit): Json<GeneratedScanEdit>,
+) -> Result<Json<GeneratedSaveResponse>, ApiError> {
+    let _guard = state.generated_lock.lock().await;
+    let spec = edit.apply(&load_generated_spec(&id)?);
+    save_generated_spec(spec).map(Json)
+}
+
+/// `POST /api/generated/:id/revert?version=N` - save version N again as the
+/// newest version, so the versions in between stay revertible
+async fn revert_generated_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<RevertQuery>,
+) -> Result<Json<GeneratedSaveResponse>, ApiError> {
+    let _guard = state.generated_lock.lock().await;
+    let current = load_generated_spec(&id)?;
+    if query.version == current.version {
+        return Err(ApiError::BadRequest(format!("{} is already at version {}", current.id, query.version)));
+    }
+    let spec = current.at_version(query.version).cloned().ok_or_else(|| {
+        ApiError::NotFound(format!("{} has no kept version {}", current.id, query.version))
+    })?;
+    save_generated_spec(spec).map(Json)
+}
+
+#[derive(Serialize)]
+struct RollbackResponse {
+    /// Generated scan ids after the rollback
+    scan_ids: Vec<String>,
+    requires_restart: bool,
+    message: String,
+}
+
+/// `POST /api/generated/rollback` - put back the generated scans from before
+/// the last compile
+async fn rollback_generated_handler(State(state): State<Arc<AppState>>) -> Result<Json<RollbackResponse>, ApiError> {
+    let (json_path, rs_path) = generated_store::generated_paths();
+    let _guard = state.generated_lock.lock().await;
+    let specs = generated_store::rollback_generated(&json_path, &rs_path)
+        .map_err(|e| ApiError::Internal(format!("Rollback failed: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound("No previous generation to roll back to".into()))?;
+
+    Ok(Json(RollbackResponse {
+        scan_ids: specs.into_iter().map(|s| s.id).collect(),
+        requires_restart: true,
+        message: "Previous generated scans restored. Restart the server to load them.".into(),
+    }))
+}
+
+#[derive(Serialize)]
+struct ValidateSpecResponse {
+    valid: bool,
+    errors: Vec<String>,
+    compile_checked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compiler_output: Option<String>,
+}
+
+/// Dry-run a generated scan spec: static checks, plus `cargo check` against the
+/// existing generated scans when RETRO_VALIDATE_COMPILE is set. Persists nothing.
+async fn validate_scan_spec_handler(
+    Json(spec): Json<GeneratedScanSpec>,
+) -> Result<Json<ValidateSpecResponse>, ApiError> {
+    let errors = generated_store::validate_spec(&spec);
+    let compile_enabled = std::env::var("RETRO_VALIDATE_COMPILE")
+        .map(|v| !v.is_empty() && v != "0" && v != "false")
+        .unwrap_or(false);
+
+    if !errors.is_empty() || !compile_enabled {
+        return Ok(Json(ValidateSpecResponse {
+            valid: errors.is_empty(),
+            errors,
+            compile_checked: false,
+            compiler_output: None,
+        }));
+    }
+
+    let compiler_output = tokio::task::spawn_blocking(move || {
+        let (json_path, _) = generated_store::generated_paths();
+        let mut specs = generated_store::load_specs(&json_path)?;
+        generated_store::upsert_spec(&mut specs, spec);
+        generated_store::compile_check(&specs)
+    })
+    .await
+    .map_err(|_| ApiError::Internal("Validation task panicked".into()))?
+    .map_err(|e| ApiError::Internal(format!("Compile check failed to run: {}", e)))?;
+
+    Ok(Json(ValidateSpecResponse {
+        valid: compiler_output.is_none(),
+        errors,
+        compile_checked: true,
+        compiler_output,
+    }))
+}
+
+/// Listed scans; each `id` is the canonical `scan_type`
+async fn get_scan_types(State(state): State<Arc<AppState>>) -> Json<Vec<ScanType>> {
+    Json(state.scan_registry.list())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::generate_sample_data;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    /// State over an in-memory store of `tickers`, running one scan at a time
+    fn test_state(tickers: Vec<(&str, TickerData)>) -> AppState {
+        let mut store = DataStore::new();
+        for (ticker, data) in tickers {
+            store.data.insert(ticker.to_string(), Arc::new(data));
+            store.tickers.push(ticker.to_string());
+        }
+        store.tickers.sort();
+        store.refresh_stats();
+
+        let scratch = std::env::temp_dir().join(format!("retro-server-{}", std::process::id()));
+        AppState {
+            data_store: RwLock::new(store),
+            metrics: PrometheusBuilder::new().build_recorder().handle(),
+            scan_registry: Arc::new(ScanRegistry::load()),
+            scan_history: ScanHistory::spawn(scratch.join("scan_history.jsonl")),
+            run_history: RunHistory::spawn(scratch.join("history"), 20),
+            scan_jobs: ScanJobs::default(),
+            scan_limiter: ScanLimiter::new(1, false),
+            scan_timeout_ms: 60_000,
+            saved_scans: RwLock::new(SavedScanStore::default()),
+            rate_limits: Arc::new(RateLimits { llm: None, scan: None }),
+            generated_lock: tokio::sync::Mutex::new(()),
+            data_dir: PathBuf::from("./data/ohlcv"),
+        }
+    }
+
+    fn sample_state() -> AppState {
+        test_state(vec![("AAA", generate_sample_data(300)), ("BBB", generate_sample_data(300))])
+    }
+
+    /// Daily bars on `dates`, each opening, peaking and bottoming at its close
+    fn bars(dates: &[&str], closes: &[f64]) -> TickerData {
+        TickerData::from_columns(
+            dates.iter().map(|d| d.to_string()).collect(),
+            closes.to_vec(),
+            closes.to_vec(),
+            closes.to_vec(),
+            closes.to_vec(),
+            vec![1000.0; closes.len()],
+        )
+    }
+
+    async fn batch(state: &Arc<AppState>, body: serde_json::Value) -> Result<Json<BatchTickerResponse>, ApiError> {
+        get_batch_ticker_data(State(state.clone()), Json(serde_json::from_value(body).unwrap())).await
+    }
+
+    #[tokio::test]
+    async fn test_backtest_and_breadth_wait_for_a_scan_permit() {
+        let state = Arc::new(sample_state());
+        let held = state.scan_limiter.acquire().await.unwrap();
+
+        let query = BreadthQuery { lookback: 20, from: None, to: None, sector: None };
+        let breadth = tokio::spawn(breadth_handler(State(state.clone()), Query(query)));
+        let request: BacktestRequest = serde_json::from_value(serde_json::json!({
+            "entry": { "scan_type": "rsi_oversold", "params": {} },
+            "exit": { "hold_bars": 5 },
+        }))
+        .unwrap();
+        let backtest = tokio::spawn(backtest_handler(State(state.clone()), Json(request)));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!breadth.is_finished(), "breadth ran while a scan held the only permit");
+        assert!(!backtest.is_finished(), "backtest ran while a scan held the only permit");
+
+        drop(held);
+        assert!(breadth.await.unwrap().is_ok());
+        assert!(backtest.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_backtest_entries_span_the_whole_history() {
+        // volume_spike scans default to the last 365 days; a backtest shouldn't
+        let data = generate_sample_data(1000);
+        let first_day = data.days[0];
+        let state = Arc::new(test_state(vec![("AAA", data)]));
+        let request: BacktestRequest = serde_json::from_value(serde_json::json!({
+            "entry": { "scan_type": "volume_spike", "params": { "multiplier": 1.2 } },
+            "exit": { "hold_bars": 5 },
+            "sizing": { "max_positions": 1 },
+        }))
+        .unwrap();
+
+        let Json(result) = backtest_handler(State(state), Json(request)).await.unwrap();
+        let first_entry = result.trades.iter().filter_map(|t| parse_date(&t.entry_date)).min().unwrap();
+        assert!((first_entry - first_day).num_days() < 100, "first entry on {}", first_entry);
+    }
+
+    #[tokio::test]
+    async fn test_queued_scan_stops_within_its_timeout() {
+        let state = Arc::new(AppState { scan_timeout_ms: 200, ..sample_state() });
+        let held = state.scan_limiter.acquire().await.unwrap();
+
+        let start = Instant::now();
+        let query = ScanQuery { scan_type: "rsi_oversold".into(), ..Default::default() };
+        let scan = tokio::spawn({
+            let state = state.clone();
+            async move { execute_scan(&state, query).await }
+        });
+        // The scan in front outlasts the queued one's whole timeout
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        drop(held);
+
+        let response = scan.await.unwrap().unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500), "ran a full timeout after queueing");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result["timed_out"], true);
+        assert_eq!(result["tickers_unscanned"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_batch_normalize_without_a_base() {
+        let state = Arc::new(test_state(vec![
+            ("AAA", bars(&["2024-01-02", "2024-01-03"], &[10.0, 12.0])),
+            ("BBB", bars(&["2024-01-04", "2024-01-05"], &[20.0, 25.0])),
+            ("ZERO", bars(&["2024-01-02", "2024-01-03"], &[0.0, 5.0])),
+        ]));
+
+        let disjoint = batch(&state, serde_json::json!({ "tickers": ["AAA", "BBB"], "normalize": true })).await;
+        assert!(matches!(disjoint, Err(ApiError::BadRequest(_))));
+        // Volume alone is never rebased
+        let volume = serde_json::json!({ "tickers": ["AAA", "BBB"], "fields": ["volume"], "normalize": true });
+        assert!(batch(&state, volume).await.is_ok());
+
+        let Json(response) = batch(&state, serde_json::json!({ "tickers": ["AAA", "ZERO"], "normalize": true }))
+            .await
+            .unwrap();
+        assert_eq!(response.series["AAA"]["close"], vec![Some(100.0), Some(120.0)]);
+        assert_eq!(response.series["ZERO"]["close"], vec![None, None]);
+    }
+
+    #[tokio::test]
+    async fn test_batch_rejects_intraday_tickers() {
+        let mut intraday = bars(&["2024-01-02 09:30:00", "2024-01-02 09:35:00"], &[10.0, 11.0]);
+        intraday.timeframe = Timeframe::Intraday;
+        let state = Arc::new(test_state(vec![("AAA", bars(&["2024-01-02"], &[10.0])), ("MIN", intraday)]));
+
+        let response = batch(&state, serde_json::json!({ "tickers": ["AAA", "MIN"] })).await;
+        assert!(matches!(response, Err(ApiError::BadRequest(e)) if e.contains("MIN")));
+    }
+
+    #[tokio::test]
+    async fn test_batch_alignment_and_normalize() {
+        let state = Arc::new(test_state(vec![
+            ("AAA", bars(&["2024-01-02", "2024-01-03", "2024-01-04"], &[10.0, 11.0, 12.0])),
+            ("BBB", bars(&["2024-01-03", "2024-01-04", "2024-01-05"], &[50.0, 40.0, 45.0])),
+        ]));
+
+        let Json(union) = batch(&state, serde_json::json!({ "tickers": ["aaa", "BBB", "NOPE"] })).await.unwrap();
+        assert_eq!(union.dates, ["2024-01-02", "2024-01-03", "2024-01-04", "2024-01-05"]);
+        assert_eq!(union.series["AAA"]["close"], vec![Some(10.0), Some(11.0), Some(12.0), None]);
+        assert_eq!(union.series["BBB"]["close"], vec![None, Some(50.0), Some(40.0), Some(45.0)]);
+        assert_eq!(union.not_found, ["NOPE"]);
+
+        let body = serde_json::json!({ "tickers": ["AAA", "BBB"], "align": "intersection", "fields": ["close", "volume"] });
+        let Json(shared) = batch(&state, body).await.unwrap();
+        assert_eq!(shared.dates, ["2024-01-03", "2024-01-04"]);
+        assert_eq!(shared.series["BBB"]["close"], vec![Some(50.0), Some(40.0)]);
+        assert_eq!(shared.series["BBB"]["volume"], vec![Some(1000.0), Some(1000.0)]);
+        assert!(shared.not_found.is_empty());
+
+        // Rebased at 01-03, the first date both have, even though AAA starts earlier
+        let Json(normalized) = batch(&state, serde_json::json!({ "tickers": ["AAA", "BBB"], "normalize": true }))
+            .await
+            .unwrap();
+        assert_eq!(normalized.series["AAA"]["close"][1], Some(100.0));
+        assert_eq!(normalized.series["BBB"]["close"], vec![None, Some(100.0), Some(80.0), Some(90.0)]);
+        let aaa_first = normalized.series["AAA"]["close"][0].unwrap();
+        assert!((aaa_first - 10.0 / 11.0 * 100.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_batch_ticker_cap() {
+        let state = Arc::new(sample_state());
+        let tickers = |n: usize| (0..n).map(|i| format!("T{}", i)).collect::<Vec<_>>();
+
+        let Json(at_cap) = batch(&state, serde_json::json!({ "tickers": tickers(MAX_BATCH_TICKERS) })).await.unwrap();
+        assert_eq!(at_cap.not_found.len(), MAX_BATCH_TICKERS);
+        let over = batch(&state, serde_json::json!({ "tickers": tickers(MAX_BATCH_TICKERS + 1) })).await;
+        assert!(matches!(over, Err(ApiError::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_batch_request_is_documented() {
+        let doc = api_doc(&ScanRegistry::load());
+        assert!(doc["paths"]["/api/tickers/data"]["post"]["requestBody"].is_object());
+        assert_eq!(doc["components"]["schemas"]["DateAlign"]["enum"], serde_json::json!(["union", "intersection"]));
+    }
+
+    #[tokio::test]
+    async fn test_empty_store_is_reported() {
+        let state = Arc::new(test_state(vec![]));
+
+        let query = ScanQuery { scan_type: "rsi_oversold".into(), ..Default::default() };
+        let scan = execute_scan(&state, query).await;
+        assert!(matches!(&scan, Err(e) if e.status() == StatusCode::CONFLICT));
+
+        fn says_no_data<T>(result: Result<T, ApiError>) -> bool {
+            matches!(result, Err(ApiError::NotFound(e)) if e.contains("no market data is loaded"))
+        }
+        let ticker = || Path("AAPL".to_string());
+        assert!(says_no_data(get_snapshot(State(state.clone()), ticker()).await));
+        let rsi = Query(HashMap::from([("name".to_string(), "rsi".to_string())]));
+        assert!(says_no_data(get_indicator(State(state.clone()), ticker(), rsi).await));
+        assert!(says_no_data(get_fib_levels(State(state.clone()), ticker(), Query(FibQuery { lookback: None })).await));
+        let range = DrawdownQuery { from: None, to: None };
+        assert!(says_no_data(get_drawdown(State(state.clone()), ticker(), Query(range)).await));
+        let beta = BetaQuery { benchmark: "SPY".into(), period: 20 };
+        assert!(says_no_data(get_beta(State(state.clone()), ticker(), Query(beta)).await));
+        let pair = CorrelationRequest { tickers: vec!["AAPL".into(), "MSFT".into()], lookback: 20 };
+        assert!(says_no_data(correlation_handler(State(state.clone()), Json(pair)).await));
+
+        let health = health_check(State(state)).await.into_response();
+        assert_eq!(health.headers()["x-data-tickers"], "tickers=0; bars=0");
+        let body = axum::body::to_bytes(health.into_body(), usize::MAX).await.unwrap();
+        let health: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(health["tickers_loaded"], 0);
+
+        let loaded = health_check(State(Arc::new(sample_state()))).await.into_response();
+        let body = axum::body::to_bytes(loaded.into_body(), usize::MAX).await.unwrap();
+        let loaded: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!((loaded["tickers_loaded"].as_u64(), loaded["total_bars"].as_u64()), (Some(2), Some(600)));
+    }
+
+    /// Matches every bar, slowly enough to cancel part way through a universe
+    fn slow_scan(data: &TickerData, _: &HashMap<String, serde_json::Value>) -> Vec<bool> {
+        std::thread::sleep(Duration::from_millis(20));
+        vec![true; data.len()]
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_job_keeps_partial_matches() {
+        let mut registry = ScanRegistry::load();
+        registry.register(crate::registry::ScanDefinition {
+            meta: ScanType {
+                id: "slow".into(),
+                name: "Slow".into(),
+                description: "Matches everything, slowly".into(),
+                params: vec![],
+                default_lookback_days: None,
+                version: None,
+            },
+            func: slow_scan,
+            warmup: None,
+            min_bars: None,
+            warmup_bars: None,
+            output: None,
+            benchmark_output: None,
+            listed: true,
+        });
+        let tickers: Vec<String> = (0..64).map(|i| format!("T{:02}", i)).collect();
+        let state = Arc::new(AppState {
+            scan_registry: Arc::new(registry),
+            ..test_state(tickers.iter().map(|t| (t.as_str(), generate_sample_data(30))).collect())
+        });
+
+        let query = ScanQuery { scan_type: "slow".into(), ..Default::default() };
+        let started = start_scan_job(state.clone(), query).await.unwrap();
+        assert_eq!(started.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(started.into_body(), usize::MAX).await.unwrap();
+        let job_id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["job_id"].as_str().unwrap().to_string();
+
+        let job = state.scan_jobs.get(&job_id).unwrap();
+        while job.progress.matches.load(Ordering::Relaxed) == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let (status, _) = cancel_scan_job(State(state.clone()), Path(job_id.clone())).await.unwrap();
+        assert_eq!(status, StatusCode::ACCEPTED);
+
+        let result = loop {
+            match job.status() {
+                JobStatus::Done(result) => break result,
+                JobStatus::Failed(e) => panic!("job failed: {}", e),
+                JobStatus::Running => tokio::time::sleep(Duration::from_millis(5)).await,
+            }
+        };
+        assert_eq!(result["cancelled"], true);
+        let matches = result["matches"].as_array().unwrap();
+        assert!(!matches.is_empty());
+        assert!(matches.len() < 64 * 30);
+        assert!(result["tickers_unscanned"].as_u64().unwrap() > 0);
+
+        // A finished job can't be cancelled again
+        let again = cancel_scan_job(State(state), Path(job_id)).await;
+        assert!(matches!(again, Err(ApiError::Unprocessable(_))));
+    }
+
+    #[tokio::test]
+    async fn test_resampled_aggregates_weeks_and_months() {
+        // Two weeks straddling a month end, with a spike high and a dip low
+        let dates = [
+            "2024-01-29", "2024-01-30", "2024-01-31", "2024-02-01", "2024-02-02",
+            "2024-02-05", "2024-02-06", "2024-02-07", "2024-02-08", "2024-02-09",
+        ];
+        let mut high: Vec<f64> = (0..10).map(|i| 20.0 + i as f64).collect();
+        let mut low: Vec<f64> = (0..10).map(|i| 5.0 + i as f64).collect();
+        (high[2], low[7]) = (40.0, 1.0);
+        let data = TickerData::from_columns(
+            dates.iter().map(|d| d.to_string()).collect(),
+            (0..10).map(|i| 10.0 + i as f64).collect(),
+            high,
+            low,
+            (0..10).map(|i| 12.0 + i as f64).collect(),
+            (1..=10).map(|i| 100.0 * i as f64).collect(),
+        );
+        let state = Arc::new(test_state(vec![("AAA", data)]));
+        let resampled = |ticker: &str, timeframe: &str| {
+            let query = ResampledQuery { timeframe: Some(timeframe.into()), from: None, to: None, adjusted: false };
+            get_resampled(State(state.clone()), Path(ticker.to_string()), Query(query))
+        };
+        let ohlcv = |p: &OHLCVPoint| (p.date.clone(), p.open, p.high, p.low, p.close, p.volume);
+
+        let Json(weekly) = resampled("aaa", "weekly").await.unwrap();
+        let weeks: Vec<_> = weekly.data.iter().map(ohlcv).collect();
+        assert_eq!(
+            weeks,
+            [
+                ("2024-01-29".to_string(), 10.0, 40.0, 5.0, 16.0, 1500.0),
+                ("2024-02-05".to_string(), 15.0, 29.0, 1.0, 21.0, 4000.0),
+            ]
+        );
+
+        let Json(monthly) = resampled("AAA", "monthly").await.unwrap();
+        let months: Vec<_> = monthly.data.iter().map(ohlcv).collect();
+        assert_eq!(
+            months,
+            [
+                ("2024-01-29".to_string(), 10.0, 40.0, 5.0, 14.0, 600.0),
+                ("2024-02-01".to_string(), 13.0, 29.0, 1.0, 21.0, 4900.0),
+            ]
+        );
+
+        assert!(matches!(resampled("AAA", "hourly").await, Err(ApiError::BadRequest(_))));
+        assert!(matches!(resampled("NOPE", "weekly").await, Err(ApiError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_reports_the_latest_bar() {
+        let closes: Vec<f64> = (0..30).map(|i| 100.0 + i as f64).collect();
+        let dates: Vec<String> = (0..30).map(|i| format!("2024-01-{:02}", i + 1)).collect();
+        let dates: Vec<&str> = dates.iter().map(|d| d.as_str()).collect();
+        let state = Arc::new(test_state(vec![
+            ("AAA", bars(&dates, &closes)),
+            ("ONE", bars(&["2024-01-02"], &[50.0])),
+        ]));
+
+        let Json(snapshot) = get_snapshot(State(state.clone()), Path("aaa".into())).await.unwrap();
+        assert_eq!((snapshot.ticker.as_str(), snapshot.date.as_str(), snapshot.close), ("AAA", "2024-01-30", 129.0));
+        assert_eq!(snapshot.prev_close, Some(128.0));
+        assert!((snapshot.change_pct.unwrap() - 1.0 / 128.0 * 100.0).abs() < 1e-9);
+        // Only rising closes, so RSI is pinned at 100
+        let rsi = snapshot.rsi_14.unwrap();
+        assert_eq!((rsi.value, rsi.date.as_str()), (100.0, "2024-01-30"));
+        // Too short for SMA(50) or a 52-week window
+        assert!(snapshot.sma_50.is_none() && snapshot.pct_below_52w_high.is_none());
+
+        let Json(one) = get_snapshot(State(state.clone()), Path("ONE".into())).await.unwrap();
+        assert_eq!((one.prev_close, one.change_pct), (None, None));
+
+        let missing = get_snapshot(State(state), Path("NOPE".into())).await;
+        assert!(matches!(missing, Err(ApiError::NotFound(e)) if e == "Ticker not found: NOPE"));
+    }
+
+    #[tokio::test]
+    async fn test_request_limits() {
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route("/echo", post(|body: Bytes| async move { body.len().to_string() }))
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    "too late"
+                }),
+            );
+        let app = with_request_limits(app, 16, Duration::from_millis(50));
+        let send = |request: axum::http::Request<Body>| app.clone().oneshot(request);
+
+        let small = send(axum::http::Request::post("/echo").body(Body::from("x".repeat(16))).unwrap()).await.unwrap();
+        assert_eq!(small.status(), StatusCode::OK);
+        let large = send(axum::http::Request::post("/echo").body(Body::from("x".repeat(17))).unwrap()).await.unwrap();
+        assert_eq!(large.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let start = Instant::now();
+        let slow = send(axum::http::Request::get("/slow").body(Body::empty()).unwrap()).await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(slow.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(slow.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "Request timed out");
+    }
+
+    /// The process-wide recorder, installed by the first test that asks for it
+    fn global_metrics() -> PrometheusHandle {
+        static HANDLE: std::sync::OnceLock<PrometheusHandle> = std::sync::OnceLock::new();
+        HANDLE.get_or_init(telemetry::install_recorder).clone()
+    }
+
+    #[tokio::test]
+    async fn test_metrics_report_scans() {
+        let state = Arc::new(AppState { metrics: global_metrics(), ..sample_state() });
+        let query = ScanQuery { scan_type: "golden_cross".into(), ..Default::default() };
+        execute_scan(&state, query).await.unwrap();
+
+        let scrape = metrics_handler(State(state)).await;
+        for series in [
+            "scans_total{scan_type=\"golden_cross\"}",
+            "scan_matches_total{scan_type=\"golden_cross\"}",
+            "scan_duration_seconds_bucket{scan_type=\"golden_cross\",le=\"0.005\"}",
+            "scan_duration_seconds_count{scan_type=\"golden_cross\"}",
+        ] {
+            assert!(scrape.contains(series), "{} missing from:\n{}", series, scrape);
+        }
+        assert!(scrape.contains("# HELP scans_total Scans run, by scan type"));
+    }
 }
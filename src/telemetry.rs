@@ -0,0 +1,69 @@
+//! Prometheus metrics - recorder setup and HTTP instrumentation
+//!
+//! Labels are kept to bounded sets: scan types fall back to "unknown" for ids
+//! that aren't registered, and HTTP paths use the matched route template.
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use metrics::Unit;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::time::Instant;
+
+const DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+/// Install the global Prometheus recorder; call once at startup
+pub fn install_recorder() -> PrometheusHandle {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), DURATION_BUCKETS)
+        .expect("valid histogram buckets")
+        .install_recorder()
+        .expect("failed to install Prometheus recorder");
+    describe_metrics();
+    handle
+}
+
+/// `# HELP` text for every metric the app records
+fn describe_metrics() {
+    metrics::describe_counter!("scans_total", "Scans run, by scan type");
+    metrics::describe_counter!("scan_matches_total", "Matches returned, by scan type");
+    metrics::describe_counter!("scan_errors_total", "Tickers skipped because a scan panicked, by scan type");
+    metrics::describe_counter!("scans_rejected_total", "Scans turned away because the concurrent scan limit was reached");
+    metrics::describe_histogram!("scan_duration_seconds", Unit::Seconds, "Scan wall time, by scan type");
+    metrics::describe_gauge!("tickers_loaded", "Tickers in the data store");
+    metrics::describe_histogram!("data_load_duration_seconds", Unit::Seconds, "Time to load the data directory");
+    metrics::describe_counter!("llm_calls_total", "LLM requests made");
+    metrics::describe_counter!("llm_call_failures_total", "LLM requests that failed");
+    metrics::describe_histogram!("llm_call_duration_seconds", Unit::Seconds, "LLM request latency");
+    metrics::describe_counter!("http_requests_total", "HTTP requests, by method, route and status");
+    metrics::describe_histogram!(
+        "http_request_duration_seconds",
+        Unit::Seconds,
+        "HTTP request latency, by method, route and status"
+    );
+}
+
+/// Middleware recording request count and latency per method/route/status
+pub async fn track_http(req: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(req).await;
+
+    let status = response.status().as_u16().to_string();
+    let labels = [("method", method), ("path", path), ("status", status)];
+    metrics::counter!("http_requests_total", &labels).increment(1);
+    metrics::histogram!("http_request_duration_seconds", &labels)
+        .record(start.elapsed().as_secs_f64());
+
+    response
+}